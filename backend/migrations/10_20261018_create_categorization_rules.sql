@@ -0,0 +1,35 @@
+-- Create the categorization_rules table. A rule matches transactions on any of its
+-- conditions that are set: the description (contains or regex, ignoring case), the
+-- amount's size, the account and whether money went out (debit) or came in (credit).
+-- The matching rule with the highest priority assigns its category and tags.
+CREATE TABLE IF NOT EXISTS categorization_rules (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0,
+    description_pattern TEXT,
+    match_kind TEXT NOT NULL DEFAULT 'contains' CHECK (match_kind IN ('contains', 'regex')),
+    min_amount DECIMAL(10, 2) CHECK (min_amount >= 0),
+    max_amount DECIMAL(10, 2) CHECK (max_amount >= 0),
+    account TEXT,
+    sign TEXT CHECK (sign IN ('debit', 'credit')),
+    category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create the trigger for categorization_rules table
+DO $$ 
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'update_categorization_rules_updated_at') THEN
+        CREATE TRIGGER update_categorization_rules_updated_at
+            BEFORE UPDATE ON categorization_rules
+            FOR EACH ROW
+            EXECUTE FUNCTION update_updated_at_column();
+    END IF;
+END
+$$;
+
+-- Tags a rule gave a transaction
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,29 @@
+-- The description as normalize::clean_text gives it, next to the raw one. The server
+-- fills it in for rows written before this column existed.
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS normalized_description TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_normalized_description
+    ON transactions(normalized_description);
+
+-- Filling in the normalized description isn't an edit, so it keeps updated_at. An
+-- import's transactions still count as unedited when it's rolled back.
+CREATE OR REPLACE FUNCTION update_transactions_updated_at_column()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    IF to_jsonb(NEW) - 'normalized_description' - 'updated_at'
+        = to_jsonb(OLD) - 'normalized_description' - 'updated_at' THEN
+        NEW.updated_at = OLD.updated_at;
+    ELSE
+        NEW.updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    RETURN NEW;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS update_transactions_updated_at ON transactions;
+CREATE TRIGGER update_transactions_updated_at
+    BEFORE UPDATE ON transactions
+    FOR EACH ROW
+    EXECUTE FUNCTION update_transactions_updated_at_column();
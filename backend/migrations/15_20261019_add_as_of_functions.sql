@@ -0,0 +1,60 @@
+-- The version of every audited row of an entity type as it stood at as_of, with a NULL
+-- version for rows that didn't exist then. That's the row after its last change up to
+-- as_of, or failing that the row before its first change after it.
+CREATE OR REPLACE FUNCTION audited_versions(entity TEXT, as_of TIMESTAMP WITH TIME ZONE)
+RETURNS TABLE (entity_id INTEGER, version JSONB)
+LANGUAGE sql
+STABLE
+AS $$
+    SELECT DISTINCT ON (a.entity_id)
+        a.entity_id,
+        CASE
+            WHEN a.created_at > as_of THEN a.before
+            WHEN a.action IN ('create', 'update') THEN a.after
+        END
+    FROM audit_log a
+    WHERE a.entity_type = entity
+    ORDER BY
+        a.entity_id,
+        a.created_at <= as_of DESC,
+        CASE WHEN a.created_at <= as_of THEN -a.id ELSE a.id END
+$$;
+
+-- The transactions as they stood at as_of. Rows that haven't changed since the audit
+-- log was added are as they were created.
+CREATE OR REPLACE FUNCTION transactions_as_of(as_of TIMESTAMP WITH TIME ZONE)
+RETURNS SETOF transactions
+LANGUAGE sql
+STABLE
+AS $$
+    SELECT (jsonb_populate_record(NULL::transactions, v.version)).*
+    FROM audited_versions('transaction', as_of) v
+    WHERE v.version IS NOT NULL
+    UNION ALL
+    SELECT t.*
+    FROM transactions t
+    WHERE t.created_at <= as_of
+        AND NOT EXISTS (
+            SELECT 1 FROM audit_log a
+            WHERE a.entity_type = 'transaction' AND a.entity_id = t.id
+        )
+$$;
+
+-- The categories as they stood at as_of, in the same way
+CREATE OR REPLACE FUNCTION categories_as_of(as_of TIMESTAMP WITH TIME ZONE)
+RETURNS SETOF categories
+LANGUAGE sql
+STABLE
+AS $$
+    SELECT (jsonb_populate_record(NULL::categories, v.version)).*
+    FROM audited_versions('category', as_of) v
+    WHERE v.version IS NOT NULL
+    UNION ALL
+    SELECT c.*
+    FROM categories c
+    WHERE c.created_at <= as_of
+        AND NOT EXISTS (
+            SELECT 1 FROM audit_log a
+            WHERE a.entity_type = 'category' AND a.entity_id = c.id
+        )
+$$;
@@ -0,0 +1,193 @@
+-- Group the audit log's entries into operations that undo reverses as a whole: the
+-- changes one database transaction made. Entries written before this have no operation.
+ALTER TABLE audit_log ADD COLUMN IF NOT EXISTS operation_id BIGINT;
+CREATE INDEX IF NOT EXISTS idx_audit_log_operation ON audit_log(operation_id);
+
+-- Undo and redo are written to the audit log as where their changes came from
+ALTER TABLE audit_log DROP CONSTRAINT IF EXISTS audit_log_source_check;
+ALTER TABLE audit_log ADD CONSTRAINT audit_log_source_check
+    CHECK (source IN ('api', 'import', 'rule', 'recurring', 'undo', 'redo'));
+
+-- The operations that were undone, newest last. Redo takes them back until the actor
+-- makes a new change after undo_entry_id, the last entry the undo wrote.
+CREATE TABLE IF NOT EXISTS undone_operations (
+    id SERIAL PRIMARY KEY,
+    operation_id BIGINT NOT NULL UNIQUE,
+    actor TEXT NOT NULL,
+    undo_entry_id INTEGER NOT NULL,
+    undone_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The operations that can't be undone any more because a row they changed was changed
+-- since, with the conflict as the reason. Undo skips them.
+CREATE TABLE IF NOT EXISTS stale_operations (
+    id SERIAL PRIMARY KEY,
+    operation_id BIGINT NOT NULL UNIQUE,
+    actor TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE OR REPLACE FUNCTION write_audit_log()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    before_row JSONB;
+    after_row JSONB;
+    audit_action TEXT;
+    merged_into INTEGER;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        audit_action := 'create';
+        after_row := to_jsonb(NEW);
+    ELSIF TG_OP = 'UPDATE' THEN
+        audit_action := 'update';
+        before_row := to_jsonb(OLD);
+        after_row := to_jsonb(NEW);
+        -- filling in the normalized description isn't a change
+        IF after_row - 'normalized_description' - 'updated_at'
+            = before_row - 'normalized_description' - 'updated_at' THEN
+            RETURN NULL;
+        END IF;
+    ELSE
+        audit_action := 'delete';
+        before_row := to_jsonb(OLD);
+        merged_into := NULLIF(current_setting('audit.merged_into', true), '')::INTEGER;
+        IF TG_TABLE_NAME = 'transactions' AND merged_into IS NOT NULL THEN
+            audit_action := 'merge';
+            SELECT to_jsonb(t) INTO after_row FROM transactions t WHERE t.id = merged_into;
+        END IF;
+    END IF;
+
+    INSERT INTO audit_log (entity_type, entity_id, action, actor, source, before, after, operation_id)
+    VALUES (
+        CASE TG_TABLE_NAME WHEN 'transactions' THEN 'transaction' ELSE 'category' END,
+        (COALESCE(before_row, after_row) ->> 'id')::INTEGER,
+        audit_action,
+        COALESCE(NULLIF(current_setting('audit.actor', true), ''), session_user),
+        COALESCE(NULLIF(current_setting('audit.source', true), ''), 'api'),
+        before_row,
+        after_row,
+        txid_current()
+    );
+    RETURN NULL;
+END;
+$$;
+
+-- Put a transaction or category back to a version from the audit log, deleting it when
+-- the version is NULL. Fails when the row isn't as expected (NULL for no row), because
+-- something changed it since, or when a row it would delete is still referenced, since
+-- deleting it would fail or cascade to rows the audit log doesn't have.
+CREATE OR REPLACE FUNCTION restore_audited_row(
+    entity TEXT,
+    row_id INTEGER,
+    expected JSONB,
+    version JSONB
+)
+RETURNS VOID
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    table_name TEXT := CASE entity WHEN 'transaction' THEN 'transactions' ELSE 'categories' END;
+    current_row JSONB;
+    columns TEXT;
+    reference RECORD;
+    referenced BOOLEAN;
+BEGIN
+    EXECUTE format('SELECT to_jsonb(t) FROM %I t WHERE t.id = $1', table_name)
+        INTO current_row
+        USING row_id;
+    IF (current_row - 'normalized_description' - 'updated_at')
+        IS DISTINCT FROM (expected - 'normalized_description' - 'updated_at') THEN
+        RAISE EXCEPTION 'The % with id % changed since, so it can''t be restored', entity, row_id
+            USING ERRCODE = 'object_not_in_prerequisite_state';
+    END IF;
+
+    IF version IS NULL THEN
+        FOR reference IN
+            SELECT c.conrelid::regclass AS referencing_table, a.attname AS referencing_column
+            FROM pg_constraint c
+            JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = c.conkey[1]
+            WHERE c.contype = 'f' AND c.confrelid = table_name::regclass
+        LOOP
+            EXECUTE format(
+                'SELECT EXISTS (SELECT 1 FROM %s WHERE %I = $1)',
+                reference.referencing_table,
+                reference.referencing_column
+            ) INTO referenced USING row_id;
+            IF referenced THEN
+                RAISE EXCEPTION 'The % with id % is still used by %, so it can''t be removed',
+                    entity, row_id, reference.referencing_table
+                    USING ERRCODE = 'object_not_in_prerequisite_state';
+            END IF;
+        END LOOP;
+        EXECUTE format('DELETE FROM %I WHERE id = $1', table_name) USING row_id;
+    ELSIF current_row IS NULL THEN
+        EXECUTE format(
+            'INSERT INTO %I SELECT * FROM jsonb_populate_record(NULL::%I, $1)',
+            table_name,
+            table_name
+        ) USING version;
+    ELSE
+        SELECT string_agg(quote_ident(k.key), ', ') INTO columns
+        FROM jsonb_object_keys(version) AS k(key)
+        WHERE k.key NOT IN ('id', 'updated_at');
+        EXECUTE format(
+            'UPDATE %I SET (%s) = (SELECT %s FROM jsonb_populate_record(NULL::%I, $1)) WHERE id = $2',
+            table_name,
+            columns,
+            columns,
+            table_name
+        ) USING version, row_id;
+    END IF;
+END;
+$$;
+
+-- Undo an operation, restoring every row it changed to how it was before it, or redo it,
+-- restoring them to how it left them. Undo goes through the rows newest first and redo
+-- oldest first, so a category is there before its transactions. Returns how many rows
+-- were restored. A row in the closed period fails it until the period is reopened.
+CREATE OR REPLACE FUNCTION restore_operation(operation BIGINT, undo BOOLEAN)
+RETURNS INTEGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    change RECORD;
+    restored INTEGER := 0;
+BEGIN
+    FOR change IN
+        SELECT
+            a.entity_type,
+            a.entity_id,
+            (ARRAY_AGG(a.before ORDER BY a.id))[1] AS before_row,
+            -- a deleted or merged row is gone afterwards
+            (ARRAY_AGG(
+                CASE WHEN a.action IN ('delete', 'merge') THEN NULL ELSE a.after END
+                ORDER BY a.id DESC
+            ))[1] AS after_row
+        FROM audit_log a
+        WHERE a.operation_id = operation AND a.entity_type IN ('transaction', 'category')
+        GROUP BY a.entity_type, a.entity_id
+        ORDER BY
+            CASE WHEN undo THEN -MIN(a.id) ELSE MIN(a.id) END
+    LOOP
+        IF undo THEN
+            PERFORM restore_audited_row(
+                change.entity_type, change.entity_id, change.after_row, change.before_row
+            );
+        ELSE
+            PERFORM restore_audited_row(
+                change.entity_type, change.entity_id, change.before_row, change.after_row
+            );
+        END IF;
+        restored := restored + 1;
+    END LOOP;
+    RETURN restored;
+EXCEPTION
+    -- what reject_closed_period_changes raises
+    WHEN raise_exception THEN
+        RAISE EXCEPTION '%, reopen it to % this operation',
+            SQLERRM, CASE WHEN undo THEN 'undo' ELSE 'redo' END;
+END;
+$$;
@@ -0,0 +1,28 @@
+-- Create the budgets table
+-- A budget applies from period_start onwards, repeating every period, until a
+-- budget with a later period_start is created for the same category.
+CREATE TABLE IF NOT EXISTS budgets (
+    id SERIAL PRIMARY KEY,
+    category_id INTEGER NOT NULL REFERENCES categories(id),
+    period_start DATE NOT NULL,
+    period_type TEXT NOT NULL DEFAULT 'monthly' CHECK (period_type IN ('monthly', 'quarterly', 'yearly')),
+    amount DECIMAL(10, 2) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (category_id, period_start)
+);
+
+-- Create index on category_id for faster queries
+CREATE INDEX IF NOT EXISTS idx_budgets_category_id ON budgets(category_id);
+
+-- Create the trigger for budgets table
+DO $$ 
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'update_budgets_updated_at') THEN
+        CREATE TRIGGER update_budgets_updated_at
+            BEFORE UPDATE ON budgets
+            FOR EACH ROW
+            EXECUTE FUNCTION update_updated_at_column();
+    END IF;
+END
+$$;
@@ -0,0 +1,29 @@
+-- Carry unspent or overspent amounts of a monthly budget into the next month
+ALTER TABLE budgets ADD COLUMN IF NOT EXISTS rollover BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Create the envelope_assignments table
+-- Income assigned to a category for a month, month is always the first day of the month
+CREATE TABLE IF NOT EXISTS envelope_assignments (
+    id SERIAL PRIMARY KEY,
+    category_id INTEGER NOT NULL REFERENCES categories(id),
+    month DATE NOT NULL,
+    amount DECIMAL(10, 2) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (category_id, month)
+);
+
+-- Create index on month for faster queries
+CREATE INDEX IF NOT EXISTS idx_envelope_assignments_month ON envelope_assignments(month);
+
+-- Create the trigger for envelope_assignments table
+DO $$ 
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'update_envelope_assignments_updated_at') THEN
+        CREATE TRIGGER update_envelope_assignments_updated_at
+            BEFORE UPDATE ON envelope_assignments
+            FOR EACH ROW
+            EXECUTE FUNCTION update_updated_at_column();
+    END IF;
+END
+$$;
@@ -0,0 +1,13 @@
+-- Keep what the classifier said about each imported transaction, so uncertain
+-- predictions can be reviewed. predicted_category is the category name as the
+-- classifier wrote it, original_category the one the file had before classification.
+-- category_confirmed_at is set once someone has checked the category.
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS predicted_category TEXT;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS original_category TEXT;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS confidence DOUBLE PRECISION;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS classifier_model TEXT;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS category_confirmed_at TIMESTAMP WITH TIME ZONE;
+
+-- The review queue walks unconfirmed predictions from the least confident up
+CREATE INDEX IF NOT EXISTS idx_transactions_review_queue ON transactions(confidence, id)
+    WHERE confidence IS NOT NULL AND category_confirmed_at IS NULL;
@@ -0,0 +1,104 @@
+use juniper::GraphQLEnum;
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AuditEntity {
+    Transaction,
+    Category,
+    // the date through which transactions are closed
+    Period,
+}
+
+impl AuditEntity {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Transaction => "transaction",
+            AuditEntity::Category => "category",
+            AuditEntity::Period => "period",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "category" => AuditEntity::Category,
+            "period" => AuditEntity::Period,
+            _ => AuditEntity::Transaction,
+        }
+    }
+}
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    // a duplicate transaction was deleted in favor of the one it was merged into
+    Merge,
+    // transactions were closed through a later date
+    Close,
+    // transactions were reopened from a date on
+    Reopen,
+}
+
+impl AuditAction {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Merge => "merge",
+            AuditAction::Close => "close",
+            AuditAction::Reopen => "reopen",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "create" => AuditAction::Create,
+            "delete" => AuditAction::Delete,
+            "merge" => AuditAction::Merge,
+            "close" => AuditAction::Close,
+            "reopen" => AuditAction::Reopen,
+            _ => AuditAction::Update,
+        }
+    }
+}
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AuditSource {
+    // a GraphQL mutation, or anything else that doesn't say
+    Api,
+    // an import or its rollback
+    Import,
+    // a categorization rule
+    Rule,
+    // a recurring schedule's occurrence
+    Recurring,
+    // undoing an earlier operation
+    Undo,
+    // redoing an undone operation
+    Redo,
+}
+
+impl AuditSource {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            AuditSource::Api => "api",
+            AuditSource::Import => "import",
+            AuditSource::Rule => "rule",
+            AuditSource::Recurring => "recurring",
+            AuditSource::Undo => "undo",
+            AuditSource::Redo => "redo",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "import" => AuditSource::Import,
+            "rule" => AuditSource::Rule,
+            "recurring" => AuditSource::Recurring,
+            "undo" => AuditSource::Undo,
+            "redo" => AuditSource::Redo,
+            _ => AuditSource::Api,
+        }
+    }
+}
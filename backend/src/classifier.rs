@@ -0,0 +1,231 @@
+use crate::db_models::DbTrainingExample;
+use crate::recurrence::merchant_key;
+use chrono::NaiveDateTime;
+use rust_decimal::prelude::ToPrimitive;
+use sqlx::types::BigDecimal;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// The trained classifier the server suggests categories with, None until it's trained
+pub type SharedClassifier = Arc<RwLock<Option<CategoryClassifier>>>;
+
+// Additive smoothing, so a word never seen with a category doesn't rule it out
+const SMOOTHING: f64 = 0.1;
+
+// A category someone corrected a transaction to counts this many times over, so the
+// classifier learns from the mistakes made before
+const CORRECTION_WEIGHT: f64 = 2.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategorySuggestion {
+    pub category_id: i32,
+    pub category_name: String,
+    pub probability: f64,
+}
+
+#[derive(Debug, Clone)]
+struct CategoryModel {
+    category_id: i32,
+    category_name: String,
+    log_prior: f64,
+    // log probability of each vocabulary feature given the category
+    log_likelihoods: Vec<f64>,
+}
+
+/// Multinomial naive Bayes over TF-IDF weighted words of the description, with the
+/// amount's sign and order of magnitude as two more features
+#[derive(Debug, Clone)]
+pub struct CategoryClassifier {
+    vocabulary: HashMap<String, usize>,
+    idf: Vec<f64>,
+    categories: Vec<CategoryModel>,
+    pub examples: usize,
+    pub trained_at: NaiveDateTime,
+}
+
+/// The words of the description and tokens for the amount, as the classifier sees them
+pub fn features(description: &str, amount: &BigDecimal) -> Vec<String> {
+    let mut features: Vec<String> = merchant_key(description)
+        .split(' ')
+        .filter(|word| word.chars().count() > 1)
+        .map(str::to_string)
+        .collect();
+
+    let amount = amount.to_f64().unwrap_or(0.0);
+    let sign = if amount < 0.0 { "debit" } else { "credit" };
+    // 0 for under 1, 1 for under 10, 2 for under 100 and so on
+    let magnitude = if amount.abs() < 1.0 {
+        0
+    } else {
+        (amount.abs().log10().floor() as i32 + 1).min(6)
+    };
+    features.push(format!("__sign_{}", sign));
+    features.push(format!("__amount_{}_{}", sign, magnitude));
+    features
+}
+
+impl CategoryClassifier {
+    /// Train on labeled transactions, or None when there are none to learn from
+    pub fn train(examples: &[DbTrainingExample]) -> Option<Self> {
+        if examples.is_empty() {
+            return None;
+        }
+
+        let documents: Vec<Vec<String>> = examples
+            .iter()
+            .map(|example| features(&example.description, &example.amount))
+            .collect();
+
+        let mut vocabulary = HashMap::new();
+        let mut document_frequency: Vec<usize> = Vec::new();
+        for document in &documents {
+            let mut seen = vec![];
+            for feature in document {
+                let next = vocabulary.len();
+                let index = *vocabulary.entry(feature.clone()).or_insert(next);
+                if index == document_frequency.len() {
+                    document_frequency.push(0);
+                }
+                if !seen.contains(&index) {
+                    seen.push(index);
+                    document_frequency[index] += 1;
+                }
+            }
+        }
+        // smoothed so a feature in every document still counts a little
+        let total = documents.len() as f64;
+        let idf: Vec<f64> = document_frequency
+            .iter()
+            .map(|frequency| ((1.0 + total) / (1.0 + *frequency as f64)).ln() + 1.0)
+            .collect();
+
+        // Sum the weighted features of every category's transactions
+        let mut by_category: BTreeMap<i32, (String, f64, Vec<f64>)> = BTreeMap::new();
+        let mut total_count = 0.0;
+        for (example, document) in examples.iter().zip(&documents) {
+            let example_weight = if example.corrected {
+                CORRECTION_WEIGHT
+            } else {
+                1.0
+            };
+            let (_, count, weights) = by_category
+                .entry(example.category_id)
+                .or_insert_with(|| (example.category_name.clone(), 0.0, vec![0.0; idf.len()]));
+            *count += example_weight;
+            total_count += example_weight;
+            for (index, weight) in tf_idf(document, &vocabulary, &idf) {
+                weights[index] += example_weight * weight;
+            }
+        }
+
+        let categories = by_category
+            .into_iter()
+            .map(|(category_id, (category_name, count, weights))| {
+                let total_weight: f64 = weights.iter().sum();
+                let denominator = total_weight + SMOOTHING * idf.len() as f64;
+                CategoryModel {
+                    category_id,
+                    category_name,
+                    log_prior: (count / total_count).ln(),
+                    log_likelihoods: weights
+                        .iter()
+                        .map(|weight| ((weight + SMOOTHING) / denominator).ln())
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Some(Self {
+            vocabulary,
+            idf,
+            categories,
+            examples: examples.len(),
+            trained_at: chrono::Local::now().naive_local(),
+        })
+    }
+
+    pub fn category_count(&self) -> usize {
+        self.categories.len()
+    }
+
+    pub fn vocabulary_size(&self) -> usize {
+        self.vocabulary.len()
+    }
+
+    /// The `k` most likely categories, most likely first. Words the classifier has
+    /// never seen are ignored
+    pub fn suggest(
+        &self,
+        description: &str,
+        amount: &BigDecimal,
+        k: usize,
+    ) -> Vec<CategorySuggestion> {
+        let weights = tf_idf(&features(description, amount), &self.vocabulary, &self.idf);
+        let scores: Vec<f64> = self
+            .categories
+            .iter()
+            .map(|category| {
+                category.log_prior
+                    + weights
+                        .iter()
+                        .map(|(index, weight)| weight * category.log_likelihoods[*index])
+                        .sum::<f64>()
+            })
+            .collect();
+
+        // softmax, shifted by the best score so the exponents can't overflow
+        let best = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exponents: Vec<f64> = scores.iter().map(|score| (score - best).exp()).collect();
+        let sum: f64 = exponents.iter().sum();
+
+        let mut suggestions: Vec<CategorySuggestion> = self
+            .categories
+            .iter()
+            .zip(exponents)
+            .map(|(category, exponent)| CategorySuggestion {
+                category_id: category.category_id,
+                category_name: category.category_name.clone(),
+                probability: exponent / sum,
+            })
+            .collect();
+        suggestions.sort_by(|a, b| {
+            b.probability
+                .total_cmp(&a.probability)
+                .then(a.category_id.cmp(&b.category_id))
+        });
+        suggestions.truncate(k);
+        suggestions
+    }
+}
+
+// The document's known features weighted by term frequency times inverse document
+// frequency, scaled to unit length
+fn tf_idf(
+    document: &[String],
+    vocabulary: &HashMap<String, usize>,
+    idf: &[f64],
+) -> Vec<(usize, f64)> {
+    let mut counts: BTreeMap<usize, f64> = BTreeMap::new();
+    for feature in document {
+        if let Some(index) = vocabulary.get(feature) {
+            *counts.entry(*index).or_default() += 1.0;
+        }
+    }
+    let weights: Vec<(usize, f64)> = counts
+        .into_iter()
+        .map(|(index, count)| (index, count * idf[index]))
+        .collect();
+    let norm = weights
+        .iter()
+        .map(|(_, weight)| weight * weight)
+        .sum::<f64>()
+        .sqrt();
+    if norm == 0.0 {
+        return weights;
+    }
+    weights
+        .into_iter()
+        .map(|(index, weight)| (index, weight / norm))
+        .collect()
+}
@@ -1,11 +1,21 @@
-use crate::db_traits::{CategoryRepository, TransactionRepository};
+use crate::audit::{AuditAction, AuditSource};
+use crate::db_traits::{
+    AuditLogRepository, BudgetRepository, CategorizationRuleRepository, CategoryRepository,
+    ImportBatchRepository, PeriodClosingRepository, RecurringTransactionRepository,
+    TransactionRepository,
+};
+use crate::duplicates::group_duplicates;
+use crate::import::fingerprints;
+use crate::normalize::normalize_description;
 use async_trait::async_trait;
-use chrono::{Datelike, NaiveDate};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
 use sqlx::types::time::Date;
 use sqlx::types::time::OffsetDateTime;
 use sqlx::types::BigDecimal;
 use sqlx::FromRow;
-use sqlx::PgPool;
+use sqlx::{Connection, PgConnection, PgPool};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 
 #[derive(FromRow, Debug, Clone)]
 pub struct DbCategorySummary {
@@ -15,6 +25,16 @@ pub struct DbCategorySummary {
     pub transaction_count: Option<i64>,
 }
 
+#[derive(FromRow, Debug, Clone)]
+pub struct DbPeriodComparison {
+    pub category_id: Option<i32>, // only set when grouping by category
+    pub group_name: Option<String>,
+    pub period_a_total: Option<BigDecimal>, // options are needed for the same reason as DbCategorySummary
+    pub period_a_count: Option<i64>,
+    pub period_b_total: Option<BigDecimal>,
+    pub period_b_count: Option<i64>,
+}
+
 #[derive(FromRow, Debug, Clone)]
 pub struct DbCategory {
     pub id: i32,
@@ -26,6 +46,16 @@ pub struct DbCategory {
     pub updated_at: Option<OffsetDateTime>,
 }
 
+/// Another name for a category, as a bank or the classifier calls it
+#[derive(FromRow, Debug, Clone)]
+pub struct DbCategoryAlias {
+    pub id: i32,
+    pub name: String,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub created_at: Option<OffsetDateTime>,
+}
+
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct DbTransaction {
     pub id: i32,
@@ -39,6 +69,332 @@ pub struct DbTransaction {
     pub updated_at: Option<OffsetDateTime>,
 }
 
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DbBudget {
+    pub id: i32,
+    pub category_id: i32,
+    #[sqlx(default)]
+    pub category_name: Option<String>,
+    pub period_start: Date,
+    pub period_type: String,
+    pub amount: BigDecimal,
+    pub rollover: bool,
+    pub created_at: Option<OffsetDateTime>,
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DbEnvelopeAssignment {
+    pub id: i32,
+    pub category_id: i32,
+    #[sqlx(default)]
+    pub category_name: Option<String>,
+    pub month: Date,
+    pub amount: BigDecimal,
+    pub created_at: Option<OffsetDateTime>,
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct DbMonthlyCategoryTotal {
+    pub category_id: i32,
+    pub month: Date,
+    pub total_amount: BigDecimal,
+}
+
+/// A transaction read from an import file or the importTransactions mutation
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbImportRow {
+    pub account: String,
+    pub external_id: Option<String>,
+    pub amount: BigDecimal,
+    pub description: String,
+    pub date: Date,
+    pub category_id: i32,
+    // what the classifier said about the category, when it was predicted
+    pub classification: Option<DbClassification>,
+    // the categorization rule that chose the category, and its tags
+    pub rule_id: Option<i32>,
+    pub tags: Vec<String>,
+    // a category the import creates for the row, in place of category_id
+    pub new_category: Option<String>,
+}
+
+/// A classifier's prediction of a transaction's category
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DbClassification {
+    // the category name as the classifier wrote it
+    pub predicted_category: Option<String>,
+    // the category the transaction had before it was classified
+    pub original_category: Option<String>,
+    pub confidence: Option<f64>,
+    pub model: Option<String>,
+}
+
+/// A transaction whose predicted category hasn't been confirmed yet
+#[derive(FromRow, Debug, Clone)]
+pub struct DbReviewItem {
+    pub id: i32,
+    pub amount: BigDecimal,
+    pub description: String,
+    pub date: Date,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub predicted_category: Option<String>,
+    pub original_category: Option<String>,
+    pub confidence: f64,
+    pub classifier_model: Option<String>,
+}
+
+/// What importing a row would do, as counted on an import batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DbImportAction {
+    Insert,
+    // the row's external id was imported before with different values
+    Update,
+    // the row was imported before, by external id or fingerprint
+    Skip,
+}
+
+/// Where an import came from, recorded with the counts of what it did
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbNewImportBatch {
+    pub source_name: String,
+    pub file_hash: Option<String>,
+    pub imported_by: Option<String>,
+    // rows read from the source, including rows rejected before the import
+    pub row_count: i32,
+    pub warnings: Vec<String>,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DbImportBatch {
+    pub id: i32,
+    pub source_name: String,
+    pub file_hash: Option<String>,
+    pub imported_by: Option<String>,
+    pub row_count: i32,
+    pub inserted_count: i32,
+    pub updated_count: i32,
+    pub skipped_count: i32,
+    pub warnings: Vec<String>,
+    pub rolled_back_at: Option<OffsetDateTime>,
+    pub created_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DbImportRollback {
+    pub removed: u64,
+    // transactions edited since the import, which are left alone
+    pub kept: u64,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DbRecurringTransaction {
+    pub id: i32,
+    pub description: String,
+    pub amount: BigDecimal,
+    pub category_id: i32,
+    #[sqlx(default)]
+    pub category_name: Option<String>,
+    pub frequency: String,
+    pub interval_count: i32,
+    pub day_of_month: Option<i32>,
+    pub start_date: Date,
+    pub end_date: Option<Date>,
+    pub last_materialized_date: Option<Date>,
+    pub active: bool,
+    pub created_at: Option<OffsetDateTime>,
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DbCategorizationRule {
+    pub id: i32,
+    pub name: String,
+    pub priority: i32,
+    pub description_pattern: Option<String>,
+    // 'contains' or 'regex'
+    pub match_kind: String,
+    pub min_amount: Option<BigDecimal>,
+    pub max_amount: Option<BigDecimal>,
+    pub account: Option<String>,
+    // 'debit' or 'credit'
+    pub sign: Option<String>,
+    pub category_id: i32,
+    #[sqlx(default)]
+    pub category_name: Option<String>,
+    pub tags: Vec<String>,
+    pub enabled: bool,
+    pub created_at: Option<OffsetDateTime>,
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+/// The fields of a categorization rule that are set when it's created or updated
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbNewCategorizationRule {
+    pub name: String,
+    pub priority: i32,
+    pub description_pattern: Option<String>,
+    pub match_kind: String,
+    pub min_amount: Option<BigDecimal>,
+    pub max_amount: Option<BigDecimal>,
+    pub account: Option<String>,
+    pub sign: Option<String>,
+    pub category_id: i32,
+    pub tags: Vec<String>,
+    pub enabled: bool,
+}
+
+/// A transaction with the fields categorization rules look at and change
+#[derive(FromRow, Debug, Clone)]
+pub struct DbTaggedTransaction {
+    pub id: i32,
+    pub amount: BigDecimal,
+    pub description: String,
+    pub date: Date,
+    pub account: String,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// A categorized transaction the category classifier learns from
+#[derive(FromRow, Debug, Clone)]
+pub struct DbTrainingExample {
+    pub description: String,
+    pub amount: BigDecimal,
+    pub category_id: i32,
+    pub category_name: String,
+    // someone changed the category to this one after it was chosen for them
+    pub corrected: bool,
+}
+
+/// A change of a transaction's category through updateTransaction, with what chose the
+/// category that was corrected
+#[derive(FromRow, Debug, Clone)]
+pub struct DbCategoryCorrection {
+    pub id: i32,
+    pub transaction_id: Option<i32>,
+    pub description: String,
+    pub amount: BigDecimal,
+    pub old_category_id: i32,
+    pub old_category_name: Option<String>,
+    pub new_category_id: i32,
+    pub new_category_name: Option<String>,
+    pub source: String,
+    pub rule_id: Option<i32>,
+    pub classifier_model: Option<String>,
+    pub confidence: Option<f64>,
+    pub corrected_at: Option<OffsetDateTime>,
+}
+
+/// How many of the classifier's predictions of a category were corrected
+#[derive(FromRow, Debug, Clone)]
+pub struct DbCategoryAccuracy {
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub predictions: i64,
+    pub corrections: i64,
+}
+
+/// A rule being applied to existing transactions, with the category each one had
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbNewRuleApplication {
+    pub rule_id: i32,
+    pub rule_name: String,
+    pub category_id: i32,
+    pub tags: Vec<String>,
+    pub start_date: Date,
+    pub end_date: Date,
+    pub transaction_ids: Vec<i32>,
+    // lines up with transaction_ids
+    pub previous_category_ids: Vec<i32>,
+    pub applied_by: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DbRuleApplication {
+    pub id: i32,
+    // None once the rule has been deleted
+    pub rule_id: Option<i32>,
+    pub rule_name: String,
+    pub category_id: i32,
+    #[sqlx(default)]
+    pub category_name: Option<String>,
+    pub tags: Vec<String>,
+    pub start_date: Date,
+    pub end_date: Date,
+    pub transaction_ids: Vec<i32>,
+    pub previous_category_ids: Vec<i32>,
+    pub applied_by: Option<String>,
+    pub applied_at: Option<OffsetDateTime>,
+}
+
+/// A change to a transaction or category, with the row before and after it as JSON
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DbAuditEntry {
+    pub id: i32,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub action: String,
+    pub actor: String,
+    pub source: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub created_at: Option<OffsetDateTime>,
+}
+
+/// The date through which transactions were closed from then on, None when they
+/// were all reopened
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DbPeriodClosing {
+    pub id: i32,
+    pub closed_through: Option<Date>,
+    pub action: String,
+    pub actor: Option<String>,
+    pub created_at: Option<OffsetDateTime>,
+}
+
+pub fn to_sql_date(date: &NaiveDate) -> Date {
+    Date::from_calendar_date(
+        date.year(),
+        time::Month::try_from(date.month() as u8).expect("Invalid month number"),
+        date.day() as u8,
+    )
+    .unwrap()
+}
+
+pub fn to_sql_timestamp(at: &DateTime<Utc>) -> OffsetDateTime {
+    OffsetDateTime::from_unix_timestamp(at.timestamp()).expect("Invalid timestamp")
+        + time::Duration::nanoseconds(at.timestamp_subsec_nanos() as i64)
+}
+
+pub fn to_naive_date(date: &Date) -> NaiveDate {
+    NaiveDate::from_ymd_opt(date.year(), date.month() as u32, date.day() as u32)
+        .unwrap_or_else(|| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+}
+
+// Record the changes the rest of the database transaction makes as coming from `source`,
+// made by `actor` or by the database user when None
+async fn set_audit_context(
+    conn: &mut PgConnection,
+    source: AuditSource,
+    actor: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        SELECT set_config('audit.source', $1, true) as source,
+            set_config('audit.actor', $2, true) as actor
+        "#,
+        source.as_db_str(),
+        actor.unwrap_or_default()
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(())
+}
+
 #[derive(Clone)]
 pub struct PgCategoryRepository {
     pub pool: PgPool,
@@ -93,7 +449,11 @@ impl CategoryRepository for PgCategoryRepository {
         description: Option<String>,
         icon: Option<String>,
         color: Option<String>,
+        actor: Option<String>,
     ) -> Result<DbCategory, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Api, actor.as_deref()).await?;
+
         // First insert the category
         let result = sqlx::query!(
             r#"
@@ -106,11 +466,11 @@ impl CategoryRepository for PgCategoryRepository {
             icon,
             color
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
 
         // Then fetch the full record
-        sqlx::query_as!(
+        let category = sqlx::query_as!(
             DbCategory,
             r#"
             SELECT 
@@ -126,8 +486,11 @@ impl CategoryRepository for PgCategoryRepository {
             "#,
             result.id
         )
-        .fetch_one(&self.pool)
-        .await
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(category)
     }
 
     async fn update(
@@ -137,8 +500,12 @@ impl CategoryRepository for PgCategoryRepository {
         description: Option<String>,
         icon: Option<String>,
         color: Option<String>,
+        actor: Option<String>,
     ) -> Result<DbCategory, sqlx::Error> {
-        sqlx::query_as!(
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Api, actor.as_deref()).await?;
+
+        let category = sqlx::query_as!(
             DbCategory,
             r#"
             WITH updated AS (
@@ -167,9 +534,65 @@ impl CategoryRepository for PgCategoryRepository {
             color,
             id
         )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(category)
+    }
+
+    async fn aliases(&self) -> Result<Vec<DbCategoryAlias>, sqlx::Error> {
+        sqlx::query_as!(
+            DbCategoryAlias,
+            r#"
+            SELECT a.id, a.name, a.category_id, c.name as "category_name?", a.created_at
+            FROM category_aliases a
+            LEFT JOIN categories c ON c.id = a.category_id
+            ORDER BY LOWER(a.name)
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn set_alias(
+        &self,
+        name: String,
+        category_id: i32,
+    ) -> Result<DbCategoryAlias, sqlx::Error> {
+        sqlx::query_as!(
+            DbCategoryAlias,
+            r#"
+            WITH alias AS (
+                INSERT INTO category_aliases (name, category_id)
+                VALUES ($1, $2)
+                ON CONFLICT (LOWER(name))
+                DO UPDATE SET name = EXCLUDED.name, category_id = EXCLUDED.category_id
+                RETURNING *
+            )
+            SELECT a.id, a.name, a.category_id, c.name as "category_name?", a.created_at
+            FROM alias a
+            LEFT JOIN categories c ON c.id = a.category_id
+            "#,
+            name.trim(),
+            category_id
+        )
         .fetch_one(&self.pool)
         .await
     }
+
+    async fn delete_alias(&self, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM category_aliases WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
 }
 
 #[derive(Clone)]
@@ -177,6 +600,156 @@ pub struct PgTransactionRepository {
     pub pool: PgPool,
 }
 
+// Rows are written in chunks of this many, one statement per chunk
+const IMPORT_CHUNK_SIZE: usize = 1000;
+
+// The rows with their fingerprints, in chunks to write together. One statement can't
+// upsert the same transaction twice, so a repeated external id starts a new chunk
+fn import_chunks(rows: Vec<DbImportRow>) -> Vec<Vec<(DbImportRow, String)>> {
+    let fingerprints = fingerprints(&rows);
+    let mut chunks: Vec<Vec<(DbImportRow, String)>> = Vec::new();
+    let mut external_ids = HashSet::new();
+
+    for (row, fingerprint) in rows.into_iter().zip(fingerprints) {
+        let key = row
+            .external_id
+            .as_ref()
+            .map(|external_id| (row.account.clone(), external_id.clone()));
+        let repeated = key.as_ref().is_some_and(|key| external_ids.contains(key));
+        match chunks.last_mut() {
+            Some(chunk) if chunk.len() < IMPORT_CHUNK_SIZE && !repeated => {
+                chunk.push((row, fingerprint))
+            }
+            _ => {
+                external_ids.clear();
+                chunks.push(vec![(row, fingerprint)]);
+            }
+        }
+        external_ids.extend(key);
+    }
+    chunks
+}
+
+// One classification field of every row, None for rows that weren't classified
+fn classification_column<T>(
+    rows: &[(DbImportRow, String)],
+    value: impl Fn(&DbClassification) -> Option<T>,
+) -> Vec<Option<T>> {
+    rows.iter()
+        .map(|(row, _)| row.classification.as_ref().and_then(&value))
+        .collect()
+}
+
+// A chunk of rows as one array per column, for UNNEST
+struct ImportColumns {
+    amounts: Vec<BigDecimal>,
+    descriptions: Vec<String>,
+    normalized_descriptions: Vec<String>,
+    dates: Vec<Date>,
+    category_ids: Vec<i32>,
+    accounts: Vec<String>,
+    // the external id or fingerprint that identifies each row
+    keys: Vec<String>,
+    predicted_categories: Vec<Option<String>>,
+    original_categories: Vec<Option<String>>,
+    confidences: Vec<Option<f64>>,
+    models: Vec<Option<String>>,
+    rule_ids: Vec<Option<i32>>,
+    // each row's tags as a JSON array, UNNEST can't take an array of arrays of different lengths
+    tags: Vec<String>,
+}
+
+impl ImportColumns {
+    fn new(rows: &[(DbImportRow, String)], key: impl Fn(&(DbImportRow, String)) -> String) -> Self {
+        Self {
+            amounts: rows.iter().map(|(row, _)| row.amount.clone()).collect(),
+            descriptions: rows
+                .iter()
+                .map(|(row, _)| row.description.clone())
+                .collect(),
+            normalized_descriptions: rows
+                .iter()
+                .map(|(row, _)| normalize_description(&row.description))
+                .collect(),
+            dates: rows.iter().map(|(row, _)| row.date).collect(),
+            category_ids: rows.iter().map(|(row, _)| row.category_id).collect(),
+            accounts: rows.iter().map(|(row, _)| row.account.clone()).collect(),
+            keys: rows.iter().map(key).collect(),
+            predicted_categories: classification_column(rows, |c| c.predicted_category.clone()),
+            original_categories: classification_column(rows, |c| c.original_category.clone()),
+            confidences: classification_column(rows, |c| c.confidence),
+            models: classification_column(rows, |c| c.model.clone()),
+            rule_ids: rows.iter().map(|(row, _)| row.rule_id).collect(),
+            tags: rows
+                .iter()
+                .map(|(row, _)| serde_json::to_string(&row.tags).unwrap_or_default())
+                .collect(),
+        }
+    }
+}
+
+impl PgTransactionRepository {
+    // A new transaction, with the categorization rule that chose its category and the
+    // rule's tags when one did
+    async fn insert(
+        &self,
+        amount: BigDecimal,
+        description: String,
+        date: Date,
+        category_id: i32,
+        rule: Option<DbCategorizationRule>,
+        actor: Option<String>,
+    ) -> Result<DbTransaction, sqlx::Error> {
+        let normalized_description = normalize_description(&description);
+        let (rule_id, tags) = match rule {
+            Some(rule) => (Some(rule.id), rule.tags),
+            None => (None, Vec::new()),
+        };
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Api, actor.as_deref()).await?;
+
+        let transaction = sqlx::query_as!(
+            DbTransaction,
+            r#"
+            WITH inserted AS (
+                INSERT INTO transactions (
+                    amount, description, date, category_id, normalized_description,
+                    category_rule_id, tags
+                )
+                VALUES (
+                    $1, $2, $3, $4, $5, $6,
+                    ARRAY(SELECT DISTINCT tag FROM UNNEST($7::text[]) AS tag ORDER BY tag)
+                )
+                RETURNING *
+            )
+            SELECT 
+                i.id,
+                i.amount,
+                i.description,
+                i.date,
+                i.category_id,
+                c.name as "category_name?",
+                i.created_at,
+                i.updated_at
+            FROM inserted i
+            JOIN categories c ON i.category_id = c.id
+            "#,
+            amount,
+            description,
+            date,
+            category_id,
+            normalized_description,
+            rule_id,
+            &tags
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(transaction)
+    }
+}
+
 #[async_trait]
 impl TransactionRepository for PgTransactionRepository {
     async fn all(&self) -> Result<Vec<DbTransaction>, sqlx::Error> {
@@ -207,33 +780,28 @@ impl TransactionRepository for PgTransactionRepository {
         description: String,
         date: Date,
         category_id: i32,
+        actor: Option<String>,
     ) -> Result<DbTransaction, sqlx::Error> {
-        sqlx::query_as!(
-            DbTransaction,
-            r#"
-            WITH inserted AS (
-                INSERT INTO transactions (amount, description, date, category_id)
-                VALUES ($1, $2, $3, $4)
-                RETURNING *
-            )
-            SELECT 
-                i.id,
-                i.amount,
-                i.description,
-                i.date,
-                i.category_id,
-                c.name as "category_name?",
-                i.created_at,
-                i.updated_at
-            FROM inserted i
-            JOIN categories c ON i.category_id = c.id
-            "#,
+        self.insert(amount, description, date, category_id, None, actor)
+            .await
+    }
+
+    async fn create_by_rule(
+        &self,
+        amount: BigDecimal,
+        description: String,
+        date: Date,
+        rule: DbCategorizationRule,
+        actor: Option<String>,
+    ) -> Result<DbTransaction, sqlx::Error> {
+        self.insert(
             amount,
             description,
             date,
-            category_id
+            rule.category_id,
+            Some(rule),
+            actor,
         )
-        .fetch_one(&self.pool)
         .await
     }
 
@@ -244,20 +812,60 @@ impl TransactionRepository for PgTransactionRepository {
         description: String,
         date: Date,
         category_id: i32,
+        actor: Option<String>,
     ) -> Result<DbTransaction, sqlx::Error> {
-        sqlx::query_as!(
+        let normalized_description = normalize_description(&description);
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Api, actor.as_deref()).await?;
+
+        let transaction = sqlx::query_as!(
             DbTransaction,
             r#"
-            WITH updated AS (
+            WITH previous AS (
+                SELECT id, category_id, category_rule_id, confidence, classifier_model,
+                    category_confirmed_at
+                FROM transactions
+                WHERE id = $5
+            ),
+            updated AS (
                 UPDATE transactions 
                 SET 
                     amount = $1,
                     description = $2,
+                    normalized_description = $6,
                     date = $3,
                     category_id = $4,
+                    -- a category changed by hand has been checked, and no rule chose it
+                    category_confirmed_at = CASE
+                        WHEN category_id IS DISTINCT FROM $4 THEN CURRENT_TIMESTAMP
+                        ELSE category_confirmed_at
+                    END,
+                    category_rule_id = CASE
+                        WHEN category_id IS DISTINCT FROM $4 THEN NULL
+                        ELSE category_rule_id
+                    END,
                     updated_at = CURRENT_TIMESTAMP
                 WHERE id = $5
                 RETURNING *
+            ),
+            -- the change of category is a correction of whoever chose the old one
+            correction AS (
+                INSERT INTO category_corrections (
+                    transaction_id, description, amount, old_category_id, new_category_id,
+                    source, rule_id, classifier_model, confidence
+                )
+                SELECT
+                    u.id, u.description, u.amount, p.category_id, u.category_id,
+                    CASE
+                        WHEN p.category_rule_id IS NOT NULL THEN 'rule'
+                        WHEN p.confidence IS NOT NULL AND p.category_confirmed_at IS NULL
+                            THEN 'model'
+                        ELSE 'manual'
+                    END,
+                    p.category_rule_id, p.classifier_model, p.confidence
+                FROM previous p
+                JOIN updated u ON u.id = p.id
+                WHERE p.category_id IS DISTINCT FROM u.category_id
             )
             SELECT 
                 u.id,
@@ -275,10 +883,31 @@ impl TransactionRepository for PgTransactionRepository {
             description,
             date,
             category_id,
+            id,
+            normalized_description
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(transaction)
+    }
+
+    async fn delete(&self, id: i32, actor: Option<String>) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Api, actor.as_deref()).await?;
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM transactions WHERE id = $1
+            "#,
             id
         )
-        .fetch_one(&self.pool)
-        .await
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
     }
 
     async fn by_category_id(&self, category_id: i32) -> Result<Vec<DbTransaction>, sqlx::Error> {
@@ -396,4 +1025,2007 @@ impl TransactionRepository for PgTransactionRepository {
         .fetch_all(&self.pool)
         .await
     }
+
+    async fn all_as_of(&self, as_of: &OffsetDateTime) -> Result<Vec<DbTransaction>, sqlx::Error> {
+        sqlx::query_as!(
+            DbTransaction,
+            r#"
+            SELECT
+                t.id as "id!",
+                t.amount as "amount!",
+                t.description as "description!",
+                t.date as "date!",
+                t.category_id as "category_id!",
+                c.name as "category_name?",
+                t.created_at as "created_at?",
+                t.updated_at as "updated_at?"
+            FROM transactions_as_of($1) t
+            LEFT JOIN categories_as_of($1) c ON t.category_id = c.id
+            ORDER BY t.date DESC
+            "#,
+            as_of
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn by_category_id_as_of(
+        &self,
+        category_id: i32,
+        as_of: &OffsetDateTime,
+    ) -> Result<Vec<DbTransaction>, sqlx::Error> {
+        sqlx::query_as!(
+            DbTransaction,
+            r#"
+            SELECT
+                t.id as "id!",
+                t.amount as "amount!",
+                t.description as "description!",
+                t.date as "date!",
+                t.category_id as "category_id!",
+                c.name as "category_name?",
+                t.created_at as "created_at?",
+                t.updated_at as "updated_at?"
+            FROM transactions_as_of($2) t
+            LEFT JOIN categories_as_of($2) c ON t.category_id = c.id
+            WHERE t.category_id = $1
+            ORDER BY t.date DESC
+            "#,
+            category_id,
+            as_of
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn by_date_range_as_of(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+        as_of: &OffsetDateTime,
+    ) -> Result<Vec<DbTransaction>, sqlx::Error> {
+        sqlx::query_as!(
+            DbTransaction,
+            r#"
+            SELECT
+                t.id as "id!",
+                t.amount as "amount!",
+                t.description as "description!",
+                t.date as "date!",
+                t.category_id as "category_id!",
+                c.name as "category_name?",
+                t.created_at as "created_at?",
+                t.updated_at as "updated_at?"
+            FROM transactions_as_of($3) t
+            LEFT JOIN categories_as_of($3) c ON t.category_id = c.id
+            WHERE t.date BETWEEN $1 AND $2
+            ORDER BY t.date DESC
+            "#,
+            to_sql_date(start_date),
+            to_sql_date(end_date),
+            as_of
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn sum_by_category_as_of(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+        as_of: &OffsetDateTime,
+    ) -> Result<Vec<DbCategorySummary>, sqlx::Error> {
+        sqlx::query_as!(
+            DbCategorySummary,
+            r#"
+            SELECT
+                t.category_id as "category_id!",
+                COALESCE(c.name, 'Uncategorized') as category_name,
+                SUM(t.amount) as total_amount,
+                COUNT(t.id) as transaction_count
+            FROM transactions_as_of($3) t
+            LEFT JOIN categories_as_of($3) c ON t.category_id = c.id
+            WHERE t.date BETWEEN $1 AND $2
+            GROUP BY t.category_id, c.name
+            ORDER BY category_name
+            "#,
+            to_sql_date(start_date),
+            to_sql_date(end_date),
+            as_of
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn compare_by_category(
+        &self,
+        period_a_start: &NaiveDate,
+        period_a_end: &NaiveDate,
+        period_b_start: &NaiveDate,
+        period_b_end: &NaiveDate,
+    ) -> Result<Vec<DbPeriodComparison>, sqlx::Error> {
+        // Both periods' category totals in one pass over their transactions
+        sqlx::query_as!(
+            DbPeriodComparison,
+            r#"
+            SELECT 
+                c.id as "category_id?",
+                c.name as "group_name?",
+                COALESCE(SUM(t.amount) FILTER (WHERE t.date BETWEEN $1 AND $2), 0)
+                    as period_a_total,
+                COUNT(t.id) FILTER (WHERE t.date BETWEEN $1 AND $2) as period_a_count,
+                COALESCE(SUM(t.amount) FILTER (WHERE t.date BETWEEN $3 AND $4), 0)
+                    as period_b_total,
+                COUNT(t.id) FILTER (WHERE t.date BETWEEN $3 AND $4) as period_b_count
+            FROM transactions t
+            JOIN categories c ON c.id = t.category_id
+            WHERE t.date BETWEEN $1 AND $2 OR t.date BETWEEN $3 AND $4
+            GROUP BY c.id, c.name
+            ORDER BY c.name
+            "#,
+            to_sql_date(period_a_start),
+            to_sql_date(period_a_end),
+            to_sql_date(period_b_start),
+            to_sql_date(period_b_end)
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn compare_by_description(
+        &self,
+        period_a_start: &NaiveDate,
+        period_a_end: &NaiveDate,
+        period_b_start: &NaiveDate,
+        period_b_end: &NaiveDate,
+    ) -> Result<Vec<DbPeriodComparison>, sqlx::Error> {
+        sqlx::query_as!(
+            DbPeriodComparison,
+            r#"
+            WITH period_a AS (
+                SELECT 
+                    t.description,
+                    SUM(t.amount) as total,
+                    COUNT(t.id) as transaction_count
+                FROM transactions t
+                WHERE t.date BETWEEN $1 AND $2
+                GROUP BY t.description
+            ),
+            period_b AS (
+                SELECT 
+                    t.description,
+                    SUM(t.amount) as total,
+                    COUNT(t.id) as transaction_count
+                FROM transactions t
+                WHERE t.date BETWEEN $3 AND $4
+                GROUP BY t.description
+            )
+            SELECT 
+                NULL::INTEGER as "category_id?",
+                COALESCE(a.description, b.description) as group_name,
+                COALESCE(a.total, 0) as period_a_total,
+                COALESCE(a.transaction_count, 0) as period_a_count,
+                COALESCE(b.total, 0) as period_b_total,
+                COALESCE(b.transaction_count, 0) as period_b_count
+            FROM period_a a
+            FULL OUTER JOIN period_b b ON a.description = b.description
+            ORDER BY group_name
+            "#,
+            to_sql_date(period_a_start),
+            to_sql_date(period_a_end),
+            to_sql_date(period_b_start),
+            to_sql_date(period_b_end)
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn monthly_totals_by_category(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> Result<Vec<DbMonthlyCategoryTotal>, sqlx::Error> {
+        sqlx::query_as!(
+            DbMonthlyCategoryTotal,
+            r#"
+            SELECT 
+                t.category_id,
+                date_trunc('month', t.date)::DATE as "month!",
+                SUM(t.amount) as "total_amount!"
+            FROM transactions t
+            WHERE t.date BETWEEN $1 AND $2
+            GROUP BY t.category_id, date_trunc('month', t.date)
+            ORDER BY "month!", t.category_id
+            "#,
+            to_sql_date(start_date),
+            to_sql_date(end_date)
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn find_duplicates(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+        window_days: i64,
+    ) -> Result<Vec<Vec<DbTransaction>>, sqlx::Error> {
+        let transactions = self.by_date_range(start_date, end_date).await?;
+        Ok(group_duplicates(transactions, window_days))
+    }
+
+    async fn resolve_duplicates(
+        &self,
+        keep_id: i32,
+        remove_ids: Vec<i32>,
+        actor: Option<String>,
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Api, actor.as_deref()).await?;
+
+        // Fails with RowNotFound if the transaction to keep is gone
+        let keep = sqlx::query!(
+            r#"
+            SELECT amount FROM transactions WHERE id = $1 FOR UPDATE
+            "#,
+            keep_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // the audit log records the duplicates as merged into the one kept
+        sqlx::query!(
+            r#"
+            SELECT set_config('audit.merged_into', $1, true) as merged_into
+            "#,
+            keep_id.to_string()
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM transactions
+            WHERE id = ANY($1) AND id <> $2 AND amount = $3
+            "#,
+            &remove_ids,
+            keep_id,
+            keep.amount
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn import_transactions(
+        &self,
+        batch: DbNewImportBatch,
+        rows: Vec<DbImportRow>,
+        actor: Option<String>,
+    ) -> Result<DbImportBatch, sqlx::Error> {
+        // nobody is listening, so the progress sent is dropped
+        let (progress, _) = unbounded_channel();
+        self.import_transactions_with_progress(batch, rows, progress, actor)
+            .await
+    }
+
+    async fn import_transactions_with_progress(
+        &self,
+        batch: DbNewImportBatch,
+        mut rows: Vec<DbImportRow>,
+        progress: UnboundedSender<usize>,
+        actor: Option<String>,
+    ) -> Result<DbImportBatch, sqlx::Error> {
+        let (mut inserted_count, mut updated_count, mut skipped_count) = (0, 0, 0);
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Import, actor.as_deref()).await?;
+
+        // New categories are created with the rows, so a failed import leaves none behind
+        let mut new_categories: HashMap<String, i32> = HashMap::new();
+        for row in rows.iter_mut() {
+            let Some(name) = row.new_category.take() else {
+                continue;
+            };
+            row.category_id = match new_categories.get(&name) {
+                Some(id) => *id,
+                None => {
+                    let id = sqlx::query!(
+                        "INSERT INTO categories (name) VALUES ($1) RETURNING id",
+                        name
+                    )
+                    .fetch_one(&mut *tx)
+                    .await?
+                    .id;
+                    new_categories.insert(name, id);
+                    id
+                }
+            };
+        }
+
+        let batch_id = sqlx::query!(
+            r#"
+            INSERT INTO import_batches (source_name, file_hash, imported_by, row_count, warnings)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+            batch.source_name,
+            batch.file_hash,
+            batch.imported_by,
+            batch.row_count,
+            &batch.warnings
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .id;
+
+        let mut written = 0;
+        for chunk in import_chunks(rows) {
+            written += chunk.len();
+            let (with_id, without_id): (Vec<_>, Vec<_>) = chunk
+                .into_iter()
+                .partition(|(row, _)| row.external_id.is_some());
+
+            // The bank's id is stable, so a row seen before is updated if the bank changed it.
+            // It keeps the batch that first inserted it
+            if !with_id.is_empty() {
+                let columns = ImportColumns::new(&with_id, |(row, _)| {
+                    row.external_id.clone().unwrap_or_default()
+                });
+                let upserted = sqlx::query!(
+                    r#"
+                    INSERT INTO transactions (
+                        amount, description, date, category_id, account, external_id, import_batch_id,
+                        predicted_category, original_category, confidence, classifier_model, tags,
+                        category_rule_id, normalized_description
+                    )
+                    SELECT
+                        amount, description, date, category_id, account, external_id, $7,
+                        predicted_category, original_category, confidence, classifier_model,
+                        ARRAY(SELECT jsonb_array_elements_text(tags::jsonb)), category_rule_id,
+                        normalized_description
+                    FROM UNNEST(
+                        $1::numeric[], $2::text[], $3::date[], $4::int4[], $5::text[], $6::text[],
+                        $8::text[], $9::text[], $10::float8[], $11::text[], $12::text[], $13::int4[],
+                        $14::text[]
+                    ) AS row(
+                        amount, description, date, category_id, account, external_id,
+                        predicted_category, original_category, confidence, classifier_model, tags,
+                        category_rule_id, normalized_description
+                    )
+                    ON CONFLICT (account, external_id) WHERE external_id IS NOT NULL
+                    DO UPDATE SET
+                        amount = EXCLUDED.amount,
+                        description = EXCLUDED.description,
+                        normalized_description = EXCLUDED.normalized_description,
+                        date = EXCLUDED.date
+                    WHERE (transactions.amount, transactions.description, transactions.date)
+                        IS DISTINCT FROM (EXCLUDED.amount, EXCLUDED.description, EXCLUDED.date)
+                    RETURNING (xmax = 0) as "inserted!"
+                    "#,
+                    &columns.amounts,
+                    &columns.descriptions,
+                    &columns.dates,
+                    &columns.category_ids,
+                    &columns.accounts,
+                    &columns.keys,
+                    batch_id,
+                    &columns.predicted_categories as &[Option<String>],
+                    &columns.original_categories as &[Option<String>],
+                    &columns.confidences as &[Option<f64>],
+                    &columns.models as &[Option<String>],
+                    &columns.tags,
+                    &columns.rule_ids as &[Option<i32>],
+                    &columns.normalized_descriptions
+                )
+                .fetch_all(&mut *tx)
+                .await?;
+
+                let inserted = upserted.iter().filter(|row| row.inserted).count();
+                inserted_count += inserted as i32;
+                updated_count += (upserted.len() - inserted) as i32;
+                skipped_count += (with_id.len() - upserted.len()) as i32;
+            }
+
+            // Without an id the content is all there is, so a row seen before is skipped
+            if !without_id.is_empty() {
+                let columns =
+                    ImportColumns::new(&without_id, |(_, fingerprint)| fingerprint.clone());
+                let inserted = sqlx::query!(
+                    r#"
+                    INSERT INTO transactions (
+                        amount, description, date, category_id, account, import_fingerprint, import_batch_id,
+                        predicted_category, original_category, confidence, classifier_model, tags,
+                        category_rule_id, normalized_description
+                    )
+                    SELECT
+                        amount, description, date, category_id, account, import_fingerprint, $7,
+                        predicted_category, original_category, confidence, classifier_model,
+                        ARRAY(SELECT jsonb_array_elements_text(tags::jsonb)), category_rule_id,
+                        normalized_description
+                    FROM UNNEST(
+                        $1::numeric[], $2::text[], $3::date[], $4::int4[], $5::text[], $6::text[],
+                        $8::text[], $9::text[], $10::float8[], $11::text[], $12::text[], $13::int4[],
+                        $14::text[]
+                    ) AS row(
+                        amount, description, date, category_id, account, import_fingerprint,
+                        predicted_category, original_category, confidence, classifier_model, tags,
+                        category_rule_id, normalized_description
+                    )
+                    ON CONFLICT (account, import_fingerprint) WHERE import_fingerprint IS NOT NULL
+                    DO NOTHING
+                    RETURNING id
+                    "#,
+                    &columns.amounts,
+                    &columns.descriptions,
+                    &columns.dates,
+                    &columns.category_ids,
+                    &columns.accounts,
+                    &columns.keys,
+                    batch_id,
+                    &columns.predicted_categories as &[Option<String>],
+                    &columns.original_categories as &[Option<String>],
+                    &columns.confidences as &[Option<f64>],
+                    &columns.models as &[Option<String>],
+                    &columns.tags,
+                    &columns.rule_ids as &[Option<i32>],
+                    &columns.normalized_descriptions
+                )
+                .fetch_all(&mut *tx)
+                .await?;
+
+                inserted_count += inserted.len() as i32;
+                skipped_count += (without_id.len() - inserted.len()) as i32;
+            }
+            // the import carries on if the receiver has gone away
+            let _ = progress.send(written);
+        }
+
+        let batch = sqlx::query_as!(
+            DbImportBatch,
+            r#"
+            UPDATE import_batches
+            SET inserted_count = $2, updated_count = $3, skipped_count = $4
+            WHERE id = $1
+            RETURNING *
+            "#,
+            batch_id,
+            inserted_count,
+            updated_count,
+            skipped_count
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(batch)
+    }
+
+    async fn preview_import(
+        &self,
+        rows: Vec<DbImportRow>,
+    ) -> Result<Vec<DbImportAction>, sqlx::Error> {
+        let fingerprints = fingerprints(&rows);
+        let mut conn = self.pool.acquire().await?;
+        // the transactions each external id and fingerprint in the file was imported as
+        let mut imported: HashMap<(String, String), (BigDecimal, String, Date)> = HashMap::new();
+        let mut imported_fingerprints: HashSet<(String, String)> = HashSet::new();
+
+        for (chunk, chunk_fingerprints) in rows
+            .chunks(IMPORT_CHUNK_SIZE)
+            .zip(fingerprints.chunks(IMPORT_CHUNK_SIZE))
+        {
+            let (accounts, external_ids): (Vec<String>, Vec<String>) = chunk
+                .iter()
+                .filter_map(|row| Some((row.account.clone(), row.external_id.clone()?)))
+                .unzip();
+            let existing = sqlx::query!(
+                r#"
+                SELECT account, external_id as "external_id!", amount, description, date
+                FROM transactions
+                JOIN UNNEST($1::text[], $2::text[]) AS row(account, external_id)
+                    USING (account, external_id)
+                "#,
+                &accounts,
+                &external_ids
+            )
+            .fetch_all(&mut *conn)
+            .await?;
+            imported.extend(existing.into_iter().map(|t| {
+                (
+                    (t.account, t.external_id),
+                    (t.amount, t.description, t.date),
+                )
+            }));
+
+            let (accounts, keys): (Vec<String>, Vec<String>) = chunk
+                .iter()
+                .zip(chunk_fingerprints)
+                .filter(|(row, _)| row.external_id.is_none())
+                .map(|(row, fingerprint)| (row.account.clone(), fingerprint.clone()))
+                .unzip();
+            let existing = sqlx::query!(
+                r#"
+                SELECT account, import_fingerprint as "import_fingerprint!"
+                FROM transactions
+                JOIN UNNEST($1::text[], $2::text[]) AS row(account, import_fingerprint)
+                    USING (account, import_fingerprint)
+                "#,
+                &accounts,
+                &keys
+            )
+            .fetch_all(&mut *conn)
+            .await?;
+            imported_fingerprints.extend(
+                existing
+                    .into_iter()
+                    .map(|t| (t.account, t.import_fingerprint)),
+            );
+        }
+
+        // a repeated external id is only inserted once, later rows are compared with it
+        let actions = rows
+            .into_iter()
+            .zip(fingerprints)
+            .map(|(row, fingerprint)| match row.external_id {
+                Some(external_id) => {
+                    let values = (row.amount, row.description, row.date);
+                    let action = match imported.get(&(row.account.clone(), external_id.clone())) {
+                        None => DbImportAction::Insert,
+                        Some(existing) if *existing == values => DbImportAction::Skip,
+                        Some(_) => DbImportAction::Update,
+                    };
+                    imported.insert((row.account, external_id), values);
+                    action
+                }
+                None if imported_fingerprints.contains(&(row.account, fingerprint)) => {
+                    DbImportAction::Skip
+                }
+                None => DbImportAction::Insert,
+            })
+            .collect();
+        Ok(actions)
+    }
+
+    async fn review_queue(
+        &self,
+        threshold: f64,
+        after: Option<(f64, i32)>,
+        limit: i64,
+    ) -> Result<Vec<DbReviewItem>, sqlx::Error> {
+        let (after_confidence, after_id) = after.unzip();
+        sqlx::query_as!(
+            DbReviewItem,
+            r#"
+            SELECT
+                t.id,
+                t.amount,
+                t.description,
+                t.date,
+                t.category_id,
+                c.name as "category_name?",
+                t.predicted_category,
+                t.original_category,
+                t.confidence as "confidence!",
+                t.classifier_model
+            FROM transactions t
+            JOIN categories c ON c.id = t.category_id
+            WHERE t.confidence < $1
+              AND t.category_confirmed_at IS NULL
+              AND ($2::float8 IS NULL OR (t.confidence, t.id) > ($2, $3))
+            ORDER BY t.confidence, t.id
+            LIMIT $4
+            "#,
+            threshold,
+            after_confidence,
+            after_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn confirm_categories(
+        &self,
+        ids: Vec<i32>,
+        actor: Option<String>,
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Api, actor.as_deref()).await?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE transactions
+            SET category_confirmed_at = CURRENT_TIMESTAMP
+            WHERE id = ANY($1) AND category_confirmed_at IS NULL
+            "#,
+            &ids
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn tagged_between(
+        &self,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<Vec<DbTaggedTransaction>, sqlx::Error> {
+        sqlx::query_as!(
+            DbTaggedTransaction,
+            r#"
+            SELECT
+                t.id,
+                t.amount,
+                t.description,
+                t.date,
+                t.account,
+                t.category_id,
+                c.name as "category_name?",
+                t.tags
+            FROM transactions t
+            JOIN categories c ON c.id = t.category_id
+            WHERE t.date BETWEEN $1 AND $2
+            ORDER BY t.date, t.id
+            "#,
+            to_sql_date(start),
+            to_sql_date(end)
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn training_examples(
+        &self,
+        min_confidence: f64,
+    ) -> Result<Vec<DbTrainingExample>, sqlx::Error> {
+        sqlx::query_as!(
+            DbTrainingExample,
+            r#"
+            SELECT
+                t.description,
+                t.amount,
+                t.category_id,
+                c.name as category_name,
+                EXISTS (
+                    SELECT 1 FROM category_corrections cc
+                    WHERE cc.transaction_id = t.id AND cc.new_category_id = t.category_id
+                ) as "corrected!"
+            FROM transactions t
+            JOIN categories c ON c.id = t.category_id
+            WHERE t.confidence IS NULL
+               OR t.confidence >= $1
+               OR t.category_confirmed_at IS NOT NULL
+            ORDER BY t.id
+            "#,
+            min_confidence
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn normalize_descriptions(&self) -> Result<u64, sqlx::Error> {
+        let missing = sqlx::query!(
+            "SELECT id, description FROM transactions WHERE normalized_description IS NULL"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let (ids, normalized): (Vec<i32>, Vec<String>) = missing
+            .into_iter()
+            .map(|row| (row.id, normalize_description(&row.description)))
+            .unzip();
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE transactions t
+            SET normalized_description = n.normalized_description
+            FROM UNNEST($1::int4[], $2::text[]) AS n(id, normalized_description)
+            WHERE t.id = n.id
+            "#,
+            &ids,
+            &normalized
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn corrections(&self) -> Result<Vec<DbCategoryCorrection>, sqlx::Error> {
+        sqlx::query_as!(
+            DbCategoryCorrection,
+            r#"
+            SELECT
+                cc.id,
+                cc.transaction_id,
+                cc.description,
+                cc.amount,
+                cc.old_category_id,
+                old_category.name as "old_category_name?",
+                cc.new_category_id,
+                new_category.name as "new_category_name?",
+                cc.source,
+                cc.rule_id,
+                cc.classifier_model,
+                cc.confidence,
+                cc.corrected_at
+            FROM category_corrections cc
+            JOIN categories old_category ON old_category.id = cc.old_category_id
+            JOIN categories new_category ON new_category.id = cc.new_category_id
+            ORDER BY cc.corrected_at DESC, cc.id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn classifier_accuracy(
+        &self,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<Vec<DbCategoryAccuracy>, sqlx::Error> {
+        // A classified transaction counts for the category the classifier predicted: the
+        // one its first correction changed, or the one it still has
+        sqlx::query_as!(
+            DbCategoryAccuracy,
+            r#"
+            WITH predictions AS (
+                SELECT
+                    COALESCE(first_correction.old_category_id, t.category_id) as category_id,
+                    first_correction.old_category_id IS NOT NULL as corrected
+                FROM transactions t
+                LEFT JOIN LATERAL (
+                    SELECT cc.old_category_id
+                    FROM category_corrections cc
+                    WHERE cc.transaction_id = t.id AND cc.source = 'model'
+                    ORDER BY cc.corrected_at, cc.id
+                    LIMIT 1
+                ) first_correction ON TRUE
+                WHERE t.confidence IS NOT NULL AND t.date BETWEEN $1 AND $2
+            )
+            SELECT
+                p.category_id as "category_id!",
+                c.name as "category_name?",
+                COUNT(*) as "predictions!",
+                COUNT(*) FILTER (WHERE p.corrected) as "corrections!"
+            FROM predictions p
+            JOIN categories c ON c.id = p.category_id
+            GROUP BY p.category_id, c.name
+            ORDER BY c.name
+            "#,
+            to_sql_date(start),
+            to_sql_date(end)
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn account_balance(
+        &self,
+        account: &str,
+        through: &NaiveDate,
+    ) -> Result<BigDecimal, sqlx::Error> {
+        let balance = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(amount), 0) as "balance!"
+            FROM transactions
+            WHERE account = $1 AND date <= $2
+            "#,
+            account,
+            to_sql_date(through)
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(balance.balance)
+    }
+}
+
+#[derive(Clone)]
+pub struct PgImportBatchRepository {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl ImportBatchRepository for PgImportBatchRepository {
+    async fn all(&self) -> Result<Vec<DbImportBatch>, sqlx::Error> {
+        sqlx::query_as!(
+            DbImportBatch,
+            r#"
+            SELECT 
+                id,
+                source_name,
+                file_hash,
+                imported_by,
+                row_count,
+                inserted_count,
+                updated_count,
+                skipped_count,
+                warnings,
+                rolled_back_at,
+                created_at
+            FROM import_batches
+            ORDER BY created_at DESC, id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn by_file_hash(&self, file_hash: &str) -> Result<Vec<DbImportBatch>, sqlx::Error> {
+        sqlx::query_as!(
+            DbImportBatch,
+            r#"
+            SELECT 
+                id,
+                source_name,
+                file_hash,
+                imported_by,
+                row_count,
+                inserted_count,
+                updated_count,
+                skipped_count,
+                warnings,
+                rolled_back_at,
+                created_at
+            FROM import_batches
+            WHERE file_hash = $1
+            ORDER BY created_at DESC, id DESC
+            "#,
+            file_hash
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn rollback(
+        &self,
+        id: i32,
+        actor: Option<String>,
+    ) -> Result<DbImportRollback, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Import, actor.as_deref()).await?;
+
+        // Fails with RowNotFound for an unknown batch
+        sqlx::query!(
+            r#"
+            SELECT id FROM import_batches WHERE id = $1 FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // A transaction was edited after the import when the audit log has a change to it
+        // from anywhere but an import. One imported before the audit log has no entries,
+        // so a later updated_at than created_at is all that says it was edited.
+        let removed = sqlx::query!(
+            r#"
+            DELETE FROM transactions t
+            WHERE t.import_batch_id = $1
+                AND NOT EXISTS (
+                    SELECT 1 FROM audit_log a
+                    WHERE a.entity_type = 'transaction'
+                        AND a.entity_id = t.id
+                        AND a.action = 'update'
+                        AND a.source <> 'import'
+                )
+                AND (
+                    t.updated_at IS NOT DISTINCT FROM t.created_at
+                    OR EXISTS (
+                        SELECT 1 FROM audit_log a
+                        WHERE a.entity_type = 'transaction' AND a.entity_id = t.id
+                    )
+                )
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let kept = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!" FROM transactions WHERE import_batch_id = $1
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .count;
+
+        sqlx::query!(
+            r#"
+            UPDATE import_batches SET rolled_back_at = CURRENT_TIMESTAMP WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(DbImportRollback {
+            removed,
+            kept: kept as u64,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct PgBudgetRepository {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl BudgetRepository for PgBudgetRepository {
+    async fn all(&self) -> Result<Vec<DbBudget>, sqlx::Error> {
+        sqlx::query_as!(
+            DbBudget,
+            r#"
+            SELECT 
+                b.id,
+                b.category_id,
+                c.name as "category_name?",
+                b.period_start,
+                b.period_type,
+                b.amount,
+                b.rollover,
+                b.created_at,
+                b.updated_at
+            FROM budgets b
+            JOIN categories c ON b.category_id = c.id
+            ORDER BY c.name, b.period_start DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn effective_on(&self, date: &NaiveDate) -> Result<Vec<DbBudget>, sqlx::Error> {
+        sqlx::query_as!(
+            DbBudget,
+            r#"
+            SELECT DISTINCT ON (b.category_id)
+                b.id,
+                b.category_id,
+                c.name as "category_name?",
+                b.period_start,
+                b.period_type,
+                b.amount,
+                b.rollover,
+                b.created_at,
+                b.updated_at
+            FROM budgets b
+            JOIN categories c ON b.category_id = c.id
+            WHERE b.period_start <= $1
+            ORDER BY b.category_id, b.period_start DESC
+            "#,
+            to_sql_date(date)
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn create(
+        &self,
+        category_id: i32,
+        period_start: Date,
+        period_type: String,
+        amount: BigDecimal,
+        rollover: bool,
+    ) -> Result<DbBudget, sqlx::Error> {
+        sqlx::query_as!(
+            DbBudget,
+            r#"
+            WITH inserted AS (
+                INSERT INTO budgets (category_id, period_start, period_type, amount, rollover)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING *
+            )
+            SELECT 
+                i.id,
+                i.category_id,
+                c.name as "category_name?",
+                i.period_start,
+                i.period_type,
+                i.amount,
+                i.rollover,
+                i.created_at,
+                i.updated_at
+            FROM inserted i
+            JOIN categories c ON i.category_id = c.id
+            "#,
+            category_id,
+            period_start,
+            period_type,
+            amount,
+            rollover
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn update(
+        &self,
+        id: i32,
+        category_id: i32,
+        period_start: Date,
+        period_type: String,
+        amount: BigDecimal,
+        rollover: Option<bool>,
+    ) -> Result<DbBudget, sqlx::Error> {
+        sqlx::query_as!(
+            DbBudget,
+            r#"
+            WITH updated AS (
+                UPDATE budgets 
+                SET 
+                    category_id = $1,
+                    period_start = $2,
+                    period_type = $3,
+                    amount = $4,
+                    rollover = COALESCE($5, rollover),
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE id = $6
+                RETURNING *
+            )
+            SELECT 
+                u.id,
+                u.category_id,
+                c.name as "category_name?",
+                u.period_start,
+                u.period_type,
+                u.amount,
+                u.rollover,
+                u.created_at,
+                u.updated_at
+            FROM updated u
+            JOIN categories c ON u.category_id = c.id
+            "#,
+            category_id,
+            period_start,
+            period_type,
+            amount,
+            rollover,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn delete(&self, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM budgets WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn assignments_through(
+        &self,
+        end_date: &NaiveDate,
+    ) -> Result<Vec<DbEnvelopeAssignment>, sqlx::Error> {
+        sqlx::query_as!(
+            DbEnvelopeAssignment,
+            r#"
+            SELECT 
+                e.id,
+                e.category_id,
+                c.name as "category_name?",
+                e.month,
+                e.amount,
+                e.created_at,
+                e.updated_at
+            FROM envelope_assignments e
+            JOIN categories c ON e.category_id = c.id
+            WHERE e.month <= $1
+            ORDER BY e.month, c.name
+            "#,
+            to_sql_date(end_date)
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn assign(
+        &self,
+        category_id: i32,
+        month: Date,
+        amount: BigDecimal,
+    ) -> Result<DbEnvelopeAssignment, sqlx::Error> {
+        sqlx::query_as!(
+            DbEnvelopeAssignment,
+            r#"
+            WITH upserted AS (
+                INSERT INTO envelope_assignments (category_id, month, amount)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (category_id, month) DO UPDATE
+                SET amount = EXCLUDED.amount
+                RETURNING *
+            )
+            SELECT 
+                u.id,
+                u.category_id,
+                c.name as "category_name?",
+                u.month,
+                u.amount,
+                u.created_at,
+                u.updated_at
+            FROM upserted u
+            JOIN categories c ON u.category_id = c.id
+            "#,
+            category_id,
+            month,
+            amount
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+}
+
+#[derive(Clone)]
+pub struct PgRecurringTransactionRepository {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl RecurringTransactionRepository for PgRecurringTransactionRepository {
+    async fn all(&self) -> Result<Vec<DbRecurringTransaction>, sqlx::Error> {
+        sqlx::query_as!(
+            DbRecurringTransaction,
+            r#"
+            SELECT 
+                r.id,
+                r.description,
+                r.amount,
+                r.category_id,
+                c.name as "category_name?",
+                r.frequency,
+                r.interval_count,
+                r.day_of_month,
+                r.start_date,
+                r.end_date,
+                r.last_materialized_date,
+                r.active,
+                r.created_at,
+                r.updated_at
+            FROM recurring_transactions r
+            JOIN categories c ON r.category_id = c.id
+            ORDER BY r.description
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn active(&self) -> Result<Vec<DbRecurringTransaction>, sqlx::Error> {
+        sqlx::query_as!(
+            DbRecurringTransaction,
+            r#"
+            SELECT 
+                r.id,
+                r.description,
+                r.amount,
+                r.category_id,
+                c.name as "category_name?",
+                r.frequency,
+                r.interval_count,
+                r.day_of_month,
+                r.start_date,
+                r.end_date,
+                r.last_materialized_date,
+                r.active,
+                r.created_at,
+                r.updated_at
+            FROM recurring_transactions r
+            JOIN categories c ON r.category_id = c.id
+            WHERE r.active
+            ORDER BY r.description
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn create(
+        &self,
+        description: String,
+        amount: BigDecimal,
+        category_id: i32,
+        frequency: String,
+        interval_count: i32,
+        day_of_month: Option<i32>,
+        start_date: Date,
+        end_date: Option<Date>,
+    ) -> Result<DbRecurringTransaction, sqlx::Error> {
+        sqlx::query_as!(
+            DbRecurringTransaction,
+            r#"
+            WITH inserted AS (
+                INSERT INTO recurring_transactions (
+                    description, amount, category_id, frequency, interval_count,
+                    day_of_month, start_date, end_date
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                RETURNING *
+            )
+            SELECT 
+                i.id,
+                i.description,
+                i.amount,
+                i.category_id,
+                c.name as "category_name?",
+                i.frequency,
+                i.interval_count,
+                i.day_of_month,
+                i.start_date,
+                i.end_date,
+                i.last_materialized_date,
+                i.active,
+                i.created_at,
+                i.updated_at
+            FROM inserted i
+            JOIN categories c ON i.category_id = c.id
+            "#,
+            description,
+            amount,
+            category_id,
+            frequency,
+            interval_count,
+            day_of_month,
+            start_date,
+            end_date
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn update(
+        &self,
+        id: i32,
+        description: String,
+        amount: BigDecimal,
+        category_id: i32,
+        frequency: String,
+        interval_count: i32,
+        day_of_month: Option<i32>,
+        start_date: Date,
+        end_date: Option<Date>,
+        active: bool,
+    ) -> Result<DbRecurringTransaction, sqlx::Error> {
+        sqlx::query_as!(
+            DbRecurringTransaction,
+            r#"
+            WITH updated AS (
+                UPDATE recurring_transactions 
+                SET 
+                    description = $1,
+                    amount = $2,
+                    category_id = $3,
+                    frequency = $4,
+                    interval_count = $5,
+                    day_of_month = $6,
+                    start_date = $7,
+                    end_date = $8,
+                    active = $9,
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE id = $10
+                RETURNING *
+            )
+            SELECT 
+                u.id,
+                u.description,
+                u.amount,
+                u.category_id,
+                c.name as "category_name?",
+                u.frequency,
+                u.interval_count,
+                u.day_of_month,
+                u.start_date,
+                u.end_date,
+                u.last_materialized_date,
+                u.active,
+                u.created_at,
+                u.updated_at
+            FROM updated u
+            JOIN categories c ON u.category_id = c.id
+            "#,
+            description,
+            amount,
+            category_id,
+            frequency,
+            interval_count,
+            day_of_month,
+            start_date,
+            end_date,
+            active,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn delete(&self, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM recurring_transactions WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn materialize(
+        &self,
+        id: i32,
+        dates: Vec<NaiveDate>,
+        through: &NaiveDate,
+    ) -> Result<u64, sqlx::Error> {
+        // Inserting the occurrences and moving the pointer together means a restart
+        // either sees both or neither, and the unique index catches any overlap
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Recurring, None).await?;
+
+        let normalized_description = sqlx::query_scalar!(
+            "SELECT description FROM recurring_transactions WHERE id = $1",
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(|description| normalize_description(&description));
+
+        // Occurrences in a closed period are skipped, they can't be created until it's
+        // reopened and the schedule would be stuck on them
+        let closed_through = sqlx::query_scalar!(
+            "SELECT closed_through FROM period_closings ORDER BY id DESC LIMIT 1"
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .flatten()
+        .map(|date| to_naive_date(&date));
+
+        let mut created = 0;
+        for date in dates
+            .iter()
+            .filter(|date| closed_through.is_none_or(|closed| **date > closed))
+        {
+            let result = sqlx::query!(
+                r#"
+                INSERT INTO transactions (
+                    amount, description, date, category_id, recurring_transaction_id,
+                    normalized_description
+                )
+                SELECT amount, description, $2, category_id, id, $3
+                FROM recurring_transactions
+                WHERE id = $1
+                ON CONFLICT DO NOTHING
+                "#,
+                id,
+                to_sql_date(date),
+                normalized_description
+            )
+            .execute(&mut *tx)
+            .await?;
+            created += result.rows_affected();
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE recurring_transactions
+            SET last_materialized_date = $2
+            WHERE id = $1
+            "#,
+            id,
+            to_sql_date(through)
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(created)
+    }
+}
+
+#[derive(Clone)]
+pub struct PgCategorizationRuleRepository {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl CategorizationRuleRepository for PgCategorizationRuleRepository {
+    async fn all(&self) -> Result<Vec<DbCategorizationRule>, sqlx::Error> {
+        sqlx::query_as!(
+            DbCategorizationRule,
+            r#"
+            SELECT
+                r.id,
+                r.name,
+                r.priority,
+                r.description_pattern,
+                r.match_kind,
+                r.min_amount,
+                r.max_amount,
+                r.account,
+                r.sign,
+                r.category_id,
+                c.name as "category_name?",
+                r.tags,
+                r.enabled,
+                r.created_at,
+                r.updated_at
+            FROM categorization_rules r
+            JOIN categories c ON r.category_id = c.id
+            ORDER BY r.priority DESC, r.id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn by_id(&self, id: i32) -> Result<DbCategorizationRule, sqlx::Error> {
+        sqlx::query_as!(
+            DbCategorizationRule,
+            r#"
+            SELECT
+                r.id,
+                r.name,
+                r.priority,
+                r.description_pattern,
+                r.match_kind,
+                r.min_amount,
+                r.max_amount,
+                r.account,
+                r.sign,
+                r.category_id,
+                c.name as "category_name?",
+                r.tags,
+                r.enabled,
+                r.created_at,
+                r.updated_at
+            FROM categorization_rules r
+            JOIN categories c ON r.category_id = c.id
+            WHERE r.id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn create(
+        &self,
+        rule: DbNewCategorizationRule,
+    ) -> Result<DbCategorizationRule, sqlx::Error> {
+        sqlx::query_as!(
+            DbCategorizationRule,
+            r#"
+            WITH inserted AS (
+                INSERT INTO categorization_rules (
+                    name, priority, description_pattern, match_kind, min_amount,
+                    max_amount, account, sign, category_id, tags, enabled
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                RETURNING *
+            )
+            SELECT
+                i.id,
+                i.name,
+                i.priority,
+                i.description_pattern,
+                i.match_kind,
+                i.min_amount,
+                i.max_amount,
+                i.account,
+                i.sign,
+                i.category_id,
+                c.name as "category_name?",
+                i.tags,
+                i.enabled,
+                i.created_at,
+                i.updated_at
+            FROM inserted i
+            JOIN categories c ON i.category_id = c.id
+            "#,
+            rule.name,
+            rule.priority,
+            rule.description_pattern,
+            rule.match_kind,
+            rule.min_amount,
+            rule.max_amount,
+            rule.account,
+            rule.sign,
+            rule.category_id,
+            &rule.tags,
+            rule.enabled
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn update(
+        &self,
+        id: i32,
+        rule: DbNewCategorizationRule,
+    ) -> Result<DbCategorizationRule, sqlx::Error> {
+        sqlx::query_as!(
+            DbCategorizationRule,
+            r#"
+            WITH updated AS (
+                UPDATE categorization_rules
+                SET
+                    name = $1,
+                    priority = $2,
+                    description_pattern = $3,
+                    match_kind = $4,
+                    min_amount = $5,
+                    max_amount = $6,
+                    account = $7,
+                    sign = $8,
+                    category_id = $9,
+                    tags = $10,
+                    enabled = $11,
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE id = $12
+                RETURNING *
+            )
+            SELECT
+                u.id,
+                u.name,
+                u.priority,
+                u.description_pattern,
+                u.match_kind,
+                u.min_amount,
+                u.max_amount,
+                u.account,
+                u.sign,
+                u.category_id,
+                c.name as "category_name?",
+                u.tags,
+                u.enabled,
+                u.created_at,
+                u.updated_at
+            FROM updated u
+            JOIN categories c ON u.category_id = c.id
+            "#,
+            rule.name,
+            rule.priority,
+            rule.description_pattern,
+            rule.match_kind,
+            rule.min_amount,
+            rule.max_amount,
+            rule.account,
+            rule.sign,
+            rule.category_id,
+            &rule.tags,
+            rule.enabled,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn delete(&self, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM categorization_rules WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn apply(
+        &self,
+        application: DbNewRuleApplication,
+        actor: Option<String>,
+    ) -> Result<DbRuleApplication, sqlx::Error> {
+        // The changes and their audit entry are written together
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Rule, actor.as_deref()).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE transactions
+            SET
+                category_id = $2,
+                category_rule_id = $4,
+                tags = ARRAY(
+                    SELECT DISTINCT tag FROM UNNEST(tags || $3::text[]) AS tag ORDER BY tag
+                ),
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = ANY($1)
+            "#,
+            &application.transaction_ids,
+            application.category_id,
+            &application.tags,
+            application.rule_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let applied = sqlx::query_as!(
+            DbRuleApplication,
+            r#"
+            WITH inserted AS (
+                INSERT INTO rule_applications (
+                    rule_id, rule_name, category_id, tags, start_date, end_date,
+                    transaction_ids, previous_category_ids, applied_by
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                RETURNING *
+            )
+            SELECT
+                i.id,
+                i.rule_id,
+                i.rule_name,
+                i.category_id,
+                c.name as "category_name?",
+                i.tags,
+                i.start_date,
+                i.end_date,
+                i.transaction_ids,
+                i.previous_category_ids,
+                i.applied_by,
+                i.applied_at
+            FROM inserted i
+            JOIN categories c ON i.category_id = c.id
+            "#,
+            application.rule_id,
+            application.rule_name,
+            application.category_id,
+            &application.tags,
+            application.start_date,
+            application.end_date,
+            &application.transaction_ids,
+            &application.previous_category_ids,
+            application.applied_by
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(applied)
+    }
+
+    async fn applications(
+        &self,
+        rule_id: Option<i32>,
+    ) -> Result<Vec<DbRuleApplication>, sqlx::Error> {
+        sqlx::query_as!(
+            DbRuleApplication,
+            r#"
+            SELECT
+                a.id,
+                a.rule_id,
+                a.rule_name,
+                a.category_id,
+                c.name as "category_name?",
+                a.tags,
+                a.start_date,
+                a.end_date,
+                a.transaction_ids,
+                a.previous_category_ids,
+                a.applied_by,
+                a.applied_at
+            FROM rule_applications a
+            JOIN categories c ON a.category_id = c.id
+            WHERE $1::int4 IS NULL OR a.rule_id = $1
+            ORDER BY a.applied_at DESC, a.id DESC
+            "#,
+            rule_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+}
+
+#[derive(Clone)]
+pub struct PgAuditLogRepository {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl AuditLogRepository for PgAuditLogRepository {
+    async fn entries(
+        &self,
+        entity_type: &str,
+        entity_id: Option<i32>,
+    ) -> Result<Vec<DbAuditEntry>, sqlx::Error> {
+        sqlx::query_as!(
+            DbAuditEntry,
+            r#"
+            SELECT
+                id,
+                entity_type,
+                entity_id,
+                action,
+                actor,
+                source,
+                before::text as "before?",
+                after::text as "after?",
+                created_at
+            FROM audit_log
+            WHERE entity_type = $1 AND ($2::int4 IS NULL OR entity_id = $2)
+            ORDER BY id DESC
+            "#,
+            entity_type,
+            entity_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn undo(&self, actor: Option<String>) -> Result<Vec<DbAuditEntry>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Undo, actor.as_deref()).await?;
+
+        // Changes made through the api or by applying a rule, not imports, recurring
+        // schedules or earlier undos and redos
+        let operation = sqlx::query!(
+            r#"
+            SELECT a.operation_id as "operation_id!"
+            FROM audit_log a
+            WHERE a.actor = COALESCE($1, session_user)
+                AND a.operation_id IS NOT NULL
+                AND a.entity_type IN ('transaction', 'category')
+                AND a.source IN ('api', 'rule')
+                AND NOT EXISTS (
+                    SELECT 1 FROM undone_operations u WHERE u.operation_id = a.operation_id
+                )
+                AND NOT EXISTS (
+                    SELECT 1 FROM stale_operations s WHERE s.operation_id = a.operation_id
+                )
+            GROUP BY a.operation_id
+            ORDER BY MAX(a.id) DESC
+            LIMIT 1
+            "#,
+            actor
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(operation) = operation else {
+            return Ok(Vec::new());
+        };
+
+        if let Some(conflict) =
+            restore_or_mark_stale(&mut tx, operation.operation_id, true, &actor).await?
+        {
+            tx.commit().await?;
+            return Err(conflict);
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO undone_operations (operation_id, actor, undo_entry_id)
+            SELECT $1, COALESCE($2, session_user), COALESCE(MAX(id), 0)
+            FROM audit_log
+            WHERE operation_id = txid_current()
+            "#,
+            operation.operation_id,
+            actor
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let entries = operation_entries(&mut tx, operation.operation_id).await?;
+        tx.commit().await?;
+        Ok(entries)
+    }
+
+    async fn redo(&self, actor: Option<String>) -> Result<Vec<DbAuditEntry>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        set_audit_context(&mut tx, AuditSource::Redo, actor.as_deref()).await?;
+
+        // A change after the undo starts a new history, like in an editor
+        let operation = sqlx::query!(
+            r#"
+            SELECT u.operation_id
+            FROM undone_operations u
+            WHERE u.actor = COALESCE($1, session_user)
+                AND NOT EXISTS (
+                    SELECT 1 FROM audit_log a
+                    WHERE a.actor = u.actor
+                        AND a.id > u.undo_entry_id
+                        AND a.entity_type IN ('transaction', 'category')
+                        AND a.source IN ('api', 'rule')
+                )
+            ORDER BY u.id DESC
+            LIMIT 1
+            "#,
+            actor
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(operation) = operation else {
+            return Ok(Vec::new());
+        };
+
+        if let Some(conflict) =
+            restore_or_mark_stale(&mut tx, operation.operation_id, false, &actor).await?
+        {
+            tx.commit().await?;
+            return Err(conflict);
+        }
+        sqlx::query!(
+            r#"
+            DELETE FROM undone_operations WHERE operation_id = $1
+            "#,
+            operation.operation_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let entries = operation_entries(&mut tx, operation.operation_id).await?;
+        tx.commit().await?;
+        Ok(entries)
+    }
+}
+
+// What restore_audited_row raises for a row that was changed since its operation, or
+// that it would delete while other rows still use it
+const RESTORE_CONFLICT: &str = "55000";
+
+// Undo or redo an operation. When a row it changed was changed since or is still used,
+// it can never be restored, so instead it's marked stale and taken off the undo and redo
+// stacks, leaving the operations before it, and the conflict is returned for the caller
+// to commit and report. A closed period only fails it until the period is reopened
+async fn restore_or_mark_stale(
+    conn: &mut PgConnection,
+    operation_id: i64,
+    undo: bool,
+    actor: &Option<String>,
+) -> Result<Option<sqlx::Error>, sqlx::Error> {
+    let mut restore = conn.begin().await?;
+    let restored = sqlx::query!(
+        r#"
+        SELECT restore_operation($1, $2) as restored
+        "#,
+        operation_id,
+        undo
+    )
+    .fetch_one(&mut *restore)
+    .await;
+    let error = match restored {
+        Ok(_) => return restore.commit().await.map(|_| None),
+        Err(sqlx::Error::Database(error)) if error.code().as_deref() == Some(RESTORE_CONFLICT) => {
+            error
+        }
+        Err(error) => return Err(error),
+    };
+    restore.rollback().await?;
+
+    sqlx::query!(
+        r#"
+        WITH undone AS (
+            DELETE FROM undone_operations WHERE operation_id = $1
+        )
+        INSERT INTO stale_operations (operation_id, actor, reason)
+        VALUES ($1, COALESCE($2, session_user), $3)
+        "#,
+        operation_id,
+        actor.as_deref(),
+        error.message()
+    )
+    .execute(conn)
+    .await?;
+    Ok(Some(sqlx::Error::Database(error)))
+}
+
+// The entries an operation wrote, newest first
+async fn operation_entries(
+    conn: &mut PgConnection,
+    operation_id: i64,
+) -> Result<Vec<DbAuditEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        DbAuditEntry,
+        r#"
+        SELECT
+            id,
+            entity_type,
+            entity_id,
+            action,
+            actor,
+            source,
+            before::text as "before?",
+            after::text as "after?",
+            created_at
+        FROM audit_log
+        WHERE operation_id = $1 AND entity_type IN ('transaction', 'category')
+        ORDER BY id DESC
+        "#,
+        operation_id
+    )
+    .fetch_all(conn)
+    .await
+}
+
+#[derive(Clone)]
+pub struct PgPeriodClosingRepository {
+    pub pool: PgPool,
+}
+
+impl PgPeriodClosingRepository {
+    // Move the closed period and write the move to the audit log with the closing it replaces.
+    // `label` is stored on the closing, the audit log entry is made by `actor`
+    async fn record(
+        &self,
+        action: AuditAction,
+        closed_through: Option<Date>,
+        label: Option<String>,
+        actor: Option<String>,
+    ) -> Result<DbPeriodClosing, sqlx::Error> {
+        sqlx::query_as!(
+            DbPeriodClosing,
+            r#"
+            WITH previous AS (
+                SELECT * FROM period_closings ORDER BY id DESC LIMIT 1
+            ),
+            inserted AS (
+                INSERT INTO period_closings (closed_through, action, actor)
+                VALUES ($1, $2, $3)
+                RETURNING *
+            ),
+            audit AS (
+                INSERT INTO audit_log (entity_type, entity_id, action, actor, source, before, after)
+                SELECT 'period', i.id, i.action, COALESCE($4, session_user), 'api',
+                    (SELECT to_jsonb(p) FROM previous p), to_jsonb(i)
+                FROM inserted i
+            )
+            SELECT id, closed_through, action, actor, created_at FROM inserted
+            "#,
+            closed_through,
+            action.as_db_str(),
+            label,
+            actor
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+}
+
+#[async_trait]
+impl PeriodClosingRepository for PgPeriodClosingRepository {
+    async fn closings(&self) -> Result<Vec<DbPeriodClosing>, sqlx::Error> {
+        sqlx::query_as!(
+            DbPeriodClosing,
+            r#"
+            SELECT id, closed_through, action, actor, created_at
+            FROM period_closings
+            ORDER BY id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn close(
+        &self,
+        through: Date,
+        closed_by: Option<String>,
+        actor: Option<String>,
+    ) -> Result<DbPeriodClosing, sqlx::Error> {
+        self.record(AuditAction::Close, Some(through), closed_by, actor)
+            .await
+    }
+
+    async fn reopen(
+        &self,
+        from: Date,
+        reopened_by: Option<String>,
+        actor: Option<String>,
+    ) -> Result<DbPeriodClosing, sqlx::Error> {
+        self.record(AuditAction::Reopen, from.previous_day(), reopened_by, actor)
+            .await
+    }
 }
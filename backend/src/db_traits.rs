@@ -1,9 +1,19 @@
-use crate::db_models::{DbCategory, DbCategorySummary, DbTransaction};
+use crate::db_models::{
+    DbAuditEntry, DbBudget, DbCategorizationRule, DbCategory, DbCategoryAccuracy, DbCategoryAlias,
+    DbCategoryCorrection, DbCategorySummary, DbEnvelopeAssignment, DbImportAction, DbImportBatch,
+    DbImportRollback, DbImportRow, DbMonthlyCategoryTotal, DbNewCategorizationRule,
+    DbNewImportBatch, DbNewRuleApplication, DbPeriodClosing, DbPeriodComparison,
+    DbRecurringTransaction, DbReviewItem, DbRuleApplication, DbTaggedTransaction,
+    DbTrainingExample, DbTransaction,
+};
 use async_trait::async_trait;
 use chrono::NaiveDate;
-use sqlx::types::time::Date;
+use sqlx::types::time::{Date, OffsetDateTime};
 use sqlx::types::BigDecimal;
+use tokio::sync::mpsc::UnboundedSender;
 
+/// Writes take the `actor` who makes them for the audit log, which falls back to the
+/// database user without one
 #[cfg_attr(feature = "test-utils", mockall::automock)]
 #[async_trait]
 pub trait CategoryRepository: Send + Sync {
@@ -15,6 +25,7 @@ pub trait CategoryRepository: Send + Sync {
         description: Option<String>,
         icon: Option<String>,
         color: Option<String>,
+        actor: Option<String>,
     ) -> Result<DbCategory, sqlx::Error>;
     async fn update(
         &self,
@@ -23,9 +34,22 @@ pub trait CategoryRepository: Send + Sync {
         description: Option<String>,
         icon: Option<String>,
         color: Option<String>,
+        actor: Option<String>,
     ) -> Result<DbCategory, sqlx::Error>;
+
+    async fn aliases(&self) -> Result<Vec<DbCategoryAlias>, sqlx::Error>;
+
+    /// Map `name` to the category, replacing the category it mapped to before
+    async fn set_alias(
+        &self,
+        name: String,
+        category_id: i32,
+    ) -> Result<DbCategoryAlias, sqlx::Error>;
+
+    async fn delete_alias(&self, id: i32) -> Result<bool, sqlx::Error>;
 }
 
+/// Writes take the `actor` who makes them for the audit log, as `CategoryRepository`'s do
 #[cfg_attr(feature = "test-utils", mockall::automock)]
 #[async_trait]
 pub trait TransactionRepository: Send + Sync {
@@ -37,6 +61,18 @@ pub trait TransactionRepository: Send + Sync {
         description: String,
         date: Date,
         category_id: i32,
+        actor: Option<String>,
+    ) -> Result<DbTransaction, sqlx::Error>;
+
+    /// Create a transaction in the category a categorization rule chose, remembering the
+    /// rule and adding its tags
+    async fn create_by_rule(
+        &self,
+        amount: BigDecimal,
+        description: String,
+        date: Date,
+        rule: DbCategorizationRule,
+        actor: Option<String>,
     ) -> Result<DbTransaction, sqlx::Error>;
 
     async fn update(
@@ -46,8 +82,12 @@ pub trait TransactionRepository: Send + Sync {
         description: String,
         date: Date,
         category_id: i32,
+        actor: Option<String>,
     ) -> Result<DbTransaction, sqlx::Error>;
 
+    /// Delete a transaction, false when there's none with the id
+    async fn delete(&self, id: i32, actor: Option<String>) -> Result<bool, sqlx::Error>;
+
     async fn by_category_id(&self, category_id: i32) -> Result<Vec<DbTransaction>, sqlx::Error>;
 
     async fn by_date_range(
@@ -61,4 +101,337 @@ pub trait TransactionRepository: Send + Sync {
         start_date: &NaiveDate,
         end_date: &NaiveDate,
     ) -> Result<Vec<DbCategorySummary>, sqlx::Error>;
+
+    /// `all` as the transactions stood at `as_of`, rebuilt from the audit log
+    async fn all_as_of(&self, as_of: &OffsetDateTime) -> Result<Vec<DbTransaction>, sqlx::Error>;
+
+    /// `by_category_id` as the transactions stood at `as_of`
+    async fn by_category_id_as_of(
+        &self,
+        category_id: i32,
+        as_of: &OffsetDateTime,
+    ) -> Result<Vec<DbTransaction>, sqlx::Error>;
+
+    /// `by_date_range` as the transactions stood at `as_of`
+    async fn by_date_range_as_of(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+        as_of: &OffsetDateTime,
+    ) -> Result<Vec<DbTransaction>, sqlx::Error>;
+
+    /// `sum_by_category` as the transactions and categories stood at `as_of`
+    async fn sum_by_category_as_of(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+        as_of: &OffsetDateTime,
+    ) -> Result<Vec<DbCategorySummary>, sqlx::Error>;
+
+    async fn compare_by_category(
+        &self,
+        period_a_start: &NaiveDate,
+        period_a_end: &NaiveDate,
+        period_b_start: &NaiveDate,
+        period_b_end: &NaiveDate,
+    ) -> Result<Vec<DbPeriodComparison>, sqlx::Error>;
+
+    async fn compare_by_description(
+        &self,
+        period_a_start: &NaiveDate,
+        period_a_end: &NaiveDate,
+        period_b_start: &NaiveDate,
+        period_b_end: &NaiveDate,
+    ) -> Result<Vec<DbPeriodComparison>, sqlx::Error>;
+
+    /// Net amount per category for every month in the range
+    async fn monthly_totals_by_category(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> Result<Vec<DbMonthlyCategoryTotal>, sqlx::Error>;
+
+    /// Groups of transactions with the same amount and normalized description
+    /// whose dates are at most `window_days` apart
+    async fn find_duplicates(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+        window_days: i64,
+    ) -> Result<Vec<Vec<DbTransaction>>, sqlx::Error>;
+
+    /// Delete the transactions in `remove_ids` that have the same amount as `keep_id`,
+    /// returning the number deleted
+    async fn resolve_duplicates(
+        &self,
+        keep_id: i32,
+        remove_ids: Vec<i32>,
+        actor: Option<String>,
+    ) -> Result<u64, sqlx::Error>;
+
+    /// Insert the rows in one database transaction, recorded as a new import batch.
+    /// Rows with an external id that was imported before are updated, rows without one
+    /// are skipped if their fingerprint was imported before
+    async fn import_transactions(
+        &self,
+        batch: DbNewImportBatch,
+        rows: Vec<DbImportRow>,
+        actor: Option<String>,
+    ) -> Result<DbImportBatch, sqlx::Error>;
+
+    /// `import_transactions`, sending the number of rows written so far to `progress`
+    /// after each chunk of rows
+    async fn import_transactions_with_progress(
+        &self,
+        batch: DbNewImportBatch,
+        rows: Vec<DbImportRow>,
+        progress: UnboundedSender<usize>,
+        actor: Option<String>,
+    ) -> Result<DbImportBatch, sqlx::Error>;
+
+    /// What `import_transactions` would do with each row, without writing anything
+    async fn preview_import(
+        &self,
+        rows: Vec<DbImportRow>,
+    ) -> Result<Vec<DbImportAction>, sqlx::Error>;
+
+    /// Unconfirmed transactions predicted with less than `threshold` confidence, least
+    /// confident first, starting after the (confidence, id) of the last one seen
+    async fn review_queue(
+        &self,
+        threshold: f64,
+        after: Option<(f64, i32)>,
+        limit: i64,
+    ) -> Result<Vec<DbReviewItem>, sqlx::Error>;
+
+    /// Mark the transactions' categories as checked, returning how many weren't already
+    async fn confirm_categories(
+        &self,
+        ids: Vec<i32>,
+        actor: Option<String>,
+    ) -> Result<u64, sqlx::Error>;
+
+    /// Transactions dated from `start` through `end` with their account and tags, for
+    /// categorization rules to be applied to
+    async fn tagged_between(
+        &self,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<Vec<DbTaggedTransaction>, sqlx::Error>;
+
+    /// Every categorized transaction except unconfirmed predictions with less than
+    /// `min_confidence`, for the category classifier to be trained on
+    async fn training_examples(
+        &self,
+        min_confidence: f64,
+    ) -> Result<Vec<DbTrainingExample>, sqlx::Error>;
+
+    /// Fill in the normalized description of transactions written without one,
+    /// returning how many there were
+    async fn normalize_descriptions(&self) -> Result<u64, sqlx::Error>;
+
+    /// Every category changed through `update`, newest first
+    async fn corrections(&self) -> Result<Vec<DbCategoryCorrection>, sqlx::Error>;
+
+    /// Per predicted category, how many transactions dated from `start` through `end` the
+    /// classifier categorized and how many of those were corrected
+    async fn classifier_accuracy(
+        &self,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<Vec<DbCategoryAccuracy>, sqlx::Error>;
+
+    /// Sum of every transaction in the account dated on or before `through`
+    async fn account_balance(
+        &self,
+        account: &str,
+        through: &NaiveDate,
+    ) -> Result<BigDecimal, sqlx::Error>;
+}
+
+#[cfg_attr(feature = "test-utils", mockall::automock)]
+#[async_trait]
+pub trait ImportBatchRepository: Send + Sync {
+    /// All import batches, newest first
+    async fn all(&self) -> Result<Vec<DbImportBatch>, sqlx::Error>;
+
+    async fn by_file_hash(&self, file_hash: &str) -> Result<Vec<DbImportBatch>, sqlx::Error>;
+
+    /// Delete the transactions the batch inserted, except those edited since the import,
+    /// audited under `actor`
+    async fn rollback(
+        &self,
+        id: i32,
+        actor: Option<String>,
+    ) -> Result<DbImportRollback, sqlx::Error>;
+}
+
+#[cfg_attr(feature = "test-utils", mockall::automock)]
+#[async_trait]
+pub trait BudgetRepository: Send + Sync {
+    async fn all(&self) -> Result<Vec<DbBudget>, sqlx::Error>;
+
+    /// The budget in effect for each category on the given date
+    async fn effective_on(&self, date: &NaiveDate) -> Result<Vec<DbBudget>, sqlx::Error>;
+
+    async fn create(
+        &self,
+        category_id: i32,
+        period_start: Date,
+        period_type: String,
+        amount: BigDecimal,
+        rollover: bool,
+    ) -> Result<DbBudget, sqlx::Error>;
+
+    async fn update(
+        &self,
+        id: i32,
+        category_id: i32,
+        period_start: Date,
+        period_type: String,
+        amount: BigDecimal,
+        // None keeps the budget's rollover as it is
+        rollover: Option<bool>,
+    ) -> Result<DbBudget, sqlx::Error>;
+
+    async fn delete(&self, id: i32) -> Result<bool, sqlx::Error>;
+
+    /// Envelope assignments for every month up to and including the given date
+    async fn assignments_through(
+        &self,
+        end_date: &NaiveDate,
+    ) -> Result<Vec<DbEnvelopeAssignment>, sqlx::Error>;
+
+    /// Set the amount assigned to a category for a month, replacing any earlier assignment
+    async fn assign(
+        &self,
+        category_id: i32,
+        month: Date,
+        amount: BigDecimal,
+    ) -> Result<DbEnvelopeAssignment, sqlx::Error>;
+}
+
+#[cfg_attr(feature = "test-utils", mockall::automock)]
+#[async_trait]
+pub trait RecurringTransactionRepository: Send + Sync {
+    async fn all(&self) -> Result<Vec<DbRecurringTransaction>, sqlx::Error>;
+
+    async fn active(&self) -> Result<Vec<DbRecurringTransaction>, sqlx::Error>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn create(
+        &self,
+        description: String,
+        amount: BigDecimal,
+        category_id: i32,
+        frequency: String,
+        interval_count: i32,
+        day_of_month: Option<i32>,
+        start_date: Date,
+        end_date: Option<Date>,
+    ) -> Result<DbRecurringTransaction, sqlx::Error>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn update(
+        &self,
+        id: i32,
+        description: String,
+        amount: BigDecimal,
+        category_id: i32,
+        frequency: String,
+        interval_count: i32,
+        day_of_month: Option<i32>,
+        start_date: Date,
+        end_date: Option<Date>,
+        active: bool,
+    ) -> Result<DbRecurringTransaction, sqlx::Error>;
+
+    async fn delete(&self, id: i32) -> Result<bool, sqlx::Error>;
+
+    /// Insert the occurrences on `dates` that do not exist yet and aren't in a closed
+    /// period, and record that the schedule has been materialized through `through`,
+    /// returning the number inserted
+    async fn materialize(
+        &self,
+        id: i32,
+        dates: Vec<NaiveDate>,
+        through: &NaiveDate,
+    ) -> Result<u64, sqlx::Error>;
+}
+
+#[cfg_attr(feature = "test-utils", mockall::automock)]
+#[async_trait]
+pub trait CategorizationRuleRepository: Send + Sync {
+    /// Every rule, in the order they're tried: highest priority first
+    async fn all(&self) -> Result<Vec<DbCategorizationRule>, sqlx::Error>;
+
+    async fn by_id(&self, id: i32) -> Result<DbCategorizationRule, sqlx::Error>;
+
+    async fn create(
+        &self,
+        rule: DbNewCategorizationRule,
+    ) -> Result<DbCategorizationRule, sqlx::Error>;
+
+    async fn update(
+        &self,
+        id: i32,
+        rule: DbNewCategorizationRule,
+    ) -> Result<DbCategorizationRule, sqlx::Error>;
+
+    async fn delete(&self, id: i32) -> Result<bool, sqlx::Error>;
+
+    /// Give the transactions the rule's category and tags, and record that the rule was applied
+    async fn apply(
+        &self,
+        application: DbNewRuleApplication,
+        actor: Option<String>,
+    ) -> Result<DbRuleApplication, sqlx::Error>;
+
+    /// Every time a rule, or any rule when None, was applied, newest first
+    async fn applications(
+        &self,
+        rule_id: Option<i32>,
+    ) -> Result<Vec<DbRuleApplication>, sqlx::Error>;
+}
+
+#[cfg_attr(feature = "test-utils", mockall::automock)]
+#[async_trait]
+pub trait AuditLogRepository: Send + Sync {
+    /// The changes to entities of the type, or to the one with `entity_id`, newest first
+    async fn entries(
+        &self,
+        entity_type: &str,
+        entity_id: Option<i32>,
+    ) -> Result<Vec<DbAuditEntry>, sqlx::Error>;
+
+    /// Revert the actor's newest operation that isn't undone yet, the database user's
+    /// without an actor. Returns its entries, newest first, or none when there's nothing
+    /// to undo
+    async fn undo(&self, actor: Option<String>) -> Result<Vec<DbAuditEntry>, sqlx::Error>;
+
+    /// Make the operation the actor undid last again, unless they changed something since
+    async fn redo(&self, actor: Option<String>) -> Result<Vec<DbAuditEntry>, sqlx::Error>;
+}
+
+#[cfg_attr(feature = "test-utils", mockall::automock)]
+#[async_trait]
+pub trait PeriodClosingRepository: Send + Sync {
+    /// Every time the closed period moved, newest first. The newest is in effect
+    async fn closings(&self) -> Result<Vec<DbPeriodClosing>, sqlx::Error>;
+
+    /// Close every transaction dated on or before `through`
+    async fn close(
+        &self,
+        through: Date,
+        closed_by: Option<String>,
+        actor: Option<String>,
+    ) -> Result<DbPeriodClosing, sqlx::Error>;
+
+    /// Reopen the transactions dated from `from` on, keeping the ones before it closed
+    async fn reopen(
+        &self,
+        from: Date,
+        reopened_by: Option<String>,
+        actor: Option<String>,
+    ) -> Result<DbPeriodClosing, sqlx::Error>;
 }
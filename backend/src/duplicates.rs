@@ -0,0 +1,65 @@
+use crate::db_models::{to_naive_date, DbTransaction};
+use crate::normalize::normalize_description;
+use juniper::GraphQLEnum;
+use std::collections::BTreeMap;
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum DuplicateStrategy {
+    // same amount, normalized description and date
+    Exact,
+    // same amount and normalized description within a window of days
+    DateWindow,
+}
+
+// A bank can post the same charge on consecutive days, so the window covers a long weekend
+pub const DEFAULT_WINDOW_DAYS: i64 = 3;
+
+impl DuplicateStrategy {
+    pub fn window_days(&self, window_days: Option<i64>) -> i64 {
+        match self {
+            DuplicateStrategy::Exact => 0,
+            DuplicateStrategy::DateWindow => window_days.unwrap_or(DEFAULT_WINDOW_DAYS),
+        }
+    }
+}
+
+/// Group transactions with the same amount and normalized description where each
+/// is at most `window_days` after the previous one. Only groups of two or more are
+/// returned, oldest transaction first
+pub fn group_duplicates(
+    transactions: Vec<DbTransaction>,
+    window_days: i64,
+) -> Vec<Vec<DbTransaction>> {
+    let mut by_key: BTreeMap<(String, String), Vec<DbTransaction>> = BTreeMap::new();
+    for tx in transactions {
+        let key = (
+            normalize_description(&tx.description),
+            tx.amount.normalized().to_string(),
+        );
+        by_key.entry(key).or_default().push(tx);
+    }
+
+    let mut groups = Vec::new();
+    for (_, mut candidates) in by_key {
+        candidates.sort_by_key(|tx| (tx.date, tx.id));
+
+        let mut group: Vec<DbTransaction> = Vec::new();
+        for tx in candidates {
+            let within_window = group.last().is_some_and(|previous| {
+                (to_naive_date(&tx.date) - to_naive_date(&previous.date)).num_days() <= window_days
+            });
+            if !within_window && group.len() > 1 {
+                groups.push(std::mem::take(&mut group));
+            } else if !within_window {
+                group.clear();
+            }
+            group.push(tx);
+        }
+        if group.len() > 1 {
+            groups.push(group);
+        }
+    }
+
+    groups.sort_by_key(|group| (group[0].date, group[0].id));
+    groups
+}
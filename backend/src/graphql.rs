@@ -1,15 +1,36 @@
-use crate::db_models::{DbCategory, DbCategorySummary, DbTransaction};
-use crate::db_traits::{CategoryRepository, TransactionRepository};
-use chrono::{NaiveDate, NaiveDateTime};
-use juniper::{FieldResult, GraphQLObject};
+use crate::audit::{AuditAction, AuditEntity, AuditSource};
+use crate::classifier::{CategoryClassifier, SharedClassifier};
+use crate::db_models::{
+    to_naive_date, to_sql_date, to_sql_timestamp, DbAuditEntry, DbBudget, DbCategorizationRule,
+    DbCategory, DbCategoryAccuracy, DbCategoryAlias, DbCategoryCorrection, DbCategorySummary,
+    DbClassification, DbEnvelopeAssignment, DbImportBatch, DbImportRow, DbMonthlyCategoryTotal,
+    DbNewCategorizationRule, DbNewImportBatch, DbNewRuleApplication, DbPeriodClosing,
+    DbPeriodComparison, DbRecurringTransaction, DbReviewItem, DbRuleApplication, DbTransaction,
+};
+use crate::db_traits::{
+    AuditLogRepository, BudgetRepository, CategorizationRuleRepository, CategoryRepository,
+    ImportBatchRepository, PeriodClosingRepository, RecurringTransactionRepository,
+    TransactionRepository,
+};
+use crate::duplicates::DuplicateStrategy;
+use crate::import::DEFAULT_ACCOUNT;
+use crate::normalize::normalize_description;
+use crate::recurrence::{detect_recurring, Frequency, RecurringCandidate, Schedule};
+use crate::rules::{
+    compile_pattern, rule_changes, suggest_rules, AmountSign, CorrectionSource, RuleChange,
+    RuleMatchKind, RuleSet, RuleSuggestion as DbRuleSuggestion,
+};
+use chrono::{DateTime, Datelike, Months, NaiveDate, NaiveDateTime, Utc};
+use juniper::{FieldResult, GraphQLEnum, GraphQLInputObject, GraphQLObject};
 use rust_decimal::prelude::ToPrimitive;
 use sqlx::types::time::Date;
 use sqlx::types::BigDecimal;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use time::macros::format_description;
 
-#[derive(GraphQLObject)]
 pub struct Transaction {
     pub id: i32,
     pub amount: f64,
@@ -21,6 +42,51 @@ pub struct Transaction {
     pub updated_at: Option<NaiveDateTime>,
 }
 
+#[juniper::graphql_object(Context = GraphQLContext)]
+impl Transaction {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    fn category_id(&self) -> i32 {
+        self.category_id
+    }
+
+    fn category_name(&self) -> Option<&str> {
+        self.category_name.as_deref()
+    }
+
+    fn created_at(&self) -> Option<NaiveDateTime> {
+        self.created_at
+    }
+
+    fn updated_at(&self) -> Option<NaiveDateTime> {
+        self.updated_at
+    }
+
+    #[graphql(description = "Every change to the transaction, newest first")]
+    async fn history(&self, context: &GraphQLContext) -> FieldResult<Vec<AuditEntry>> {
+        context
+            .audit_log_repository
+            .entries(AuditEntity::Transaction.as_db_str(), Some(self.id))
+            .await
+            .map_err(Into::into)
+            .map(|entries| entries.into_iter().map(Into::into).collect())
+    }
+}
+
 #[derive(GraphQLObject)]
 pub struct Category {
     pub id: i32,
@@ -32,36 +98,1059 @@ pub struct Category {
     pub updated_at: Option<NaiveDateTime>,
 }
 
-#[derive(GraphQLObject)]
-pub struct CategorySummary {
-    pub category_id: i32,
-    pub category_name: String,
-    pub total_amount: f64,
-    pub transaction_count: i32,
+#[derive(GraphQLObject)]
+#[graphql(description = "Another name for a category, as a bank or the classifier calls it")]
+pub struct CategoryAlias {
+    pub id: i32,
+    pub name: String,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject)]
+pub struct CategorySummary {
+    pub category_id: i32,
+    pub category_name: String,
+    pub total_amount: f64,
+    pub transaction_count: i32,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(description = "A transaction whose predicted category is waiting to be confirmed")]
+pub struct ReviewItem {
+    pub id: i32,
+    pub amount: f64,
+    pub description: String,
+    pub date: NaiveDate,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub predicted_category: Option<String>,
+    pub original_category: Option<String>,
+    pub confidence: f64,
+    pub model: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+pub struct ReviewQueue {
+    pub items: Vec<ReviewItem>,
+    #[graphql(description = "Pass as `after` to get the next page")]
+    pub end_cursor: Option<String>,
+    pub has_next_page: bool,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(description = "A category the classifier suggests for a transaction")]
+pub struct CategorySuggestion {
+    pub category_id: i32,
+    pub category_name: String,
+    pub probability: f64,
+}
+
+impl From<crate::classifier::CategorySuggestion> for CategorySuggestion {
+    fn from(suggestion: crate::classifier::CategorySuggestion) -> Self {
+        Self {
+            category_id: suggestion.category_id,
+            category_name: suggestion.category_name,
+            probability: suggestion.probability,
+        }
+    }
+}
+
+#[derive(GraphQLObject)]
+#[graphql(description = "What the category classifier was trained on")]
+pub struct ClassifierInfo {
+    pub examples: i32,
+    pub categories: i32,
+    pub vocabulary_size: i32,
+    pub trained_at: NaiveDateTime,
+}
+
+impl From<&CategoryClassifier> for ClassifierInfo {
+    fn from(classifier: &CategoryClassifier) -> Self {
+        Self {
+            examples: classifier.examples as i32,
+            categories: classifier.category_count() as i32,
+            vocabulary_size: classifier.vocabulary_size() as i32,
+            trained_at: classifier.trained_at,
+        }
+    }
+}
+
+// Transactions predicted with less confidence than this are reviewed, and left out of
+// the classifier's training until they're confirmed
+const DEFAULT_REVIEW_THRESHOLD: f64 = 0.6;
+const DEFAULT_REVIEW_PAGE_SIZE: i32 = 50;
+const MAX_REVIEW_PAGE_SIZE: i32 = 500;
+
+// A mover must change by at least this percentage and amount to be flagged significant
+const DEFAULT_SIGNIFICANT_PERCENT: f64 = 25.0;
+const DEFAULT_SIGNIFICANT_AMOUNT: f64 = 50.0;
+
+// Transactions in this category fund the envelopes
+const INCOME_CATEGORY: &str = "Income";
+
+// Import batches created through the API rather than from a file
+const API_IMPORT_SOURCE: &str = "api";
+
+// Category suggestions returned when the query doesn't say how many
+const DEFAULT_SUGGESTIONS: i32 = 3;
+
+// Recurring charge detection looks for at least 3 charges in the last year by default
+const DEFAULT_MIN_OCCURRENCES: i32 = 3;
+const DEFAULT_LOOKBACK_MONTHS: i32 = 12;
+const MAX_LOOKBACK_MONTHS: i32 = 1200;
+
+// Upcoming occurrences are listed up to ten years ahead
+const MAX_UPCOMING_DAYS: i32 = 3660;
+
+// A recurring schedule repeats at least every hundred periods
+const MAX_SCHEDULE_INTERVAL: i32 = 100;
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SpendingGroupBy {
+    Category,
+    Description,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct PeriodInput {
+    pub start_date: String,
+    pub end_date: String,
+}
+
+impl PeriodInput {
+    fn parse(&self) -> Result<(NaiveDate, NaiveDate), String> {
+        let start_date = NaiveDate::parse_from_str(&self.start_date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid start date format: {}, expected YYYY-MM-DD", e))?;
+        let end_date = NaiveDate::parse_from_str(&self.end_date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid end date format: {}, expected YYYY-MM-DD", e))?;
+        Ok((start_date, end_date))
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct SpendingComparison {
+    pub category_id: Option<i32>,
+    pub group_name: String,
+    pub period_a_total: f64,
+    pub period_a_count: i32,
+    pub period_b_total: f64,
+    pub period_b_count: i32,
+    pub absolute_change: f64,
+    // null when period A has no spending to compare against
+    pub percent_change: Option<f64>,
+    pub significant: bool,
+}
+
+impl SpendingComparison {
+    fn from_db(cmp: DbPeriodComparison, significant_percent: f64, significant_amount: f64) -> Self {
+        let period_a_total = cmp.period_a_total.and_then(|v| v.to_f64()).unwrap_or(0.0);
+        let period_b_total = cmp.period_b_total.and_then(|v| v.to_f64()).unwrap_or(0.0);
+        let absolute_change = period_b_total - period_a_total;
+        let percent_change = if period_a_total == 0.0 {
+            None
+        } else {
+            Some(absolute_change / period_a_total.abs() * 100.0)
+        };
+        let significant = absolute_change.abs() >= significant_amount
+            && percent_change.is_none_or(|pct| pct.abs() >= significant_percent);
+
+        Self {
+            category_id: cmp.category_id,
+            group_name: cmp.group_name.unwrap_or_default(),
+            period_a_total,
+            period_a_count: cmp.period_a_count.and_then(|c| c.to_i32()).unwrap_or(0),
+            period_b_total,
+            period_b_count: cmp.period_b_count.and_then(|c| c.to_i32()).unwrap_or(0),
+            absolute_change,
+            percent_change,
+            significant,
+        }
+    }
+}
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum BudgetPeriod {
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl BudgetPeriod {
+    fn as_db_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Monthly => "monthly",
+            BudgetPeriod::Quarterly => "quarterly",
+            BudgetPeriod::Yearly => "yearly",
+        }
+    }
+
+    fn from_db_str(value: &str) -> Self {
+        match value {
+            "quarterly" => BudgetPeriod::Quarterly,
+            "yearly" => BudgetPeriod::Yearly,
+            _ => BudgetPeriod::Monthly,
+        }
+    }
+
+    fn months(&self) -> u32 {
+        match self {
+            BudgetPeriod::Monthly => 1,
+            BudgetPeriod::Quarterly => 3,
+            BudgetPeriod::Yearly => 12,
+        }
+    }
+
+    /// The first and last day of the period that contains `month`, with
+    /// periods counted from the budget's `period_start`
+    fn bounds(&self, period_start: NaiveDate, month: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let months_since = (month.year() - period_start.year()) * 12 + month.month() as i32
+            - period_start.month() as i32;
+        let offset = months_since.max(0) as u32 / self.months() * self.months();
+        let start = period_start + Months::new(offset);
+        let end = start + Months::new(self.months()) - chrono::Duration::days(1);
+        (start, end)
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct Budget {
+    pub id: i32,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub period_start: NaiveDate,
+    pub period_type: BudgetPeriod,
+    pub amount: f64,
+    pub rollover: bool,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject)]
+pub struct BudgetStatus {
+    pub budget_id: i32,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub budget_amount: f64,
+    pub rollover: bool,
+    // unspent (positive) or overspent (negative) budget from earlier months
+    pub carried_over: f64,
+    // net outflow for the category in the period, debits are stored as negative amounts
+    pub actual: f64,
+    pub remaining: f64,
+    pub percent_used: Option<f64>,
+    pub projected: f64,
+}
+
+impl BudgetStatus {
+    fn new(
+        budget: &DbBudget,
+        period_start: NaiveDate,
+        period_end: NaiveDate,
+        carried_over: f64,
+        actual: f64,
+        today: NaiveDate,
+    ) -> Self {
+        let budget_amount = budget.amount.to_f64().unwrap_or(0.0);
+        let available = budget_amount + carried_over;
+        let percent_used = if available == 0.0 {
+            None
+        } else {
+            Some(actual / available * 100.0)
+        };
+
+        // Extrapolate the spend so far over the whole period while it is in progress
+        let projected = if today < period_start || today >= period_end {
+            actual
+        } else {
+            let elapsed_days = (today - period_start).num_days() + 1;
+            let total_days = (period_end - period_start).num_days() + 1;
+            actual / elapsed_days as f64 * total_days as f64
+        };
+
+        Self {
+            budget_id: budget.id,
+            category_id: budget.category_id,
+            category_name: budget.category_name.clone(),
+            period_start,
+            period_end,
+            budget_amount,
+            rollover: budget.rollover,
+            carried_over,
+            actual,
+            remaining: available - actual,
+            percent_used,
+            projected,
+        }
+    }
+}
+
+/// Unspent or overspent budget carried into `month` for a category. The carry
+/// starts over after any month whose budget was not a monthly rollover budget.
+fn rollover_carry(
+    category_id: i32,
+    history: &[DbBudget],
+    monthly_totals: &HashMap<(i32, NaiveDate), f64>,
+    month: NaiveDate,
+) -> f64 {
+    let mut budgets: Vec<&DbBudget> = history
+        .iter()
+        .filter(|b| b.category_id == category_id)
+        .collect();
+    budgets.sort_by_key(|b| b.period_start);
+
+    let mut carry = 0.0;
+    let Some(first) = budgets.first() else {
+        return carry;
+    };
+    let mut current = to_naive_date(&first.period_start);
+    while current < month {
+        let effective = budgets
+            .iter()
+            .rev()
+            .find(|b| to_naive_date(&b.period_start) <= current);
+        match effective {
+            Some(budget)
+                if budget.rollover
+                    && BudgetPeriod::from_db_str(&budget.period_type) == BudgetPeriod::Monthly =>
+            {
+                let spent = -monthly_totals
+                    .get(&(category_id, current))
+                    .copied()
+                    .unwrap_or(0.0);
+                carry += budget.amount.to_f64().unwrap_or(0.0) - spent;
+            }
+            _ => carry = 0.0,
+        }
+        current = current + Months::new(1);
+    }
+    carry
+}
+
+#[derive(GraphQLObject)]
+pub struct EnvelopeAssignment {
+    pub id: i32,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub month: NaiveDate,
+    pub amount: f64,
+}
+
+#[derive(GraphQLObject)]
+pub struct EnvelopeBalance {
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub carried_in: f64,
+    pub assigned: f64,
+    // net transactions in the month, spending is negative
+    pub activity: f64,
+    pub available: f64,
+}
+
+#[derive(GraphQLObject)]
+pub struct EnvelopeMonth {
+    pub month: NaiveDate,
+    pub income: f64,
+    pub assigned: f64,
+    // all income so far less everything assigned so far
+    pub available_to_assign: f64,
+    pub envelopes: Vec<EnvelopeBalance>,
+}
+
+/// Walk every month from `first` to `end`, carrying each envelope's balance
+/// forward, and return the months from `start` onwards
+fn build_envelope_ledger(
+    assignments: &[DbEnvelopeAssignment],
+    monthly_totals: &[DbMonthlyCategoryTotal],
+    income_category_id: Option<i32>,
+    first: NaiveDate,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Vec<EnvelopeMonth> {
+    let mut assigned: HashMap<(i32, NaiveDate), f64> = HashMap::new();
+    let mut envelopes: Vec<(i32, Option<String>, NaiveDate)> = Vec::new();
+    for assignment in assignments {
+        let month = to_naive_date(&assignment.month);
+        *assigned
+            .entry((assignment.category_id, month))
+            .or_insert(0.0) += assignment.amount.to_f64().unwrap_or(0.0);
+        match envelopes
+            .iter_mut()
+            .find(|(id, _, _)| *id == assignment.category_id)
+        {
+            Some((_, _, opened)) => *opened = (*opened).min(month),
+            None => envelopes.push((
+                assignment.category_id,
+                assignment.category_name.clone(),
+                month,
+            )),
+        }
+    }
+    envelopes.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let activity: HashMap<(i32, NaiveDate), f64> = monthly_totals
+        .iter()
+        .map(|t| {
+            (
+                (t.category_id, to_naive_date(&t.month)),
+                t.total_amount.to_f64().unwrap_or(0.0),
+            )
+        })
+        .collect();
+
+    let mut balances: HashMap<i32, f64> = HashMap::new();
+    let mut total_income = 0.0;
+    let mut total_assigned = 0.0;
+    let mut ledger = Vec::new();
+    let mut month = first;
+    while month <= end {
+        let income = income_category_id
+            .and_then(|id| activity.get(&(id, month)).copied())
+            .unwrap_or(0.0);
+        total_income += income;
+
+        let mut month_assigned = 0.0;
+        let mut balances_for_month = Vec::new();
+        for (category_id, category_name, opened) in &envelopes {
+            if *opened > month {
+                continue;
+            }
+            let carried_in = balances.get(category_id).copied().unwrap_or(0.0);
+            let assigned_now = assigned.get(&(*category_id, month)).copied().unwrap_or(0.0);
+            let activity_now = activity.get(&(*category_id, month)).copied().unwrap_or(0.0);
+            let available = carried_in + assigned_now + activity_now;
+            balances.insert(*category_id, available);
+            month_assigned += assigned_now;
+
+            balances_for_month.push(EnvelopeBalance {
+                category_id: *category_id,
+                category_name: category_name.clone(),
+                carried_in,
+                assigned: assigned_now,
+                activity: activity_now,
+                available,
+            });
+        }
+        total_assigned += month_assigned;
+
+        if month >= start {
+            ledger.push(EnvelopeMonth {
+                month,
+                income,
+                assigned: month_assigned,
+                available_to_assign: total_income - total_assigned,
+                envelopes: balances_for_month,
+            });
+        }
+        month = month + Months::new(1);
+    }
+    ledger
+}
+
+#[derive(GraphQLInputObject)]
+pub struct ImportTransactionInput {
+    // the bank's id for the transaction, rows without one are matched on their content
+    pub external_id: Option<String>,
+    pub amount: f64,
+    pub description: String,
+    pub date: String,
+    // without a category, the first categorization rule that matches chooses one
+    pub category_id: Option<i32>,
+    // set when a classifier predicted the category, so uncertain ones can be reviewed
+    pub confidence: Option<f64>,
+    pub model: Option<String>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(
+    description = "Chooses the category and tags of transactions created or imported without a category"
+)]
+pub struct CategorizationRule {
+    pub id: i32,
+    pub name: String,
+    pub priority: i32,
+    pub description_pattern: Option<String>,
+    pub match_kind: RuleMatchKind,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub account: Option<String>,
+    pub sign: Option<AmountSign>,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub tags: Vec<String>,
+    pub enabled: bool,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct CategorizationRuleInput {
+    pub name: String,
+    // rules with a higher priority are tried first, defaults to 0
+    pub priority: Option<i32>,
+    pub description_pattern: Option<String>,
+    // how the description pattern is matched, defaults to CONTAINS
+    pub match_kind: Option<RuleMatchKind>,
+    // the range is on the amount's size, whichever way the money went
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub account: Option<String>,
+    pub sign: Option<AmountSign>,
+    pub category_id: i32,
+    pub tags: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+}
+
+impl CategorizationRuleInput {
+    fn into_db(self) -> Result<DbNewCategorizationRule, String> {
+        if self.name.trim().is_empty() {
+            return Err("Rule name cannot be empty".to_string());
+        }
+        let match_kind = self.match_kind.unwrap_or(RuleMatchKind::Contains);
+        let description_pattern = self
+            .description_pattern
+            .filter(|pattern| !pattern.trim().is_empty());
+        if let Some(pattern) = &description_pattern {
+            compile_pattern(match_kind, pattern)
+                .map_err(|e| format!("Invalid description pattern: {}", e))?;
+        }
+        let amount = |value: Option<f64>, field: &str| {
+            value
+                .map(|value| {
+                    if value < 0.0 {
+                        return Err(format!("{} cannot be negative", field));
+                    }
+                    BigDecimal::from_str(&value.to_string()).map_err(|e| e.to_string())
+                })
+                .transpose()
+        };
+        let min_amount = amount(self.min_amount, "Minimum amount")?;
+        let max_amount = amount(self.max_amount, "Maximum amount")?;
+        if let (Some(min), Some(max)) = (&min_amount, &max_amount) {
+            if min > max {
+                return Err("Minimum amount cannot be more than the maximum amount".to_string());
+            }
+        }
+        let account = self.account.filter(|account| !account.trim().is_empty());
+        // a rule without conditions would categorize everything
+        if description_pattern.is_none()
+            && min_amount.is_none()
+            && max_amount.is_none()
+            && account.is_none()
+            && self.sign.is_none()
+        {
+            return Err(
+                "A rule needs a description pattern, amount range, account or sign".to_string(),
+            );
+        }
+
+        Ok(DbNewCategorizationRule {
+            name: self.name.trim().to_string(),
+            priority: self.priority.unwrap_or(0),
+            description_pattern,
+            match_kind: match_kind.as_db_str().to_string(),
+            min_amount,
+            max_amount,
+            account,
+            sign: self.sign.map(|sign| sign.as_db_str().to_string()),
+            category_id: self.category_id,
+            tags: self
+                .tags
+                .unwrap_or_default()
+                .into_iter()
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+            enabled: self.enabled.unwrap_or(true),
+        })
+    }
+}
+
+#[derive(GraphQLObject)]
+#[graphql(description = "How applying a rule would change an existing transaction")]
+pub struct RuleChangePreview {
+    pub transaction_id: i32,
+    pub date: NaiveDate,
+    pub description: String,
+    pub amount: f64,
+    pub account: String,
+    pub current_category_id: i32,
+    pub current_category_name: Option<String>,
+    pub new_category_id: i32,
+    pub new_category_name: Option<String>,
+    // the rule's tags the transaction doesn't have yet
+    pub added_tags: Vec<String>,
+}
+
+impl RuleChangePreview {
+    fn new(change: RuleChange, category_id: i32, category_name: Option<String>) -> Self {
+        let tx = change.transaction;
+        Self {
+            transaction_id: tx.id,
+            date: to_naive_date(&tx.date),
+            description: tx.description,
+            amount: tx.amount.to_f64().unwrap_or(0.0),
+            account: tx.account,
+            current_category_id: tx.category_id,
+            current_category_name: tx.category_name,
+            new_category_id: category_id,
+            new_category_name: category_name,
+            added_tags: change.added_tags,
+        }
+    }
+}
+
+#[derive(GraphQLObject)]
+#[graphql(
+    description = "A rule applied to existing transactions, with the category each one had before"
+)]
+pub struct RuleApplication {
+    pub id: i32,
+    pub rule_id: Option<i32>,
+    pub rule_name: String,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub tags: Vec<String>,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub changed: i32,
+    pub transaction_ids: Vec<i32>,
+    // lines up with transactionIds
+    pub previous_category_ids: Vec<i32>,
+    pub applied_by: Option<String>,
+    pub applied_at: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(description = "A category changed through updateTransaction, and what chose the old one")]
+pub struct CategoryCorrection {
+    pub id: i32,
+    pub transaction_id: Option<i32>,
+    pub description: String,
+    pub amount: f64,
+    pub old_category_id: i32,
+    pub old_category_name: Option<String>,
+    pub new_category_id: i32,
+    pub new_category_name: Option<String>,
+    pub source: CorrectionSource,
+    pub rule_id: Option<i32>,
+    pub model: Option<String>,
+    pub confidence: Option<f64>,
+    pub corrected_at: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(
+    description = "A change to a transaction or category, with who made it and where it came from"
+)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub entity_type: AuditEntity,
+    pub entity_id: i32,
+    pub action: AuditAction,
+    pub actor: String,
+    pub source: AuditSource,
+    #[graphql(description = "The row before the change as JSON, null when it was created")]
+    pub before: Option<String>,
+    #[graphql(
+        description = "The row after the change as JSON, null when it was deleted. For a merge, the transaction it was merged into"
+    )]
+    pub after: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(
+    description = "A move of the date through which transactions can't be created, updated or deleted"
+)]
+pub struct PeriodClosing {
+    pub id: i32,
+    #[graphql(description = "The last closed date from then on, null when nothing is closed")]
+    pub closed_through: Option<NaiveDate>,
+    pub action: AuditAction,
+    pub actor: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject)]
+pub struct CategoryAccuracy {
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub predictions: i32,
+    pub corrections: i32,
+    #[graphql(description = "The share of predictions nobody corrected, null without predictions")]
+    pub accuracy: Option<f64>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(description = "How often the classifier's predictions were corrected")]
+pub struct ClassifierAccuracy {
+    pub predictions: i32,
+    pub corrections: i32,
+    pub accuracy: Option<f64>,
+    #[graphql(description = "Per predicted category")]
+    pub categories: Vec<CategoryAccuracy>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(
+    description = "A categorization rule for a merchant whose category keeps being corrected, ready for createCategorizationRule"
+)]
+pub struct RuleSuggestion {
+    pub name: String,
+    pub match_kind: RuleMatchKind,
+    pub description_pattern: String,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub corrections: i32,
+    pub last_corrected_at: Option<NaiveDateTime>,
+    pub message: String,
+}
+
+// Corrections of a merchant to the same category before a rule is suggested
+const DEFAULT_MIN_CORRECTIONS: i32 = 3;
+
+#[derive(GraphQLObject)]
+pub struct ImportBatch {
+    pub id: i32,
+    pub source_name: String,
+    pub file_hash: Option<String>,
+    pub imported_by: Option<String>,
+    pub row_count: i32,
+    pub inserted: i32,
+    pub updated: i32,
+    pub skipped: i32,
+    pub warnings: Vec<String>,
+    pub rolled_back_at: Option<NaiveDateTime>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject)]
+pub struct ImportRollback {
+    pub removed: i32,
+    #[graphql(description = "Transactions edited since the import, which were not removed")]
+    pub kept: i32,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = GraphQLContext)]
+pub struct DuplicateGroup {
+    pub normalized_description: String,
+    pub amount: f64,
+    pub transactions: Vec<Transaction>,
+}
+
+impl From<Vec<DbTransaction>> for DuplicateGroup {
+    fn from(transactions: Vec<DbTransaction>) -> Self {
+        let first = &transactions[0];
+        Self {
+            normalized_description: normalize_description(&first.description),
+            amount: first.amount.to_f64().unwrap_or(0.0),
+            transactions: transactions.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct RecurringTransaction {
+    pub id: i32,
+    pub description: String,
+    pub amount: f64,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub frequency: Frequency,
+    pub interval: i32,
+    pub day_of_month: Option<i32>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub last_materialized_date: Option<NaiveDate>,
+    pub active: bool,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject)]
+pub struct UpcomingTransaction {
+    pub recurring_transaction_id: i32,
+    pub date: NaiveDate,
+    pub amount: f64,
+    pub description: String,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+}
+
+fn parse_date(date: &str, field: &str) -> Result<Date, String> {
+    Date::parse(date, format_description!("[year]-[month]-[day]"))
+        .map_err(|e| format!("Invalid {} format: {}, expected YYYY-MM-DD", field, e))
+}
+
+fn validate_schedule(
+    frequency: Frequency,
+    interval: i32,
+    day_of_month: Option<i32>,
+) -> Result<(), String> {
+    if !(1..=MAX_SCHEDULE_INTERVAL).contains(&interval) {
+        return Err(format!(
+            "Interval must be between 1 and {}",
+            MAX_SCHEDULE_INTERVAL
+        ));
+    }
+    match day_of_month {
+        Some(_) if frequency != Frequency::Monthly => {
+            Err("Day of month is only supported for monthly schedules".to_string())
+        }
+        Some(day) if !(1..=31).contains(&day) => {
+            Err("Day of month must be between 1 and 31".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn recurring_candidates(
+    context: &GraphQLContext,
+    min_occurrences: Option<i32>,
+    lookback_months: Option<i32>,
+) -> FieldResult<Vec<RecurringCandidate>> {
+    let min_occurrences = min_occurrences.unwrap_or(DEFAULT_MIN_OCCURRENCES);
+    let lookback_months = lookback_months.unwrap_or(DEFAULT_LOOKBACK_MONTHS);
+    if min_occurrences < 2 {
+        return Err("Minimum occurrences must be at least 2".into());
+    }
+    if !(1..=MAX_LOOKBACK_MONTHS).contains(&lookback_months) {
+        return Err(format!(
+            "Lookback months must be between 1 and {}",
+            MAX_LOOKBACK_MONTHS
+        )
+        .into());
+    }
+
+    let today = chrono::Local::now().date_naive();
+    let start = today
+        .checked_sub_months(Months::new(lookback_months as u32))
+        .ok_or("Lookback months reach before the earliest supported date")?;
+    let transactions = context
+        .transaction_repository
+        .by_date_range(&start, &today)
+        .await?;
+    Ok(detect_recurring(&transactions, min_occurrences as usize))
+}
+
+fn parse_month(month: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map_err(|e| format!("Invalid month format: {}, expected YYYY-MM", e))
+}
+
+fn parse_month_start(month: &str) -> Result<Date, String> {
+    Date::parse(
+        &format!("{}-01", month),
+        format_description!("[year]-[month]-[day]"),
+    )
+    .map_err(|e| format!("Invalid month format: {}, expected YYYY-MM", e))
+}
+
+fn parse_period_start(period_start: &str) -> Result<Date, String> {
+    let date = Date::parse(period_start, format_description!("[year]-[month]-[day]"))
+        .map_err(|e| format!("Invalid period start format: {}, expected YYYY-MM-DD", e))?;
+    if date.day() != 1 {
+        return Err("Budget period start must be the first day of a month".to_string());
+    }
+    Ok(date)
+}
+
+// Implement From traits for conversions
+impl From<DbTransaction> for Transaction {
+    fn from(tx: DbTransaction) -> Self {
+        Self {
+            id: tx.id,
+            amount: tx.amount.to_f64().unwrap_or(0.0),
+            description: tx.description,
+            date: NaiveDate::from_ymd_opt(
+                tx.date.year(),
+                tx.date.month() as u32,
+                tx.date.day() as u32,
+            )
+            .unwrap_or_else(|| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
+            category_id: tx.category_id,
+            category_name: tx.category_name,
+            created_at: tx.created_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+            updated_at: tx.updated_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+        }
+    }
+}
+
+impl From<DbCategory> for Category {
+    fn from(cat: DbCategory) -> Self {
+        Self {
+            id: cat.id,
+            name: cat.name,
+            description: cat.description,
+            icon: cat.icon,
+            color: cat.color,
+            created_at: cat.created_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+            updated_at: cat.updated_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+        }
+    }
+}
+
+impl From<DbCategoryAlias> for CategoryAlias {
+    fn from(alias: DbCategoryAlias) -> Self {
+        Self {
+            id: alias.id,
+            name: alias.name,
+            category_id: alias.category_id,
+            category_name: alias.category_name,
+            created_at: alias.created_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+        }
+    }
+}
+
+impl From<DbReviewItem> for ReviewItem {
+    fn from(item: DbReviewItem) -> Self {
+        Self {
+            id: item.id,
+            amount: item.amount.to_f64().unwrap_or(0.0),
+            description: item.description,
+            date: to_naive_date(&item.date),
+            category_id: item.category_id,
+            category_name: item.category_name,
+            predicted_category: item.predicted_category,
+            original_category: item.original_category,
+            confidence: item.confidence,
+            model: item.classifier_model,
+        }
+    }
+}
+
+/// A review queue cursor is the confidence and id of the last item on the page
+fn review_cursor(item: &ReviewItem) -> String {
+    format!("{}:{}", item.confidence, item.id)
+}
+
+fn parse_review_cursor(cursor: &str) -> Result<(f64, i32), String> {
+    cursor
+        .split_once(':')
+        .and_then(|(confidence, id)| Some((confidence.parse().ok()?, id.parse().ok()?)))
+        .ok_or_else(|| format!("Invalid cursor: {}", cursor))
+}
+
+impl From<DbCategorizationRule> for CategorizationRule {
+    fn from(rule: DbCategorizationRule) -> Self {
+        Self {
+            id: rule.id,
+            name: rule.name,
+            priority: rule.priority,
+            description_pattern: rule.description_pattern,
+            match_kind: RuleMatchKind::from_db_str(&rule.match_kind),
+            min_amount: rule.min_amount.and_then(|amount| amount.to_f64()),
+            max_amount: rule.max_amount.and_then(|amount| amount.to_f64()),
+            account: rule.account,
+            sign: rule.sign.as_deref().and_then(AmountSign::from_db_str),
+            category_id: rule.category_id,
+            category_name: rule.category_name,
+            tags: rule.tags,
+            enabled: rule.enabled,
+            created_at: rule.created_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+            updated_at: rule.updated_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+        }
+    }
+}
+
+impl From<DbRuleApplication> for RuleApplication {
+    fn from(application: DbRuleApplication) -> Self {
+        Self {
+            id: application.id,
+            rule_id: application.rule_id,
+            rule_name: application.rule_name,
+            category_id: application.category_id,
+            category_name: application.category_name,
+            tags: application.tags,
+            start_date: to_naive_date(&application.start_date),
+            end_date: to_naive_date(&application.end_date),
+            changed: application.transaction_ids.len() as i32,
+            transaction_ids: application.transaction_ids,
+            previous_category_ids: application.previous_category_ids,
+            applied_by: application.applied_by,
+            applied_at: application.applied_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+        }
+    }
 }
 
-// Implement From traits for conversions
-impl From<DbTransaction> for Transaction {
-    fn from(tx: DbTransaction) -> Self {
+impl From<DbCategoryCorrection> for CategoryCorrection {
+    fn from(correction: DbCategoryCorrection) -> Self {
         Self {
-            id: tx.id,
-            amount: tx.amount.to_f64().unwrap_or(0.0),
-            description: tx.description,
-            date: NaiveDate::from_ymd_opt(
-                tx.date.year(),
-                tx.date.month() as u32,
-                tx.date.day() as u32,
-            )
-            .unwrap_or_else(|| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
-            category_id: tx.category_id,
-            category_name: tx.category_name,
-            created_at: tx.created_at.map(|dt| {
+            id: correction.id,
+            transaction_id: correction.transaction_id,
+            description: correction.description,
+            amount: correction.amount.to_f64().unwrap_or(0.0),
+            old_category_id: correction.old_category_id,
+            old_category_name: correction.old_category_name,
+            new_category_id: correction.new_category_id,
+            new_category_name: correction.new_category_name,
+            source: CorrectionSource::from_db_str(&correction.source),
+            rule_id: correction.rule_id,
+            model: correction.classifier_model,
+            confidence: correction.confidence,
+            corrected_at: correction.corrected_at.map(|dt| {
                 NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
                     .unwrap()
                     .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
                     .unwrap()
             }),
-            updated_at: tx.updated_at.map(|dt| {
+        }
+    }
+}
+
+impl From<DbPeriodClosing> for PeriodClosing {
+    fn from(closing: DbPeriodClosing) -> Self {
+        Self {
+            id: closing.id,
+            closed_through: closing.closed_through.as_ref().map(to_naive_date),
+            action: AuditAction::from_db_str(&closing.action),
+            actor: closing.actor,
+            created_at: closing.created_at.map(|dt| {
                 NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
                     .unwrap()
                     .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
@@ -71,30 +1160,97 @@ impl From<DbTransaction> for Transaction {
     }
 }
 
-impl From<DbCategory> for Category {
-    fn from(cat: DbCategory) -> Self {
+impl From<DbAuditEntry> for AuditEntry {
+    fn from(entry: DbAuditEntry) -> Self {
         Self {
-            id: cat.id,
-            name: cat.name,
-            description: cat.description,
-            icon: cat.icon,
-            color: cat.color,
-            created_at: cat.created_at.map(|dt| {
+            id: entry.id,
+            entity_type: AuditEntity::from_db_str(&entry.entity_type),
+            entity_id: entry.entity_id,
+            action: AuditAction::from_db_str(&entry.action),
+            actor: entry.actor,
+            source: AuditSource::from_db_str(&entry.source),
+            before: entry.before,
+            after: entry.after,
+            created_at: entry.created_at.map(|dt| {
                 NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
                     .unwrap()
                     .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
                     .unwrap()
             }),
-            updated_at: cat.updated_at.map(|dt| {
+        }
+    }
+}
+
+// The share of predictions that weren't corrected
+fn accuracy(predictions: i64, corrections: i64) -> Option<f64> {
+    (predictions > 0).then(|| 1.0 - corrections as f64 / predictions as f64)
+}
+
+impl From<Vec<DbCategoryAccuracy>> for ClassifierAccuracy {
+    fn from(categories: Vec<DbCategoryAccuracy>) -> Self {
+        let predictions: i64 = categories.iter().map(|c| c.predictions).sum();
+        let corrections: i64 = categories.iter().map(|c| c.corrections).sum();
+        Self {
+            predictions: predictions as i32,
+            corrections: corrections as i32,
+            accuracy: accuracy(predictions, corrections),
+            categories: categories
+                .into_iter()
+                .map(|c| CategoryAccuracy {
+                    category_id: c.category_id,
+                    category_name: c.category_name,
+                    predictions: c.predictions as i32,
+                    corrections: c.corrections as i32,
+                    accuracy: accuracy(c.predictions, c.corrections),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<DbRuleSuggestion> for RuleSuggestion {
+    fn from(suggestion: DbRuleSuggestion) -> Self {
+        let name = suggestion.merchant.to_uppercase();
+        let message = format!(
+            "You've recategorized {} to {} {} times, create a rule?",
+            name,
+            suggestion
+                .category_name
+                .as_deref()
+                .unwrap_or("this category"),
+            suggestion.corrections
+        );
+        Self {
+            name,
+            match_kind: RuleMatchKind::Regex,
+            description_pattern: suggestion.description_pattern,
+            category_id: suggestion.category_id,
+            category_name: suggestion.category_name,
+            corrections: suggestion.corrections as i32,
+            last_corrected_at: suggestion.last_corrected_at.map(|dt| {
                 NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
                     .unwrap()
                     .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
                     .unwrap()
             }),
+            message,
         }
     }
 }
 
+/// The dates from start through end, both included
+fn parse_period(start_date: String, end_date: String) -> Result<(NaiveDate, NaiveDate), String> {
+    let (start_date, end_date) = PeriodInput {
+        start_date,
+        end_date,
+    }
+    .parse()?;
+    if start_date > end_date {
+        return Err("Start date must not be after the end date".to_string());
+    }
+    Ok((start_date, end_date))
+}
+
 impl From<DbCategorySummary> for CategorySummary {
     fn from(cat: DbCategorySummary) -> Self {
         Self {
@@ -112,92 +1268,757 @@ impl From<DbCategorySummary> for CategorySummary {
                 .unwrap(),
         }
     }
-}
+}
+
+impl From<DbBudget> for Budget {
+    fn from(budget: DbBudget) -> Self {
+        Self {
+            id: budget.id,
+            category_id: budget.category_id,
+            category_name: budget.category_name,
+            period_start: to_naive_date(&budget.period_start),
+            period_type: BudgetPeriod::from_db_str(&budget.period_type),
+            amount: budget.amount.to_f64().unwrap_or(0.0),
+            rollover: budget.rollover,
+            created_at: budget.created_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+            updated_at: budget.updated_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+        }
+    }
+}
+
+impl From<DbImportBatch> for ImportBatch {
+    fn from(batch: DbImportBatch) -> Self {
+        Self {
+            id: batch.id,
+            source_name: batch.source_name,
+            file_hash: batch.file_hash,
+            imported_by: batch.imported_by,
+            row_count: batch.row_count,
+            inserted: batch.inserted_count,
+            updated: batch.updated_count,
+            skipped: batch.skipped_count,
+            warnings: batch.warnings,
+            rolled_back_at: batch.rolled_back_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+            created_at: batch.created_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+        }
+    }
+}
+
+impl From<DbEnvelopeAssignment> for EnvelopeAssignment {
+    fn from(assignment: DbEnvelopeAssignment) -> Self {
+        Self {
+            id: assignment.id,
+            category_id: assignment.category_id,
+            category_name: assignment.category_name,
+            month: to_naive_date(&assignment.month),
+            amount: assignment.amount.to_f64().unwrap_or(0.0),
+        }
+    }
+}
+
+impl From<DbRecurringTransaction> for RecurringTransaction {
+    fn from(recurring: DbRecurringTransaction) -> Self {
+        Self {
+            id: recurring.id,
+            description: recurring.description,
+            amount: recurring.amount.to_f64().unwrap_or(0.0),
+            category_id: recurring.category_id,
+            category_name: recurring.category_name,
+            frequency: Frequency::from_db_str(&recurring.frequency),
+            interval: recurring.interval_count,
+            day_of_month: recurring.day_of_month,
+            start_date: to_naive_date(&recurring.start_date),
+            end_date: recurring.end_date.as_ref().map(to_naive_date),
+            last_materialized_date: recurring.last_materialized_date.as_ref().map(to_naive_date),
+            active: recurring.active,
+            created_at: recurring.created_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+            updated_at: recurring.updated_at.map(|dt| {
+                NaiveDate::from_ymd_opt(dt.year(), dt.month() as u32, dt.day() as u32)
+                    .unwrap()
+                    .and_hms_opt(dt.hour() as u32, dt.minute() as u32, dt.second() as u32)
+                    .unwrap()
+            }),
+        }
+    }
+}
+
+// GraphQL Context
+#[derive(Clone)]
+pub struct GraphQLContext {
+    pub category_repository: Arc<dyn CategoryRepository>,
+    pub transaction_repository: Arc<dyn TransactionRepository>,
+    pub budget_repository: Arc<dyn BudgetRepository>,
+    pub recurring_transaction_repository: Arc<dyn RecurringTransactionRepository>,
+    pub import_batch_repository: Arc<dyn ImportBatchRepository>,
+    pub categorization_rule_repository: Arc<dyn CategorizationRuleRepository>,
+    pub audit_log_repository: Arc<dyn AuditLogRepository>,
+    pub period_closing_repository: Arc<dyn PeriodClosingRepository>,
+    pub classifier: SharedClassifier,
+    // who the request acts for, recorded in the audit log with every change it makes
+    pub actor: Option<String>,
+}
+
+impl GraphQLContext {
+    /// The enabled categorization rules, ready to categorize transactions
+    async fn rule_set(&self) -> FieldResult<RuleSet> {
+        let rules = self.categorization_rule_repository.all().await?;
+        RuleSet::new(rules).map_err(|e| format!("Invalid categorization rule: {}", e).into())
+    }
+
+    /// The last date of the closed period, if any
+    async fn closed_through(&self) -> FieldResult<Option<Date>> {
+        let closings = self.period_closing_repository.closings().await?;
+        Ok(closings.first().and_then(|closing| closing.closed_through))
+    }
+
+    /// Train the category classifier on the categorized transactions and start using it
+    async fn train_classifier(&self) -> FieldResult<Option<ClassifierInfo>> {
+        let examples = self
+            .transaction_repository
+            .training_examples(DEFAULT_REVIEW_THRESHOLD)
+            .await?;
+        let classifier = CategoryClassifier::train(&examples);
+        let info = classifier.as_ref().map(Into::into);
+        *self.classifier.write().await = classifier;
+        Ok(info)
+    }
+}
+
+// Implement Juniper's Context trait for our context
+impl juniper::Context for GraphQLContext {}
+
+// Root query and mutation types
+pub struct QueryRoot;
+
+#[juniper::graphql_object(Context = GraphQLContext)]
+impl QueryRoot {
+    #[graphql(description = "Get all transactions, as they stood at asOf when it's given")]
+    async fn all_transactions(
+        context: &GraphQLContext,
+        as_of: Option<DateTime<Utc>>,
+    ) -> FieldResult<Vec<Transaction>> {
+        let repository = &context.transaction_repository;
+        match as_of {
+            Some(as_of) => repository.all_as_of(&to_sql_timestamp(&as_of)).await,
+            None => repository.all().await,
+        }
+        .map_err(Into::into)
+        .map(|txs| txs.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "Get transactions by category, as they stood at asOf when it's given")]
+    async fn transactions_by_category(
+        context: &GraphQLContext,
+        category_id: i32,
+        as_of: Option<DateTime<Utc>>,
+    ) -> FieldResult<Vec<Transaction>> {
+        let repository = &context.transaction_repository;
+        match as_of {
+            Some(as_of) => {
+                repository
+                    .by_category_id_as_of(category_id, &to_sql_timestamp(&as_of))
+                    .await
+            }
+            None => repository.by_category_id(category_id).await,
+        }
+        .map_err(Into::into)
+        .map(|txs| txs.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "Get transactions by date range, as they stood at asOf when it's given"
+    )]
+    async fn transactions_by_date_range(
+        context: &GraphQLContext,
+        start_date: String,
+        end_date: String,
+        as_of: Option<DateTime<Utc>>,
+    ) -> FieldResult<Vec<Transaction>> {
+        let start_date = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid start date format: {}, expected YYYY-MM-DD", e))?;
+        let end_date = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid end date format: {}, expected YYYY-MM-DD", e))?;
+
+        let repository = &context.transaction_repository;
+        match as_of {
+            Some(as_of) => {
+                repository
+                    .by_date_range_as_of(&start_date, &end_date, &to_sql_timestamp(&as_of))
+                    .await
+            }
+            None => repository.by_date_range(&start_date, &end_date).await,
+        }
+        .map_err(Into::into)
+        .map(|txs| txs.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "Get category summary by date range, as the transactions and categories stood at asOf when it's given"
+    )]
+    async fn transactions_summary_by_category(
+        context: &GraphQLContext,
+        start_date: String,
+        end_date: String,
+        as_of: Option<DateTime<Utc>>,
+    ) -> FieldResult<Vec<CategorySummary>> {
+        let start_date = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid start date format: {}, expected YYYY-MM-DD", e))?;
+        let end_date = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid end date format: {}, expected YYYY-MM-DD", e))?;
+
+        let repository = &context.transaction_repository;
+        match as_of {
+            Some(as_of) => {
+                repository
+                    .sum_by_category_as_of(&start_date, &end_date, &to_sql_timestamp(&as_of))
+                    .await
+            }
+            None => repository.sum_by_category(&start_date, &end_date).await,
+        }
+        .map_err(Into::into)
+        .map(|cats| cats.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "Get every category alias, the importer maps these names to their categories"
+    )]
+    async fn category_aliases(context: &GraphQLContext) -> FieldResult<Vec<CategoryAlias>> {
+        context
+            .category_repository
+            .aliases()
+            .await
+            .map_err(Into::into)
+            .map(|aliases| aliases.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "Get every categorization rule, in the order they're tried")]
+    async fn categorization_rules(
+        context: &GraphQLContext,
+    ) -> FieldResult<Vec<CategorizationRule>> {
+        context
+            .categorization_rule_repository
+            .all()
+            .await
+            .map_err(Into::into)
+            .map(|rules| rules.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "List the existing transactions from startDate through endDate that the rule would change and how, without changing them"
+    )]
+    async fn preview_rule(
+        context: &GraphQLContext,
+        rule: CategorizationRuleInput,
+        start_date: String,
+        end_date: String,
+    ) -> FieldResult<Vec<RuleChangePreview>> {
+        let (start_date, end_date) = parse_period(start_date, end_date)?;
+        let rule = rule.into_db()?;
+        let category_name = context
+            .category_repository
+            .all()
+            .await?
+            .into_iter()
+            .find(|category| category.id == rule.category_id)
+            .map(|category| category.name)
+            .ok_or_else(|| format!("Category {} not found", rule.category_id))?;
+        let rule = DbCategorizationRule {
+            id: 0,
+            name: rule.name,
+            priority: rule.priority,
+            description_pattern: rule.description_pattern,
+            match_kind: rule.match_kind,
+            min_amount: rule.min_amount,
+            max_amount: rule.max_amount,
+            account: rule.account,
+            sign: rule.sign,
+            category_id: rule.category_id,
+            category_name: Some(category_name),
+            tags: rule.tags,
+            enabled: rule.enabled,
+            created_at: None,
+            updated_at: None,
+        };
+
+        let transactions = context
+            .transaction_repository
+            .tagged_between(&start_date, &end_date)
+            .await?;
+        let changes = rule_changes(&rule, transactions)?;
+        Ok(changes
+            .into_iter()
+            .map(|change| {
+                RuleChangePreview::new(change, rule.category_id, rule.category_name.clone())
+            })
+            .collect())
+    }
+
+    #[graphql(description = "Every time a rule was applied to existing transactions, newest first")]
+    async fn rule_applications(
+        context: &GraphQLContext,
+        rule_id: Option<i32>,
+    ) -> FieldResult<Vec<RuleApplication>> {
+        context
+            .categorization_rule_repository
+            .applications(rule_id)
+            .await
+            .map_err(Into::into)
+            .map(|applications| applications.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "Transactions whose predicted category has less than `threshold` confidence (default 0.6) and hasn't been confirmed, least confident first"
+    )]
+    async fn review_queue(
+        context: &GraphQLContext,
+        threshold: Option<f64>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> FieldResult<ReviewQueue> {
+        let threshold = threshold.unwrap_or(DEFAULT_REVIEW_THRESHOLD);
+        let first = first.unwrap_or(DEFAULT_REVIEW_PAGE_SIZE);
+        if !(1..=MAX_REVIEW_PAGE_SIZE).contains(&first) {
+            return Err(format!("first must be between 1 and {}", MAX_REVIEW_PAGE_SIZE).into());
+        }
+        let after = after.as_deref().map(parse_review_cursor).transpose()?;
+
+        // one more than asked for tells whether there's another page
+        let mut items: Vec<ReviewItem> = context
+            .transaction_repository
+            .review_queue(threshold, after, first as i64 + 1)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let has_next_page = items.len() > first as usize;
+        items.truncate(first as usize);
+
+        Ok(ReviewQueue {
+            end_cursor: items.last().map(review_cursor),
+            items,
+            has_next_page,
+        })
+    }
+
+    #[graphql(description = "Every category changed through updateTransaction, newest first")]
+    async fn category_corrections(
+        context: &GraphQLContext,
+    ) -> FieldResult<Vec<CategoryCorrection>> {
+        context
+            .transaction_repository
+            .corrections()
+            .await
+            .map_err(Into::into)
+            .map(|corrections| corrections.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "Every change to transactions or categories of the type, or to the one with entityId, newest first"
+    )]
+    async fn audit_log(
+        context: &GraphQLContext,
+        entity_type: AuditEntity,
+        entity_id: Option<i32>,
+    ) -> FieldResult<Vec<AuditEntry>> {
+        context
+            .audit_log_repository
+            .entries(entity_type.as_db_str(), entity_id)
+            .await
+            .map_err(Into::into)
+            .map(|entries| entries.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "Every time closePeriod or reopenPeriod moved the closed period, newest first"
+    )]
+    async fn period_closings(context: &GraphQLContext) -> FieldResult<Vec<PeriodClosing>> {
+        context
+            .period_closing_repository
+            .closings()
+            .await
+            .map_err(Into::into)
+            .map(|closings| closings.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "How many of the transactions dated from startDate through endDate the classifier categorized, and how many of those someone corrected"
+    )]
+    async fn classifier_accuracy(
+        context: &GraphQLContext,
+        start_date: String,
+        end_date: String,
+    ) -> FieldResult<ClassifierAccuracy> {
+        let (start_date, end_date) = parse_period(start_date, end_date)?;
+        context
+            .transaction_repository
+            .classifier_accuracy(&start_date, &end_date)
+            .await
+            .map_err(Into::into)
+            .map(Into::into)
+    }
+
+    #[graphql(
+        description = "Rules for merchants whose transactions were corrected to the same category at least minCorrections times (default 3), most corrected first"
+    )]
+    async fn rule_suggestions(
+        context: &GraphQLContext,
+        min_corrections: Option<i32>,
+    ) -> FieldResult<Vec<RuleSuggestion>> {
+        let min_corrections = min_corrections.unwrap_or(DEFAULT_MIN_CORRECTIONS);
+        if min_corrections < 1 {
+            return Err("minCorrections must be at least 1".into());
+        }
+        let rules = context.rule_set().await?;
+        let corrections = context.transaction_repository.corrections().await?;
+        Ok(
+            suggest_rules(&corrections, &rules, min_corrections as usize)
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        )
+    }
+
+    #[graphql(
+        description = "The k (default 3) most likely categories for a transaction, most likely first. The classifier is trained on first use"
+    )]
+    async fn suggest_category(
+        context: &GraphQLContext,
+        description: String,
+        amount: f64,
+        k: Option<i32>,
+    ) -> FieldResult<Vec<CategorySuggestion>> {
+        let k = k.unwrap_or(DEFAULT_SUGGESTIONS);
+        if k < 1 {
+            return Err("k must be at least 1".into());
+        }
+        let amount = BigDecimal::from_str(&amount.to_string())?;
+
+        if context.classifier.read().await.is_none() {
+            context.train_classifier().await?;
+        }
+        let classifier = context.classifier.read().await;
+        let classifier = classifier
+            .as_ref()
+            .ok_or("There are no categorized transactions to learn from")?;
+        Ok(classifier
+            .suggest(&description, &amount, k as usize)
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
 
-// GraphQL Context
-#[derive(Clone)]
-pub struct GraphQLContext {
-    pub category_repository: Arc<dyn CategoryRepository>,
-    pub transaction_repository: Arc<dyn TransactionRepository>,
-}
+    #[graphql(
+        description = "Compare spending between two periods, per category or per description"
+    )]
+    async fn compare_spending(
+        context: &GraphQLContext,
+        period_a: PeriodInput,
+        period_b: PeriodInput,
+        group_by: Option<SpendingGroupBy>,
+        significant_percent: Option<f64>,
+        significant_amount: Option<f64>,
+    ) -> FieldResult<Vec<SpendingComparison>> {
+        let (a_start, a_end) = period_a.parse()?;
+        let (b_start, b_end) = period_b.parse()?;
+        let significant_percent = significant_percent.unwrap_or(DEFAULT_SIGNIFICANT_PERCENT);
+        let significant_amount = significant_amount.unwrap_or(DEFAULT_SIGNIFICANT_AMOUNT);
 
-// Implement Juniper's Context trait for our context
-impl juniper::Context for GraphQLContext {}
+        let comparisons = match group_by.unwrap_or(SpendingGroupBy::Category) {
+            SpendingGroupBy::Category => {
+                context
+                    .transaction_repository
+                    .compare_by_category(&a_start, &a_end, &b_start, &b_end)
+                    .await?
+            }
+            SpendingGroupBy::Description => {
+                context
+                    .transaction_repository
+                    .compare_by_description(&a_start, &a_end, &b_start, &b_end)
+                    .await?
+            }
+        };
 
-// Root query and mutation types
-pub struct QueryRoot;
+        Ok(comparisons
+            .into_iter()
+            .map(|cmp| SpendingComparison::from_db(cmp, significant_percent, significant_amount))
+            .collect())
+    }
 
-#[juniper::graphql_object(Context = GraphQLContext)]
-impl QueryRoot {
-    #[graphql(description = "Get all transactions")]
-    async fn all_transactions(context: &GraphQLContext) -> FieldResult<Vec<Transaction>> {
+    #[graphql(description = "Get all categories")]
+    async fn categories(context: &GraphQLContext) -> FieldResult<Vec<Category>> {
         context
-            .transaction_repository
+            .category_repository
             .all()
             .await
             .map_err(Into::into)
-            .map(|txs| txs.into_iter().map(Into::into).collect())
+            .map(|cats| cats.into_iter().map(Into::into).collect())
     }
 
-    #[graphql(description = "Get transactions by category")]
-    async fn transactions_by_category(
-        context: &GraphQLContext,
-        category_id: i32,
-    ) -> FieldResult<Vec<Transaction>> {
+    #[graphql(description = "Get all budgets")]
+    async fn budgets(context: &GraphQLContext) -> FieldResult<Vec<Budget>> {
         context
+            .budget_repository
+            .all()
+            .await
+            .map_err(Into::into)
+            .map(|budgets| budgets.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "Get budget vs actual for each budgeted category, month is YYYY-MM")]
+    async fn budget_status(
+        context: &GraphQLContext,
+        month: String,
+    ) -> FieldResult<Vec<BudgetStatus>> {
+        let month = parse_month(&month)?;
+        let month_end = month + Months::new(1) - chrono::Duration::days(1);
+        let today = chrono::Local::now().date_naive();
+
+        let budgets = context.budget_repository.effective_on(&month_end).await?;
+
+        // Rollover budgets need the spending of every earlier month since they started
+        let rollover_categories: Vec<i32> = budgets
+            .iter()
+            .filter(|b| {
+                b.rollover && BudgetPeriod::from_db_str(&b.period_type) == BudgetPeriod::Monthly
+            })
+            .map(|b| b.category_id)
+            .collect();
+        let mut carried: HashMap<i32, f64> = HashMap::new();
+        if !rollover_categories.is_empty() {
+            let history = context.budget_repository.all().await?;
+            let first = history
+                .iter()
+                .filter(|b| rollover_categories.contains(&b.category_id))
+                .map(|b| to_naive_date(&b.period_start))
+                .min();
+            if let Some(first) = first.filter(|first| *first < month) {
+                let monthly_totals: HashMap<(i32, NaiveDate), f64> = context
+                    .transaction_repository
+                    .monthly_totals_by_category(&first, &(month - chrono::Duration::days(1)))
+                    .await?
+                    .into_iter()
+                    .map(|t| {
+                        (
+                            (t.category_id, to_naive_date(&t.month)),
+                            t.total_amount.to_f64().unwrap_or(0.0),
+                        )
+                    })
+                    .collect();
+                for category_id in rollover_categories {
+                    let carry = rollover_carry(category_id, &history, &monthly_totals, month);
+                    carried.insert(category_id, carry);
+                }
+            }
+        }
+
+        // Budgets with different period types cover different windows, sum each window once
+        let mut totals: HashMap<(NaiveDate, NaiveDate), HashMap<i32, f64>> = HashMap::new();
+        let mut statuses = Vec::with_capacity(budgets.len());
+        for budget in &budgets {
+            let period_type = BudgetPeriod::from_db_str(&budget.period_type);
+            let (start, end) = period_type.bounds(to_naive_date(&budget.period_start), month);
+
+            if let Entry::Vacant(entry) = totals.entry((start, end)) {
+                let summary = context
+                    .transaction_repository
+                    .sum_by_category(&start, &end)
+                    .await?;
+                entry.insert(
+                    summary
+                        .into_iter()
+                        .map(|s| {
+                            let total = s.total_amount.and_then(|t| t.to_f64()).unwrap_or(0.0);
+                            (s.category_id, total)
+                        })
+                        .collect(),
+                );
+            }
+
+            let actual = -totals[&(start, end)]
+                .get(&budget.category_id)
+                .copied()
+                .unwrap_or(0.0);
+            let carried_over = carried.get(&budget.category_id).copied().unwrap_or(0.0);
+            statuses.push(BudgetStatus::new(
+                budget,
+                start,
+                end,
+                carried_over,
+                actual,
+                today,
+            ));
+        }
+
+        statuses.sort_by(|a, b| a.category_name.cmp(&b.category_name));
+        Ok(statuses)
+    }
+
+    #[graphql(
+        description = "Get the envelope ledger for each month from startMonth to endMonth (YYYY-MM). Income counts from the first month with an assignment, or from startMonth if it is earlier"
+    )]
+    async fn envelope_ledger(
+        context: &GraphQLContext,
+        start_month: String,
+        end_month: String,
+    ) -> FieldResult<Vec<EnvelopeMonth>> {
+        let start = parse_month(&start_month)?;
+        let end = parse_month(&end_month)?;
+        if end < start {
+            return Err("End month must not be before start month".into());
+        }
+        let end_date = end + Months::new(1) - chrono::Duration::days(1);
+
+        let assignments = context
+            .budget_repository
+            .assignments_through(&end_date)
+            .await?;
+        let first = assignments
+            .iter()
+            .map(|a| to_naive_date(&a.month))
+            .min()
+            .map_or(start, |first| first.min(start));
+        let monthly_totals = context
             .transaction_repository
-            .by_category_id(category_id)
+            .monthly_totals_by_category(&first, &end_date)
+            .await?;
+        let income_category_id = match context
+            .category_repository
+            .find_by_name(INCOME_CATEGORY)
+            .await
+        {
+            Ok(category) => Some(category.id),
+            Err(sqlx::Error::RowNotFound) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(build_envelope_ledger(
+            &assignments,
+            &monthly_totals,
+            income_category_id,
+            first,
+            start,
+            end,
+        ))
+    }
+
+    #[graphql(description = "Get all recurring transaction schedules")]
+    async fn recurring_transactions(
+        context: &GraphQLContext,
+    ) -> FieldResult<Vec<RecurringTransaction>> {
+        context
+            .recurring_transaction_repository
+            .all()
             .await
             .map_err(Into::into)
-            .map(|txs| txs.into_iter().map(Into::into).collect())
+            .map(|recurring| recurring.into_iter().map(Into::into).collect())
     }
 
-    #[graphql(description = "Get transactions by date range")]
-    async fn transactions_by_date_range(
+    #[graphql(
+        description = "Get the occurrences of active schedules due in the next number of days"
+    )]
+    async fn upcoming_transactions(
         context: &GraphQLContext,
-        start_date: String,
-        end_date: String,
-    ) -> FieldResult<Vec<Transaction>> {
-        let start_date = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
-            .map_err(|e| format!("Invalid start date format: {}, expected YYYY-MM-DD", e))?;
-        let end_date = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
-            .map_err(|e| format!("Invalid end date format: {}, expected YYYY-MM-DD", e))?;
+        days: i32,
+    ) -> FieldResult<Vec<UpcomingTransaction>> {
+        if !(0..=MAX_UPCOMING_DAYS).contains(&days) {
+            return Err(format!("Days must be between 0 and {}", MAX_UPCOMING_DAYS).into());
+        }
+        let today = chrono::Local::now().date_naive();
+        let until = today + chrono::Duration::days(days.into());
+
+        // Anything up to today is materialized by the background task
+        let from = today + chrono::Duration::days(1);
+        let mut upcoming = Vec::new();
+        for recurring in context.recurring_transaction_repository.active().await? {
+            for date in Schedule::from(&recurring).occurrences_between(from, until) {
+                upcoming.push(UpcomingTransaction {
+                    recurring_transaction_id: recurring.id,
+                    date,
+                    amount: recurring.amount.to_f64().unwrap_or(0.0),
+                    description: recurring.description.clone(),
+                    category_id: recurring.category_id,
+                    category_name: recurring.category_name.clone(),
+                });
+            }
+        }
+
+        upcoming.sort_by_key(|u| u.date);
+        Ok(upcoming)
+    }
 
+    #[graphql(description = "Get all import batches, newest first")]
+    async fn import_batches(context: &GraphQLContext) -> FieldResult<Vec<ImportBatch>> {
         context
-            .transaction_repository
-            .by_date_range(&start_date, &end_date)
+            .import_batch_repository
+            .all()
             .await
             .map_err(Into::into)
-            .map(|txs| txs.into_iter().map(Into::into).collect())
+            .map(|batches| batches.into_iter().map(Into::into).collect())
     }
 
-    #[graphql(description = "Get category summary by date range")]
-    async fn transactions_summary_by_category(
+    #[graphql(
+        description = "Find groups of likely duplicate transactions with the same amount and normalized description"
+    )]
+    async fn find_duplicates(
         context: &GraphQLContext,
         start_date: String,
         end_date: String,
-    ) -> FieldResult<Vec<CategorySummary>> {
+        strategy: Option<DuplicateStrategy>,
+        window_days: Option<i32>,
+    ) -> FieldResult<Vec<DuplicateGroup>> {
         let start_date = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
             .map_err(|e| format!("Invalid start date format: {}, expected YYYY-MM-DD", e))?;
         let end_date = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
             .map_err(|e| format!("Invalid end date format: {}, expected YYYY-MM-DD", e))?;
+        if window_days.is_some_and(|days| days < 0) {
+            return Err("Window days must not be negative".into());
+        }
+        let window_days = strategy
+            .unwrap_or(DuplicateStrategy::DateWindow)
+            .window_days(window_days.map(i64::from));
 
         context
             .transaction_repository
-            .sum_by_category(&start_date, &end_date)
+            .find_duplicates(&start_date, &end_date, window_days)
             .await
             .map_err(Into::into)
-            .map(|cats| cats.into_iter().map(Into::into).collect())
+            .map(|groups| groups.into_iter().map(Into::into).collect())
     }
 
-    #[graphql(description = "Get all categories")]
-    async fn categories(context: &GraphQLContext) -> FieldResult<Vec<Category>> {
-        context
-            .category_repository
-            .all()
-            .await
-            .map_err(Into::into)
-            .map(|cats| cats.into_iter().map(Into::into).collect())
+    #[graphql(
+        description = "Find merchants charging similar amounts at a regular cadence that could be recurring schedules"
+    )]
+    async fn detect_recurring(
+        context: &GraphQLContext,
+        min_occurrences: Option<i32>,
+        lookback_months: Option<i32>,
+    ) -> FieldResult<Vec<RecurringCandidate>> {
+        recurring_candidates(context, min_occurrences, lookback_months).await
     }
 
     #[graphql(description = "Get category by name")]
@@ -215,22 +2036,137 @@ pub struct MutationRoot;
 
 #[juniper::graphql_object(Context = GraphQLContext)]
 impl MutationRoot {
+    #[graphql(
+        description = "Create a transaction. Without a category the first categorization rule that matches chooses it"
+    )]
     async fn create_transaction(
         context: &GraphQLContext,
         amount: f64,
         description: String,
         date: String,
-        category_id: i32,
+        category_id: Option<i32>,
     ) -> FieldResult<Transaction> {
         let amount = BigDecimal::from_str(&amount.to_string())?;
 
         let date = Date::parse(date.as_str(), format_description!("[year]-[month]-[day]")).unwrap();
+        let transaction = match category_id {
+            Some(category_id) => {
+                context
+                    .transaction_repository
+                    .create(
+                        amount,
+                        description,
+                        date,
+                        category_id,
+                        context.actor.clone(),
+                    )
+                    .await?
+            }
+            None => {
+                let rules = context.rule_set().await?;
+                let rule = rules
+                    .first_match(&description, &amount, DEFAULT_ACCOUNT)
+                    .ok_or_else(|| {
+                        format!(
+                            "No category given and no categorization rule matches '{}'",
+                            description
+                        )
+                    })?
+                    .clone();
+                context
+                    .transaction_repository
+                    .create_by_rule(amount, description, date, rule, context.actor.clone())
+                    .await?
+            }
+        };
+        Ok(transaction.into())
+    }
+
+    #[graphql(
+        description = "Import transactions into an account as a new import batch. Importing the same transactions again updates or skips them instead of adding duplicates"
+    )]
+    async fn import_transactions(
+        context: &GraphQLContext,
+        account: Option<String>,
+        source_name: Option<String>,
+        imported_by: Option<String>,
+        transactions: Vec<ImportTransactionInput>,
+    ) -> FieldResult<ImportBatch> {
+        let account = account.unwrap_or_else(|| DEFAULT_ACCOUNT.to_string());
+        let rules = if transactions.iter().any(|input| input.category_id.is_none()) {
+            Some(context.rule_set().await?)
+        } else {
+            None
+        };
+        let mut rows = Vec::with_capacity(transactions.len());
+        for (i, input) in transactions.into_iter().enumerate() {
+            let date =
+                parse_date(&input.date, "date").map_err(|e| format!("Transaction {}: {}", i, e))?;
+            let amount = BigDecimal::from_str(&input.amount.to_string())?;
+            let (category_id, rule_id, tags) = match (input.category_id, &rules) {
+                (Some(category_id), _) => (category_id, None, Vec::new()),
+                (None, Some(rules)) => rules
+                    .first_match(&input.description, &amount, &account)
+                    .map(|rule| (rule.category_id, Some(rule.id), rule.tags.clone()))
+                    .ok_or_else(|| {
+                        format!(
+                            "Transaction {}: no category given and no categorization rule matches",
+                            i
+                        )
+                    })?,
+                (None, None) => unreachable!("rules are loaded when a category is missing"),
+            };
+            rows.push(DbImportRow {
+                account: account.clone(),
+                external_id: input.external_id.filter(|id| !id.trim().is_empty()),
+                amount,
+                description: input.description,
+                date,
+                category_id,
+                classification: (input.confidence.is_some() || input.model.is_some()).then(|| {
+                    DbClassification {
+                        confidence: input.confidence,
+                        model: input.model,
+                        ..Default::default()
+                    }
+                }),
+                rule_id,
+                tags,
+                new_category: None,
+            });
+        }
+
+        let batch = DbNewImportBatch {
+            source_name: source_name.unwrap_or_else(|| API_IMPORT_SOURCE.to_string()),
+            file_hash: None,
+            imported_by: imported_by.or_else(|| context.actor.clone()),
+            row_count: rows.len() as i32,
+            warnings: Vec::new(),
+        };
+
         context
             .transaction_repository
-            .create(amount, description, date, category_id)
+            .import_transactions(batch, rows, context.actor.clone())
             .await
             .map_err(Into::into)
-            .map(|tx| tx.into())
+            .map(|batch| batch.into())
+    }
+
+    #[graphql(
+        description = "Remove the transactions an import batch inserted. Transactions edited since the import are kept"
+    )]
+    async fn rollback_import(
+        context: &GraphQLContext,
+        batch_id: i32,
+    ) -> FieldResult<ImportRollback> {
+        let rollback = context
+            .import_batch_repository
+            .rollback(batch_id, context.actor.clone())
+            .await?;
+        Ok(ImportRollback {
+            removed: rollback.removed as i32,
+            kept: rollback.kept as i32,
+        })
     }
 
     async fn update_transaction(
@@ -246,12 +2182,75 @@ impl MutationRoot {
         let date = Date::parse(date.as_str(), format_description!("[year]-[month]-[day]")).unwrap();
         context
             .transaction_repository
-            .update(id, amount, description, date, category_id)
+            .update(
+                id,
+                amount,
+                description,
+                date,
+                category_id,
+                context.actor.clone(),
+            )
             .await
             .map_err(Into::into)
             .map(|tx| tx.into())
     }
 
+    async fn delete_transaction(context: &GraphQLContext, id: i32) -> FieldResult<bool> {
+        context
+            .transaction_repository
+            .delete(id, context.actor.clone())
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Revert the newest change to transactions or categories the request's user made through the API or by applying a rule that isn't undone yet, and return its audit log entries"
+    )]
+    async fn undo(context: &GraphQLContext) -> FieldResult<Vec<AuditEntry>> {
+        let entries = context
+            .audit_log_repository
+            .undo(context.actor.clone())
+            .await?;
+        if entries.is_empty() {
+            return Err("Nothing to undo".into());
+        }
+        Ok(entries.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "Make the change the request's user undid last again, unless they made another change since, and return its audit log entries"
+    )]
+    async fn redo(context: &GraphQLContext) -> FieldResult<Vec<AuditEntry>> {
+        let entries = context
+            .audit_log_repository
+            .redo(context.actor.clone())
+            .await?;
+        if entries.is_empty() {
+            return Err("Nothing to redo".into());
+        }
+        Ok(entries.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "Keep one transaction and delete its duplicates, returning the number deleted. Only transactions with the same amount as the one kept are deleted"
+    )]
+    async fn resolve_duplicates(
+        context: &GraphQLContext,
+        keep_id: i32,
+        remove_ids: Vec<i32>,
+    ) -> FieldResult<i32> {
+        if remove_ids.contains(&keep_id) {
+            return Err("The transaction to keep cannot also be removed".into());
+        }
+
+        context
+            .transaction_repository
+            .resolve_duplicates(keep_id, remove_ids, context.actor.clone())
+            .await
+            .map_err(Into::into)
+            .map(|removed| removed as i32)
+    }
+
     async fn create_category(
         context: &GraphQLContext,
         name: String,
@@ -261,7 +2260,7 @@ impl MutationRoot {
     ) -> FieldResult<Category> {
         context
             .category_repository
-            .create(name, description, icon, color)
+            .create(name, description, icon, color, context.actor.clone())
             .await
             .map_err(Into::into)
             .map(|cat| cat.into())
@@ -277,9 +2276,386 @@ impl MutationRoot {
     ) -> FieldResult<Category> {
         context
             .category_repository
-            .update(id, name, description, icon, color)
+            .update(id, name, description, icon, color, context.actor.clone())
             .await
             .map_err(Into::into)
             .map(|cat| cat.into())
     }
+
+    #[graphql(
+        description = "Map a category name used in imported files to a category, replacing any alias with the same name"
+    )]
+    async fn set_category_alias(
+        context: &GraphQLContext,
+        name: String,
+        category_id: i32,
+    ) -> FieldResult<CategoryAlias> {
+        if name.trim().is_empty() {
+            return Err("Alias name cannot be empty".into());
+        }
+        context
+            .category_repository
+            .set_alias(name, category_id)
+            .await
+            .map_err(Into::into)
+            .map(|alias| alias.into())
+    }
+
+    async fn delete_category_alias(context: &GraphQLContext, id: i32) -> FieldResult<bool> {
+        context
+            .category_repository
+            .delete_alias(id)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Accept the categories of these transactions so they leave the review queue, returns how many were confirmed"
+    )]
+    async fn confirm_category(context: &GraphQLContext, ids: Vec<i32>) -> FieldResult<i32> {
+        let confirmed = context
+            .transaction_repository
+            .confirm_categories(ids, context.actor.clone())
+            .await?;
+        Ok(confirmed as i32)
+    }
+
+    #[graphql(
+        description = "Train the category classifier again on the current categorized transactions"
+    )]
+    async fn retrain_classifier(context: &GraphQLContext) -> FieldResult<ClassifierInfo> {
+        context
+            .train_classifier()
+            .await?
+            .ok_or("There are no categorized transactions to learn from".into())
+    }
+
+    #[graphql(
+        description = "Create a categorization rule. It needs at least one condition: a description pattern, an amount range, an account or a sign"
+    )]
+    async fn create_categorization_rule(
+        context: &GraphQLContext,
+        rule: CategorizationRuleInput,
+    ) -> FieldResult<CategorizationRule> {
+        let rule = rule.into_db()?;
+        context
+            .categorization_rule_repository
+            .create(rule)
+            .await
+            .map_err(Into::into)
+            .map(|rule| rule.into())
+    }
+
+    async fn update_categorization_rule(
+        context: &GraphQLContext,
+        id: i32,
+        rule: CategorizationRuleInput,
+    ) -> FieldResult<CategorizationRule> {
+        let rule = rule.into_db()?;
+        context
+            .categorization_rule_repository
+            .update(id, rule)
+            .await
+            .map_err(Into::into)
+            .map(|rule| rule.into())
+    }
+
+    async fn delete_categorization_rule(context: &GraphQLContext, id: i32) -> FieldResult<bool> {
+        context
+            .categorization_rule_repository
+            .delete(id)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Give the existing transactions from startDate through endDate that the rule matches its category and tags, and record it in ruleApplications"
+    )]
+    async fn apply_rule(
+        context: &GraphQLContext,
+        rule_id: i32,
+        start_date: String,
+        end_date: String,
+        applied_by: Option<String>,
+    ) -> FieldResult<RuleApplication> {
+        let (start_date, end_date) = parse_period(start_date, end_date)?;
+        let rule = context
+            .categorization_rule_repository
+            .by_id(rule_id)
+            .await?;
+        let transactions = context
+            .transaction_repository
+            .tagged_between(&start_date, &end_date)
+            .await?;
+        let changes = rule_changes(&rule, transactions)?;
+
+        let application = DbNewRuleApplication {
+            rule_id: rule.id,
+            rule_name: rule.name,
+            category_id: rule.category_id,
+            tags: rule.tags,
+            start_date: to_sql_date(&start_date),
+            end_date: to_sql_date(&end_date),
+            transaction_ids: changes.iter().map(|change| change.transaction.id).collect(),
+            previous_category_ids: changes
+                .iter()
+                .map(|change| change.transaction.category_id)
+                .collect(),
+            applied_by: applied_by.or_else(|| context.actor.clone()),
+        };
+        context
+            .categorization_rule_repository
+            .apply(application, context.actor.clone())
+            .await
+            .map_err(Into::into)
+            .map(|application| application.into())
+    }
+
+    #[graphql(
+        description = "Close every transaction dated on or before throughDate, so creating, updating or deleting one fails until reopenPeriod"
+    )]
+    async fn close_period(
+        context: &GraphQLContext,
+        through_date: String,
+        closed_by: Option<String>,
+    ) -> FieldResult<PeriodClosing> {
+        let through_date = parse_date(&through_date, "through date")?;
+        if let Some(closed_through) = context.closed_through().await? {
+            if through_date <= closed_through {
+                return Err(
+                    format!("Transactions are already closed through {}", closed_through).into(),
+                );
+            }
+        }
+        context
+            .period_closing_repository
+            .close(
+                through_date,
+                closed_by.or_else(|| context.actor.clone()),
+                context.actor.clone(),
+            )
+            .await
+            .map_err(Into::into)
+            .map(|closing| closing.into())
+    }
+
+    #[graphql(
+        description = "Reopen the closed transactions dated on or after fromDate, keeping the ones before it closed. Recorded in the audit log"
+    )]
+    async fn reopen_period(
+        context: &GraphQLContext,
+        from_date: String,
+        reopened_by: Option<String>,
+    ) -> FieldResult<PeriodClosing> {
+        let from_date = parse_date(&from_date, "from date")?;
+        match context.closed_through().await? {
+            Some(closed_through) if from_date <= closed_through => {}
+            _ => {
+                return Err(format!("No transactions are closed on or after {}", from_date).into())
+            }
+        }
+        context
+            .period_closing_repository
+            .reopen(
+                from_date,
+                reopened_by.or_else(|| context.actor.clone()),
+                context.actor.clone(),
+            )
+            .await
+            .map_err(Into::into)
+            .map(|closing| closing.into())
+    }
+
+    async fn create_budget(
+        context: &GraphQLContext,
+        category_id: i32,
+        period_start: String,
+        period_type: BudgetPeriod,
+        amount: f64,
+        rollover: Option<bool>,
+    ) -> FieldResult<Budget> {
+        let amount = BigDecimal::from_str(&amount.to_string())?;
+        let period_start = parse_period_start(&period_start)?;
+        context
+            .budget_repository
+            .create(
+                category_id,
+                period_start,
+                period_type.as_db_str().to_string(),
+                amount,
+                rollover.unwrap_or(false),
+            )
+            .await
+            .map_err(Into::into)
+            .map(|budget| budget.into())
+    }
+
+    async fn update_budget(
+        context: &GraphQLContext,
+        id: i32,
+        category_id: i32,
+        period_start: String,
+        period_type: BudgetPeriod,
+        amount: f64,
+        rollover: Option<bool>,
+    ) -> FieldResult<Budget> {
+        let amount = BigDecimal::from_str(&amount.to_string())?;
+        let period_start = parse_period_start(&period_start)?;
+        context
+            .budget_repository
+            .update(
+                id,
+                category_id,
+                period_start,
+                period_type.as_db_str().to_string(),
+                amount,
+                rollover,
+            )
+            .await
+            .map_err(Into::into)
+            .map(|budget| budget.into())
+    }
+
+    async fn delete_budget(context: &GraphQLContext, id: i32) -> FieldResult<bool> {
+        context
+            .budget_repository
+            .delete(id)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn assign_to_envelope(
+        context: &GraphQLContext,
+        category_id: i32,
+        month: String,
+        amount: f64,
+    ) -> FieldResult<EnvelopeAssignment> {
+        let amount = BigDecimal::from_str(&amount.to_string())?;
+        let month = parse_month_start(&month)?;
+        context
+            .budget_repository
+            .assign(category_id, month, amount)
+            .await
+            .map_err(Into::into)
+            .map(|assignment| assignment.into())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn create_recurring_transaction(
+        context: &GraphQLContext,
+        description: String,
+        amount: f64,
+        category_id: i32,
+        frequency: Frequency,
+        interval: Option<i32>,
+        day_of_month: Option<i32>,
+        start_date: String,
+        end_date: Option<String>,
+    ) -> FieldResult<RecurringTransaction> {
+        let interval = interval.unwrap_or(1);
+        validate_schedule(frequency, interval, day_of_month)?;
+        let amount = BigDecimal::from_str(&amount.to_string())?;
+        let start_date = parse_date(&start_date, "start date")?;
+        let end_date = end_date
+            .map(|end_date| parse_date(&end_date, "end date"))
+            .transpose()?;
+
+        context
+            .recurring_transaction_repository
+            .create(
+                description,
+                amount,
+                category_id,
+                frequency.as_db_str().to_string(),
+                interval,
+                day_of_month,
+                start_date,
+                end_date,
+            )
+            .await
+            .map_err(Into::into)
+            .map(|recurring| recurring.into())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn update_recurring_transaction(
+        context: &GraphQLContext,
+        id: i32,
+        description: String,
+        amount: f64,
+        category_id: i32,
+        frequency: Frequency,
+        interval: Option<i32>,
+        day_of_month: Option<i32>,
+        start_date: String,
+        end_date: Option<String>,
+        active: Option<bool>,
+    ) -> FieldResult<RecurringTransaction> {
+        let interval = interval.unwrap_or(1);
+        validate_schedule(frequency, interval, day_of_month)?;
+        let amount = BigDecimal::from_str(&amount.to_string())?;
+        let start_date = parse_date(&start_date, "start date")?;
+        let end_date = end_date
+            .map(|end_date| parse_date(&end_date, "end date"))
+            .transpose()?;
+
+        context
+            .recurring_transaction_repository
+            .update(
+                id,
+                description,
+                amount,
+                category_id,
+                frequency.as_db_str().to_string(),
+                interval,
+                day_of_month,
+                start_date,
+                end_date,
+                active.unwrap_or(true),
+            )
+            .await
+            .map_err(Into::into)
+            .map(|recurring| recurring.into())
+    }
+
+    #[graphql(
+        description = "Create a recurring schedule from a detected candidate, starting at its next expected date"
+    )]
+    async fn create_recurring_from_candidate(
+        context: &GraphQLContext,
+        merchant: String,
+        min_occurrences: Option<i32>,
+        lookback_months: Option<i32>,
+    ) -> FieldResult<RecurringTransaction> {
+        let candidate = recurring_candidates(context, min_occurrences, lookback_months)
+            .await?
+            .into_iter()
+            .find(|candidate| candidate.merchant == merchant)
+            .ok_or_else(|| format!("No recurring candidate found for merchant: {}", merchant))?;
+        let schedule = candidate.schedule();
+
+        context
+            .recurring_transaction_repository
+            .create(
+                candidate.description,
+                candidate.last_amount,
+                candidate.category_id,
+                schedule.frequency.as_db_str().to_string(),
+                schedule.interval as i32,
+                schedule.day_of_month.map(|day| day as i32),
+                to_sql_date(&schedule.start_date),
+                None,
+            )
+            .await
+            .map_err(Into::into)
+            .map(|recurring| recurring.into())
+    }
+
+    async fn delete_recurring_transaction(context: &GraphQLContext, id: i32) -> FieldResult<bool> {
+        context
+            .recurring_transaction_repository
+            .delete(id)
+            .await
+            .map_err(Into::into)
+    }
 }
@@ -0,0 +1,249 @@
+use crate::db_models::DbImportRow;
+use encoding_rs::Encoding;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+pub mod camt;
+pub mod categories;
+pub mod csv_file;
+pub mod ofx;
+pub mod profile;
+pub mod qif;
+pub mod rejected;
+pub mod report;
+pub mod spreadsheet;
+
+// Transactions imported without an account name all share this one
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// The kinds of file the importer reads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    Csv,
+    // OFX and Quicken's QFX, which is OFX with a few extra tags
+    Ofx,
+    Qif,
+    // ISO 20022 camt.053 XML bank statements
+    Camt,
+    // XLSX, ODS and the older XLS, read with the same profiles as CSV
+    Spreadsheet,
+}
+
+impl FileFormat {
+    /// The format from the file's extension, or from its contents when the extension
+    /// is missing or unknown
+    pub fn detect(path: &Path, contents: &[u8]) -> Self {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        match extension.as_deref().map(Self::from_str) {
+            Some(Ok(format)) => format,
+            _ if spreadsheet::is_spreadsheet(contents) => FileFormat::Spreadsheet,
+            _ if ofx::is_ofx(contents) => FileFormat::Ofx,
+            _ if qif::is_qif(contents) => FileFormat::Qif,
+            _ if camt::is_camt(contents) => FileFormat::Camt,
+            _ => FileFormat::Csv,
+        }
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = ImportError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "csv" => Ok(FileFormat::Csv),
+            "ofx" | "qfx" => Ok(FileFormat::Ofx),
+            "qif" => Ok(FileFormat::Qif),
+            "camt" | "camt.053" | "camt053" => Ok(FileFormat::Camt),
+            "xlsx" | "xlsm" | "xls" | "ods" => Ok(FileFormat::Spreadsheet),
+            _ => Err(ImportError::new(format!(
+                "Unknown file format '{}', expected csv, ofx, qfx, qif, camt, xlsx, xls or ods",
+                value
+            ))),
+        }
+    }
+}
+
+/// Everything read from an import file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    // the bank's account number, for formats that have one
+    pub account_id: Option<String>,
+    pub records: Vec<Result<ImportRecord, ImportError>>,
+    pub ledger_balance: Option<LedgerBalance>,
+}
+
+impl Statement {
+    pub fn from_records(records: Vec<Result<ImportRecord, ImportError>>) -> Self {
+        Self {
+            account_id: None,
+            records,
+            ledger_balance: None,
+        }
+    }
+}
+
+/// The closing balance the bank reports for the account
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerBalance {
+    pub amount: BigDecimal,
+    pub as_of: Date,
+}
+
+/// A transaction read from an import file, before its category is resolved
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRecord {
+    // line of the file the record was read from, for error messages
+    pub line: usize,
+    pub external_id: Option<String>,
+    pub date: Date,
+    pub amount: BigDecimal,
+    pub description: String,
+    pub category: Option<String>,
+    // the category the file had before the classifier predicted `category`
+    pub original_category: Option<String>,
+    // the classifier's confidence in `category`, from 0 to 1
+    pub confidence: Option<f64>,
+    // the classifier that predicted `category`
+    pub model: Option<String>,
+}
+
+/// A problem reading an import file, with where in the file it was found
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportError {
+    // the sheet of a spreadsheet, whose lines are its rows
+    pub sheet: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+impl ImportError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            sheet: None,
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn at_line(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(line),
+            ..Self::new(message)
+        }
+    }
+
+    pub fn in_column(mut self, column: &str) -> Self {
+        self.column = Some(column.to_string());
+        self
+    }
+
+    pub fn in_sheet(mut self, sheet: &str) -> Self {
+        self.sheet = Some(sheet.to_string());
+        self
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.sheet, self.line) {
+            (Some(sheet), Some(row)) => write!(f, "sheet '{}' row {}: ", sheet, row)?,
+            (Some(sheet), None) => write!(f, "sheet '{}': ", sheet)?,
+            (None, Some(line)) => write!(f, "line {}: ", line)?,
+            (None, None) => {}
+        }
+        if let Some(column) = &self.column {
+            write!(f, "column '{}': ", column)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Decode a file from `encoding`, a byte order mark takes precedence
+pub fn decode(contents: &[u8], encoding: &str) -> Result<String, ImportError> {
+    let encoding = Encoding::for_label(encoding.as_bytes())
+        .ok_or_else(|| ImportError::new(format!("Unknown encoding '{}'", encoding)))?;
+    let (text, used, had_errors) = encoding.decode(contents);
+    if had_errors {
+        return Err(ImportError::new(format!(
+            "The file is not valid {}, check its encoding",
+            used.name()
+        )));
+    }
+    Ok(text.into_owned())
+}
+
+/// Join the non-empty parts of a description, leaving out parts that repeat an
+/// earlier one, as a memo often repeats the payee
+pub(crate) fn describe(parts: &[Option<&str>]) -> String {
+    let mut description: Vec<&str> = Vec::new();
+    for part in parts.iter().flatten().map(|part| part.trim()) {
+        if !part.is_empty() && !description.iter().any(|earlier| earlier.contains(part)) {
+            description.push(part);
+        }
+    }
+    description.join(" ")
+}
+
+/// A hash of the row's account, date, amount and description. `occurrence` counts
+/// earlier identical rows in the same file, so two genuine coffees on the same day
+/// get different fingerprints while the same file imported twice gets the same ones
+pub fn fingerprint(row: &DbImportRow, occurrence: usize) -> String {
+    let description = row
+        .description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    sha256_hex(
+        format!(
+            "{}|{}|{}|{}|{}",
+            row.account,
+            row.date,
+            row.amount.normalized(),
+            description,
+            occurrence
+        )
+        .as_bytes(),
+    )
+}
+
+/// The SHA-256 of an import file, recorded on its batch
+pub fn file_hash(contents: &[u8]) -> String {
+    sha256_hex(contents)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The fingerprint of every row, in order
+pub fn fingerprints(rows: &[DbImportRow]) -> Vec<String> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    rows.iter()
+        .map(|row| {
+            let first = fingerprint(row, 0);
+            let occurrence = seen.entry(first.clone()).or_insert(0);
+            *occurrence += 1;
+            if *occurrence == 1 {
+                first
+            } else {
+                fingerprint(row, *occurrence - 1)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,171 @@
+use super::{decode, describe, ImportError, ImportRecord, LedgerBalance, Statement};
+use crate::db_models::to_sql_date;
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+
+// Banks fill in "NOTPROVIDED" when the payer gave no end to end reference
+const NOT_PROVIDED: &str = "NOTPROVIDED";
+
+/// Whether the file looks like an ISO 20022 camt.053 bank statement
+pub fn is_camt(contents: &[u8]) -> bool {
+    let start = String::from_utf8_lossy(&contents[..contents.len().min(2048)]);
+    start.contains("camt.053") || start.contains("BkToCstmrStmt")
+}
+
+/// Read every booked entry of the statements in the file. Elements are matched by
+/// their local name, so every version of the camt.053 namespace reads the same
+pub fn read_camt(contents: &[u8]) -> Result<Statement, ImportError> {
+    // XML is UTF-8 unless it says otherwise, which camt.053 never does
+    let text = decode(contents, "utf-8")?;
+    let document = Document::parse(&text)
+        .map_err(|e| ImportError::at_line(e.pos().row as usize, format!("Invalid XML: {}", e)))?;
+
+    let mut statement = Statement::from_records(Vec::new());
+    for stmt in document
+        .descendants()
+        .filter(|node| node.has_tag_name("Stmt"))
+    {
+        let account = child(stmt, &["Acct", "Id", "IBAN"])
+            .or_else(|| child(stmt, &["Acct", "Id", "Othr", "Id"]))
+            .and_then(|node| node.text());
+        if statement.account_id.is_none() {
+            statement.account_id = account.map(|account| account.trim().to_string());
+        }
+
+        for balance in children(stmt, "Bal") {
+            if text_of(balance, &["Tp", "CdOrPrtry", "Cd"]) == Some("CLBD") {
+                statement.ledger_balance = Some(closing_balance(&document, balance)?);
+            }
+        }
+        for entry in children(stmt, "Ntry") {
+            // a statement can still list entries the bank hasn't booked yet
+            if matches!(
+                text_of(entry, &["Sts"]).or_else(|| text_of(entry, &["Sts", "Cd"])),
+                Some("PDNG")
+            ) {
+                continue;
+            }
+            statement.records.push(record(&document, entry));
+        }
+    }
+
+    if statement.records.is_empty() && statement.account_id.is_none() {
+        return Err(ImportError::new("The file has no camt.053 statement"));
+    }
+    Ok(statement)
+}
+
+fn record(document: &Document, entry: Node) -> Result<ImportRecord, ImportError> {
+    let line = line_of(document, entry);
+    let amount =
+        signed_amount(entry).map_err(|e| ImportError::at_line(line, e).in_column("Amt"))?;
+    let date = text_of(entry, &["BookgDt", "Dt"])
+        .or_else(|| text_of(entry, &["BookgDt", "DtTm"]))
+        .or_else(|| text_of(entry, &["ValDt", "Dt"]))
+        .ok_or_else(|| {
+            ImportError::at_line(line, "Entry has no booking date").in_column("BookgDt")
+        })?;
+    let date = parse_date(date).map_err(|e| ImportError::at_line(line, e).in_column("BookgDt"))?;
+
+    let details = child(entry, &["NtryDtls", "TxDtls"]);
+    let detail = |path: &[&str]| details.and_then(|details| text_of(details, path));
+
+    // the bank's own reference is unique, the end to end id is the payer's and may repeat
+    let external_id = text_of(entry, &["AcctSvcrRef"])
+        .or_else(|| detail(&["Refs", "AcctSvcrRef"]))
+        .or_else(|| detail(&["Refs", "EndToEndId"]).filter(|id| *id != NOT_PROVIDED))
+        .map(str::to_string);
+
+    // the other party is the creditor of money going out and the debtor of money coming in
+    let party = if text_of(entry, &["CdtDbtInd"]) == Some("DBIT") {
+        "Cdtr"
+    } else {
+        "Dbtr"
+    };
+    let counterparty =
+        detail(&["RltdPties", party, "Nm"]).or_else(|| detail(&["RltdPties", party, "Pty", "Nm"]));
+    let remittance = detail(&["RmtInf", "Ustrd"]);
+    let description = match describe(&[counterparty, remittance]) {
+        description if !description.is_empty() => description,
+        _ => text_of(entry, &["AddtlNtryInf"])
+            .unwrap_or_default()
+            .to_string(),
+    };
+
+    Ok(ImportRecord {
+        line,
+        external_id,
+        date,
+        amount,
+        description,
+        category: None,
+        original_category: None,
+        confidence: None,
+        model: None,
+    })
+}
+
+fn closing_balance(document: &Document, balance: Node) -> Result<LedgerBalance, ImportError> {
+    let line = line_of(document, balance);
+    let amount =
+        signed_amount(balance).map_err(|e| ImportError::at_line(line, e).in_column("Bal"))?;
+    let as_of = text_of(balance, &["Dt", "Dt"])
+        .or_else(|| text_of(balance, &["Dt", "DtTm"]))
+        .ok_or_else(|| ImportError::at_line(line, "Balance has no date").in_column("Bal"))
+        .and_then(|date| {
+            parse_date(date).map_err(|e| ImportError::at_line(line, e).in_column("Bal"))
+        })?;
+    Ok(LedgerBalance { amount, as_of })
+}
+
+// Amounts are always positive, CdtDbtInd says which way the money went
+fn signed_amount(node: Node) -> Result<BigDecimal, String> {
+    let value = text_of(node, &["Amt"]).ok_or("Missing amount")?;
+    let amount = BigDecimal::from_str(value).map_err(|_| format!("Invalid amount '{}'", value))?;
+    match text_of(node, &["CdtDbtInd"]) {
+        Some("DBIT") => Ok(-amount),
+        Some("CRDT") => Ok(amount),
+        other => Err(format!(
+            "Invalid credit/debit indicator '{}', expected CRDT or DBIT",
+            other.unwrap_or_default()
+        )),
+    }
+}
+
+// Dates are ISO 8601, date-times keep only their date
+fn parse_date(value: &str) -> Result<Date, String> {
+    value
+        .get(..10)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .map(|date| to_sql_date(&date))
+        .ok_or_else(|| format!("Invalid date '{}', expected YYYY-MM-DD", value))
+}
+
+fn line_of(document: &Document, node: Node) -> usize {
+    document.text_pos_at(node.range().start).row as usize
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| child.has_tag_name(name))
+}
+
+// The element at `path` below the node, following the first match at each step
+fn child<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| {
+        node.children().find(|child| child.has_tag_name(*name))
+    })
+}
+
+fn text_of<'a>(node: Node<'a, '_>, path: &[&str]) -> Option<&'a str> {
+    child(node, path)
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
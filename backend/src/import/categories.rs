@@ -0,0 +1,40 @@
+use crate::db_models::{DbCategory, DbCategoryAlias};
+use std::collections::HashMap;
+
+/// Finds the category for a name in an imported file, by the category's own name or
+/// by an alias, ignoring case and surrounding spaces
+#[derive(Debug, Clone, Default)]
+pub struct CategoryMap {
+    // id and name of the category for each lowercased name
+    names: HashMap<String, (i32, String)>,
+}
+
+impl CategoryMap {
+    pub fn new(categories: &[DbCategory], aliases: &[DbCategoryAlias]) -> Self {
+        let mut map = Self::default();
+        for alias in aliases {
+            if let Some(category) = categories.iter().find(|c| c.id == alias.category_id) {
+                map.insert(&alias.name, category.id, &category.name);
+            }
+        }
+        // a category's own name wins over an alias spelled the same
+        for category in categories {
+            map.insert(&category.name, category.id, &category.name);
+        }
+        map
+    }
+
+    /// The id and name of the category `name` refers to
+    pub fn get(&self, name: &str) -> Option<(i32, String)> {
+        self.names.get(&key(name)).cloned()
+    }
+
+    pub fn insert(&mut self, name: &str, id: i32, category_name: &str) {
+        self.names
+            .insert(key(name), (id, category_name.to_string()));
+    }
+}
+
+fn key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
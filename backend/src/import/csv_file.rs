@@ -0,0 +1,108 @@
+use super::profile::Profile;
+use super::{decode, ImportError, ImportRecord};
+use csv::{ReaderBuilder, StringRecord};
+
+/// The header row of the file as the profile reads it, or None if it has no such row
+pub fn read_headers(contents: &[u8], profile: &Profile) -> Option<Vec<String>> {
+    let text = decode(contents, &profile.encoding).ok()?;
+    let mut rows = reader(&text, profile).into_records();
+    find_header(&LineIndex::new(&text), &mut rows, profile).ok()
+}
+
+/// Every data row of the file mapped through the profile. The outer error is for a
+/// file that can't be read at all, the inner ones for single rows
+pub fn read_csv(
+    contents: &[u8],
+    profile: &Profile,
+) -> Result<Vec<Result<ImportRecord, ImportError>>, ImportError> {
+    let text = decode(contents, &profile.encoding)?;
+    let lines = LineIndex::new(&text);
+    let mut rows = reader(&text, profile).into_records();
+    let headers = find_header(&lines, &mut rows, profile)?;
+    let index = profile.column_index(&headers)?;
+
+    let mut records = Vec::new();
+    for row in rows {
+        let record = match row {
+            Ok(row) if is_blank(&row) => continue,
+            Ok(row) => {
+                let fields: Vec<String> = row.iter().map(str::to_string).collect();
+                profile.record(&index, &fields, lines.line_of(&row))
+            }
+            Err(e) => Err(csv_error(&lines, e)),
+        };
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn reader<'a>(text: &'a str, profile: &Profile) -> csv::Reader<&'a [u8]> {
+    ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(profile.delimiter as u8)
+        .from_reader(text.as_bytes())
+}
+
+fn find_header(
+    lines: &LineIndex,
+    rows: &mut csv::StringRecordsIntoIter<&[u8]>,
+    profile: &Profile,
+) -> Result<Vec<String>, ImportError> {
+    for row in rows {
+        let row = row.map_err(|e| csv_error(lines, e))?;
+        if lines.line_of(&row) >= profile.header_row {
+            return Ok(row.iter().map(|header| header.trim().to_string()).collect());
+        }
+    }
+    Err(ImportError::new(format!(
+        "The file has no header on line {}",
+        profile.header_row
+    )))
+}
+
+// The csv reader's own line count and positions don't account for the blank lines it
+// skips, so lines are numbered from where the newlines are in the text
+struct LineIndex<'a> {
+    bytes: &'a [u8],
+    newlines: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let bytes = text.as_bytes();
+        let newlines = bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| **b == b'\n')
+            .map(|(position, _)| position)
+            .collect();
+        Self { bytes, newlines }
+    }
+
+    fn line_of(&self, row: &StringRecord) -> usize {
+        row.position()
+            .map_or(0, |position| self.line_at(position.byte()))
+    }
+
+    // The line of the first byte of a record, which may start with the newlines before it
+    fn line_at(&self, byte: u64) -> usize {
+        let mut start = (byte as usize).min(self.bytes.len());
+        while start < self.bytes.len() && matches!(self.bytes[start], b'\r' | b'\n') {
+            start += 1;
+        }
+        self.newlines.partition_point(|newline| *newline < start) + 1
+    }
+}
+
+fn is_blank(row: &StringRecord) -> bool {
+    row.iter().all(|field| field.trim().is_empty())
+}
+
+fn csv_error(lines: &LineIndex, error: csv::Error) -> ImportError {
+    let message = format!("Invalid CSV: {}", error);
+    match error.position() {
+        Some(position) => ImportError::at_line(lines.line_at(position.byte()), message),
+        None => ImportError::new(message),
+    }
+}
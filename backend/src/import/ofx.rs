@@ -0,0 +1,194 @@
+use super::{decode, describe, ImportError, ImportRecord, LedgerBalance, Statement};
+use crate::db_models::to_sql_date;
+use chrono::NaiveDate;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Whether the file looks like OFX, either the 1.x SGML or the 2.x XML flavour
+pub fn is_ofx(contents: &[u8]) -> bool {
+    let start = String::from_utf8_lossy(&contents[..contents.len().min(1024)]).to_uppercase();
+    start.trim_start().starts_with("OFXHEADER")
+        || start.contains("<?OFX")
+        || start.contains("<OFX>")
+}
+
+/// Read every transaction in the file. OFX 1.x leaves most closing tags out, so
+/// values run until the next tag and only aggregates like STMTTRN are closed.
+/// OFX 2.x is XML and closes every tag, the same reader handles both
+pub fn read_ofx(contents: &[u8]) -> Result<Statement, ImportError> {
+    let text = decode(contents, encoding(contents))?;
+    let start = text
+        .find("<OFX>")
+        .or_else(|| text.find("<ofx>"))
+        .ok_or_else(|| ImportError::new("The file has no <OFX> element"))?;
+
+    let mut statement = Statement::from_records(Vec::new());
+    // the open aggregate each value belongs to
+    let mut path: Vec<String> = Vec::new();
+    let mut transaction: Option<(usize, HashMap<String, String>)> = None;
+    let mut balance: HashMap<String, String> = HashMap::new();
+
+    for tag in Tags::new(&text, start) {
+        if let Some(name) = tag.name.strip_prefix('/') {
+            // OFX 2.x also closes elements, those were never pushed and close nothing
+            if let Some(position) = path.iter().rposition(|open| open == name) {
+                path.truncate(position);
+            }
+            if name == "STMTTRN" {
+                if let Some((line, fields)) = transaction.take() {
+                    statement.records.push(record(line, &fields));
+                }
+            }
+            continue;
+        }
+
+        match (tag.name.as_str(), tag.value.is_empty()) {
+            ("STMTTRN", _) => transaction = Some((tag.line, HashMap::new())),
+            (_, true) => path.push(tag.name),
+            (name, false) => {
+                if let Some((_, fields)) = transaction.as_mut() {
+                    fields.insert(name.to_string(), tag.value);
+                } else if path.last().map(String::as_str) == Some("LEDGERBAL") {
+                    balance.insert(name.to_string(), tag.value);
+                } else if name == "ACCTID" {
+                    statement.account_id = Some(tag.value);
+                }
+            }
+        }
+    }
+
+    if let (Some(amount), Some(as_of)) = (balance.get("BALAMT"), balance.get("DTASOF")) {
+        statement.ledger_balance = Some(LedgerBalance {
+            amount: parse_amount(amount).map_err(|e| ImportError::new(e).in_column("LEDGERBAL"))?,
+            as_of: parse_date(as_of).map_err(|e| ImportError::new(e).in_column("LEDGERBAL"))?,
+        });
+    }
+    Ok(statement)
+}
+
+fn record(line: usize, fields: &HashMap<String, String>) -> Result<ImportRecord, ImportError> {
+    let field = |name: &str| {
+        fields
+            .get(name)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    };
+    let required = |name: &str| {
+        field(name).ok_or_else(|| {
+            ImportError::at_line(line, "Transaction is missing its value").in_column(name)
+        })
+    };
+
+    let date = parse_date(required("DTPOSTED")?)
+        .map_err(|e| ImportError::at_line(line, e).in_column("DTPOSTED"))?;
+    let amount = parse_amount(required("TRNAMT")?)
+        .map_err(|e| ImportError::at_line(line, e).in_column("TRNAMT"))?;
+
+    // NAME is often cut short with the rest of the payee in MEMO, a check may have neither
+    let description = match describe(&[field("NAME"), field("MEMO")]) {
+        description if !description.is_empty() => description,
+        _ => match field("CHECKNUM") {
+            Some(number) => format!("Check {}", number),
+            None => field("TRNTYPE").unwrap_or_default().to_string(),
+        },
+    };
+
+    Ok(ImportRecord {
+        line,
+        external_id: field("FITID").map(str::to_string),
+        date,
+        amount,
+        description,
+        category: None,
+        original_category: None,
+        confidence: None,
+        model: None,
+    })
+}
+
+/// OFX dates are YYYYMMDD followed by an optional time and time zone
+fn parse_date(value: &str) -> Result<Date, String> {
+    value
+        .get(..8)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .map(|date| to_sql_date(&date))
+        .ok_or_else(|| format!("Invalid date '{}', expected YYYYMMDD", value))
+}
+
+// Some banks write amounts with a decimal comma, OFX never uses thousands separators
+fn parse_amount(value: &str) -> Result<BigDecimal, String> {
+    BigDecimal::from_str(&value.trim().replace(',', "."))
+        .map_err(|_| format!("Invalid amount '{}'", value))
+}
+
+// OFX 1.x names the character set in its header, OFX 2.x in the XML declaration
+fn encoding(contents: &[u8]) -> &'static str {
+    let start = String::from_utf8_lossy(&contents[..contents.len().min(1024)]).to_uppercase();
+    if start.contains("CHARSET:1252") || start.contains("ENCODING=\"WINDOWS-1252\"") {
+        "windows-1252"
+    } else if start.contains("CHARSET:8859-1") || start.contains("ENCODING=\"ISO-8859-1\"") {
+        "iso-8859-1"
+    } else {
+        "utf-8"
+    }
+}
+
+/// One tag of the file and the text after it
+struct Tag {
+    name: String,
+    value: String,
+    line: usize,
+}
+
+struct Tags<'a> {
+    text: &'a str,
+    position: usize,
+    // the line `position` is on
+    line: usize,
+}
+
+impl<'a> Tags<'a> {
+    fn new(text: &'a str, start: usize) -> Self {
+        Self {
+            text,
+            position: start,
+            line: text[..start].matches('\n').count() + 1,
+        }
+    }
+}
+
+impl Iterator for Tags<'_> {
+    type Item = Tag;
+
+    fn next(&mut self) -> Option<Tag> {
+        let open = self.position + self.text[self.position..].find('<')?;
+        let close = open + self.text[open..].find('>')?;
+        let value_end = self.text[close..]
+            .find('<')
+            .map_or(self.text.len(), |next| close + next);
+        let line = self.line + self.text[self.position..open].matches('\n').count();
+        self.line = line + self.text[open..value_end].matches('\n').count();
+        self.position = value_end;
+
+        let name = self.text[open + 1..close].trim().to_uppercase();
+        // a self-closing tag has no value
+        let name = name.trim_end_matches('/').trim_end().to_string();
+        Some(Tag {
+            name,
+            value: unescape(self.text[close + 1..value_end].trim()),
+            line,
+        })
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,469 @@
+use super::{ImportError, ImportRecord};
+use crate::db_models::to_sql_date;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use sqlx::types::BigDecimal;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// How a bank writes money going out and coming in
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignConvention {
+    // one amount column, negative for money going out
+    Signed,
+    // one amount column, positive for money going out, as most card exports do
+    Inverted,
+    // separate unsigned debit and credit columns
+    DebitCredit,
+}
+
+/// The file's column name for each transaction field
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnMap {
+    pub date: String,
+    pub description: String,
+    #[serde(default)]
+    pub amount: Option<String>,
+    #[serde(default)]
+    pub debit: Option<String>,
+    #[serde(default)]
+    pub credit: Option<String>,
+    #[serde(default)]
+    pub transaction_id: Option<String>,
+    #[serde(default)]
+    pub reference_number: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    // the category before classification, when `category` is the classifier's prediction
+    #[serde(default)]
+    pub original_category: Option<String>,
+    // the classifier's confidence in `category`, from 0 to 1
+    #[serde(default)]
+    pub confidence: Option<String>,
+    // the classifier that predicted `category`
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// How to read one bank's export, loaded from a TOML or YAML file
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    // defaults to the file name without its extension
+    #[serde(default)]
+    pub name: String,
+    pub columns: ColumnMap,
+    // a chrono format string, e.g. "%d.%m.%Y"
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: char,
+    #[serde(default = "default_sign")]
+    pub sign: SignConvention,
+    // any WHATWG encoding label, e.g. "utf-8" or "windows-1252"
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    // 1-based line of the header, lines above it are skipped. The row for spreadsheets
+    #[serde(default = "default_header_row")]
+    pub header_row: usize,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    // the sheet to read from a spreadsheet, the first one when not given
+    #[serde(default)]
+    pub sheet: Option<String>,
+}
+
+// The profile used when none is given
+pub const BUILT_IN_PROFILE: &str = "default";
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+fn default_decimal_separator() -> char {
+    '.'
+}
+
+fn default_sign() -> SignConvention {
+    SignConvention::Signed
+}
+
+fn default_encoding() -> String {
+    "utf-8".to_string()
+}
+
+fn default_header_row() -> usize {
+    1
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+impl Profile {
+    /// The export of the bank the importer was first written for, with the category
+    /// predicted by the classifier
+    pub fn built_in() -> Self {
+        Self {
+            name: BUILT_IN_PROFILE.to_string(),
+            columns: ColumnMap {
+                date: "Effective Date".to_string(),
+                description: "Description".to_string(),
+                amount: Some("Amount".to_string()),
+                debit: None,
+                credit: None,
+                transaction_id: Some("Transaction ID".to_string()),
+                reference_number: Some("Reference Number".to_string()),
+                category: Some("Predicted_Category".to_string()),
+                original_category: Some("Category".to_string()),
+                confidence: Some("Confidence_Score".to_string()),
+                model: None,
+            },
+            date_format: "%m/%d/%Y".to_string(),
+            decimal_separator: default_decimal_separator(),
+            sign: default_sign(),
+            encoding: default_encoding(),
+            header_row: default_header_row(),
+            delimiter: default_delimiter(),
+            sheet: None,
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ImportError> {
+        let profile: Profile = toml::from_str(text)
+            .map_err(|e| ImportError::new(format!("Invalid profile: {}", e)))?;
+        profile.validate()
+    }
+
+    pub fn from_yaml(text: &str) -> Result<Self, ImportError> {
+        let profile: Profile = serde_yaml::from_str(text)
+            .map_err(|e| ImportError::new(format!("Invalid profile: {}", e)))?;
+        profile.validate()
+    }
+
+    /// Load a .toml, .yaml or .yml profile
+    pub fn load(path: &Path) -> Result<Self, ImportError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            ImportError::new(format!("Cannot read profile {}: {}", path.display(), e))
+        })?;
+        let mut profile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&text),
+            Some("yaml") | Some("yml") => Self::from_yaml(&text),
+            _ => Err(ImportError::new(
+                "Profiles must be .toml, .yaml or .yml files",
+            )),
+        }
+        .map_err(|e| ImportError::new(format!("{}: {}", path.display(), e.message)))?;
+
+        if profile.name.is_empty() {
+            profile.name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+        }
+        Ok(profile)
+    }
+
+    /// The built-in profile followed by every profile in `dir`, sorted by name.
+    /// A missing directory just means there are no extra profiles
+    pub fn load_all(dir: &Path) -> Result<Vec<Self>, ImportError> {
+        let mut profiles = Vec::new();
+        if dir.is_dir() {
+            let entries = std::fs::read_dir(dir).map_err(|e| {
+                ImportError::new(format!("Cannot read profiles in {}: {}", dir.display(), e))
+            })?;
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let is_profile = matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("toml") | Some("yaml") | Some("yml")
+                );
+                if is_profile {
+                    profiles.push(Self::load(&path)?);
+                }
+            }
+        }
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        profiles.insert(0, Self::built_in());
+        Ok(profiles)
+    }
+
+    fn validate(self) -> Result<Self, ImportError> {
+        if self.header_row == 0 {
+            return Err(ImportError::new(
+                "header_row is 1-based and must be at least 1",
+            ));
+        }
+        if self.decimal_separator != '.' && self.decimal_separator != ',' {
+            return Err(ImportError::new("decimal_separator must be '.' or ','"));
+        }
+        if !self.delimiter.is_ascii() {
+            return Err(ImportError::new(
+                "delimiter must be a single ASCII character",
+            ));
+        }
+        if encoding_rs::Encoding::for_label(self.encoding.as_bytes()).is_none() {
+            return Err(ImportError::new(format!(
+                "Unknown encoding '{}'",
+                self.encoding
+            )));
+        }
+        match self.sign {
+            SignConvention::Signed | SignConvention::Inverted if self.columns.amount.is_none() => {
+                Err(ImportError::new(
+                    "An amount column is needed for this sign convention",
+                ))
+            }
+            SignConvention::DebitCredit
+                if self.columns.debit.is_none() || self.columns.credit.is_none() =>
+            {
+                Err(ImportError::new(
+                    "Debit and credit columns are needed for debit_credit",
+                ))
+            }
+            _ => Ok(self),
+        }
+    }
+
+    /// Every column the profile reads
+    pub fn mapped_columns(&self) -> Vec<&str> {
+        let columns = &self.columns;
+        [
+            Some(&columns.date),
+            Some(&columns.description),
+            columns.amount.as_ref(),
+            columns.debit.as_ref(),
+            columns.credit.as_ref(),
+            columns.transaction_id.as_ref(),
+            columns.reference_number.as_ref(),
+            columns.category.as_ref(),
+            columns.original_category.as_ref(),
+            columns.confidence.as_ref(),
+            columns.model.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect()
+    }
+
+    /// Whether every mapped column is in `headers`
+    pub fn matches(&self, headers: &[String]) -> bool {
+        self.mapped_columns()
+            .iter()
+            .all(|column| headers.iter().any(|header| header.trim() == *column))
+    }
+
+    /// The position of each mapped column in `headers`
+    pub fn column_index(&self, headers: &[String]) -> Result<ColumnIndex, ImportError> {
+        let mut positions = HashMap::new();
+        for column in self.mapped_columns() {
+            let position = headers
+                .iter()
+                .position(|header| header.trim() == column)
+                .ok_or_else(|| {
+                    ImportError::new(format!(
+                        "Column '{}' from profile '{}' is not in the header",
+                        column, self.name
+                    ))
+                })?;
+            positions.insert(column.to_string(), position);
+        }
+        Ok(ColumnIndex { positions })
+    }
+
+    /// Map one row of fields to a record
+    pub fn record(
+        &self,
+        index: &ColumnIndex,
+        fields: &[String],
+        line: usize,
+    ) -> Result<ImportRecord, ImportError> {
+        let columns = &self.columns;
+        let field = |column: &str| index.field(fields, column);
+        let optional = |column: &Option<String>| {
+            column
+                .as_deref()
+                .map(field)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        let date = self
+            .parse_date(field(&columns.date))
+            .map_err(|e| ImportError::at_line(line, e).in_column(&columns.date))?;
+
+        let amount = match self.sign {
+            SignConvention::Signed | SignConvention::Inverted => {
+                let column = columns.amount.as_deref().unwrap_or_default();
+                let amount = self
+                    .parse_amount(field(column))
+                    .map_err(|e| ImportError::at_line(line, e).in_column(column))?;
+                if self.sign == SignConvention::Inverted {
+                    -amount
+                } else {
+                    amount
+                }
+            }
+            SignConvention::DebitCredit => {
+                let mut amount = BigDecimal::from(0);
+                for (column, sign) in [(&columns.credit, 1), (&columns.debit, -1)] {
+                    let column = column.as_deref().unwrap_or_default();
+                    let value = field(column);
+                    if !value.is_empty() {
+                        let value = self
+                            .parse_amount(value)
+                            .map_err(|e| ImportError::at_line(line, e).in_column(column))?;
+                        amount += value.abs() * BigDecimal::from(sign);
+                    }
+                }
+                amount
+            }
+        };
+
+        let confidence = match optional(&columns.confidence) {
+            Some(value) => Some(self.parse_confidence(&value).map_err(|e| {
+                ImportError::at_line(line, e)
+                    .in_column(columns.confidence.as_deref().unwrap_or_default())
+            })?),
+            None => None,
+        };
+
+        Ok(ImportRecord {
+            line,
+            // prefer the bank's transaction id, some exports only fill in the reference number
+            external_id: optional(&columns.transaction_id)
+                .or_else(|| optional(&columns.reference_number)),
+            date,
+            amount,
+            description: field(&columns.description).to_string(),
+            category: optional(&columns.category),
+            original_category: optional(&columns.original_category),
+            confidence,
+            model: optional(&columns.model),
+        })
+    }
+
+    /// A confidence from 0 to 1, written with the profile's decimal separator
+    pub fn parse_confidence(&self, value: &str) -> Result<f64, String> {
+        value
+            .trim()
+            .replace(self.decimal_separator, ".")
+            .parse::<f64>()
+            .ok()
+            .filter(|confidence| (0.0..=1.0).contains(confidence))
+            .ok_or_else(|| {
+                format!(
+                    "Invalid confidence '{}', expected a number from 0 to 1",
+                    value
+                )
+            })
+    }
+
+    pub fn parse_date(&self, value: &str) -> Result<sqlx::types::time::Date, String> {
+        NaiveDate::parse_from_str(value, &self.date_format)
+            .map(|date| to_sql_date(&date))
+            .map_err(|e| {
+                format!(
+                    "Invalid date '{}', expected format {}: {}",
+                    value, self.date_format, e
+                )
+            })
+    }
+
+    /// Parse an amount like "1.234,56", "$1,234.56" or "(12.00)" using the profile's
+    /// decimal separator
+    pub fn parse_amount(&self, value: &str) -> Result<BigDecimal, String> {
+        let thousands_separator = if self.decimal_separator == ',' {
+            '.'
+        } else {
+            ','
+        };
+        let (negative, number) = match value.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+            Some(inner) => (true, inner),
+            None => (false, value),
+        };
+        let cleaned: String = number
+            .chars()
+            .filter(|c| {
+                !c.is_whitespace()
+                    && *c != thousands_separator
+                    && !matches!(c, '$' | '€' | '£' | '¥' | '\'')
+            })
+            .map(|c| if c == self.decimal_separator { '.' } else { c })
+            .collect();
+
+        let amount =
+            BigDecimal::from_str(&cleaned).map_err(|_| format!("Invalid amount '{}'", value))?;
+        Ok(if negative { -amount } else { amount })
+    }
+}
+
+/// Where each mapped column is in a file's rows
+#[derive(Debug, Clone)]
+pub struct ColumnIndex {
+    positions: HashMap<String, usize>,
+}
+
+impl ColumnIndex {
+    // A short row reads as empty fields rather than failing
+    fn field<'a>(&self, fields: &'a [String], column: &str) -> &'a str {
+        self.positions
+            .get(column)
+            .and_then(|position| fields.get(*position))
+            .map(|value| value.trim())
+            .unwrap_or_default()
+    }
+}
+
+/// The profile named `name`, or loaded from `name` if it is a path to a profile file
+pub fn find_profile(profiles: &[Profile], name: &str) -> Result<Profile, ImportError> {
+    if let Some(profile) = profiles.iter().find(|profile| profile.name == name) {
+        return Ok(profile.clone());
+    }
+    let path = Path::new(name);
+    if path.is_file() {
+        return Profile::load(path);
+    }
+    Err(ImportError::new(format!(
+        "No profile named '{}', available profiles: {}",
+        name,
+        profile_names(profiles)
+    )))
+}
+
+/// The profile whose columns are all in the file's header. When several match, the
+/// one mapping the most columns is the most specific
+pub fn detect_profile(
+    profiles: &[Profile],
+    headers_for: impl Fn(&Profile) -> Option<Vec<String>>,
+) -> Result<&Profile, ImportError> {
+    let mut best: Option<&Profile> = None;
+    for profile in profiles {
+        let Some(headers) = headers_for(profile) else {
+            continue;
+        };
+        let more_specific =
+            best.is_none_or(|best| profile.mapped_columns().len() > best.mapped_columns().len());
+        if profile.matches(&headers) && more_specific {
+            best = Some(profile);
+        }
+    }
+    best.ok_or_else(|| {
+        ImportError::new(format!(
+            "No profile matches the file's header, available profiles: {}",
+            profile_names(profiles)
+        ))
+    })
+}
+
+fn profile_names(profiles: &[Profile]) -> String {
+    profiles
+        .iter()
+        .map(|profile| profile.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,168 @@
+use super::{decode, describe, ImportError, ImportRecord, Statement};
+use crate::db_models::to_sql_date;
+use chrono::NaiveDate;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+
+// Sections holding bank style transactions, investment, category and class lists are skipped
+const TRANSACTION_TYPES: [&str; 5] = ["bank", "cash", "ccard", "oth a", "oth l"];
+
+/// Whether the file looks like a Quicken QIF export
+pub fn is_qif(contents: &[u8]) -> bool {
+    let start = String::from_utf8_lossy(&contents[..contents.len().min(64)]).to_lowercase();
+    let start = start.trim_start_matches('\u{feff}').trim_start();
+    start.starts_with("!type:") || start.starts_with("!account") || start.starts_with("!option")
+}
+
+/// Read every transaction in the file. Each one is a group of lines starting with a
+/// field code and ending with `^`. Quicken writes dates month first, with `'` before
+/// the year for years from 2000
+pub fn read_qif(contents: &[u8]) -> Result<Statement, ImportError> {
+    // older Quicken versions write Windows-1252 without saying so
+    let text = decode(contents, "utf-8").or_else(|_| decode(contents, "windows-1252"))?;
+
+    let mut statement = Statement::from_records(Vec::new());
+    let mut section = String::new();
+    // the start line and fields of the record being read
+    let mut record: Option<(usize, Vec<(char, String)>)> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim_end();
+        if let Some(header) = line.strip_prefix('!') {
+            let header = header.trim().to_lowercase();
+            if header.starts_with("type:") || header == "account" {
+                section = header;
+            }
+            continue;
+        }
+        let Some(code) = line.chars().next() else {
+            continue;
+        };
+        let value = line[code.len_utf8()..].trim().to_string();
+
+        if code == '^' {
+            if let Some((start, fields)) = record.take() {
+                if section == "account" {
+                    if statement.account_id.is_none() {
+                        statement.account_id = field(&fields, 'N').map(str::to_string);
+                    }
+                } else if is_transaction_section(&section) {
+                    statement.records.push(transaction(start, &fields));
+                }
+            }
+            continue;
+        }
+        record
+            .get_or_insert_with(|| (line_number, Vec::new()))
+            .1
+            .push((code, value));
+    }
+
+    if record.is_some_and(|(_, fields)| !fields.is_empty()) && is_transaction_section(&section) {
+        return Err(ImportError::new(
+            "The file ends in the middle of a transaction",
+        ));
+    }
+    Ok(statement)
+}
+
+fn is_transaction_section(section: &str) -> bool {
+    section
+        .strip_prefix("type:")
+        .is_some_and(|kind| TRANSACTION_TYPES.contains(&kind))
+}
+
+// The first value with the code, a split transaction repeats some codes per split
+fn field(fields: &[(char, String)], code: char) -> Option<&str> {
+    fields
+        .iter()
+        .find(|(field_code, value)| *field_code == code && !value.is_empty())
+        .map(|(_, value)| value.as_str())
+}
+
+fn transaction(line: usize, fields: &[(char, String)]) -> Result<ImportRecord, ImportError> {
+    let date = field(fields, 'D')
+        .ok_or_else(|| ImportError::at_line(line, "Transaction has no date").in_column("D"))?;
+    let date = parse_date(date).map_err(|e| ImportError::at_line(line, e).in_column("D"))?;
+    let (code, amount) = [('T', field(fields, 'T')), ('U', field(fields, 'U'))]
+        .into_iter()
+        .find_map(|(code, value)| value.map(|value| (code, value)))
+        .ok_or_else(|| ImportError::at_line(line, "Transaction has no amount").in_column("T"))?;
+    let amount = parse_amount(amount)
+        .map_err(|e| ImportError::at_line(line, e).in_column(&code.to_string()))?;
+
+    let description = match describe(&[field(fields, 'P'), field(fields, 'M')]) {
+        description if !description.is_empty() => description,
+        _ => field(fields, 'N')
+            .map(|number| format!("Check {}", number))
+            .unwrap_or_default(),
+    };
+
+    Ok(ImportRecord {
+        line,
+        external_id: None,
+        date,
+        amount,
+        description,
+        category: field(fields, 'L').and_then(category),
+        original_category: None,
+        confidence: None,
+        model: None,
+    })
+}
+
+/// Parse dates like "03/01/2025", "3/ 1/25", "3/1'25" and "2025-03-01"
+pub fn parse_date(value: &str) -> Result<Date, String> {
+    let invalid = || format!("Invalid date '{}', expected month/day/year", value);
+    let normalized: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '\'' | '-' | '.' => '/',
+            c => c,
+        })
+        .collect();
+    let parts: Vec<i32> = normalized
+        .split('/')
+        .map(|part| part.parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    let [first, second, third] = parts[..] else {
+        return Err(invalid());
+    };
+
+    let (year, month, day) = if first > 31 {
+        (first, second, third)
+    } else {
+        // two digit years before 70 are this century, as Quicken's own window has them
+        let year = match third {
+            0..=69 => third + 2000,
+            70..=99 => third + 1900,
+            _ => third,
+        };
+        (year, first, second)
+    };
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+        .map(|date| to_sql_date(&date))
+        .ok_or_else(invalid)
+}
+
+fn parse_amount(value: &str) -> Result<BigDecimal, String> {
+    BigDecimal::from_str(&value.replace([',', ' '], ""))
+        .map_err(|_| format!("Invalid amount '{}'", value))
+}
+
+// "Food:Groceries/Vacation" is the Groceries subcategory with the Vacation class,
+// categories are flat here so the subcategory is the name to look up. A transfer
+// to another account is written "[Savings]" and has no category
+fn category(value: &str) -> Option<String> {
+    let category = value.split('/').next().unwrap_or_default().trim();
+    if category.starts_with('[') || category.is_empty() {
+        return None;
+    }
+    category
+        .rsplit(':')
+        .next()
+        .map(|name| name.trim().to_string())
+}
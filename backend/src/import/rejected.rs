@@ -0,0 +1,67 @@
+use super::{ImportError, ImportRecord};
+use serde::Serialize;
+use std::io::Write;
+
+/// A row that wasn't imported, written to a CSV file so it can be fixed and imported again
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RejectedRow {
+    pub line: Option<usize>,
+    pub reason: String,
+    pub date: Option<String>,
+    pub amount: Option<String>,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub external_id: Option<String>,
+}
+
+impl RejectedRow {
+    /// A row read from the file whose category isn't known
+    pub fn unknown_category(record: &ImportRecord, category: &str) -> Self {
+        Self {
+            line: Some(record.line),
+            reason: format!("Category '{}' not found in database", category),
+            date: Some(record.date.to_string()),
+            amount: Some(record.amount.to_string()),
+            description: Some(record.description.clone()),
+            category: Some(category.to_string()),
+            external_id: record.external_id.clone(),
+        }
+    }
+
+    /// A row that couldn't be read, only the error says where it is
+    pub fn unreadable(error: &ImportError) -> Self {
+        Self {
+            line: error.line,
+            reason: error.to_string(),
+            date: None,
+            amount: None,
+            description: None,
+            category: None,
+            external_id: None,
+        }
+    }
+}
+
+const HEADER: [&str; 7] = [
+    "line",
+    "reason",
+    "date",
+    "amount",
+    "description",
+    "category",
+    "external_id",
+];
+
+/// Write the rows as CSV. The header is written even without rows, so the file from
+/// an earlier run doesn't look like this one's
+pub fn write_rejected<W: Write>(writer: W, rows: &[RejectedRow]) -> Result<(), ImportError> {
+    let error = |e: csv::Error| ImportError::new(format!("Cannot write rejected rows: {}", e));
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+    writer.write_record(HEADER).map_err(error)?;
+    for row in rows {
+        writer.serialize(row).map_err(error)?;
+    }
+    writer.flush().map_err(|e| error(e.into()))
+}
@@ -0,0 +1,191 @@
+use super::ImportError;
+use crate::db_models::DbImportAction;
+use serde::{Serialize, Serializer};
+use sqlx::types::BigDecimal;
+use std::fmt;
+
+/// What an import did, or with `--dry-run` would do, for reviewing before it's committed
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub source: String,
+    pub account: String,
+    pub dry_run: bool,
+    // rows read from the file, including the ones that could not be parsed
+    pub rows: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    pub duplicates: usize,
+    // rows left out because their category is unknown
+    pub rejected: usize,
+    pub unknown_categories: Vec<UnknownCategory>,
+    // unknown categories that are, or with `--dry-run` would be, created
+    pub new_categories: Vec<String>,
+    pub parse_errors: Vec<ImportError>,
+    // rows that are inserted or updated, by category
+    pub category_totals: Vec<CategoryTotal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnknownCategory {
+    pub name: String,
+    pub lines: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CategoryTotal {
+    pub category: String,
+    pub rows: usize,
+    #[serde(serialize_with = "as_string")]
+    pub total: BigDecimal,
+}
+
+// Amounts are written as strings so a script reading the report gets them exactly
+fn as_string<S: Serializer>(value: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&value.to_string())
+}
+
+impl ImportReport {
+    pub fn new(source: &str, account: &str, dry_run: bool) -> Self {
+        Self {
+            source: source.to_string(),
+            account: account.to_string(),
+            dry_run,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_parse_error(&mut self, error: &ImportError) {
+        self.rows += 1;
+        self.parse_errors.push(error.clone());
+    }
+
+    /// Note the line of a row whose category is unknown, the row is counted by what's
+    /// done with it
+    pub fn add_unknown_category(&mut self, name: &str, line: usize) {
+        match self
+            .unknown_categories
+            .iter_mut()
+            .find(|unknown| unknown.name.eq_ignore_ascii_case(name))
+        {
+            Some(unknown) => unknown.lines.push(line),
+            None => self.unknown_categories.push(UnknownCategory {
+                name: name.to_string(),
+                lines: vec![line],
+            }),
+        }
+    }
+
+    pub fn add_rejected(&mut self) {
+        self.rows += 1;
+        self.rejected += 1;
+    }
+
+    pub fn add_new_category(&mut self, name: &str) {
+        self.new_categories.push(name.to_string());
+    }
+
+    /// Count a row with a known category by what the import does with it
+    pub fn add_row(&mut self, category: &str, amount: &BigDecimal, action: DbImportAction) {
+        self.rows += 1;
+        match action {
+            DbImportAction::Insert => self.inserted += 1,
+            DbImportAction::Update => self.updated += 1,
+            DbImportAction::Skip => {
+                self.duplicates += 1;
+                return;
+            }
+        }
+        match self
+            .category_totals
+            .iter_mut()
+            .find(|total| total.category == category)
+        {
+            Some(total) => {
+                total.rows += 1;
+                total.total += amount;
+            }
+            None => self.category_totals.push(CategoryTotal {
+                category: category.to_string(),
+                rows: 1,
+                total: amount.clone(),
+            }),
+        }
+    }
+
+    /// Sort the unknown categories and totals by name, for a stable report
+    pub fn finish(mut self) -> Self {
+        self.unknown_categories.sort_by(|a, b| a.name.cmp(&b.name));
+        self.new_categories.sort();
+        self.category_totals
+            .sort_by(|a, b| a.category.cmp(&b.category));
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (insert, update) = if self.dry_run {
+            ("to insert", "to update")
+        } else {
+            ("inserted", "updated")
+        };
+        let heading = if self.dry_run {
+            "Dry run of"
+        } else {
+            "Import of"
+        };
+        writeln!(f, "{} {} into '{}'", heading, self.source, self.account)?;
+        for (label, count) in [
+            ("Rows read".to_string(), self.rows),
+            (format!("Rows {}", insert), self.inserted),
+            (format!("Rows {}", update), self.updated),
+            ("Duplicates skipped".to_string(), self.duplicates),
+            ("Rows rejected".to_string(), self.rejected),
+            (
+                "Unknown categories".to_string(),
+                self.unknown_categories.len(),
+            ),
+            ("Parse errors".to_string(), self.parse_errors.len()),
+        ] {
+            writeln!(f, "  {:<24}{}", format!("{}:", label), count)?;
+        }
+
+        if !self.unknown_categories.is_empty() {
+            writeln!(f, "Rows with unknown categories:")?;
+            for unknown in &self.unknown_categories {
+                let lines: Vec<String> = unknown.lines.iter().map(usize::to_string).collect();
+                writeln!(f, "  {} (lines {})", unknown.name, lines.join(", "))?;
+            }
+        }
+        if !self.new_categories.is_empty() {
+            let created = if self.dry_run {
+                "Categories to create"
+            } else {
+                "Categories created"
+            };
+            writeln!(f, "{}: {}", created, self.new_categories.join(", "))?;
+        }
+        if !self.parse_errors.is_empty() {
+            writeln!(f, "Rows that could not be read:")?;
+            for error in &self.parse_errors {
+                writeln!(f, "  {}", error)?;
+            }
+        }
+        if !self.category_totals.is_empty() {
+            writeln!(f, "Totals by category:")?;
+            for total in &self.category_totals {
+                let amount = total.total.with_scale(2).to_string();
+                writeln!(
+                    f,
+                    "  {:<24}{:>6} rows {:>14}",
+                    total.category, total.rows, amount
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
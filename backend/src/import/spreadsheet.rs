@@ -0,0 +1,159 @@
+use super::profile::Profile;
+use super::{ImportError, ImportRecord};
+use calamine::{open_workbook_auto_from_rs, Data, Range, Reader};
+use chrono::NaiveDate;
+use std::io::Cursor;
+
+/// Whether the file is a zip archive, as XLSX and ODS files are, or an old binary XLS
+pub fn is_spreadsheet(contents: &[u8]) -> bool {
+    contents.starts_with(b"PK\x03\x04") || contents.starts_with(&[0xD0, 0xCF, 0x11, 0xE0])
+}
+
+/// The names of the sheets in the workbook
+pub fn sheet_names(contents: &[u8]) -> Result<Vec<String>, ImportError> {
+    let workbook = open_workbook_auto_from_rs(Cursor::new(contents))
+        .map_err(|e| ImportError::new(format!("Cannot open the spreadsheet: {}", e)))?;
+    Ok(workbook.sheet_names())
+}
+
+/// The header row of the profile's sheet, or None if it has no such sheet or row
+pub fn read_headers(contents: &[u8], profile: &Profile) -> Option<Vec<String>> {
+    let (_, range) = read_sheet(contents, profile).ok()?;
+    rows(&range, profile)
+        .into_iter()
+        .find(|(row, _)| *row == profile.header_row)
+        .map(|(_, fields)| header(&fields))
+}
+
+/// Every data row of the profile's sheet mapped through the profile, like `read_csv`.
+/// Rows are numbered as the spreadsheet shows them and errors name the sheet
+pub fn read_spreadsheet(
+    contents: &[u8],
+    profile: &Profile,
+) -> Result<Vec<Result<ImportRecord, ImportError>>, ImportError> {
+    let (sheet, range) = read_sheet(contents, profile)?;
+    let mut rows = rows(&range, profile).into_iter();
+
+    let headers: Vec<String> = rows
+        .by_ref()
+        .find(|(row, _)| *row >= profile.header_row)
+        .filter(|(row, _)| *row == profile.header_row)
+        .map(|(_, fields)| header(&fields))
+        .ok_or_else(|| {
+            ImportError::new(format!(
+                "The sheet has no header in row {}",
+                profile.header_row
+            ))
+            .in_sheet(&sheet)
+        })?;
+    let index = profile
+        .column_index(&headers)
+        .map_err(|e| e.in_sheet(&sheet))?;
+    let first_column = range.start().map_or(0, |(_, column)| column as usize);
+
+    let mut records = Vec::new();
+    for (row, fields) in rows {
+        if fields.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let record = profile.record(&index, &fields, row).map_err(|mut e| {
+            // name the column by its letter too, as the spreadsheet shows it
+            if let Some(column) = &e.column {
+                if let Some(position) = headers.iter().position(|header| header == column) {
+                    e.column = Some(format!(
+                        "{} ({})",
+                        column,
+                        column_letter(first_column + position)
+                    ));
+                }
+            }
+            e.in_sheet(&sheet)
+        });
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// The profile's sheet, or the first one
+fn read_sheet(contents: &[u8], profile: &Profile) -> Result<(String, Range<Data>), ImportError> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(contents))
+        .map_err(|e| ImportError::new(format!("Cannot open the spreadsheet: {}", e)))?;
+    let names = workbook.sheet_names();
+    let sheet = match &profile.sheet {
+        Some(sheet) if names.contains(sheet) => sheet.clone(),
+        Some(sheet) => {
+            return Err(ImportError::new(format!(
+                "No sheet named '{}', the sheets are: {}",
+                sheet,
+                names.join(", ")
+            )))
+        }
+        None => names
+            .first()
+            .cloned()
+            .ok_or_else(|| ImportError::new("The spreadsheet has no sheets"))?,
+    };
+    let range = workbook
+        .worksheet_range(&sheet)
+        .map_err(|e| ImportError::new(format!("Cannot read the sheet: {}", e)).in_sheet(&sheet))?;
+    Ok((sheet, range))
+}
+
+// Each row with its 1-based row number, the range starts at the first cell in use
+fn rows(range: &Range<Data>, profile: &Profile) -> Vec<(usize, Vec<String>)> {
+    let first_row = range.start().map_or(0, |(row, _)| row as usize);
+    range
+        .rows()
+        .enumerate()
+        .map(|(index, cells)| {
+            let fields = cells.iter().map(|cell| cell_text(cell, profile)).collect();
+            (first_row + index + 1, fields)
+        })
+        .collect()
+}
+
+fn header(fields: &[String]) -> Vec<String> {
+    fields
+        .iter()
+        .map(|field| field.trim().to_string())
+        .collect()
+}
+
+// Cells are turned into the text the profile expects in a CSV export of the same
+// bank, so dates are written in its date format and numbers with its decimal
+// separator. Reading dates from the cells is what keeps them from being mangled
+fn cell_text(cell: &Data, profile: &Profile) -> String {
+    let format_date = |date: NaiveDate| date.format(&profile.date_format).to_string();
+    let format_number =
+        |number: String| number.replace('.', &profile.decimal_separator.to_string());
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(text) => text.clone(),
+        Data::Int(number) => number.to_string(),
+        Data::Float(number) => format_number(number.to_string()),
+        Data::Bool(value) => value.to_string(),
+        Data::DateTime(date) => date
+            .as_datetime()
+            .map(|date| format_date(date.date()))
+            .unwrap_or_else(|| format_number(date.as_f64().to_string())),
+        Data::DateTimeIso(text) => text
+            .get(..10)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .map(format_date)
+            .unwrap_or_else(|| text.clone()),
+        Data::DurationIso(text) => text.clone(),
+        Data::Error(error) => format!("#{:?}", error),
+    }
+}
+
+/// The spreadsheet's name for a 0-based column: A, B, ... Z, AA, AB, ...
+pub fn column_letter(column: usize) -> String {
+    let mut letters = Vec::new();
+    let mut column = column + 1;
+    while column > 0 {
+        let remainder = (column - 1) % 26;
+        letters.push((b'A' + remainder as u8) as char);
+        column = (column - 1) / 26;
+    }
+    letters.iter().rev().collect()
+}
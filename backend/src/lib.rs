@@ -1,4 +1,11 @@
+pub mod audit;
+pub mod classifier;
 pub mod db_models;
 pub mod db_traits;
+pub mod duplicates;
 pub mod gql_schema;
 pub mod graphql;
+pub mod import;
+pub mod normalize;
+pub mod recurrence;
+pub mod rules;
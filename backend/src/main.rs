@@ -3,9 +3,15 @@ use actix_web::{web, App, HttpServer};
 use juniper_actix::{graphiql_handler, graphql_handler};
 use sqlx::postgres::PgPoolOptions;
 use std::sync::Arc;
-use transaction_server::db_models::{PgCategoryRepository, PgTransactionRepository};
+use transaction_server::db_models::{
+    PgAuditLogRepository, PgBudgetRepository, PgCategorizationRuleRepository,
+    PgCategoryRepository, PgImportBatchRepository, PgPeriodClosingRepository,
+    PgRecurringTransactionRepository, PgTransactionRepository,
+};
+use transaction_server::db_traits::TransactionRepository;
 use transaction_server::gql_schema;
 use transaction_server::gql_schema::GraphQLContext;
+use transaction_server::recurrence;
 
 mod config;
 
@@ -40,9 +46,41 @@ async fn main() -> std::io::Result<()> {
         .await
         .map_err(std::io::Error::other)?;
 
+    // Transactions written before descriptions were normalized get theirs now
+    let transaction_repository = Arc::new(PgTransactionRepository { pool: pool.clone() });
+    let normalized = transaction_repository
+        .normalize_descriptions()
+        .await
+        .map_err(std::io::Error::other)?;
+    if normalized > 0 {
+        println!("Normalized the descriptions of {} transactions", normalized);
+    }
+
+    // Materialize due recurring transactions in the background
+    let recurring_transaction_repository =
+        Arc::new(PgRecurringTransactionRepository { pool: pool.clone() });
+    let recurring_interval =
+        config::Config::get_env_var_with_default("RECURRING_INTERVAL_SECONDS", "3600")
+            .parse::<u64>()
+            .map_err(std::io::Error::other)?;
+    actix_web::rt::spawn(recurrence::run_materializer(
+        recurring_transaction_repository.clone(),
+        std::time::Duration::from_secs(recurring_interval),
+    ));
+
     let context: GraphQLContext = GraphQLContext {
         category_repository: Arc::new(PgCategoryRepository { pool: pool.clone() }),
-        transaction_repository: Arc::new(PgTransactionRepository { pool: pool.clone() }),
+        transaction_repository,
+        budget_repository: Arc::new(PgBudgetRepository { pool: pool.clone() }),
+        recurring_transaction_repository,
+        import_batch_repository: Arc::new(PgImportBatchRepository { pool: pool.clone() }),
+        categorization_rule_repository: Arc::new(PgCategorizationRuleRepository {
+            pool: pool.clone(),
+        }),
+        audit_log_repository: Arc::new(PgAuditLogRepository { pool: pool.clone() }),
+        period_closing_repository: Arc::new(PgPeriodClosingRepository { pool: pool.clone() }),
+        classifier: Default::default(),
+        actor: None,
     };
     let schema = Arc::new(gql_schema::create_schema());
 
@@ -74,6 +112,16 @@ async fn graphql_endpoint(
     req: actix_web::HttpRequest,
     payload: web::Payload,
 ) -> actix_web::Result<actix_web::HttpResponse> {
+    // Changes are audited under the user the X-User header names
+    let actor = req
+        .headers()
+        .get("X-User")
+        .and_then(|user| user.to_str().ok())
+        .map(str::to_string);
+    let context = gql_schema::GraphQLContext {
+        actor,
+        ..context.get_ref().clone()
+    };
     let resp = graphql_handler(&schema, &context, req, payload).await;
     println!("GraphQL response: {:#?}", resp);
     resp
@@ -0,0 +1,107 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+// What card processors and banks put in front of the merchant, e.g. "SQ *BLUE BOTTLE",
+// "TST* SHAKE SHACK" or "POS DEBIT SAFEWAY"
+static PROCESSOR_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^\s*(?:(?:SQ|TST|PP|PAYPAL|SP|DD|GOOGLE)\s*\*|POS\s+(?:DEBIT|PURCHASE|WITHDRAWAL)\b|POS\b|DEBIT\s+CARD\s+PURCHASE\b|CHECKCARD\b|PURCHASE\s+AUTHORIZED\s+ON\b|ACH\s+DEBIT\b)\s*",
+    )
+    .unwrap()
+});
+
+// 03/14, 3-14-25 and 2025-03-14
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:\d{1,2}[/-]\d{1,2}(?:[/-]\d{2,4})?|\d{4}-\d{2}-\d{2})$").unwrap()
+});
+
+// A masked card number like XXXX1234 or ****1234, or a store number like #1234
+static CARD_OR_STORE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^(?:[X*]{2,}\d*|#\d+)$").unwrap());
+
+// Two letter US state and territory codes banks put after the city
+const STATES: [&str; 51] = [
+    "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "DC", "FL", "GA", "HI", "ID", "IL", "IN", "IA",
+    "KS", "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE", "NV", "NH", "NJ", "NM",
+    "NY", "NC", "ND", "OH", "OK", "OR", "PA", "RI", "SC", "SD", "TN", "TX", "UT", "VT", "VA", "WA",
+    "WV", "WI", "WY",
+];
+
+// First words of city names with two words, so "SAN FRANCISCO CA" goes as a whole
+const CITY_PREFIXES: [&str; 10] = [
+    "SAN", "SANTA", "LOS", "LAS", "NEW", "FORT", "FT", "ST", "SAINT", "SALT",
+];
+
+/// The description without what changes from one charge to the next or says nothing
+/// about the merchant: processor prefixes, card and store numbers, dates, reference
+/// numbers and a trailing city and state. Letter case and punctuation are kept
+pub fn strip_noise(description: &str) -> String {
+    let mut rest = description;
+    while let Some(prefix) = PROCESSOR_PREFIX.find(rest) {
+        if prefix.is_empty() {
+            break;
+        }
+        rest = &rest[prefix.end()..];
+    }
+
+    let mut words: Vec<&str> = rest
+        .split_whitespace()
+        .filter(|word| {
+            !DATE.is_match(word)
+                && !CARD_OR_STORE.is_match(word)
+                // card, reference and phone numbers, zip codes
+                && word.chars().filter(char::is_ascii_digit).count() < 3
+        })
+        .collect();
+
+    // "SEATTLE WA", only in upper case and only when the merchant's name is left
+    let trimmed = |word: &str| {
+        word.trim_matches(|c: char| !c.is_alphanumeric())
+            .to_string()
+    };
+    if words.len() >= 3 && STATES.contains(&words[words.len() - 1]) {
+        words.truncate(words.len() - 2);
+        if words.len() >= 2 && CITY_PREFIXES.contains(&trimmed(words[words.len() - 1]).as_str()) {
+            words.pop();
+        }
+    }
+    words.join(" ")
+}
+
+// Descriptions containing one of these are replaced by its label before they're cleaned,
+// in this order. A copy of `TEXT_TRANSFORMS` in `classifier/utils/config.py`
+const TEXT_TRANSFORMS: [(&str, &str); 2] = [("NETFLIX", "netflix"), ("AMAZON.COM", "amazon goods")];
+
+// What Python's `\s` matches, which includes the ASCII separators \x1c to \x1f
+fn is_python_space(c: char) -> bool {
+    c.is_whitespace() || ('\x1c'..='\x1f').contains(&c)
+}
+
+/// `clean_text` in `classifier/scripts/preprocess.py`: lower case, with nothing but
+/// ASCII letters, digits and single spaces left
+pub fn clean_text(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter(|&c| c.is_ascii_alphanumeric() || is_python_space(c))
+        .collect::<String>()
+        .split(is_python_space)
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `transform_text` in `classifier/scripts/preprocess.py` with its `TEXT_TRANSFORMS`,
+/// which turns a description into the text the classifier model is trained on: the
+/// label of the first pattern it contains, or else `clean_text` of it
+pub fn transform_text(text: &str) -> String {
+    TEXT_TRANSFORMS
+        .iter()
+        .find(|(pattern, _)| text.contains(pattern))
+        .map_or_else(|| clean_text(text), |(_, label)| label.to_string())
+}
+
+/// The description stored in `normalized_description` and compared to find duplicates:
+/// `transform_text` of the description with its noise stripped first
+pub fn normalize_description(description: &str) -> String {
+    transform_text(&strip_noise(description))
+}
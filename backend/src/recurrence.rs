@@ -0,0 +1,346 @@
+use crate::db_models::{to_naive_date, DbRecurringTransaction, DbTransaction};
+use crate::db_traits::RecurringTransactionRepository;
+use crate::normalize::strip_noise;
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use juniper::{GraphQLEnum, GraphQLObject};
+use rust_decimal::prelude::ToPrimitive;
+use sqlx::types::BigDecimal;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Frequency {
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            Frequency::Weekly => "weekly",
+            Frequency::Monthly => "monthly",
+            Frequency::Yearly => "yearly",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "weekly" => Frequency::Weekly,
+            "yearly" => Frequency::Yearly,
+            _ => Frequency::Monthly,
+        }
+    }
+}
+
+/// A repeating date rule, e.g. every 2 weeks from the start date or monthly on day 15
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    // only used by monthly schedules, defaults to the day of the start date
+    pub day_of_month: Option<u32>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl From<&DbRecurringTransaction> for Schedule {
+    fn from(recurring: &DbRecurringTransaction) -> Self {
+        Self {
+            frequency: Frequency::from_db_str(&recurring.frequency),
+            interval: recurring.interval_count.max(1) as u32,
+            day_of_month: recurring.day_of_month.map(|d| d as u32),
+            start_date: to_naive_date(&recurring.start_date),
+            end_date: recurring.end_date.as_ref().map(to_naive_date),
+        }
+    }
+}
+
+impl Schedule {
+    /// All occurrences between `from` and `to`, both inclusive
+    pub fn occurrences_between(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let last = self.end_date.map_or(to, |end| end.min(to));
+        let mut occurrences = Vec::new();
+        let mut n = 0;
+        while let Some(date) = self.nth(n) {
+            if date > last {
+                break;
+            }
+            // a monthly day before the start day means the first month is skipped
+            if date >= from && date >= self.start_date {
+                occurrences.push(date);
+            }
+            n += 1;
+        }
+        occurrences
+    }
+
+    // None once the occurrence is past the last date chrono can represent
+    fn nth(&self, n: u32) -> Option<NaiveDate> {
+        let periods = n.checked_mul(self.interval)?;
+        match self.frequency {
+            Frequency::Weekly => self
+                .start_date
+                .checked_add_signed(Duration::try_weeks(i64::from(periods))?),
+            Frequency::Monthly => {
+                let month =
+                    first_of_month(self.start_date).checked_add_months(Months::new(periods))?;
+                let day = self.day_of_month.unwrap_or(self.start_date.day());
+                Some(clamp_to_month(month, day))
+            }
+            Frequency::Yearly => {
+                let month = first_of_month(self.start_date)
+                    .checked_add_months(Months::new(periods.checked_mul(12)?))?;
+                Some(clamp_to_month(month, self.start_date.day()))
+            }
+        }
+    }
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap()
+}
+
+// Day 31 falls on the last day of shorter months, Feb 29 on Feb 28 outside leap years
+fn clamp_to_month(month: NaiveDate, day: u32) -> NaiveDate {
+    let last_day = (month + Months::new(1) - Duration::days(1)).day();
+    month.with_day(day.min(last_day)).unwrap()
+}
+
+/// Write every occurrence that is due by `today` and not yet materialized
+/// into transactions, returning the number of transactions created. A schedule
+/// that fails is logged and retried on the next run without holding up the rest
+pub async fn materialize_due(
+    repository: &dyn RecurringTransactionRepository,
+    today: NaiveDate,
+) -> Result<u64, sqlx::Error> {
+    let mut created = 0;
+    for recurring in repository.active().await? {
+        let schedule = Schedule::from(&recurring);
+        let from = recurring
+            .last_materialized_date
+            .map(|d| to_naive_date(&d) + Duration::days(1))
+            .unwrap_or(schedule.start_date);
+        if from > today {
+            continue;
+        }
+
+        let dates = schedule.occurrences_between(from, today);
+        match repository.materialize(recurring.id, dates, &today).await {
+            Ok(count) => created += count,
+            Err(e) => eprintln!(
+                "Failed to materialize recurring transaction {}: {}",
+                recurring.id, e
+            ),
+        }
+    }
+    Ok(created)
+}
+
+/// Materialize due occurrences now and then on every tick of `every`
+pub async fn run_materializer(
+    repository: Arc<dyn RecurringTransactionRepository>,
+    every: std::time::Duration,
+) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        let today = chrono::Local::now().date_naive();
+        match materialize_due(repository.as_ref(), today).await {
+            Ok(0) => {}
+            Ok(created) => println!("Created {} recurring transactions", created),
+            Err(e) => eprintln!("Failed to materialize recurring transactions: {}", e),
+        }
+    }
+}
+
+/// How often a detected recurring charge repeats
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Cadence {
+    Weekly,
+    Biweekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Cadence {
+    const ALL: [Cadence; 5] = [
+        Cadence::Weekly,
+        Cadence::Biweekly,
+        Cadence::Monthly,
+        Cadence::Quarterly,
+        Cadence::Yearly,
+    ];
+
+    /// Accepted gap in days between two charges, allowing for weekends and short months
+    fn gap_range(&self) -> (i64, i64) {
+        match self {
+            Cadence::Weekly => (6, 8),
+            Cadence::Biweekly => (12, 16),
+            Cadence::Monthly => (26, 35),
+            Cadence::Quarterly => (84, 98),
+            Cadence::Yearly => (350, 380),
+        }
+    }
+
+    /// The schedule frequency and interval equivalent to this cadence
+    pub fn frequency(&self) -> (Frequency, u32) {
+        match self {
+            Cadence::Weekly => (Frequency::Weekly, 1),
+            Cadence::Biweekly => (Frequency::Weekly, 2),
+            Cadence::Monthly => (Frequency::Monthly, 1),
+            Cadence::Quarterly => (Frequency::Monthly, 3),
+            Cadence::Yearly => (Frequency::Yearly, 1),
+        }
+    }
+
+    fn from_gaps(gaps: &[i64]) -> Option<Self> {
+        Cadence::ALL.into_iter().find(|cadence| {
+            let (min, max) = cadence.gap_range();
+            gaps.iter().all(|gap| (min..=max).contains(gap))
+        })
+    }
+}
+
+/// A merchant that charged similar amounts at a regular cadence
+#[derive(GraphQLObject, Debug, Clone)]
+pub struct RecurringCandidate {
+    pub merchant: String,
+    // the most recent raw description, used as the template for a schedule
+    pub description: String,
+    pub cadence: Cadence,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub occurrences: i32,
+    pub average_amount: f64,
+    #[graphql(skip)]
+    pub last_amount: BigDecimal,
+    pub last_date: NaiveDate,
+    pub next_date: NaiveDate,
+    pub price_change: Option<PriceChange>,
+}
+
+/// The most recent change in the amount charged
+#[derive(GraphQLObject, Debug, Clone, PartialEq)]
+pub struct PriceChange {
+    pub previous_amount: f64,
+    pub new_amount: f64,
+    pub changed_on: NaiveDate,
+}
+
+impl RecurringCandidate {
+    /// A schedule continuing the charge from its next expected date
+    pub fn schedule(&self) -> Schedule {
+        let (frequency, interval) = self.cadence.frequency();
+        Schedule {
+            frequency,
+            interval,
+            day_of_month: (frequency == Frequency::Monthly).then(|| self.last_date.day()),
+            start_date: self.next_date,
+            end_date: None,
+        }
+    }
+}
+
+// Amounts that differ by more than this fraction between two charges are a different purchase
+const MAX_PRICE_CHANGE: f64 = 0.5;
+
+/// The words of the description with its noise stripped, digits and punctuation
+/// removed, so "NETFLIX.COM 8841" and "Netflix.com 1193" group together
+pub fn merchant_key(description: &str) -> String {
+    strip_noise(description)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Find debits from the same merchant with at least `min_occurrences` charges of
+/// similar amounts at a regular cadence, sorted by merchant
+pub fn detect_recurring(
+    transactions: &[DbTransaction],
+    min_occurrences: usize,
+) -> Vec<RecurringCandidate> {
+    let mut by_merchant: BTreeMap<String, Vec<&DbTransaction>> = BTreeMap::new();
+    for tx in transactions {
+        if tx.amount.to_f64().unwrap_or(0.0) >= 0.0 {
+            continue;
+        }
+        let key = merchant_key(&tx.description);
+        if !key.is_empty() {
+            by_merchant.entry(key).or_default().push(tx);
+        }
+    }
+
+    by_merchant
+        .into_iter()
+        .filter_map(|(merchant, mut charges)| {
+            if charges.len() < min_occurrences.max(2) {
+                return None;
+            }
+            charges.sort_by_key(|tx| (to_naive_date(&tx.date), tx.id));
+            candidate_for(merchant, &charges)
+        })
+        .collect()
+}
+
+fn candidate_for(merchant: String, charges: &[&DbTransaction]) -> Option<RecurringCandidate> {
+    let dates: Vec<NaiveDate> = charges.iter().map(|tx| to_naive_date(&tx.date)).collect();
+    let amounts: Vec<f64> = charges
+        .iter()
+        .map(|tx| -tx.amount.to_f64().unwrap_or(0.0))
+        .collect();
+
+    let gaps: Vec<i64> = dates
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).num_days())
+        .collect();
+    let cadence = Cadence::from_gaps(&gaps)?;
+
+    let mut price_change = None;
+    for (i, pair) in amounts.windows(2).enumerate() {
+        let (previous, new) = (pair[0], pair[1]);
+        if (new - previous).abs() > previous * MAX_PRICE_CHANGE {
+            return None;
+        }
+        if (new - previous).abs() >= 0.01 {
+            price_change = Some(PriceChange {
+                previous_amount: previous,
+                new_amount: new,
+                changed_on: dates[i + 1],
+            });
+        }
+    }
+
+    let last = charges.last()?;
+    let last_date = *dates.last()?;
+    let mut candidate = RecurringCandidate {
+        merchant,
+        description: last.description.clone(),
+        cadence,
+        category_id: last.category_id,
+        category_name: last.category_name.clone(),
+        occurrences: charges.len() as i32,
+        average_amount: amounts.iter().sum::<f64>() / amounts.len() as f64,
+        last_amount: last.amount.clone(),
+        last_date,
+        next_date: last_date,
+        price_change,
+    };
+
+    // The first occurrence after the last charge, on the same day of the month for monthly cadences
+    let schedule = Schedule {
+        start_date: last_date,
+        ..candidate.schedule()
+    };
+    candidate.next_date = schedule
+        .occurrences_between(
+            last_date + Duration::days(1),
+            last_date + Duration::days(400),
+        )
+        .first()
+        .copied()?;
+    Some(candidate)
+}
@@ -0,0 +1,280 @@
+use crate::db_models::{DbCategorizationRule, DbCategoryCorrection, DbTaggedTransaction};
+use crate::import::DEFAULT_ACCOUNT;
+use crate::recurrence::merchant_key;
+use juniper::GraphQLEnum;
+use regex::{Regex, RegexBuilder};
+use sqlx::types::time::OffsetDateTime;
+use sqlx::types::BigDecimal;
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum RuleMatchKind {
+    // the description contains the pattern
+    Contains,
+    // the pattern is a regular expression found somewhere in the description
+    Regex,
+}
+
+impl RuleMatchKind {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            RuleMatchKind::Contains => "contains",
+            RuleMatchKind::Regex => "regex",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "regex" => RuleMatchKind::Regex,
+            _ => RuleMatchKind::Contains,
+        }
+    }
+}
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AmountSign {
+    // money going out, a negative amount
+    Debit,
+    // money coming in, a positive amount
+    Credit,
+}
+
+impl AmountSign {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            AmountSign::Debit => "debit",
+            AmountSign::Credit => "credit",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Option<Self> {
+        match value {
+            "debit" => Some(AmountSign::Debit),
+            "credit" => Some(AmountSign::Credit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CorrectionSource {
+    // someone chose the category by hand, or it came with the imported file
+    Manual,
+    // the classifier predicted it
+    Model,
+    // a categorization rule chose it
+    Rule,
+}
+
+impl CorrectionSource {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            CorrectionSource::Manual => "manual",
+            CorrectionSource::Model => "model",
+            CorrectionSource::Rule => "rule",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Self {
+        match value {
+            "model" => CorrectionSource::Model,
+            "rule" => CorrectionSource::Rule,
+            _ => CorrectionSource::Manual,
+        }
+    }
+}
+
+/// The description pattern as a case-insensitive regex, a `contains` pattern matches literally
+pub fn compile_pattern(kind: RuleMatchKind, pattern: &str) -> Result<Regex, regex::Error> {
+    let pattern = match kind {
+        RuleMatchKind::Contains => regex::escape(pattern.trim()),
+        RuleMatchKind::Regex => pattern.to_string(),
+    };
+    RegexBuilder::new(&pattern).case_insensitive(true).build()
+}
+
+// A rule with its description pattern compiled
+struct CompiledRule {
+    rule: DbCategorizationRule,
+    pattern: Option<Regex>,
+}
+
+impl CompiledRule {
+    fn new(rule: DbCategorizationRule) -> Result<Self, regex::Error> {
+        let pattern = rule
+            .description_pattern
+            .as_deref()
+            .map(|pattern| compile_pattern(RuleMatchKind::from_db_str(&rule.match_kind), pattern))
+            .transpose()?;
+        Ok(Self { rule, pattern })
+    }
+
+    fn matches(&self, description: &str, amount: &BigDecimal, account: &str) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(description))
+            && rule_matches_amount(&self.rule, amount)
+            && self
+                .rule
+                .account
+                .as_deref()
+                .is_none_or(|rule_account| rule_account.eq_ignore_ascii_case(account))
+    }
+}
+
+/// The enabled rules ready to be evaluated, in the order they're tried
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    pub fn new(rules: Vec<DbCategorizationRule>) -> Result<Self, regex::Error> {
+        let mut compiled = rules
+            .into_iter()
+            .filter(|rule| rule.enabled)
+            .map(CompiledRule::new)
+            .collect::<Result<Vec<_>, _>>()?;
+        // highest priority first, the older rule wins a tie
+        compiled.sort_by_key(|compiled| (-compiled.rule.priority, compiled.rule.id));
+        Ok(Self { rules: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The first rule whose conditions all hold for the transaction
+    pub fn first_match(
+        &self,
+        description: &str,
+        amount: &BigDecimal,
+        account: &str,
+    ) -> Option<&DbCategorizationRule> {
+        self.rules
+            .iter()
+            .find(|compiled| compiled.matches(description, amount, account))
+            .map(|compiled| &compiled.rule)
+    }
+}
+
+/// What applying a rule would do to a transaction it matches
+#[derive(Debug, Clone)]
+pub struct RuleChange {
+    pub transaction: DbTaggedTransaction,
+    // the rule's tags the transaction doesn't have yet
+    pub added_tags: Vec<String>,
+}
+
+/// The transactions the rule matches that don't have its category or all of its tags yet.
+/// Unlike a rule set this applies the rule whatever its priority and whether it's enabled
+pub fn rule_changes(
+    rule: &DbCategorizationRule,
+    transactions: Vec<DbTaggedTransaction>,
+) -> Result<Vec<RuleChange>, regex::Error> {
+    let compiled = CompiledRule::new(rule.clone())?;
+    Ok(transactions
+        .into_iter()
+        .filter(|tx| compiled.matches(&tx.description, &tx.amount, &tx.account))
+        .map(|tx| {
+            let added_tags = rule
+                .tags
+                .iter()
+                .filter(|tag| !tx.tags.contains(tag))
+                .cloned()
+                .collect();
+            RuleChange {
+                transaction: tx,
+                added_tags,
+            }
+        })
+        .filter(|change: &RuleChange| {
+            change.transaction.category_id != rule.category_id || !change.added_tags.is_empty()
+        })
+        .collect())
+}
+
+// The sign says which way the money went, the range is on the amount's size
+fn rule_matches_amount(rule: &DbCategorizationRule, amount: &BigDecimal) -> bool {
+    let zero = BigDecimal::from(0);
+    let sign_matches = match rule.sign.as_deref().and_then(AmountSign::from_db_str) {
+        Some(AmountSign::Debit) => *amount < zero,
+        Some(AmountSign::Credit) => *amount > zero,
+        None => true,
+    };
+    let size = amount.abs();
+    sign_matches
+        && rule.min_amount.as_ref().is_none_or(|min| size >= *min)
+        && rule.max_amount.as_ref().is_none_or(|max| size <= *max)
+}
+
+/// A rule that would have saved someone from correcting the same merchant's category
+/// over and over
+#[derive(Debug, Clone)]
+pub struct RuleSuggestion {
+    // the merchant's words as merchant_key gives them
+    pub merchant: String,
+    // a regex pattern matching the merchant's words with anything but letters between them
+    pub description_pattern: String,
+    pub category_id: i32,
+    pub category_name: Option<String>,
+    pub corrections: usize,
+    pub last_corrected_at: Option<OffsetDateTime>,
+}
+
+/// Merchants whose transactions were corrected to the same category at least
+/// `min_corrections` times, most corrected first. Only a transaction's latest correction
+/// counts, and merchants the rules already give that category are left out
+pub fn suggest_rules(
+    corrections: &[DbCategoryCorrection],
+    rules: &RuleSet,
+    min_corrections: usize,
+) -> Vec<RuleSuggestion> {
+    let mut seen_transactions = HashSet::new();
+    let mut by_merchant: BTreeMap<(String, i32), Vec<&DbCategoryCorrection>> = BTreeMap::new();
+    let mut newest_first: Vec<&DbCategoryCorrection> = corrections.iter().collect();
+    newest_first.sort_by_key(|c| std::cmp::Reverse((c.corrected_at, c.id)));
+    for correction in newest_first {
+        if let Some(transaction_id) = correction.transaction_id {
+            if !seen_transactions.insert(transaction_id) {
+                continue;
+            }
+        }
+        let merchant = merchant_key(&correction.description);
+        if merchant.is_empty() {
+            continue;
+        }
+        by_merchant
+            .entry((merchant, correction.new_category_id))
+            .or_default()
+            .push(correction);
+    }
+
+    let mut suggestions: Vec<RuleSuggestion> = by_merchant
+        .into_iter()
+        .filter(|(_, corrections)| corrections.len() >= min_corrections)
+        .filter(|((_, category_id), corrections)| {
+            let latest = corrections[0];
+            rules
+                .first_match(&latest.description, &latest.amount, DEFAULT_ACCOUNT)
+                .is_none_or(|rule| rule.category_id != *category_id)
+        })
+        .map(|((merchant, category_id), corrections)| RuleSuggestion {
+            description_pattern: merchant
+                .split(' ')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join("[^a-z]+"),
+            merchant,
+            category_id,
+            category_name: corrections[0].new_category_name.clone(),
+            corrections: corrections.len(),
+            last_corrected_at: corrections[0].corrected_at,
+        })
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.corrections
+            .cmp(&a.corrections)
+            .then_with(|| a.merchant.cmp(&b.merchant))
+    });
+    suggestions
+}
@@ -0,0 +1,107 @@
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+use transaction_server::{
+    classifier::{features, CategoryClassifier},
+    db_models::DbTrainingExample,
+};
+
+fn amount(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+fn example(description: &str, value: &str, category_id: i32, name: &str) -> DbTrainingExample {
+    DbTrainingExample {
+        description: description.to_string(),
+        amount: amount(value),
+        category_id,
+        category_name: name.to_string(),
+        corrected: false,
+    }
+}
+
+fn training_set() -> Vec<DbTrainingExample> {
+    vec![
+        example("STARBUCKS STORE 1234", "-4.50", 1, "Dining"),
+        example("Starbucks #998 Seattle", "-6.10", 1, "Dining"),
+        example("CHIPOTLE ONLINE", "-12.80", 1, "Dining"),
+        example("WHOLE FOODS MARKET", "-84.20", 2, "Groceries"),
+        example("WHOLEFDS MKT 10233", "-45.00", 2, "Groceries"),
+        example("TRADER JOE'S #552", "-61.75", 2, "Groceries"),
+        example("ACME CORP PAYROLL", "2500.00", 3, "Income"),
+        example("ACME CORP PAYROLL DIRECT DEP", "2500.00", 3, "Income"),
+    ]
+}
+
+#[test]
+fn test_features() {
+    let tokens = features("POS STARBUCKS #1234 Seattle WA", &amount("-4.50"));
+    assert_eq!(
+        tokens,
+        vec!["starbucks", "__sign_debit", "__amount_debit_1"]
+    );
+
+    // Amounts under 1 fall in the lowest bucket, large ones in the highest
+    let tokens = features("Interest", &amount("0.42"));
+    assert!(tokens.contains(&"__amount_credit_0".to_string()));
+    let tokens = features("House", &amount("-250000000.00"));
+    assert!(tokens.contains(&"__amount_debit_6".to_string()));
+}
+
+#[test]
+fn test_train_without_examples() {
+    assert!(CategoryClassifier::train(&[]).is_none());
+}
+
+#[test]
+fn test_suggest_ranks_categories() {
+    let classifier = CategoryClassifier::train(&training_set()).unwrap();
+    assert_eq!(classifier.examples, 8);
+    assert_eq!(classifier.category_count(), 3);
+
+    let suggestions = classifier.suggest("STARBUCKS 5521 PORTLAND", &amount("-5.25"), 3);
+    assert_eq!(suggestions.len(), 3);
+    assert_eq!(suggestions[0].category_id, 1);
+    assert_eq!(suggestions[0].category_name, "Dining");
+    // most likely first, probabilities over every category add up to one
+    assert!(suggestions[0].probability > suggestions[1].probability);
+    assert!(suggestions[1].probability >= suggestions[2].probability);
+    let total: f64 = suggestions.iter().map(|s| s.probability).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+
+    let suggestions = classifier.suggest("Whole Foods Market #10", &amount("-70.00"), 1);
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!(suggestions[0].category_id, 2);
+
+    // The amount alone tells a paycheck apart from purchases
+    let suggestions = classifier.suggest("DIRECT DEPOSIT", &amount("1800.00"), 2);
+    assert_eq!(suggestions[0].category_id, 3);
+}
+
+#[test]
+fn test_suggest_unknown_words() {
+    let classifier = CategoryClassifier::train(&training_set()).unwrap();
+
+    // Nothing but unseen words and an amount still gives every category a probability
+    let suggestions = classifier.suggest("ZZZ QQQ", &amount("-10.00"), 5);
+    assert_eq!(suggestions.len(), 3);
+    assert!(suggestions.iter().all(|s| s.probability > 0.0));
+    assert_ne!(suggestions[0].category_id, 3);
+}
+
+#[test]
+fn test_corrections_count_more() {
+    // One word seen as often with either category leans to the one it was corrected to
+    let mut examples = vec![
+        example("AMAZON MKTP", "-20.00", 1, "Shopping"),
+        example("AMAZON MKTP", "-20.00", 2, "Books"),
+    ];
+    let classifier = CategoryClassifier::train(&examples).unwrap();
+    let suggestions = classifier.suggest("AMAZON MKTP", &amount("-20.00"), 2);
+    assert!((suggestions[0].probability - 0.5).abs() < 1e-9);
+
+    examples[1].corrected = true;
+    let classifier = CategoryClassifier::train(&examples).unwrap();
+    let suggestions = classifier.suggest("AMAZON MKTP", &amount("-20.00"), 2);
+    assert_eq!(suggestions[0].category_id, 2);
+    assert!(suggestions[0].probability > 0.5);
+}
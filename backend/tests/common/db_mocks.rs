@@ -2,10 +2,16 @@ use chrono::NaiveDate;
 use sqlx::types::time::Date;
 use sqlx::types::BigDecimal;
 use std::sync::Arc;
+use sqlx::types::time::OffsetDateTime;
+use tokio::sync::mpsc::UnboundedSender;
 use transaction_server::db_traits::MockCategoryRepository;
 use transaction_server::db_traits::MockTransactionRepository;
 use transaction_server::{
-    db_models::{DbCategory, DbCategorySummary, DbTransaction},
+    db_models::{
+        DbCategorizationRule, DbCategory, DbCategoryAccuracy, DbCategoryAlias, DbCategoryCorrection, DbCategorySummary, DbImportAction, DbImportBatch,
+        DbImportRow, DbMonthlyCategoryTotal, DbNewImportBatch, DbPeriodComparison, DbReviewItem,
+        DbTaggedTransaction, DbTrainingExample, DbTransaction,
+    },
     db_traits::{CategoryRepository, TransactionRepository},
 };
 
@@ -25,8 +31,9 @@ impl CategoryRepository for LocalMockCategoryRepository {
         description: Option<String>,
         icon: Option<String>,
         color: Option<String>,
+        actor: Option<String>,
     ) -> Result<DbCategory, sqlx::Error> {
-        self.inner.create(name, description, icon, color).await
+        self.inner.create(name, description, icon, color, actor).await
     }
 
     async fn update(
@@ -36,13 +43,30 @@ impl CategoryRepository for LocalMockCategoryRepository {
         description: Option<String>,
         icon: Option<String>,
         color: Option<String>,
+        actor: Option<String>,
     ) -> Result<DbCategory, sqlx::Error> {
-        self.inner.update(id, name, description, icon, color).await
+        self.inner.update(id, name, description, icon, color, actor).await
     }
 
     async fn find_by_name(&self, name: &str) -> Result<DbCategory, sqlx::Error> {
         self.inner.find_by_name(name).await
     }
+
+    async fn aliases(&self) -> Result<Vec<DbCategoryAlias>, sqlx::Error> {
+        self.inner.aliases().await
+    }
+
+    async fn set_alias(
+        &self,
+        name: String,
+        category_id: i32,
+    ) -> Result<DbCategoryAlias, sqlx::Error> {
+        self.inner.set_alias(name, category_id).await
+    }
+
+    async fn delete_alias(&self, id: i32) -> Result<bool, sqlx::Error> {
+        self.inner.delete_alias(id).await
+    }
 }
 
 pub struct LocalMockTransactionRepository {
@@ -58,9 +82,23 @@ impl TransactionRepository for LocalMockTransactionRepository {
         description: String,
         date: Date,
         category_id: i32,
+        actor: Option<String>,
+    ) -> Result<DbTransaction, sqlx::Error> {
+        self.inner
+            .create(amount, description, date, category_id, actor)
+            .await
+    }
+
+    async fn create_by_rule(
+        &self,
+        amount: BigDecimal,
+        description: String,
+        date: Date,
+        rule: DbCategorizationRule,
+        actor: Option<String>,
     ) -> Result<DbTransaction, sqlx::Error> {
         self.inner
-            .create(amount, description, date, category_id)
+            .create_by_rule(amount, description, date, rule, actor)
             .await
     }
 
@@ -75,12 +113,17 @@ impl TransactionRepository for LocalMockTransactionRepository {
         description: String,
         date: Date,
         category_id: i32,
+        actor: Option<String>,
     ) -> Result<DbTransaction, sqlx::Error> {
         self.inner
-            .update(id, amount, description, date, category_id)
+            .update(id, amount, description, date, category_id, actor)
             .await
     }
 
+    async fn delete(&self, id: i32, actor: Option<String>) -> Result<bool, sqlx::Error> {
+        self.inner.delete(id, actor).await
+    }
+
     async fn by_category_id(&self, category_id: i32) -> Result<Vec<DbTransaction>, sqlx::Error> {
         self.inner.by_category_id(category_id).await
     }
@@ -100,4 +143,168 @@ impl TransactionRepository for LocalMockTransactionRepository {
     ) -> Result<Vec<DbCategorySummary>, sqlx::Error> {
         self.inner.sum_by_category(_start_date, _end_date).await
     }
+
+    async fn all_as_of(&self, as_of: &OffsetDateTime) -> Result<Vec<DbTransaction>, sqlx::Error> {
+        self.inner.all_as_of(as_of).await
+    }
+
+    async fn by_category_id_as_of(
+        &self,
+        category_id: i32,
+        as_of: &OffsetDateTime,
+    ) -> Result<Vec<DbTransaction>, sqlx::Error> {
+        self.inner.by_category_id_as_of(category_id, as_of).await
+    }
+
+    async fn by_date_range_as_of(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+        as_of: &OffsetDateTime,
+    ) -> Result<Vec<DbTransaction>, sqlx::Error> {
+        self.inner.by_date_range_as_of(start_date, end_date, as_of).await
+    }
+
+    async fn sum_by_category_as_of(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+        as_of: &OffsetDateTime,
+    ) -> Result<Vec<DbCategorySummary>, sqlx::Error> {
+        self.inner.sum_by_category_as_of(start_date, end_date, as_of).await
+    }
+
+    async fn compare_by_category(
+        &self,
+        period_a_start: &NaiveDate,
+        period_a_end: &NaiveDate,
+        period_b_start: &NaiveDate,
+        period_b_end: &NaiveDate,
+    ) -> Result<Vec<DbPeriodComparison>, sqlx::Error> {
+        self.inner
+            .compare_by_category(period_a_start, period_a_end, period_b_start, period_b_end)
+            .await
+    }
+
+    async fn compare_by_description(
+        &self,
+        period_a_start: &NaiveDate,
+        period_a_end: &NaiveDate,
+        period_b_start: &NaiveDate,
+        period_b_end: &NaiveDate,
+    ) -> Result<Vec<DbPeriodComparison>, sqlx::Error> {
+        self.inner
+            .compare_by_description(period_a_start, period_a_end, period_b_start, period_b_end)
+            .await
+    }
+
+    async fn monthly_totals_by_category(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> Result<Vec<DbMonthlyCategoryTotal>, sqlx::Error> {
+        self.inner.monthly_totals_by_category(start_date, end_date).await
+    }
+
+    async fn find_duplicates(
+        &self,
+        start_date: &NaiveDate,
+        end_date: &NaiveDate,
+        window_days: i64,
+    ) -> Result<Vec<Vec<DbTransaction>>, sqlx::Error> {
+        self.inner.find_duplicates(start_date, end_date, window_days).await
+    }
+
+    async fn resolve_duplicates(
+        &self,
+        keep_id: i32,
+        remove_ids: Vec<i32>,
+        actor: Option<String>,
+    ) -> Result<u64, sqlx::Error> {
+        self.inner.resolve_duplicates(keep_id, remove_ids, actor).await
+    }
+
+    async fn import_transactions(
+        &self,
+        batch: DbNewImportBatch,
+        rows: Vec<DbImportRow>,
+        actor: Option<String>,
+    ) -> Result<DbImportBatch, sqlx::Error> {
+        self.inner.import_transactions(batch, rows, actor).await
+    }
+
+    async fn import_transactions_with_progress(
+        &self,
+        batch: DbNewImportBatch,
+        rows: Vec<DbImportRow>,
+        progress: UnboundedSender<usize>,
+        actor: Option<String>,
+    ) -> Result<DbImportBatch, sqlx::Error> {
+        self.inner
+            .import_transactions_with_progress(batch, rows, progress, actor)
+            .await
+    }
+
+    async fn preview_import(
+        &self,
+        rows: Vec<DbImportRow>,
+    ) -> Result<Vec<DbImportAction>, sqlx::Error> {
+        self.inner.preview_import(rows).await
+    }
+
+    async fn review_queue(
+        &self,
+        threshold: f64,
+        after: Option<(f64, i32)>,
+        limit: i64,
+    ) -> Result<Vec<DbReviewItem>, sqlx::Error> {
+        self.inner.review_queue(threshold, after, limit).await
+    }
+
+    async fn confirm_categories(
+        &self,
+        ids: Vec<i32>,
+        actor: Option<String>,
+    ) -> Result<u64, sqlx::Error> {
+        self.inner.confirm_categories(ids, actor).await
+    }
+
+    async fn tagged_between(
+        &self,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<Vec<DbTaggedTransaction>, sqlx::Error> {
+        self.inner.tagged_between(start, end).await
+    }
+
+    async fn training_examples(
+        &self,
+        min_confidence: f64,
+    ) -> Result<Vec<DbTrainingExample>, sqlx::Error> {
+        self.inner.training_examples(min_confidence).await
+    }
+
+    async fn normalize_descriptions(&self) -> Result<u64, sqlx::Error> {
+        self.inner.normalize_descriptions().await
+    }
+
+    async fn corrections(&self) -> Result<Vec<DbCategoryCorrection>, sqlx::Error> {
+        self.inner.corrections().await
+    }
+
+    async fn classifier_accuracy(
+        &self,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<Vec<DbCategoryAccuracy>, sqlx::Error> {
+        self.inner.classifier_accuracy(start, end).await
+    }
+
+    async fn account_balance(
+        &self,
+        account: &str,
+        through: &NaiveDate,
+    ) -> Result<BigDecimal, sqlx::Error> {
+        self.inner.account_balance(account, through).await
+    }
 }
@@ -0,0 +1,32 @@
+use sqlx::types::time::Date;
+use std::path::{Path, PathBuf};
+use time::Month;
+
+// Test helper to find a file in tests/fixtures
+#[allow(dead_code)]
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+// Test helper to read a file in tests/fixtures
+#[allow(dead_code)]
+pub fn fixture(name: &str) -> Vec<u8> {
+    std::fs::read(fixture_path(name)).unwrap()
+}
+
+// The bank profiles shipped with the category_importer tool
+#[allow(dead_code)]
+pub fn profiles_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tools")
+        .join("category_importer")
+        .join("profiles")
+}
+
+#[allow(dead_code)]
+pub fn date(year: i32, month: Month, day: u8) -> Date {
+    Date::from_calendar_date(year, month, day).unwrap()
+}
@@ -1,3 +1,4 @@
 pub mod db_mocks;
+pub mod fixtures;
 pub mod test_utils;
 pub mod macros;
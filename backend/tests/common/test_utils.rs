@@ -5,7 +5,11 @@ use testcontainers_modules::{
 };
 use std::sync::Arc;
 use transaction_server::{
-    db_traits::{MockCategoryRepository, MockTransactionRepository},
+    db_traits::{
+        MockAuditLogRepository, MockBudgetRepository, MockCategorizationRuleRepository,
+        MockCategoryRepository, MockImportBatchRepository, MockPeriodClosingRepository,
+        MockRecurringTransactionRepository, MockTransactionRepository,
+    },
     graphql::GraphQLContext,
 };
 use crate::common::db_mocks::{LocalMockCategoryRepository, LocalMockTransactionRepository};
@@ -53,5 +57,13 @@ pub fn get_context(
     GraphQLContext {
         category_repository: Arc::new(wrapped_category_mock),
         transaction_repository: Arc::new(wrapped_transaction_mock),
+        budget_repository: Arc::new(MockBudgetRepository::new()),
+        recurring_transaction_repository: Arc::new(MockRecurringTransactionRepository::new()),
+        import_batch_repository: Arc::new(MockImportBatchRepository::new()),
+        categorization_rule_repository: Arc::new(MockCategorizationRuleRepository::new()),
+        audit_log_repository: Arc::new(MockAuditLogRepository::new()),
+        period_closing_repository: Arc::new(MockPeriodClosingRepository::new()),
+        classifier: Default::default(),
+        actor: None,
     }
 }
@@ -0,0 +1,657 @@
+use chrono::NaiveDate;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use sqlx::PgPool;
+use time::Month;
+use transaction_server::{
+    db_models::{
+        DbImportRow, DbNewCategorizationRule, DbNewImportBatch, DbNewRuleApplication,
+        PgAuditLogRepository, PgBudgetRepository, PgCategorizationRuleRepository,
+        PgCategoryRepository, PgImportBatchRepository, PgPeriodClosingRepository,
+        PgRecurringTransactionRepository, PgTransactionRepository,
+    },
+    db_traits::{
+        AuditLogRepository, BudgetRepository, CategorizationRuleRepository, CategoryRepository,
+        ImportBatchRepository, PeriodClosingRepository, RecurringTransactionRepository,
+        TransactionRepository,
+    },
+};
+mod common;
+use common::test_utils::setup_test_db;
+
+#[tokio::test]
+async fn test_changes_are_logged() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let audit_log_repository = PgAuditLogRepository { pool: pool.clone() };
+
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    category_repository
+        .update(
+            category.id,
+            "Restaurants".to_string(),
+            None,
+            None,
+            Some("#FF8A80".to_string()),
+            Some("sam".to_string()),
+        )
+        .await
+        .expect("Failed to update category");
+
+    let transaction = transaction_repository
+        .create(
+            BigDecimal::from(-12),
+            "TACO TRUCK".to_string(),
+            Date::from_calendar_date(2025, Month::May, 2).unwrap(),
+            category.id,
+            Some("sam".to_string()),
+        )
+        .await
+        .expect("Failed to create transaction");
+    transaction_repository
+        .update(
+            transaction.id,
+            BigDecimal::from(-14),
+            "TACO TRUCK".to_string(),
+            transaction.date,
+            category.id,
+            Some("alex".to_string()),
+        )
+        .await
+        .expect("Failed to update transaction");
+
+    // Newest first, with the row before and after each change and who made it
+    let history = audit_log_repository
+        .entries("transaction", Some(transaction.id))
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].action, "update");
+    assert_eq!(history[0].source, "api");
+    assert_eq!(history[0].actor, "alex");
+    assert!(history[0].before.as_ref().unwrap().contains(r#""amount": -12.00"#));
+    assert!(history[0].after.as_ref().unwrap().contains(r#""amount": -14.00"#));
+    assert_eq!(history[1].action, "create");
+    assert_eq!(history[1].actor, "sam");
+    assert!(history[1].before.is_none());
+
+    let categories = audit_log_repository
+        .entries("category", Some(category.id))
+        .await
+        .unwrap();
+    assert_eq!(categories.len(), 2);
+    assert!(categories[0].after.as_ref().unwrap().contains("Restaurants"));
+    assert_eq!(categories[0].actor, "sam");
+    // without an actor the change is the database user's
+    assert_eq!(categories[1].actor, "postgres");
+
+    // Filling in normalized descriptions isn't a change
+    sqlx::query("UPDATE transactions SET normalized_description = NULL")
+        .execute(&pool)
+        .await
+        .unwrap();
+    transaction_repository.normalize_descriptions().await.unwrap();
+    let history = audit_log_repository
+        .entries("transaction", Some(transaction.id))
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 2);
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_merge_import_and_recurring_sources() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let import_batch_repository = PgImportBatchRepository { pool: pool.clone() };
+    let recurring_transaction_repository = PgRecurringTransactionRepository { pool: pool.clone() };
+    let audit_log_repository = PgAuditLogRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Subscription".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let date = Date::from_calendar_date(2025, Month::May, 2).unwrap();
+    let keep = transaction_repository
+        .create(BigDecimal::from(-15), "NETFLIX.COM".to_string(), date, category.id, None)
+        .await
+        .unwrap();
+    let duplicate = transaction_repository
+        .create(BigDecimal::from(-15), "NETFLIX.COM".to_string(), date, category.id, None)
+        .await
+        .unwrap();
+    transaction_repository
+        .resolve_duplicates(keep.id, vec![duplicate.id], Some("alex".to_string()))
+        .await
+        .unwrap();
+
+    let history = audit_log_repository
+        .entries("transaction", Some(duplicate.id))
+        .await
+        .unwrap();
+    assert_eq!(history[0].action, "merge");
+    assert_eq!(history[0].actor, "alex");
+    let merged_into = format!(r#""id": {}"#, keep.id);
+    assert!(history[0].after.as_ref().unwrap().contains(&merged_into));
+
+    // An import is logged as made by whoever ran it, and so is its rollback,
+    // whatever the batch's importedBy says
+    let batch = transaction_repository
+        .import_transactions(
+            DbNewImportBatch {
+                source_name: "may.csv".to_string(),
+                file_hash: None,
+                imported_by: Some("alice".to_string()),
+                row_count: 1,
+                warnings: Vec::new(),
+            },
+            vec![DbImportRow {
+                account: "checking".to_string(),
+                external_id: Some("TX-1".to_string()),
+                amount: BigDecimal::from(-25),
+                description: "SPOTIFY".to_string(),
+                date,
+                category_id: category.id,
+                classification: None,
+                rule_id: None,
+                tags: Vec::new(),
+                new_category: None,
+            }],
+            Some("sam".to_string()),
+        )
+        .await
+        .unwrap();
+    import_batch_repository
+        .rollback(batch.id, Some("sam".to_string()))
+        .await
+        .unwrap();
+
+    let entries = audit_log_repository
+        .entries("transaction", None)
+        .await
+        .unwrap();
+    let imported: Vec<_> = entries.iter().filter(|e| e.source == "import").collect();
+    assert_eq!(imported.len(), 2);
+    assert_eq!(imported[0].action, "delete");
+    assert_eq!(imported[0].actor, "sam");
+    assert_eq!(imported[1].action, "create");
+    assert_eq!(imported[1].actor, "sam");
+
+    let schedule = recurring_transaction_repository
+        .create(
+            "GYM".to_string(),
+            BigDecimal::from(-40),
+            category.id,
+            "monthly".to_string(),
+            1,
+            None,
+            date,
+            None,
+        )
+        .await
+        .unwrap();
+    let may = NaiveDate::from_ymd_opt(2025, 5, 2).unwrap();
+    recurring_transaction_repository
+        .materialize(schedule.id, vec![may], &may)
+        .await
+        .unwrap();
+    let entries = audit_log_repository
+        .entries("transaction", None)
+        .await
+        .unwrap();
+    assert_eq!(entries[0].source, "recurring");
+    assert_eq!(entries[0].action, "create");
+
+    drop(container);
+}
+
+async fn amount_of(pool: &PgPool, id: i32) -> Option<BigDecimal> {
+    sqlx::query_scalar("SELECT amount FROM transactions WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_undo_and_redo() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let audit_log_repository = PgAuditLogRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Subscription".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let date = Date::from_calendar_date(2025, Month::May, 2).unwrap();
+    let keep = transaction_repository
+        .create(BigDecimal::from(-15), "NETFLIX.COM".to_string(), date, category.id, None)
+        .await
+        .unwrap();
+    let duplicate = transaction_repository
+        .create(BigDecimal::from(-15), "NETFLIX.COM".to_string(), date, category.id, None)
+        .await
+        .unwrap();
+    transaction_repository
+        .resolve_duplicates(keep.id, vec![duplicate.id], None)
+        .await
+        .unwrap();
+    transaction_repository
+        .update(keep.id, BigDecimal::from(-16), "NETFLIX.COM".to_string(), date, category.id, None)
+        .await
+        .unwrap();
+    assert!(transaction_repository.delete(keep.id, None).await.unwrap());
+
+    // Each undo reverts the newest operation left, delete, update and merge in turn
+    let undone = audit_log_repository.undo(None).await.unwrap();
+    assert_eq!(undone.len(), 1);
+    assert_eq!(undone[0].action, "delete");
+    assert_eq!(amount_of(&pool, keep.id).await, Some(BigDecimal::from(-16)));
+    let undone = audit_log_repository.undo(None).await.unwrap();
+    assert_eq!(undone[0].action, "update");
+    assert_eq!(amount_of(&pool, keep.id).await, Some(BigDecimal::from(-15)));
+    let undone = audit_log_repository.undo(None).await.unwrap();
+    assert_eq!(undone[0].action, "merge");
+    assert_eq!(amount_of(&pool, duplicate.id).await, Some(BigDecimal::from(-15)));
+
+    // Redo takes back the last undo first, and the undo and redo are in the audit log
+    let redone = audit_log_repository.redo(None).await.unwrap();
+    assert_eq!(redone[0].action, "merge");
+    assert_eq!(amount_of(&pool, duplicate.id).await, None);
+    let history = audit_log_repository
+        .entries("transaction", Some(duplicate.id))
+        .await
+        .unwrap();
+    assert_eq!(history[0].source, "redo");
+    assert_eq!(history[0].action, "delete");
+    assert_eq!(history[1].source, "undo");
+    assert_eq!(history[1].action, "create");
+
+    // A new change ends redo
+    transaction_repository
+        .create(BigDecimal::from(-9), "SPOTIFY".to_string(), date, category.id, None)
+        .await
+        .unwrap();
+    assert!(audit_log_repository.redo(None).await.unwrap().is_empty());
+    assert_eq!(amount_of(&pool, keep.id).await, Some(BigDecimal::from(-15)));
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_undo_per_actor_and_after_changes() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let rule_repository = PgCategorizationRuleRepository { pool: pool.clone() };
+    let audit_log_repository = PgAuditLogRepository { pool: pool.clone() };
+    let misc = category_repository
+        .create("Misc".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let subscriptions = category_repository
+        .create("Subscriptions".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let rule = rule_repository
+        .create(DbNewCategorizationRule {
+            name: "Netflix".to_string(),
+            priority: 0,
+            description_pattern: Some("netflix".to_string()),
+            match_kind: "contains".to_string(),
+            min_amount: None,
+            max_amount: None,
+            account: None,
+            sign: None,
+            category_id: subscriptions.id,
+            tags: vec!["streaming".to_string()],
+            enabled: true,
+        })
+        .await
+        .expect("Failed to create rule");
+
+    let mut ids = Vec::new();
+    for day in [3, 4] {
+        let transaction = transaction_repository
+            .create(
+                BigDecimal::from(-15),
+                "NETFLIX.COM".to_string(),
+                Date::from_calendar_date(2025, Month::March, day).unwrap(),
+                misc.id,
+                None,
+            )
+            .await
+            .unwrap();
+        ids.push(transaction.id);
+    }
+    rule_repository
+        .apply(
+            DbNewRuleApplication {
+                rule_id: rule.id,
+                rule_name: rule.name.clone(),
+                category_id: subscriptions.id,
+                tags: rule.tags.clone(),
+                start_date: Date::from_calendar_date(2025, Month::March, 1).unwrap(),
+                end_date: Date::from_calendar_date(2025, Month::March, 31).unwrap(),
+                transaction_ids: ids.clone(),
+                previous_category_ids: vec![misc.id, misc.id],
+                applied_by: Some("alice".to_string()),
+            },
+            Some("sam".to_string()),
+        )
+        .await
+        .expect("Failed to apply rule");
+
+    // The recategorization is on the stack of sam, who applied it, not of its appliedBy
+    assert!(audit_log_repository
+        .undo(Some("alice".to_string()))
+        .await
+        .unwrap()
+        .is_empty());
+    let undone = audit_log_repository
+        .undo(Some("sam".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(undone.len(), 2);
+    assert!(audit_log_repository
+        .undo(Some("sam".to_string()))
+        .await
+        .unwrap()
+        .is_empty());
+    let transactions = transaction_repository
+        .by_category_id(misc.id)
+        .await
+        .unwrap();
+    assert_eq!(transactions.len(), 2);
+
+    // A transaction changed since its operation can't be restored, and nothing is
+    transaction_repository
+        .update(
+            ids[1],
+            BigDecimal::from(-16),
+            "NETFLIX.COM".to_string(),
+            Date::from_calendar_date(2025, Month::March, 4).unwrap(),
+            misc.id,
+            Some("sam".to_string()),
+        )
+        .await
+        .unwrap();
+    transaction_repository
+        .update(
+            ids[0],
+            BigDecimal::from(-15),
+            "NETFLIX.COM".to_string(),
+            Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+            subscriptions.id,
+            Some("sam".to_string()),
+        )
+        .await
+        .unwrap();
+    transaction_repository
+        .update(
+            ids[0],
+            BigDecimal::from(-20),
+            "NETFLIX.COM".to_string(),
+            Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+            subscriptions.id,
+            Some("alex".to_string()),
+        )
+        .await
+        .unwrap();
+    let conflict = audit_log_repository
+        .undo(Some("sam".to_string()))
+        .await
+        .expect_err("A changed transaction can't be restored");
+    assert!(conflict.to_string().contains("changed since"));
+    assert_eq!(amount_of(&pool, ids[0]).await, Some(BigDecimal::from(-20)));
+    let transactions = transaction_repository
+        .by_category_id(subscriptions.id)
+        .await
+        .unwrap();
+    assert_eq!(transactions.len(), 1);
+
+    // It's off sam's stack for good, so the next undo takes the change before it
+    let reason: String =
+        sqlx::query_scalar("SELECT reason FROM stale_operations WHERE actor = 'sam'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(reason.contains(&format!("id {} changed since", ids[0])));
+    let undone = audit_log_repository
+        .undo(Some("sam".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(undone[0].entity_id, ids[1]);
+    assert_eq!(amount_of(&pool, ids[1]).await, Some(BigDecimal::from(-15)));
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_undo_with_interleaved_actors() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let audit_log_repository = PgAuditLogRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let date = Date::from_calendar_date(2025, Month::May, 2).unwrap();
+    let alice = Some("alice".to_string());
+    let bob = Some("bob".to_string());
+
+    // alice and bob take turns, each on their own transaction
+    let taco = transaction_repository
+        .create(
+            BigDecimal::from(-12),
+            "TACO TRUCK".to_string(),
+            date,
+            category.id,
+            alice.clone(),
+        )
+        .await
+        .unwrap();
+    let bagel = transaction_repository
+        .create(
+            BigDecimal::from(-9),
+            "BAGEL SHOP".to_string(),
+            date,
+            category.id,
+            bob.clone(),
+        )
+        .await
+        .unwrap();
+    transaction_repository
+        .update(
+            taco.id,
+            BigDecimal::from(-14),
+            "TACO TRUCK".to_string(),
+            date,
+            category.id,
+            alice.clone(),
+        )
+        .await
+        .unwrap();
+    transaction_repository
+        .update(
+            bagel.id,
+            BigDecimal::from(-10),
+            "BAGEL SHOP".to_string(),
+            date,
+            category.id,
+            bob.clone(),
+        )
+        .await
+        .unwrap();
+
+    // Each undo takes the actor's own newest change, whatever the other did after it
+    let undone = audit_log_repository.undo(alice.clone()).await.unwrap();
+    assert_eq!(undone.len(), 1);
+    assert_eq!(undone[0].entity_id, taco.id);
+    assert_eq!(undone[0].actor, "alice");
+    assert_eq!(amount_of(&pool, taco.id).await, Some(BigDecimal::from(-12)));
+    assert_eq!(
+        amount_of(&pool, bagel.id).await,
+        Some(BigDecimal::from(-10))
+    );
+
+    let undone = audit_log_repository.undo(bob.clone()).await.unwrap();
+    assert_eq!(undone[0].entity_id, bagel.id);
+    assert_eq!(amount_of(&pool, bagel.id).await, Some(BigDecimal::from(-9)));
+
+    audit_log_repository.undo(alice.clone()).await.unwrap();
+    assert_eq!(amount_of(&pool, taco.id).await, None);
+    assert_eq!(amount_of(&pool, bagel.id).await, Some(BigDecimal::from(-9)));
+
+    // alice's redo brings back her transaction, and bob's stack is his own
+    audit_log_repository.redo(alice.clone()).await.unwrap();
+    assert_eq!(amount_of(&pool, taco.id).await, Some(BigDecimal::from(-12)));
+    let undone = audit_log_repository.undo(bob.clone()).await.unwrap();
+    assert_eq!(undone[0].entity_id, bagel.id);
+    assert_eq!(amount_of(&pool, bagel.id).await, None);
+    assert!(audit_log_repository.undo(bob).await.unwrap().is_empty());
+
+    drop(container);
+}
+
+async fn stale_count(pool: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM stale_operations")
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_undo_never_removes_a_category_in_use() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let rule_repository = PgCategorizationRuleRepository { pool: pool.clone() };
+    let budget_repository = PgBudgetRepository { pool: pool.clone() };
+    let audit_log_repository = PgAuditLogRepository { pool: pool.clone() };
+    let sam = Some("sam".to_string());
+    let streaming = category_repository
+        .create("Streaming".to_string(), None, None, None, sam.clone())
+        .await
+        .expect("Failed to create test category");
+    let dining = category_repository
+        .create("Dining".to_string(), None, None, None, sam.clone())
+        .await
+        .expect("Failed to create test category");
+    let rule = rule_repository
+        .create(DbNewCategorizationRule {
+            name: "Netflix".to_string(),
+            priority: 0,
+            description_pattern: Some("netflix".to_string()),
+            match_kind: "contains".to_string(),
+            min_amount: None,
+            max_amount: None,
+            account: None,
+            sign: None,
+            category_id: streaming.id,
+            tags: Vec::new(),
+            enabled: true,
+        })
+        .await
+        .expect("Failed to create rule");
+    budget_repository
+        .create(
+            dining.id,
+            Date::from_calendar_date(2025, Month::January, 1).unwrap(),
+            "monthly".to_string(),
+            BigDecimal::from(300),
+            false,
+        )
+        .await
+        .expect("Failed to create budget");
+
+    // Deleting a category a budget uses would fail, so the operation is stale
+    let conflict = audit_log_repository
+        .undo(sam.clone())
+        .await
+        .expect_err("A category with a budget can't be removed");
+    assert!(conflict.to_string().contains("still used by budgets"));
+    assert_eq!(stale_count(&pool).await, 1);
+
+    // and deleting one a rule uses would take the rule with it
+    let conflict = audit_log_repository
+        .undo(sam.clone())
+        .await
+        .expect_err("A category with a rule can't be removed");
+    assert!(conflict.to_string().contains("still used by categorization_rules"));
+    assert_eq!(stale_count(&pool).await, 2);
+    assert_eq!(category_repository.all().await.unwrap().len(), 2);
+    assert_eq!(rule_repository.by_id(rule.id).await.unwrap().category_id, streaming.id);
+    assert!(audit_log_repository.undo(sam).await.unwrap().is_empty());
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_undo_in_a_closed_period_waits_for_reopening() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let period_closing_repository = PgPeriodClosingRepository { pool: pool.clone() };
+    let audit_log_repository = PgAuditLogRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let sam = Some("sam".to_string());
+    let april = Date::from_calendar_date(2025, Month::April, 30).unwrap();
+    let transaction = transaction_repository
+        .create(
+            BigDecimal::from(-12),
+            "TACO TRUCK".to_string(),
+            april,
+            category.id,
+            sam.clone(),
+        )
+        .await
+        .unwrap();
+    period_closing_repository
+        .close(april, None, None)
+        .await
+        .expect("Failed to close period");
+
+    // The operation isn't stale, it can be undone once the period is reopened
+    let error = audit_log_repository
+        .undo(sam.clone())
+        .await
+        .expect_err("A transaction in the closed period can't be removed");
+    assert!(error.to_string().contains("reopen it to undo this operation"));
+    assert_eq!(stale_count(&pool).await, 0);
+    assert_eq!(amount_of(&pool, transaction.id).await, Some(BigDecimal::from(-12)));
+
+    period_closing_repository
+        .reopen(april, None, None)
+        .await
+        .expect("Failed to reopen period");
+    let undone = audit_log_repository.undo(sam).await.unwrap();
+    assert_eq!(undone[0].entity_id, transaction.id);
+    assert_eq!(amount_of(&pool, transaction.id).await, None);
+
+    drop(container);
+}
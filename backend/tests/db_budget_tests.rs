@@ -0,0 +1,228 @@
+use chrono::NaiveDate;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use time::Month;
+use transaction_server::{
+    db_models::{PgBudgetRepository, PgCategoryRepository, PgTransactionRepository},
+    db_traits::{BudgetRepository, CategoryRepository, TransactionRepository},
+};
+mod common;
+use common::test_utils::setup_test_db;
+
+#[tokio::test]
+async fn test_create_and_update_budget() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let budget_repository = PgBudgetRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let period_start = Date::from_calendar_date(2025, Month::January, 1).unwrap();
+    let budget = budget_repository
+        .create(
+            category.id,
+            period_start,
+            "monthly".to_string(),
+            BigDecimal::from(500),
+            false,
+        )
+        .await
+        .expect("Failed to create budget");
+
+    assert_eq!(budget.category_id, category.id);
+    assert_eq!(budget.category_name, Some("Dining".to_string()));
+    assert_eq!(budget.period_start, period_start);
+    assert_eq!(budget.period_type, "monthly");
+    assert_eq!(budget.amount, BigDecimal::from(500));
+
+    let updated = budget_repository
+        .update(
+            budget.id,
+            category.id,
+            period_start,
+            "quarterly".to_string(),
+            BigDecimal::from(1200),
+            Some(true),
+        )
+        .await
+        .expect("Failed to update budget");
+
+    assert_eq!(updated.id, budget.id);
+    assert_eq!(updated.period_type, "quarterly");
+    assert_eq!(updated.amount, BigDecimal::from(1200));
+    assert!(updated.rollover);
+
+    // Leaving rollover out keeps it
+    let updated = budget_repository
+        .update(
+            budget.id,
+            category.id,
+            period_start,
+            "quarterly".to_string(),
+            BigDecimal::from(1000),
+            None,
+        )
+        .await
+        .expect("Failed to update budget");
+    assert_eq!(updated.amount, BigDecimal::from(1000));
+    assert!(updated.rollover);
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_effective_budget_and_delete() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let budget_repository = PgBudgetRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    // A January budget that is replaced from March onwards
+    for (month, amount) in [(Month::January, 500), (Month::March, 650)] {
+        budget_repository
+            .create(
+                category.id,
+                Date::from_calendar_date(2025, month, 1).unwrap(),
+                "monthly".to_string(),
+                BigDecimal::from(amount),
+                false,
+            )
+            .await
+            .expect("Failed to create budget");
+    }
+
+    let february = budget_repository
+        .effective_on(&NaiveDate::from_ymd_opt(2025, 2, 28).unwrap())
+        .await
+        .expect("Failed to get effective budgets");
+    assert_eq!(february.len(), 1);
+    assert_eq!(february[0].amount, BigDecimal::from(500));
+
+    let april = budget_repository
+        .effective_on(&NaiveDate::from_ymd_opt(2025, 4, 30).unwrap())
+        .await
+        .expect("Failed to get effective budgets");
+    assert_eq!(april.len(), 1);
+    assert_eq!(april[0].amount, BigDecimal::from(650));
+
+    // Deleting the newer budget brings the older one back into effect
+    assert!(budget_repository
+        .delete(april[0].id)
+        .await
+        .expect("Failed to delete budget"));
+    assert!(!budget_repository
+        .delete(april[0].id)
+        .await
+        .expect("Failed to delete budget"));
+
+    let april = budget_repository
+        .effective_on(&NaiveDate::from_ymd_opt(2025, 4, 30).unwrap())
+        .await
+        .expect("Failed to get effective budgets");
+    assert_eq!(april[0].amount, BigDecimal::from(500));
+    assert_eq!(budget_repository.all().await.unwrap().len(), 1);
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_envelope_assignments() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let budget_repository = PgBudgetRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Kid Cash".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let january = Date::from_calendar_date(2025, Month::January, 1).unwrap();
+    let february = Date::from_calendar_date(2025, Month::February, 1).unwrap();
+    budget_repository
+        .assign(category.id, january, BigDecimal::from(50))
+        .await
+        .expect("Failed to assign");
+    budget_repository
+        .assign(category.id, february, BigDecimal::from(40))
+        .await
+        .expect("Failed to assign");
+
+    // Assigning the same month again replaces the amount
+    let reassigned = budget_repository
+        .assign(category.id, january, BigDecimal::from(60))
+        .await
+        .expect("Failed to reassign");
+    assert_eq!(reassigned.amount, BigDecimal::from(60));
+    assert_eq!(reassigned.category_name, Some("Kid Cash".to_string()));
+
+    let through_january = budget_repository
+        .assignments_through(&NaiveDate::from_ymd_opt(2025, 1, 31).unwrap())
+        .await
+        .expect("Failed to get assignments");
+    assert_eq!(through_january.len(), 1);
+    assert_eq!(through_january[0].month, january);
+
+    let through_february = budget_repository
+        .assignments_through(&NaiveDate::from_ymd_opt(2025, 2, 28).unwrap())
+        .await
+        .expect("Failed to get assignments");
+    assert_eq!(through_february.len(), 2);
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_monthly_totals_by_category() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    for (month, day, amount) in [
+        (Month::January, 5, -20),
+        (Month::January, 25, -30),
+        (Month::February, 3, -15),
+    ] {
+        transaction_repository
+            .create(
+                BigDecimal::from(amount),
+                "Lunch".to_string(),
+                Date::from_calendar_date(2025, month, day).unwrap(),
+                category.id,
+                None,
+            )
+            .await
+            .expect("Failed to create transaction");
+    }
+
+    let totals = transaction_repository
+        .monthly_totals_by_category(
+            &NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            &NaiveDate::from_ymd_opt(2025, 2, 28).unwrap(),
+        )
+        .await
+        .expect("Failed to get monthly totals");
+
+    assert_eq!(totals.len(), 2);
+    assert_eq!(totals[0].month, Date::from_calendar_date(2025, Month::January, 1).unwrap());
+    assert_eq!(totals[0].total_amount, BigDecimal::from(-50));
+    assert_eq!(totals[1].month, Date::from_calendar_date(2025, Month::February, 1).unwrap());
+    assert_eq!(totals[1].total_amount, BigDecimal::from(-15));
+
+    drop(container);
+}
@@ -0,0 +1,313 @@
+use chrono::NaiveDate;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use sqlx::PgPool;
+use time::Month;
+use transaction_server::{
+    db_models::{
+        DbImportRow, DbNewCategorizationRule, DbNewImportBatch, DbNewRuleApplication,
+        PgAuditLogRepository, PgCategorizationRuleRepository, PgCategoryRepository,
+        PgTransactionRepository,
+    },
+    db_traits::{
+        AuditLogRepository, CategorizationRuleRepository, CategoryRepository,
+        TransactionRepository,
+    },
+};
+mod common;
+use common::test_utils::setup_test_db;
+
+fn new_rule(name: &str, priority: i32, category_id: i32) -> DbNewCategorizationRule {
+    DbNewCategorizationRule {
+        name: name.to_string(),
+        priority,
+        description_pattern: Some("netflix".to_string()),
+        match_kind: "contains".to_string(),
+        min_amount: None,
+        max_amount: Some(BigDecimal::from(30)),
+        account: None,
+        sign: Some("debit".to_string()),
+        category_id,
+        tags: vec!["streaming".to_string()],
+        enabled: true,
+    }
+}
+
+async fn tags_of(pool: &PgPool, description: &str) -> Vec<String> {
+    sqlx::query_scalar("SELECT tags FROM transactions WHERE description = $1")
+        .bind(description)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to get tags")
+}
+
+#[tokio::test]
+async fn test_categorization_rule_crud() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let rule_repository = PgCategorizationRuleRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Subscriptions".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let low = rule_repository
+        .create(new_rule("Netflix", 0, category.id))
+        .await
+        .expect("Failed to create rule");
+    assert_eq!(low.category_name.as_deref(), Some("Subscriptions"));
+    assert_eq!(low.max_amount, Some(BigDecimal::from(30)));
+    assert_eq!(low.tags, vec!["streaming".to_string()]);
+    assert!(low.enabled);
+
+    let high = rule_repository
+        .create(new_rule("Netflix family", 10, category.id))
+        .await
+        .expect("Failed to create rule");
+
+    // Rules come highest priority first
+    let rules = rule_repository.all().await.expect("Failed to get rules");
+    let ids: Vec<i32> = rules.iter().map(|rule| rule.id).collect();
+    assert_eq!(ids, vec![high.id, low.id]);
+
+    let mut changed = new_rule("Netflix", 20, category.id);
+    changed.match_kind = "regex".to_string();
+    changed.enabled = false;
+    let updated = rule_repository
+        .update(low.id, changed)
+        .await
+        .expect("Failed to update rule");
+    assert_eq!(updated.priority, 20);
+    assert_eq!(updated.match_kind, "regex");
+    assert!(!updated.enabled);
+    assert!(rule_repository
+        .update(-1, new_rule("Missing", 0, category.id))
+        .await
+        .is_err());
+
+    assert!(rule_repository.delete(high.id).await.unwrap());
+    assert!(!rule_repository.delete(high.id).await.unwrap());
+    assert_eq!(rule_repository.all().await.unwrap().len(), 1);
+
+    // Deleting the category deletes its rules
+    sqlx::query("DELETE FROM categories WHERE id = $1")
+        .bind(category.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(rule_repository.all().await.unwrap().is_empty());
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_rule_tags_are_stored() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Subscriptions".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let row = |description: &str, tags: Vec<&str>| DbImportRow {
+        account: "checking".to_string(),
+        external_id: Some(description.to_string()),
+        amount: BigDecimal::from(-15),
+        description: description.to_string(),
+        date: Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+        category_id: category.id,
+        classification: None,
+        rule_id: None,
+        tags: tags.into_iter().map(str::to_string).collect(),
+        new_category: None,
+    };
+    let batch = DbNewImportBatch {
+        source_name: "march.csv".to_string(),
+        file_hash: None,
+        imported_by: None,
+        row_count: 2,
+        warnings: Vec::new(),
+    };
+    transaction_repository
+        .import_transactions(
+            batch,
+            vec![
+                row("NETFLIX.COM", vec!["streaming", "shared, family"]),
+                row("SPOTIFY", vec![]),
+            ],
+            None,
+        )
+        .await
+        .expect("Failed to import transactions");
+
+    assert_eq!(
+        tags_of(&pool, "NETFLIX.COM").await,
+        vec!["streaming".to_string(), "shared, family".to_string()]
+    );
+    assert!(tags_of(&pool, "SPOTIFY").await.is_empty());
+
+    // A transaction the rule categorizes is created with its tags, once each
+    let rule_repository = PgCategorizationRuleRepository { pool: pool.clone() };
+    let rule = rule_repository
+        .create(DbNewCategorizationRule {
+            tags: vec!["video".to_string(), "streaming".to_string(), "video".to_string()],
+            ..new_rule("Hulu", 0, category.id)
+        })
+        .await
+        .expect("Failed to create rule");
+    let transaction = transaction_repository
+        .create_by_rule(
+            BigDecimal::from(-9),
+            "HULU".to_string(),
+            Date::from_calendar_date(2025, Month::March, 4).unwrap(),
+            rule.clone(),
+            None,
+        )
+        .await
+        .expect("Failed to create transaction");
+    assert_eq!(transaction.category_id, category.id);
+    assert_eq!(
+        tags_of(&pool, "HULU").await,
+        vec!["streaming".to_string(), "video".to_string()]
+    );
+    let rule_id: Option<i32> =
+        sqlx::query_scalar("SELECT category_rule_id FROM transactions WHERE id = $1")
+            .bind(transaction.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(rule_id, Some(rule.id));
+    // the rule and its tags are written with the transaction, as one change
+    let entries = PgAuditLogRepository { pool: pool.clone() }
+        .entries("transaction", Some(transaction.id))
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].action, "create");
+
+    // Correcting the category the rule chose is recorded against the rule
+    let other = category_repository
+        .create("Entertainment".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    transaction_repository
+        .update(
+            transaction.id,
+            transaction.amount.clone(),
+            transaction.description.clone(),
+            transaction.date,
+            other.id,
+            None,
+        )
+        .await
+        .expect("Failed to update transaction");
+    let corrections = transaction_repository.corrections().await.unwrap();
+    assert_eq!(corrections.len(), 1);
+    assert_eq!(corrections[0].source, "rule");
+    assert_eq!(corrections[0].rule_id, Some(rule.id));
+    assert_eq!(corrections[0].old_category_id, category.id);
+    assert_eq!(corrections[0].new_category_name.as_deref(), Some("Entertainment"));
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_apply_rule_records_an_application() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let rule_repository = PgCategorizationRuleRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let other = category_repository
+        .create("Misc".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let subscriptions = category_repository
+        .create("Subscriptions".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let rule = rule_repository
+        .create(new_rule("Netflix", 0, subscriptions.id))
+        .await
+        .expect("Failed to create rule");
+
+    let mut ids = Vec::new();
+    for (description, day) in [("NETFLIX.COM", 3), ("SPOTIFY", 4), ("NETFLIX.COM", 28)] {
+        let transaction = transaction_repository
+            .create(
+                BigDecimal::from(-15),
+                description.to_string(),
+                Date::from_calendar_date(2025, Month::March, day).unwrap(),
+                other.id,
+                None,
+            )
+            .await
+            .expect("Failed to create transaction");
+        ids.push(transaction.id);
+    }
+
+    // Only transactions in the period are candidates
+    let candidates = transaction_repository
+        .tagged_between(
+            &NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+            &NaiveDate::from_ymd_opt(2025, 3, 10).unwrap(),
+        )
+        .await
+        .expect("Failed to get transactions");
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(candidates[0].account, "default");
+    assert_eq!(candidates[0].category_name.as_deref(), Some("Misc"));
+
+    let application = rule_repository
+        .apply(
+            DbNewRuleApplication {
+                rule_id: rule.id,
+                rule_name: rule.name.clone(),
+                category_id: subscriptions.id,
+                tags: rule.tags.clone(),
+                start_date: Date::from_calendar_date(2025, Month::March, 1).unwrap(),
+                end_date: Date::from_calendar_date(2025, Month::March, 10).unwrap(),
+                transaction_ids: vec![ids[0]],
+                previous_category_ids: vec![other.id],
+                applied_by: Some("sam".to_string()),
+            },
+            Some("sam".to_string()),
+        )
+        .await
+        .expect("Failed to apply rule");
+    assert_eq!(application.rule_id, Some(rule.id));
+    assert_eq!(application.category_name.as_deref(), Some("Subscriptions"));
+    assert_eq!(application.transaction_ids, vec![ids[0]]);
+    assert_eq!(application.previous_category_ids, vec![other.id]);
+    assert!(application.applied_at.is_some());
+
+    let transactions = transaction_repository
+        .by_category_id(subscriptions.id)
+        .await
+        .expect("Failed to get transactions");
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0].id, ids[0]);
+    let tags: Vec<String> = sqlx::query_scalar("SELECT tags FROM transactions WHERE id = $1")
+        .bind(ids[0])
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(tags, vec!["streaming".to_string()]);
+
+    // The audit trail outlives the rule
+    let applications = rule_repository.applications(Some(rule.id)).await.unwrap();
+    assert_eq!(applications.len(), 1);
+    assert!(rule_repository.delete(rule.id).await.unwrap());
+    let applications = rule_repository.applications(None).await.unwrap();
+    assert_eq!(applications.len(), 1);
+    assert_eq!(applications[0].rule_id, None);
+    assert_eq!(applications[0].rule_name, "Netflix");
+
+    drop(container);
+}
@@ -19,6 +19,7 @@ async fn test_create_category() {
             Some("Test Description".to_string()),
             Some("test-icon".to_string()),
             Some("#FF0000".to_string()),
+            None,
         )
         .await
         .expect("Failed to create test category");
@@ -54,6 +55,7 @@ async fn test_update_category() {
             Some("Test Description".to_string()),
             Some("test-icon".to_string()),
             Some("#FF0000".to_string()),
+            None,
         )
         .await
         .expect("Failed to create test category");
@@ -66,6 +68,7 @@ async fn test_update_category() {
             Some("Updated Description".to_string()),
             Some("updated-icon".to_string()),
             Some("#00FF00".to_string()),
+            None,
         )
         .await
         .expect("Failed to update test category");
@@ -92,6 +95,7 @@ async fn test_get_category_by_name() {
             Some("Test Description".to_string()),
             Some("test-icon".to_string()),
             Some("#FF0000".to_string()),
+            None,
         )
         .await
         .expect("Failed to create test category");
@@ -110,3 +114,52 @@ async fn test_get_category_by_name() {
 
     drop(container);
 }
+
+#[tokio::test]
+async fn test_category_aliases() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let dining = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let groceries = category_repository
+        .create("Groceries".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let alias = category_repository
+        .set_alias("Restaurants".to_string(), dining.id)
+        .await
+        .expect("Failed to set alias");
+    assert_eq!(alias.category_name, Some("Dining".to_string()));
+
+    // Setting a name again in another case moves the alias instead of adding one
+    let moved = category_repository
+        .set_alias("RESTAURANTS".to_string(), groceries.id)
+        .await
+        .expect("Failed to set alias");
+    assert_eq!(moved.id, alias.id);
+    assert_eq!(moved.category_id, groceries.id);
+
+    let aliases = category_repository
+        .aliases()
+        .await
+        .expect("Failed to get aliases");
+    assert_eq!(aliases.len(), 1);
+    assert_eq!(aliases[0].name, "RESTAURANTS");
+    assert_eq!(aliases[0].category_name, Some("Groceries".to_string()));
+
+    assert!(category_repository
+        .delete_alias(alias.id)
+        .await
+        .expect("Failed to delete alias"));
+    assert!(!category_repository
+        .delete_alias(alias.id)
+        .await
+        .expect("Failed to delete alias"));
+
+    drop(container);
+}
@@ -0,0 +1,301 @@
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use time::Month;
+use transaction_server::{
+    db_models::{
+        DbImportRollback, DbImportRow, DbNewImportBatch, PgCategoryRepository,
+        PgImportBatchRepository, PgTransactionRepository,
+    },
+    db_traits::{CategoryRepository, ImportBatchRepository, TransactionRepository},
+    import::file_hash,
+};
+mod common;
+use common::test_utils::setup_test_db;
+
+fn import_row(external_id: &str, description: &str, category_id: i32) -> DbImportRow {
+    DbImportRow {
+        account: "checking".to_string(),
+        external_id: Some(external_id.to_string()),
+        amount: BigDecimal::from(-25),
+        description: description.to_string(),
+        date: Date::from_calendar_date(2025, Month::May, 2).unwrap(),
+        category_id,
+        classification: None,
+        rule_id: None,
+        tags: Vec::new(),
+        new_category: None,
+    }
+}
+
+#[tokio::test]
+async fn test_import_batch_is_recorded() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let import_batch_repository = PgImportBatchRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let hash = file_hash(b"Transaction ID,Amount\nTX-1,-25\n");
+    let batch = transaction_repository
+        .import_transactions(
+            DbNewImportBatch {
+                source_name: "may.csv".to_string(),
+                file_hash: Some(hash.clone()),
+                imported_by: Some("sam".to_string()),
+                row_count: 2,
+                warnings: vec!["Category 'Pets' not found in database".to_string()],
+            },
+            vec![import_row("TX-1", "TACO TRUCK", category.id)],
+            None,
+        )
+        .await
+        .expect("Failed to import transactions");
+
+    assert_eq!(batch.source_name, "may.csv");
+    assert_eq!(batch.imported_by, Some("sam".to_string()));
+    assert_eq!(batch.row_count, 2);
+    assert_eq!(batch.inserted_count, 1);
+    assert_eq!(batch.warnings.len(), 1);
+    assert!(batch.rolled_back_at.is_none());
+
+    let batches = import_batch_repository.all().await.unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].id, batch.id);
+
+    let same_file = import_batch_repository.by_file_hash(&hash).await.unwrap();
+    assert_eq!(same_file.len(), 1);
+    assert!(import_batch_repository
+        .by_file_hash(&file_hash(b"another file"))
+        .await
+        .unwrap()
+        .is_empty());
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_rollback_keeps_edited_and_other_transactions() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let import_batch_repository = PgImportBatchRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let other_category = category_repository
+        .create("Groceries".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let new_batch = || DbNewImportBatch {
+        source_name: "may.csv".to_string(),
+        file_hash: None,
+        imported_by: None,
+        row_count: 2,
+        warnings: Vec::new(),
+    };
+    let batch = transaction_repository
+        .import_transactions(
+            new_batch(),
+            vec![
+                import_row("TX-1", "TACO TRUCK", category.id),
+                import_row("TX-2", "MARKET", category.id),
+            ],
+            None,
+        )
+        .await
+        .expect("Failed to import transactions");
+    let other_batch = transaction_repository
+        .import_transactions(new_batch(), vec![import_row("TX-3", "DINER", category.id)], None)
+        .await
+        .expect("Failed to import transactions");
+    transaction_repository
+        .create(
+            BigDecimal::from(-8),
+            "Manual entry".to_string(),
+            Date::from_calendar_date(2025, Month::May, 2).unwrap(),
+            category.id,
+            None,
+        )
+        .await
+        .expect("Failed to create transaction");
+
+    // The MARKET row was fixed by hand after the import
+    let market = transaction_repository
+        .by_category_id(category.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|t| t.description == "MARKET")
+        .unwrap();
+    transaction_repository
+        .update(market.id, market.amount, market.description, market.date, other_category.id, None)
+        .await
+        .expect("Failed to update transaction");
+
+    let rollback = import_batch_repository
+        .rollback(batch.id, None)
+        .await
+        .expect("Failed to roll back import");
+    assert_eq!(rollback, DbImportRollback { removed: 1, kept: 1 });
+
+    let remaining: Vec<String> = transaction_repository
+        .all()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.description)
+        .collect();
+    assert_eq!(remaining.len(), 3);
+    assert!(!remaining.contains(&"TACO TRUCK".to_string()));
+
+    let batches = import_batch_repository.all().await.unwrap();
+    let rolled_back = batches.iter().find(|b| b.id == batch.id).unwrap();
+    assert!(rolled_back.rolled_back_at.is_some());
+    assert!(batches
+        .iter()
+        .find(|b| b.id == other_batch.id)
+        .unwrap()
+        .rolled_back_at
+        .is_none());
+
+    // A row a later import updated wasn't edited by hand, so it goes with its batch
+    let later_batch = transaction_repository
+        .import_transactions(new_batch(), vec![import_row("TX-4", "BAKERY", category.id)], None)
+        .await
+        .expect("Failed to import transactions");
+    let mut corrected = import_row("TX-4", "BAKERY", category.id);
+    corrected.amount = BigDecimal::from(-11);
+    transaction_repository
+        .import_transactions(new_batch(), vec![corrected], None)
+        .await
+        .expect("Failed to import transactions");
+    let rollback = import_batch_repository
+        .rollback(later_batch.id, None)
+        .await
+        .expect("Failed to roll back import");
+    assert_eq!(rollback, DbImportRollback { removed: 1, kept: 0 });
+
+    let result = import_batch_repository.rollback(-1, None).await;
+    assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_large_import_is_one_transaction() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let import_batch_repository = PgImportBatchRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let mut rows: Vec<DbImportRow> = (0..2500)
+        .map(|n| import_row(&format!("TX-{}", n), "TACO TRUCK", category.id))
+        .collect();
+    // The bank corrected two rows later in the same file, and two rows have no id
+    for id in ["TX-5", "TX-2000"] {
+        let mut corrected = import_row(id, "TACO TRUCK", category.id);
+        corrected.amount = BigDecimal::from(-30);
+        rows.push(corrected);
+    }
+    let mut coffee = import_row("", "COFFEE", category.id);
+    coffee.external_id = None;
+    rows.extend([coffee.clone(), coffee]);
+    // Two rows are in a category the import creates
+    for row in [1, 3] {
+        rows[row].new_category = Some("Pets".to_string());
+    }
+
+    // A row that can't be written rolls back the whole import, batch and all
+    let mut broken = rows.clone();
+    broken[2200].category_id = category.id + 1000;
+    let batch = DbNewImportBatch {
+        source_name: "history.csv".to_string(),
+        file_hash: Some(file_hash(b"history")),
+        imported_by: None,
+        row_count: rows.len() as i32,
+        warnings: vec![],
+    };
+    assert!(transaction_repository
+        .import_transactions(batch.clone(), broken, None)
+        .await
+        .is_err());
+    assert!(transaction_repository
+        .by_category_id(category.id)
+        .await
+        .expect("Failed to get transactions")
+        .is_empty());
+    assert!(import_batch_repository
+        .by_file_hash(&file_hash(b"history"))
+        .await
+        .expect("Failed to get import batches")
+        .is_empty());
+    let categories = category_repository
+        .all()
+        .await
+        .expect("Failed to get categories");
+    assert_eq!(categories.len(), 1);
+
+    // Progress is sent after each chunk of rows
+    let (progress, mut written) = tokio::sync::mpsc::unbounded_channel();
+    let batch = transaction_repository
+        .import_transactions_with_progress(batch, rows, progress, None)
+        .await
+        .expect("Failed to import transactions");
+    let mut counts = Vec::new();
+    while let Some(count) = written.recv().await {
+        counts.push(count);
+    }
+
+    assert_eq!(
+        (
+            batch.inserted_count,
+            batch.updated_count,
+            batch.skipped_count
+        ),
+        (2502, 2, 0)
+    );
+    // the correction of TX-2000 would be in the same chunk as TX-2000, so it starts a new one
+    assert_eq!(counts, vec![1000, 2000, 2501, 2504]);
+    assert_eq!(
+        transaction_repository
+            .by_category_id(category.id)
+            .await
+            .expect("Failed to get transactions")
+            .len(),
+        2500
+    );
+    let categories = category_repository
+        .all()
+        .await
+        .expect("Failed to get categories");
+    let pets = categories
+        .iter()
+        .find(|category| category.name == "Pets")
+        .expect("Pets should be created");
+    assert_eq!(categories.len(), 2);
+    assert_eq!(
+        transaction_repository
+            .by_category_id(pets.id)
+            .await
+            .expect("Failed to get transactions")
+            .len(),
+        2
+    );
+
+    drop(container);
+}
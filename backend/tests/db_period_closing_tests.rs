@@ -0,0 +1,264 @@
+use chrono::NaiveDate;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use time::Month;
+use transaction_server::{
+    db_models::{
+        DbImportRow, DbNewImportBatch, PgAuditLogRepository, PgCategoryRepository,
+        PgPeriodClosingRepository, PgRecurringTransactionRepository, PgTransactionRepository,
+    },
+    db_traits::{
+        AuditLogRepository, CategoryRepository, PeriodClosingRepository,
+        RecurringTransactionRepository, TransactionRepository,
+    },
+    recurrence::materialize_due,
+};
+mod common;
+use common::test_utils::setup_test_db;
+
+#[tokio::test]
+async fn test_closed_period_rejects_changes_until_reopened() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let period_closing_repository = PgPeriodClosingRepository { pool: pool.clone() };
+    let audit_log_repository = PgAuditLogRepository { pool: pool.clone() };
+
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let april = Date::from_calendar_date(2025, Month::April, 30).unwrap();
+    let may = Date::from_calendar_date(2025, Month::May, 2).unwrap();
+    let closed = transaction_repository
+        .create(
+            BigDecimal::from(-12),
+            "TACO TRUCK".to_string(),
+            april,
+            category.id,
+            None,
+        )
+        .await
+        .unwrap();
+    let open = transaction_repository
+        .create(
+            BigDecimal::from(-9),
+            "BAGEL SHOP".to_string(),
+            may,
+            category.id,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let closing = period_closing_repository
+        .close(april, Some("sam".to_string()), Some("sam".to_string()))
+        .await
+        .expect("Failed to close period");
+    assert_eq!(closing.closed_through, Some(april));
+    assert_eq!(closing.action, "close");
+
+    // Nothing dated in the closed period can be created, updated or deleted
+    let created = transaction_repository
+        .create(
+            BigDecimal::from(-5),
+            "COFFEE".to_string(),
+            april,
+            category.id,
+            None,
+        )
+        .await;
+    assert!(created.is_err());
+    let updated = transaction_repository
+        .update(
+            closed.id,
+            BigDecimal::from(-14),
+            "TACO TRUCK".to_string(),
+            april,
+            category.id,
+            None,
+        )
+        .await;
+    assert!(updated.is_err());
+    let moved_in = transaction_repository
+        .update(
+            open.id,
+            BigDecimal::from(-9),
+            "BAGEL SHOP".to_string(),
+            april,
+            category.id,
+            None,
+        )
+        .await;
+    assert!(moved_in.is_err());
+    let deleted = sqlx::query("DELETE FROM transactions WHERE id = $1")
+        .bind(closed.id)
+        .execute(&pool)
+        .await;
+    assert!(deleted.is_err());
+
+    // Imports are held to it too, whatever imports them
+    let imported = transaction_repository
+        .import_transactions(
+            DbNewImportBatch {
+                source_name: "april.csv".to_string(),
+                file_hash: None,
+                imported_by: None,
+                row_count: 1,
+                warnings: Vec::new(),
+            },
+            vec![DbImportRow {
+                account: "checking".to_string(),
+                external_id: Some("TX-1".to_string()),
+                amount: BigDecimal::from(-25),
+                description: "SPOTIFY".to_string(),
+                date: april,
+                category_id: category.id,
+                classification: None,
+                rule_id: None,
+                tags: Vec::new(),
+                new_category: None,
+            }],
+            None,
+        )
+        .await;
+    assert!(imported.is_err());
+
+    // Transactions after it, and filling in normalized descriptions, still work
+    transaction_repository
+        .update(
+            open.id,
+            BigDecimal::from(-10),
+            "BAGEL SHOP".to_string(),
+            may,
+            category.id,
+            None,
+        )
+        .await
+        .expect("Failed to update open transaction");
+    sqlx::query("UPDATE transactions SET normalized_description = NULL")
+        .execute(&pool)
+        .await
+        .unwrap();
+    transaction_repository
+        .normalize_descriptions()
+        .await
+        .unwrap();
+
+    let reopening = period_closing_repository
+        .reopen(
+            april,
+            Some("year-end review".to_string()),
+            Some("alex".to_string()),
+        )
+        .await
+        .expect("Failed to reopen period");
+    assert_eq!(
+        reopening.closed_through,
+        Some(Date::from_calendar_date(2025, Month::April, 29).unwrap())
+    );
+    transaction_repository
+        .update(
+            closed.id,
+            BigDecimal::from(-14),
+            "TACO TRUCK".to_string(),
+            april,
+            category.id,
+            None,
+        )
+        .await
+        .expect("Failed to update reopened transaction");
+
+    let closings = period_closing_repository.closings().await.unwrap();
+    assert_eq!(closings.len(), 2);
+    assert_eq!(closings[0].action, "reopen");
+    assert_eq!(closings[0].actor.as_deref(), Some("year-end review"));
+
+    // The reopen is in the audit log, made by alex, with the closing it replaced
+    let entries = audit_log_repository.entries("period", None).await.unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].action, "reopen");
+    assert_eq!(entries[0].actor, "alex");
+    assert!(entries[0]
+        .before
+        .as_ref()
+        .unwrap()
+        .contains(r#""closed_through": "2025-04-30""#));
+    assert!(entries[0]
+        .after
+        .as_ref()
+        .unwrap()
+        .contains(r#""closed_through": "2025-04-29""#));
+    assert_eq!(entries[1].action, "close");
+    assert!(entries[1].before.is_none());
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_recurring_schedule_skips_closed_period() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let recurring_repository = PgRecurringTransactionRepository { pool: pool.clone() };
+    let period_closing_repository = PgPeriodClosingRepository { pool: pool.clone() };
+
+    let category = category_repository
+        .create("Fitness".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    recurring_repository
+        .create(
+            "GYM".to_string(),
+            BigDecimal::from(-40),
+            category.id,
+            "monthly".to_string(),
+            1,
+            Some(15),
+            Date::from_calendar_date(2025, Month::March, 15).unwrap(),
+            None,
+        )
+        .await
+        .expect("Failed to create recurring transaction");
+    period_closing_repository
+        .close(
+            Date::from_calendar_date(2025, Month::April, 30).unwrap(),
+            None,
+            None,
+        )
+        .await
+        .expect("Failed to close period");
+
+    // March and April are closed, so only May is created and the schedule moves on
+    let created = materialize_due(
+        &recurring_repository,
+        NaiveDate::from_ymd_opt(2025, 5, 20).unwrap(),
+    )
+    .await
+    .expect("Failed to materialize");
+    assert_eq!(created, 1);
+    let created = materialize_due(
+        &recurring_repository,
+        NaiveDate::from_ymd_opt(2025, 6, 20).unwrap(),
+    )
+    .await
+    .expect("Failed to materialize");
+    assert_eq!(created, 1);
+
+    let dates: Vec<Date> = transaction_repository
+        .by_category_id(category.id)
+        .await
+        .expect("Failed to get transactions")
+        .into_iter()
+        .map(|transaction| transaction.date)
+        .collect();
+    assert_eq!(dates.len(), 2);
+    assert!(dates.contains(&Date::from_calendar_date(2025, Month::May, 15).unwrap()));
+    assert!(dates.contains(&Date::from_calendar_date(2025, Month::June, 15).unwrap()));
+
+    drop(container);
+}
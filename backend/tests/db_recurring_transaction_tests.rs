@@ -0,0 +1,140 @@
+use chrono::NaiveDate;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use time::Month;
+use transaction_server::{
+    db_models::{PgCategoryRepository, PgRecurringTransactionRepository, PgTransactionRepository},
+    db_traits::{CategoryRepository, RecurringTransactionRepository, TransactionRepository},
+    recurrence::materialize_due,
+};
+mod common;
+use common::test_utils::setup_test_db;
+
+#[tokio::test]
+async fn test_create_and_update_recurring_transaction() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let recurring_repository = PgRecurringTransactionRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Phone".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let start_date = Date::from_calendar_date(2025, Month::January, 1).unwrap();
+    let recurring = recurring_repository
+        .create(
+            "Mobile plan".to_string(),
+            BigDecimal::from(-80),
+            category.id,
+            "monthly".to_string(),
+            1,
+            Some(15),
+            start_date,
+            None,
+        )
+        .await
+        .expect("Failed to create recurring transaction");
+
+    assert_eq!(recurring.description, "Mobile plan");
+    assert_eq!(recurring.category_name, Some("Phone".to_string()));
+    assert_eq!(recurring.day_of_month, Some(15));
+    assert_eq!(recurring.last_materialized_date, None);
+    assert!(recurring.active);
+
+    let updated = recurring_repository
+        .update(
+            recurring.id,
+            "Mobile plan".to_string(),
+            BigDecimal::from(-85),
+            category.id,
+            "monthly".to_string(),
+            1,
+            Some(15),
+            start_date,
+            None,
+            false,
+        )
+        .await
+        .expect("Failed to update recurring transaction");
+
+    assert_eq!(updated.amount, BigDecimal::from(-85));
+    assert!(!updated.active);
+    assert!(recurring_repository.active().await.unwrap().is_empty());
+    assert_eq!(recurring_repository.all().await.unwrap().len(), 1);
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_materialize_is_idempotent() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let recurring_repository = PgRecurringTransactionRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Home Loan".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    recurring_repository
+        .create(
+            "Mortgage".to_string(),
+            BigDecimal::from(-2000),
+            category.id,
+            "monthly".to_string(),
+            1,
+            Some(1),
+            Date::from_calendar_date(2025, Month::January, 1).unwrap(),
+            None,
+        )
+        .await
+        .expect("Failed to create recurring transaction");
+
+    let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+    let created = materialize_due(&recurring_repository, today)
+        .await
+        .expect("Failed to materialize");
+    assert_eq!(created, 3);
+
+    // Running again, as after a restart, creates nothing new
+    let created = materialize_due(&recurring_repository, today)
+        .await
+        .expect("Failed to materialize");
+    assert_eq!(created, 0);
+
+    // Even if the pointer is lost the unique index prevents duplicates
+    let recurring = &recurring_repository.all().await.unwrap()[0];
+    let dates = vec![
+        NaiveDate::from_ymd_opt(2025, 2, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+    ];
+    let created = recurring_repository
+        .materialize(recurring.id, dates, &today)
+        .await
+        .expect("Failed to materialize");
+    assert_eq!(created, 0);
+
+    let transactions = transaction_repository
+        .by_category_id(category.id)
+        .await
+        .expect("Failed to get transactions");
+    assert_eq!(transactions.len(), 3);
+    assert!(transactions.iter().all(|t| t.description == "Mortgage"));
+
+    // Deleting the schedule keeps the transactions it created
+    assert!(recurring_repository.delete(recurring.id).await.unwrap());
+    assert_eq!(
+        transaction_repository
+            .by_category_id(category.id)
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+
+    drop(container);
+}
@@ -3,7 +3,7 @@ use sqlx::types::time::Date;
 use sqlx::types::BigDecimal;
 use time::Month;
 use transaction_server::{
-    db_models::{PgCategoryRepository, PgTransactionRepository},
+    db_models::{DbClassification, DbImportAction, DbImportBatch, DbImportRow, DbNewImportBatch, DbTransaction, PgCategoryRepository, PgTransactionRepository},
     db_traits::{CategoryRepository, TransactionRepository},
 };
 mod common;
@@ -23,6 +23,7 @@ async fn test_create_transaction() {
             Some("Test Description".to_string()),
             Some("test-icon".to_string()),
             Some("#FF0000".to_string()),
+            None,
         )
         .await
         .expect("Failed to create test category");
@@ -37,6 +38,7 @@ async fn test_create_transaction() {
             "Test Transaction".to_string(),
             today,
             category.id,
+            None,
         )
         .await
         .expect("Failed to create transaction");
@@ -60,7 +62,7 @@ async fn test_update_transaction() {
     let category_repository = PgCategoryRepository { pool: pool.clone() };
     // Create a category
     let category = category_repository
-        .create("Test Category".to_string(), None, None, None)
+        .create("Test Category".to_string(), None, None, None, None)
         .await
         .expect("Failed to create test category");
 
@@ -72,6 +74,7 @@ async fn test_update_transaction() {
             "Test Transaction".to_string(),
             today,
             category.id,
+            None,
         )
         .await
         .expect("Failed to create transaction");
@@ -87,6 +90,7 @@ async fn test_update_transaction() {
             "Updated Transaction".to_string(),
             new_date,
             category.id,
+            None,
         )
         .await
         .expect("Failed to update transaction");
@@ -109,12 +113,12 @@ async fn test_transactions_by_category() {
     let transaction_repository = PgTransactionRepository { pool: pool.clone() };
     // Create two categories
     let category1 = category_repository
-        .create("Category 1".to_string(), None, None, None)
+        .create("Category 1".to_string(), None, None, None, None)
         .await
         .expect("Failed to create category 1");
 
     let category2 = category_repository
-        .create("Category 2".to_string(), None, None, None)
+        .create("Category 2".to_string(), None, None, None, None)
         .await
         .expect("Failed to create category 2");
 
@@ -129,6 +133,7 @@ async fn test_transactions_by_category() {
                 "Test Transaction".to_string(),
                 today,
                 category1.id,
+                None,
             )
             .await
             .expect("Failed to create transaction");
@@ -141,6 +146,7 @@ async fn test_transactions_by_category() {
             "Test Transaction 2".to_string(),
             today,
             category2.id,
+            None,
         )
         .await
         .expect("Failed to create transaction");
@@ -168,7 +174,7 @@ async fn test_transactions_by_date_range() {
     let transaction_repository = PgTransactionRepository { pool: pool.clone() };
     // Create a category
     let category = category_repository
-        .create("Test Category".to_string(), None, None, None)
+        .create("Test Category".to_string(), None, None, None, None)
         .await
         .expect("Failed to create test category");
 
@@ -184,6 +190,7 @@ async fn test_transactions_by_date_range() {
                 format!("Transaction {}", day_offset + 1),
                 date.expect("Failed to add days"),
                 category.id,
+                None,
             )
             .await
             .expect("Failed to create transaction");
@@ -217,12 +224,12 @@ async fn test_transaction_summary_by_category() {
     let transaction_repository = PgTransactionRepository { pool: pool.clone() };
     // Create two categories
     let category1 = category_repository
-        .create("Category 1".to_string(), None, None, None)
+        .create("Category 1".to_string(), None, None, None, None)
         .await
         .expect("Failed to create category 1");
 
     let category2 = category_repository
-        .create("Category 2".to_string(), None, None, None)
+        .create("Category 2".to_string(), None, None, None, None)
         .await
         .expect("Failed to create category 2");
 
@@ -243,6 +250,7 @@ async fn test_transaction_summary_by_category() {
                 "Test Transaction".to_string(),
                 date,
                 category1.id,
+                None,
             )
             .await
             .expect("Failed to create transaction");
@@ -255,6 +263,7 @@ async fn test_transaction_summary_by_category() {
             "Test Transaction 2".to_string(),
             date,
             category2.id,
+            None,
         )
         .await
         .expect("Failed to create transaction");
@@ -288,3 +297,783 @@ async fn test_transaction_summary_by_category() {
 
     drop(container);
 }
+
+#[tokio::test]
+async fn test_compare_by_category() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category1 = category_repository
+        .create("Category 1".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create category 1");
+
+    let category2 = category_repository
+        .create("Category 2".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create category 2");
+
+    // Category 1 has spending in both periods, category 2 only in the second
+    let january = Date::from_calendar_date(2025, Month::January, 10).unwrap();
+    let february = Date::from_calendar_date(2025, Month::February, 10).unwrap();
+    for (amount, date, category_id) in [
+        (1000, january, category1.id),
+        (1500, february, category1.id),
+        (500, february, category2.id),
+    ] {
+        transaction_repository
+            .create(
+                BigDecimal::from(amount),
+                "Test Transaction".to_string(),
+                date,
+                category_id,
+                None,
+            )
+            .await
+            .expect("Failed to create transaction");
+    }
+
+    let comparison = transaction_repository
+        .compare_by_category(
+            &NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            &NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            &NaiveDate::from_ymd_opt(2025, 2, 1).unwrap(),
+            &NaiveDate::from_ymd_opt(2025, 2, 28).unwrap(),
+        )
+        .await
+        .expect("Failed to compare periods");
+
+    assert_eq!(comparison.len(), 2);
+
+    let cat1 = comparison
+        .iter()
+        .find(|c| c.category_id == Some(category1.id))
+        .unwrap();
+    let cat2 = comparison
+        .iter()
+        .find(|c| c.category_id == Some(category2.id))
+        .unwrap();
+
+    assert_eq!(cat1.period_a_total, Some(BigDecimal::from(1000)));
+    assert_eq!(cat1.period_b_total, Some(BigDecimal::from(1500)));
+    assert_eq!(cat2.period_a_total, Some(BigDecimal::from(0)));
+    assert_eq!(cat2.period_a_count, Some(0));
+    assert_eq!(cat2.period_b_total, Some(BigDecimal::from(500)));
+    assert_eq!(cat2.period_b_count, Some(1));
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_find_and_resolve_duplicates() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Subscription".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    // The same charge imported twice with different reference numbers, a day apart
+    let rows = [
+        ("SPOTIFY.COM 8841", -15, 1),
+        ("Spotify.com 1193", -15, 2),
+        ("SPOTIFY.COM 0412", -15, 31),
+        ("COFFEE SHOP", -5, 2),
+    ];
+    let mut ids = Vec::new();
+    for (description, amount, day) in rows {
+        let transaction = transaction_repository
+            .create(
+                BigDecimal::from(amount),
+                description.to_string(),
+                Date::from_ordinal_date(2025, day).unwrap(),
+                category.id,
+                None,
+            )
+            .await
+            .expect("Failed to create transaction");
+        ids.push(transaction.id);
+    }
+
+    let start_date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    let end_date = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+    let groups = transaction_repository
+        .find_duplicates(&start_date, &end_date, 3)
+        .await
+        .expect("Failed to find duplicates");
+    assert_eq!(groups.len(), 1);
+    let group_ids: Vec<i32> = groups[0].iter().map(|t| t.id).collect();
+    assert_eq!(group_ids, vec![ids[0], ids[1]]);
+
+    // Exact matching needs the same date
+    let groups = transaction_repository
+        .find_duplicates(&start_date, &end_date, 0)
+        .await
+        .expect("Failed to find duplicates");
+    assert!(groups.is_empty());
+
+    // The coffee has a different amount so it is not removed
+    let removed = transaction_repository
+        .resolve_duplicates(ids[0], vec![ids[1], ids[3]], None)
+        .await
+        .expect("Failed to resolve duplicates");
+    assert_eq!(removed, 1);
+
+    let remaining: Vec<i32> = transaction_repository
+        .by_date_range(&start_date, &end_date)
+        .await
+        .unwrap()
+        .iter()
+        .map(|t| t.id)
+        .collect();
+    assert_eq!(remaining.len(), 3);
+    assert!(!remaining.contains(&ids[1]));
+
+    // Keeping a transaction that does not exist fails
+    let result = transaction_repository.resolve_duplicates(-1, vec![ids[2]], None).await;
+    assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+
+    drop(container);
+}
+
+fn new_batch() -> DbNewImportBatch {
+    DbNewImportBatch {
+        source_name: "checking.csv".to_string(),
+        file_hash: None,
+        imported_by: None,
+        row_count: 3,
+        warnings: Vec::new(),
+    }
+}
+
+fn import_counts(batch: &DbImportBatch) -> (i32, i32, i32) {
+    (batch.inserted_count, batch.updated_count, batch.skipped_count)
+}
+
+#[tokio::test]
+async fn test_import_transactions_is_idempotent() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Groceries".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let row = |external_id: Option<&str>, amount: i32, description: &str| DbImportRow {
+        account: "checking".to_string(),
+        external_id: external_id.map(str::to_string),
+        amount: BigDecimal::from(amount),
+        description: description.to_string(),
+        date: Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+        category_id: category.id,
+        classification: None,
+        rule_id: None,
+        tags: Vec::new(),
+        new_category: None,
+    };
+    // Two genuine identical purchases without ids are both kept
+    let rows = vec![
+        row(Some("TX-1"), -40, "SAFEWAY 1234"),
+        row(None, -4, "COFFEE"),
+        row(None, -4, "COFFEE"),
+    ];
+
+    // A preview says what the import will do and writes nothing
+    let preview = |rows: Vec<DbImportRow>| {
+        let repository = &transaction_repository;
+        async move {
+            repository
+                .preview_import(rows)
+                .await
+                .expect("Failed to preview import")
+        }
+    };
+    assert_eq!(preview(rows.clone()).await, vec![DbImportAction::Insert; 3]);
+
+    let batch = transaction_repository
+        .import_transactions(new_batch(), rows.clone(), None)
+        .await
+        .expect("Failed to import transactions");
+    assert_eq!(import_counts(&batch), (3, 0, 0));
+
+    // Importing the same file again changes nothing
+    assert_eq!(preview(rows.clone()).await, vec![DbImportAction::Skip; 3]);
+    let batch = transaction_repository
+        .import_transactions(new_batch(), rows, None)
+        .await
+        .expect("Failed to import transactions");
+    assert_eq!(import_counts(&batch), (0, 0, 3));
+
+    // The bank corrected the amount of TX-1, and the same id in another account is a new transaction
+    let mut other_account = row(Some("TX-1"), -40, "SAFEWAY 1234");
+    other_account.account = "savings".to_string();
+    assert_eq!(
+        preview(vec![row(Some("TX-1"), -42, "SAFEWAY 1234"), other_account.clone()]).await,
+        vec![DbImportAction::Update, DbImportAction::Insert]
+    );
+    let batch = transaction_repository
+        .import_transactions(
+            new_batch(),
+            vec![row(Some("TX-1"), -42, "SAFEWAY 1234"), other_account],
+            None,
+        )
+        .await
+        .expect("Failed to import transactions");
+    assert_eq!(import_counts(&batch), (1, 1, 0));
+
+    let transactions = transaction_repository
+        .by_category_id(category.id)
+        .await
+        .expect("Failed to get transactions");
+    let corrected = BigDecimal::from(-42);
+    assert_eq!(transactions.len(), 4);
+    assert!(transactions.iter().any(|t| t.amount == corrected));
+
+    // Balances only count the account's own transactions up to the date
+    let balance = |account: &'static str, day: u32| {
+        let repository = &transaction_repository;
+        async move {
+            repository
+                .account_balance(account, &NaiveDate::from_ymd_opt(2025, 3, day).unwrap())
+                .await
+                .expect("Failed to get balance")
+        }
+    };
+    assert_eq!(balance("checking", 3).await, BigDecimal::from(-50));
+    assert_eq!(balance("checking", 2).await, BigDecimal::from(0));
+    assert_eq!(balance("savings", 3).await, BigDecimal::from(-40));
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_review_queue_and_confirm() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let groceries = category_repository
+        .create("Groceries".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let dining = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let row = |external_id: &str, confidence: Option<f64>| DbImportRow {
+        account: "checking".to_string(),
+        external_id: Some(external_id.to_string()),
+        amount: BigDecimal::from(-20),
+        description: format!("PURCHASE {}", external_id),
+        date: Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+        category_id: groceries.id,
+        classification: confidence.map(|confidence| DbClassification {
+            predicted_category: Some("Groceries".to_string()),
+            original_category: Some("Shopping".to_string()),
+            confidence: Some(confidence),
+            model: Some("nb-2025-03".to_string()),
+        }),
+        rule_id: None,
+        tags: Vec::new(),
+        new_category: None,
+    };
+    transaction_repository
+        .import_transactions(
+            new_batch(),
+            vec![
+                row("TX-1", Some(0.5)),
+                row("TX-2", Some(0.9)),
+                row("TX-3", Some(0.3)),
+                row("TX-4", None),
+                row("TX-5", Some(0.5)),
+            ],
+            None,
+        )
+        .await
+        .expect("Failed to import transactions");
+
+    let queue = |after: Option<(f64, i32)>, limit: i64| {
+        let repository = &transaction_repository;
+        async move {
+            repository
+                .review_queue(0.6, after, limit)
+                .await
+                .expect("Failed to get review queue")
+        }
+    };
+
+    // Only unsure predictions are reviewed, least confident first
+    let items = queue(None, 10).await;
+    let descriptions: Vec<&str> = items.iter().map(|item| item.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["PURCHASE TX-3", "PURCHASE TX-1", "PURCHASE TX-5"]);
+    assert_eq!(items[0].confidence, 0.3);
+    assert_eq!(items[0].predicted_category.as_deref(), Some("Groceries"));
+    assert_eq!(items[0].original_category.as_deref(), Some("Shopping"));
+    assert_eq!(items[0].classifier_model.as_deref(), Some("nb-2025-03"));
+    assert_eq!(items[0].category_name.as_deref(), Some("Groceries"));
+
+    // Pages continue after the last item, including between equal confidences
+    let first_page = queue(None, 2).await;
+    let last = first_page.last().unwrap();
+    let next_page = queue(Some((last.confidence, last.id)), 2).await;
+    assert_eq!(next_page.len(), 1);
+    assert_eq!(next_page[0].id, items[2].id);
+
+    // Changing the category by hand confirms it, confirming twice counts once
+    transaction_repository
+        .update(
+            items[0].id,
+            items[0].amount.clone(),
+            items[0].description.clone(),
+            items[0].date,
+            dining.id,
+            None,
+        )
+        .await
+        .expect("Failed to update transaction");
+    let confirmed = transaction_repository
+        .confirm_categories(vec![items[1].id, items[2].id], None)
+        .await
+        .expect("Failed to confirm categories");
+    assert_eq!(confirmed, 2);
+    let confirmed = transaction_repository
+        .confirm_categories(vec![items[1].id], None)
+        .await
+        .expect("Failed to confirm categories");
+    assert_eq!(confirmed, 0);
+    assert!(queue(None, 10).await.is_empty());
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_training_examples() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let groceries = category_repository
+        .create("Groceries".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let row = |external_id: &str, confidence: Option<f64>| DbImportRow {
+        account: "checking".to_string(),
+        external_id: Some(external_id.to_string()),
+        amount: BigDecimal::from(-20),
+        description: format!("PURCHASE {}", external_id),
+        date: Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+        category_id: groceries.id,
+        classification: confidence.map(|confidence| DbClassification {
+            predicted_category: Some("Groceries".to_string()),
+            original_category: None,
+            confidence: Some(confidence),
+            model: None,
+        }),
+        rule_id: None,
+        tags: Vec::new(),
+        new_category: None,
+    };
+    transaction_repository
+        .import_transactions(
+            new_batch(),
+            vec![
+                row("TX-1", None),
+                row("TX-2", Some(0.9)),
+                row("TX-3", Some(0.3)),
+                row("TX-4", Some(0.2)),
+            ],
+            None,
+        )
+        .await
+        .expect("Failed to import transactions");
+    let unsure = transaction_repository
+        .review_queue(0.6, None, 10)
+        .await
+        .expect("Failed to get review queue");
+    transaction_repository
+        .confirm_categories(vec![unsure[0].id], None)
+        .await
+        .expect("Failed to confirm categories");
+
+    // Unsure predictions are left out until they're confirmed
+    let examples = transaction_repository
+        .training_examples(0.6)
+        .await
+        .expect("Failed to get training examples");
+    let descriptions: Vec<&str> =
+        examples.iter().map(|example| example.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["PURCHASE TX-1", "PURCHASE TX-2", "PURCHASE TX-4"]);
+    assert_eq!(examples[0].category_id, groceries.id);
+    assert_eq!(examples[0].category_name, "Groceries");
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_category_corrections_and_accuracy() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let groceries = category_repository
+        .create("Groceries".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let dining = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+
+    let row = |external_id: &str, category_id: i32, confidence: Option<f64>| DbImportRow {
+        account: "checking".to_string(),
+        external_id: Some(external_id.to_string()),
+        amount: BigDecimal::from(-20),
+        description: format!("PURCHASE {}", external_id),
+        date: Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+        category_id,
+        classification: confidence.map(|confidence| DbClassification {
+            confidence: Some(confidence),
+            model: Some("nb-2025-03".to_string()),
+            ..Default::default()
+        }),
+        rule_id: None,
+        tags: Vec::new(),
+        new_category: None,
+    };
+    transaction_repository
+        .import_transactions(
+            new_batch(),
+            vec![
+                row("TX-1", groceries.id, Some(0.4)),
+                row("TX-2", groceries.id, Some(0.9)),
+                row("TX-3", dining.id, Some(0.8)),
+                row("TX-4", groceries.id, None),
+            ],
+            None,
+        )
+        .await
+        .expect("Failed to import transactions");
+    let transactions = transaction_repository.all().await.unwrap();
+    let find = |description: &str| {
+        transactions
+            .iter()
+            .find(|t| t.description == description)
+            .unwrap()
+            .clone()
+    };
+    let recategorize = |transaction: DbTransaction, category_id: i32| {
+        let repository = &transaction_repository;
+        async move {
+            repository
+                .update(
+                    transaction.id,
+                    transaction.amount,
+                    transaction.description,
+                    transaction.date,
+                    category_id,
+                    None,
+                )
+                .await
+                .expect("Failed to update transaction")
+        }
+    };
+
+    // The classifier's choice is corrected, then the person's own choice is
+    let tx1 = recategorize(find("PURCHASE TX-1"), dining.id).await;
+    recategorize(tx1, groceries.id).await;
+    recategorize(find("PURCHASE TX-4"), dining.id).await;
+    // changing something else isn't a correction
+    let mut tx2 = find("PURCHASE TX-2");
+    tx2.description = "PURCHASE TX-2 EDITED".to_string();
+    recategorize(tx2, groceries.id).await;
+
+    let corrections = transaction_repository.corrections().await.unwrap();
+    let recorded: Vec<(&str, &str, i32, i32)> = corrections
+        .iter()
+        .map(|c| {
+            (
+                c.description.as_str(),
+                c.source.as_str(),
+                c.old_category_id,
+                c.new_category_id,
+            )
+        })
+        .collect();
+    assert_eq!(
+        recorded,
+        vec![
+            ("PURCHASE TX-4", "manual", groceries.id, dining.id),
+            ("PURCHASE TX-1", "manual", dining.id, groceries.id),
+            ("PURCHASE TX-1", "model", groceries.id, dining.id),
+        ]
+    );
+    assert_eq!(corrections[2].classifier_model.as_deref(), Some("nb-2025-03"));
+    assert_eq!(corrections[2].confidence, Some(0.4));
+    assert_eq!(corrections[2].old_category_name.as_deref(), Some("Groceries"));
+
+    // TX-1 counts as a corrected prediction of Groceries, whatever it has now
+    let accuracy = transaction_repository
+        .classifier_accuracy(
+            &NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+            &NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(),
+        )
+        .await
+        .expect("Failed to get classifier accuracy");
+    let counts: Vec<(Option<&str>, i64, i64)> = accuracy
+        .iter()
+        .map(|a| (a.category_name.as_deref(), a.predictions, a.corrections))
+        .collect();
+    assert_eq!(counts, vec![(Some("Dining"), 1, 0), (Some("Groceries"), 2, 1)]);
+    let accuracy = transaction_repository
+        .classifier_accuracy(
+            &NaiveDate::from_ymd_opt(2025, 4, 1).unwrap(),
+            &NaiveDate::from_ymd_opt(2025, 4, 30).unwrap(),
+        )
+        .await
+        .expect("Failed to get classifier accuracy");
+    assert!(accuracy.is_empty());
+
+    // A category someone corrected to is marked for training
+    let examples = transaction_repository.training_examples(0.6).await.unwrap();
+    let corrected: Vec<(&str, bool)> = examples
+        .iter()
+        .map(|example| (example.description.as_str(), example.corrected))
+        .collect();
+    assert_eq!(
+        corrected,
+        vec![
+            ("PURCHASE TX-1", true),
+            ("PURCHASE TX-2 EDITED", false),
+            ("PURCHASE TX-3", false),
+            ("PURCHASE TX-4", true),
+        ]
+    );
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_normalized_descriptions_are_stored() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let category = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let normalized_of = |id: i32| {
+        let pool = &pool;
+        async move {
+            sqlx::query_scalar::<_, Option<String>>(
+                "SELECT normalized_description FROM transactions WHERE id = $1",
+            )
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+        }
+    };
+
+    let created = transaction_repository
+        .create(
+            BigDecimal::from(-5),
+            "SQ *BLUE BOTTLE #12 OAKLAND CA".to_string(),
+            Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+            category.id,
+            None,
+        )
+        .await
+        .expect("Failed to create transaction");
+    assert_eq!(normalized_of(created.id).await.as_deref(), Some("blue bottle"));
+
+    let updated = transaction_repository
+        .update(
+            created.id,
+            created.amount.clone(),
+            "TST* SHAKE SHACK 0042".to_string(),
+            created.date,
+            category.id,
+            None,
+        )
+        .await
+        .expect("Failed to update transaction");
+    assert_eq!(updated.description, "TST* SHAKE SHACK 0042");
+    assert_eq!(normalized_of(created.id).await.as_deref(), Some("shake shack"));
+
+    transaction_repository
+        .import_transactions(
+            new_batch(),
+            vec![DbImportRow {
+                account: "checking".to_string(),
+                external_id: Some("TX-1".to_string()),
+                amount: BigDecimal::from(-12),
+                description: "POS DEBIT CHIPOTLE 1234 AUSTIN TX".to_string(),
+                date: Date::from_calendar_date(2025, Month::March, 4).unwrap(),
+                category_id: category.id,
+                classification: None,
+                rule_id: None,
+                tags: Vec::new(),
+                new_category: None,
+            }],
+            None,
+        )
+        .await
+        .expect("Failed to import transactions");
+    let imported: i32 =
+        sqlx::query_scalar("SELECT id FROM transactions WHERE external_id = 'TX-1'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(normalized_of(imported).await.as_deref(), Some("chipotle"));
+
+    // Rows written without one get it filled in, which isn't an edit
+    sqlx::query("UPDATE transactions SET normalized_description = NULL WHERE id = $1")
+        .bind(imported)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let before: Option<time::OffsetDateTime> =
+        sqlx::query_scalar("SELECT updated_at FROM transactions WHERE id = $1")
+            .bind(imported)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    let normalized = transaction_repository
+        .normalize_descriptions()
+        .await
+        .expect("Failed to normalize descriptions");
+    assert_eq!(normalized, 1);
+    assert_eq!(normalized_of(imported).await.as_deref(), Some("chipotle"));
+    let after: Option<time::OffsetDateTime> =
+        sqlx::query_scalar("SELECT updated_at FROM transactions WHERE id = $1")
+            .bind(imported)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(before, after);
+    assert_eq!(transaction_repository.normalize_descriptions().await.unwrap(), 0);
+
+    drop(container);
+}
+
+#[tokio::test]
+async fn test_transactions_as_of() {
+    // Set up test database
+    let (pool, container) = setup_test_db().await;
+
+    let transaction_repository = PgTransactionRepository { pool: pool.clone() };
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let dining = category_repository
+        .create("Dining".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let groceries = category_repository
+        .create("Groceries".to_string(), None, None, None, None)
+        .await
+        .expect("Failed to create test category");
+    let now = || async {
+        sqlx::query_scalar::<_, time::OffsetDateTime>("SELECT CURRENT_TIMESTAMP")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+    };
+
+    let march = Date::from_calendar_date(2025, Month::March, 14).unwrap();
+    let before_anything = now().await;
+    let lunch = transaction_repository
+        .create(BigDecimal::from(-20), "TACO TRUCK".to_string(), march, dining.id, None)
+        .await
+        .unwrap();
+    let groceries_run = transaction_repository
+        .create(BigDecimal::from(-60), "WHOLE FOODS".to_string(), march, dining.id, None)
+        .await
+        .unwrap();
+    let april_first = now().await;
+
+    // Later the groceries are recategorized, Dining renamed and the lunch removed as a
+    // duplicate of a new one
+    transaction_repository
+        .update(
+            groceries_run.id,
+            groceries_run.amount.clone(),
+            groceries_run.description.clone(),
+            march,
+            groceries.id,
+            None,
+        )
+        .await
+        .unwrap();
+    category_repository
+        .update(dining.id, "Restaurants".to_string(), None, None, None, None)
+        .await
+        .unwrap();
+    let dinner = transaction_repository
+        .create(BigDecimal::from(-20), "TACO TRUCK".to_string(), march, dining.id, None)
+        .await
+        .unwrap();
+    transaction_repository
+        .resolve_duplicates(dinner.id, vec![lunch.id], None)
+        .await
+        .unwrap();
+
+    let start = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+    let end = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+    let summary = transaction_repository
+        .sum_by_category_as_of(&start, &end, &april_first)
+        .await
+        .unwrap();
+    assert_eq!(summary.len(), 1);
+    assert_eq!(summary[0].category_name.as_deref(), Some("Dining"));
+    assert_eq!(summary[0].total_amount, Some(BigDecimal::from(-80)));
+    assert_eq!(summary[0].transaction_count, Some(2));
+
+    let mut ids: Vec<i32> = transaction_repository
+        .by_date_range_as_of(&start, &end, &april_first)
+        .await
+        .unwrap()
+        .iter()
+        .map(|tx| tx.id)
+        .collect();
+    ids.sort();
+    assert_eq!(ids, vec![lunch.id, groceries_run.id]);
+    let dining_then = transaction_repository
+        .by_category_id_as_of(dining.id, &april_first)
+        .await
+        .unwrap();
+    assert_eq!(dining_then.len(), 2);
+    assert!(transaction_repository
+        .all_as_of(&before_anything)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Today the same report reflects every change since
+    let summary = transaction_repository
+        .sum_by_category(&start, &end)
+        .await
+        .unwrap();
+    assert_eq!(summary.len(), 2);
+    let now = now().await;
+    let all = transaction_repository.all_as_of(&now).await.unwrap();
+    assert_eq!(all.len(), 2);
+    assert!(all
+        .iter()
+        .any(|tx| tx.id == dinner.id && tx.category_name.as_deref() == Some("Restaurants")));
+
+    drop(container);
+}
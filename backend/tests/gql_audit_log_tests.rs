@@ -0,0 +1,194 @@
+use juniper::Variables;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use std::sync::Arc;
+use time::Month;
+use transaction_server::{
+    db_models::{DbAuditEntry, DbTransaction},
+    db_traits::{MockAuditLogRepository, MockCategoryRepository, MockTransactionRepository},
+    gql_schema::create_schema,
+    graphql::GraphQLContext,
+};
+mod common;
+use common::test_utils::get_context;
+
+fn entry(id: i32, action: &str, source: &str) -> DbAuditEntry {
+    DbAuditEntry {
+        id,
+        entity_type: "transaction".to_string(),
+        entity_id: 7,
+        action: action.to_string(),
+        actor: "sam".to_string(),
+        source: source.to_string(),
+        before: (action != "create").then(|| r#"{"id": 7, "amount": -12.00}"#.to_string()),
+        after: Some(r#"{"id": 7, "amount": -14.00}"#.to_string()),
+        created_at: None,
+    }
+}
+
+#[tokio::test]
+async fn test_audit_log_and_transaction_history() {
+    let mut mock_transaction_repository = MockTransactionRepository::new();
+    let mut mock_audit_log_repository = MockAuditLogRepository::new();
+
+    mock_transaction_repository.expect_all().returning(|| {
+        Ok(vec![DbTransaction {
+            id: 7,
+            amount: BigDecimal::from(-14),
+            description: "TACO TRUCK".to_string(),
+            date: Date::from_calendar_date(2025, Month::May, 2).unwrap(),
+            category_id: 1,
+            category_name: Some("Dining".to_string()),
+            created_at: None,
+            updated_at: None,
+        }])
+    });
+    mock_audit_log_repository
+        .expect_entries()
+        .withf(|entity_type: &str, entity_id: &Option<i32>| {
+            entity_type == "transaction" && *entity_id == Some(7)
+        })
+        .times(2)
+        .returning(|_, _| Ok(vec![entry(2, "update", "rule"), entry(1, "create", "import")]));
+
+    let context_mock = GraphQLContext {
+        audit_log_repository: Arc::new(mock_audit_log_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(mock_transaction_repository),
+        )
+    };
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            auditLog(entityType: TRANSACTION, entityId: 7) {
+                id
+                entityType
+                entityId
+                action
+                actor
+                source
+                before
+                after
+            }
+            allTransactions {
+                id
+                history {
+                    action
+                    source
+                }
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let data = data.as_object_value().unwrap();
+    let entries = data
+        .get_field_value("auditLog")
+        .and_then(|v| v.as_list_value())
+        .expect("auditLog should be a list");
+    assert_eq!(entries.len(), 2);
+    let update = entries[0].as_object_value().unwrap();
+    assert_scalar_value!(update, "entityType", String, "TRANSACTION".to_string(), "audit entry");
+    assert_scalar_value!(update, "entityId", i32, 7, "audit entry");
+    assert_scalar_value!(update, "action", String, "UPDATE".to_string(), "audit entry");
+    assert_scalar_value!(update, "actor", String, "sam".to_string(), "audit entry");
+    assert_scalar_value!(update, "source", String, "RULE".to_string(), "audit entry");
+    assert_scalar_value!(
+        update,
+        "before",
+        String,
+        r#"{"id": 7, "amount": -12.00}"#.to_string(),
+        "audit entry"
+    );
+    let create = entries[1].as_object_value().unwrap();
+    assert_optional_scalar_value!(create, "before", String, None::<String>, "audit entry");
+
+    let transactions = data
+        .get_field_value("allTransactions")
+        .and_then(|v| v.as_list_value())
+        .expect("allTransactions should be a list");
+    let history = transactions[0]
+        .as_object_value()
+        .and_then(|o| o.get_field_value("history"))
+        .and_then(|v| v.as_list_value())
+        .expect("history should be a list");
+    assert_eq!(history.len(), 2);
+    let created = history[1].as_object_value().unwrap();
+    assert_scalar_value!(created, "action", String, "CREATE".to_string(), "history");
+    assert_scalar_value!(created, "source", String, "IMPORT".to_string(), "history");
+}
+
+#[tokio::test]
+async fn test_undo_redo_and_delete() {
+    let mut mock_transaction_repository = MockTransactionRepository::new();
+    let mut mock_audit_log_repository = MockAuditLogRepository::new();
+
+    mock_transaction_repository
+        .expect_delete()
+        .withf(|id: &i32, actor: &Option<String>| *id == 7 && actor.as_deref() == Some("sam"))
+        .times(1)
+        .returning(|_, _| Ok(true));
+    mock_audit_log_repository
+        .expect_undo()
+        .withf(|actor: &Option<String>| actor.as_deref() == Some("sam"))
+        .times(1)
+        .returning(|_| Ok(vec![entry(2, "delete", "api")]));
+    mock_audit_log_repository
+        .expect_redo()
+        .withf(|actor: &Option<String>| actor.as_deref() == Some("sam"))
+        .times(1)
+        .returning(|_| Ok(Vec::new()));
+
+    // the request's user makes the change
+    let context_mock = GraphQLContext {
+        audit_log_repository: Arc::new(mock_audit_log_repository),
+        actor: Some("sam".to_string()),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(mock_transaction_repository),
+        )
+    };
+    let schema = create_schema();
+
+    let mutation = r#"
+        mutation {
+            deleteTransaction(id: 7)
+            undo {
+                entityId
+                action
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let data = data.as_object_value().unwrap();
+    assert_scalar_value!(data, "deleteTransaction", bool, true, "mutation");
+    let undone = data
+        .get_field_value("undo")
+        .and_then(|v| v.as_list_value())
+        .expect("undo should be a list");
+    assert_eq!(undone.len(), 1);
+    let deleted = undone[0].as_object_value().unwrap();
+    assert_scalar_value!(deleted, "action", String, "DELETE".to_string(), "undo");
+
+    // Nothing left to redo is an error
+    let mutation = r#"
+        mutation {
+            redo {
+                id
+            }
+        }
+    "#;
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Mutation execution failed");
+    assert_eq!(errors.len(), 1);
+    assert!(format!("{:?}", errors[0]).contains("Nothing to redo"));
+}
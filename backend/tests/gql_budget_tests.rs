@@ -0,0 +1,392 @@
+use bigdecimal::FromPrimitive;
+use chrono::NaiveDate;
+use juniper::{InputValue, Variables};
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use std::sync::Arc;
+use time::Month;
+use transaction_server::{
+    db_models::{
+        DbBudget, DbCategory, DbCategorySummary, DbEnvelopeAssignment, DbMonthlyCategoryTotal,
+    },
+    db_traits::{MockBudgetRepository, MockCategoryRepository, MockTransactionRepository},
+    gql_schema::create_schema,
+    graphql::GraphQLContext,
+};
+mod common;
+use common::test_utils::get_context;
+
+fn budget(id: i32, category_id: i32, name: &str, period_type: &str, amount: f64) -> DbBudget {
+    DbBudget {
+        id,
+        category_id,
+        category_name: Some(name.to_string()),
+        period_start: Date::from_calendar_date(2025, Month::January, 1).unwrap(),
+        period_type: period_type.to_string(),
+        amount: BigDecimal::from_f64(amount).unwrap(),
+        rollover: false,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+#[tokio::test]
+async fn test_create_budget() {
+    let mut mock_budget_repository = MockBudgetRepository::new();
+
+    let expected_budget = budget(1, 2, "Dining", "monthly", 500.0);
+    let returned_budget = expected_budget.clone();
+    mock_budget_repository
+        .expect_create()
+        .withf(|category_id, period_start, period_type, _amount, rollover| {
+            *category_id == 2
+                && *period_start == Date::from_calendar_date(2025, Month::January, 1).unwrap()
+                && period_type == "monthly"
+                && !*rollover
+        })
+        .returning(move |_, _, _, _, _| Ok(returned_budget.clone()));
+
+    let context_mock = GraphQLContext {
+        budget_repository: Arc::new(mock_budget_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(MockTransactionRepository::new()),
+        )
+    };
+    let schema = create_schema();
+
+    let query = r#"
+        mutation CreateBudget($categoryId: Int!, $periodStart: String!, $amount: Float!) {
+            createBudget(categoryId: $categoryId, periodStart: $periodStart, periodType: MONTHLY, amount: $amount) {
+                id
+                categoryId
+                categoryName
+                periodStart
+                periodType
+                amount
+            }
+        }
+    "#;
+
+    let mut variables = Variables::new();
+    variables.insert("categoryId".to_string(), InputValue::scalar(2));
+    variables.insert("periodStart".to_string(), InputValue::scalar("2025-01-01".to_string()));
+    variables.insert("amount".to_string(), InputValue::scalar(500.0));
+    let result = juniper::execute(query, None, &schema, &variables, &context_mock).await;
+
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let obj = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("createBudget"))
+        .and_then(|v| v.as_object_value())
+        .expect("createBudget should be an object");
+    let context = "created budget";
+    assert_scalar_value!(obj, "id", i32, expected_budget.id, context);
+    assert_scalar_value!(obj, "periodStart", String, "2025-01-01".to_string(), context);
+    assert_scalar_value!(obj, "amount", f64, 500.0, context);
+    assert_eq!(
+        obj.get_field_value("periodType").and_then(|v| v.as_string_value()),
+        Some("MONTHLY")
+    );
+}
+
+#[tokio::test]
+async fn test_create_budget_rejects_mid_month_start() {
+    let context_mock = get_context(
+        Arc::new(MockCategoryRepository::new()),
+        Arc::new(MockTransactionRepository::new()),
+    );
+    let schema = create_schema();
+
+    let query = r#"
+        mutation {
+            createBudget(categoryId: 1, periodStart: "2025-01-15", periodType: MONTHLY, amount: 10.0) {
+                id
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Query execution failed");
+    assert_eq!(errors.len(), 1);
+}
+
+#[tokio::test]
+async fn test_budget_status() {
+    let mut mock_budget_repository = MockBudgetRepository::new();
+    let mut mock_transaction_repository = MockTransactionRepository::new();
+
+    // A monthly dining budget and a yearly insurance budget, both starting January 2025
+    let budgets = vec![
+        budget(1, 1, "Dining", "monthly", 400.0),
+        budget(2, 2, "Insurance", "yearly", 1200.0),
+    ];
+    mock_budget_repository
+        .expect_effective_on()
+        .withf(|date: &NaiveDate| *date == NaiveDate::from_ymd_opt(2025, 3, 31).unwrap())
+        .returning(move |_| Ok(budgets.clone()));
+
+    mock_transaction_repository
+        .expect_sum_by_category()
+        .returning(|start: &NaiveDate, end: &NaiveDate| {
+            let march = (
+                NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(),
+            );
+            let year = (
+                NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+            );
+            let (category_id, total) = if (*start, *end) == march {
+                (1, -300.0)
+            } else if (*start, *end) == year {
+                (2, -1500.0)
+            } else {
+                panic!("Unexpected period {} - {}", start, end);
+            };
+            Ok(vec![DbCategorySummary {
+                category_id,
+                category_name: None,
+                total_amount: Some(BigDecimal::from_f64(total).unwrap()),
+                transaction_count: Some(1),
+            }])
+        });
+
+    let context_mock = GraphQLContext {
+        budget_repository: Arc::new(mock_budget_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(mock_transaction_repository),
+        )
+    };
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            budgetStatus(month: "2025-03") {
+                categoryName
+                periodStart
+                periodEnd
+                budgetAmount
+                actual
+                remaining
+                percentUsed
+                projected
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let rows = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("budgetStatus"))
+        .and_then(|v| v.as_list_value())
+        .expect("budgetStatus should be a list");
+    assert_eq!(rows.len(), 2);
+
+    // Both periods are in the past so the projection is the actual spend
+    let dining = rows[0].as_object_value().unwrap();
+    assert_scalar_value!(dining, "categoryName", String, "Dining".to_string(), "dining");
+    assert_scalar_value!(dining, "periodStart", String, "2025-03-01".to_string(), "dining");
+    assert_scalar_value!(dining, "actual", f64, 300.0, "dining");
+    assert_scalar_value!(dining, "remaining", f64, 100.0, "dining");
+    assert_scalar_value!(dining, "percentUsed", f64, 75.0, "dining");
+    assert_scalar_value!(dining, "projected", f64, 300.0, "dining");
+
+    let insurance = rows[1].as_object_value().unwrap();
+    assert_scalar_value!(insurance, "periodEnd", String, "2025-12-31".to_string(), "insurance");
+    assert_scalar_value!(insurance, "remaining", f64, -300.0, "insurance");
+    assert_scalar_value!(insurance, "percentUsed", f64, 125.0, "insurance");
+}
+
+#[tokio::test]
+async fn test_budget_status_with_rollover() {
+    let mut mock_budget_repository = MockBudgetRepository::new();
+    let mut mock_transaction_repository = MockTransactionRepository::new();
+
+    // A 100 monthly rollover budget since January, underspent by 40 in January
+    // and overspent by 10 in February
+    let mut rollover_budget = budget(1, 1, "Kid Cash", "monthly", 100.0);
+    rollover_budget.rollover = true;
+    let history = vec![rollover_budget.clone()];
+    mock_budget_repository
+        .expect_effective_on()
+        .returning(move |_| Ok(vec![rollover_budget.clone()]));
+    mock_budget_repository
+        .expect_all()
+        .returning(move || Ok(history.clone()));
+
+    mock_transaction_repository
+        .expect_monthly_totals_by_category()
+        .withf(|start: &NaiveDate, end: &NaiveDate| {
+            *start == NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+                && *end == NaiveDate::from_ymd_opt(2025, 2, 28).unwrap()
+        })
+        .returning(|_, _| {
+            Ok([(Month::January, -60.0), (Month::February, -110.0)]
+                .into_iter()
+                .map(|(month, total)| DbMonthlyCategoryTotal {
+                    category_id: 1,
+                    month: Date::from_calendar_date(2025, month, 1).unwrap(),
+                    total_amount: BigDecimal::from_f64(total).unwrap(),
+                })
+                .collect())
+        });
+    mock_transaction_repository
+        .expect_sum_by_category()
+        .returning(|_, _| {
+            Ok(vec![DbCategorySummary {
+                category_id: 1,
+                category_name: None,
+                total_amount: Some(BigDecimal::from_f64(-50.0).unwrap()),
+                transaction_count: Some(2),
+            }])
+        });
+
+    let context_mock = GraphQLContext {
+        budget_repository: Arc::new(mock_budget_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(mock_transaction_repository),
+        )
+    };
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            budgetStatus(month: "2025-03") {
+                rollover
+                carriedOver
+                actual
+                remaining
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let rows = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("budgetStatus"))
+        .and_then(|v| v.as_list_value())
+        .expect("budgetStatus should be a list");
+    let status = rows[0].as_object_value().unwrap();
+    assert_scalar_value!(status, "rollover", bool, true, "rollover budget");
+    assert_scalar_value!(status, "carriedOver", f64, 30.0, "rollover budget");
+    assert_scalar_value!(status, "actual", f64, 50.0, "rollover budget");
+    assert_scalar_value!(status, "remaining", f64, 80.0, "rollover budget");
+}
+
+#[tokio::test]
+async fn test_envelope_ledger() {
+    let mut mock_category_repository = MockCategoryRepository::new();
+    let mut mock_budget_repository = MockBudgetRepository::new();
+    let mut mock_transaction_repository = MockTransactionRepository::new();
+
+    mock_category_repository
+        .expect_find_by_name()
+        .withf(|name: &str| name == "Income")
+        .returning(|_| {
+            Ok(DbCategory {
+                id: 13,
+                name: "Income".to_string(),
+                description: None,
+                icon: None,
+                color: None,
+                created_at: None,
+                updated_at: None,
+            })
+        });
+
+    // 50 assigned to Kid Cash in January and nothing in February
+    mock_budget_repository
+        .expect_assignments_through()
+        .returning(|_| {
+            Ok(vec![DbEnvelopeAssignment {
+                id: 1,
+                category_id: 16,
+                category_name: Some("Kid Cash".to_string()),
+                month: Date::from_calendar_date(2025, Month::January, 1).unwrap(),
+                amount: BigDecimal::from_f64(50.0).unwrap(),
+                created_at: None,
+                updated_at: None,
+            }])
+        });
+
+    mock_transaction_repository
+        .expect_monthly_totals_by_category()
+        .returning(|_, _| {
+            Ok([
+                (13, Month::January, 1000.0),
+                (16, Month::January, -20.0),
+                (13, Month::February, 500.0),
+                (16, Month::February, -10.0),
+            ]
+            .into_iter()
+            .map(|(category_id, month, total)| DbMonthlyCategoryTotal {
+                category_id,
+                month: Date::from_calendar_date(2025, month, 1).unwrap(),
+                total_amount: BigDecimal::from_f64(total).unwrap(),
+            })
+            .collect())
+        });
+
+    let context_mock = GraphQLContext {
+        budget_repository: Arc::new(mock_budget_repository),
+        ..get_context(
+            Arc::new(mock_category_repository),
+            Arc::new(mock_transaction_repository),
+        )
+    };
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            envelopeLedger(startMonth: "2025-02", endMonth: "2025-02") {
+                month
+                income
+                availableToAssign
+                envelopes {
+                    categoryName
+                    carriedIn
+                    assigned
+                    activity
+                    available
+                }
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let months = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("envelopeLedger"))
+        .and_then(|v| v.as_list_value())
+        .expect("envelopeLedger should be a list");
+    assert_eq!(months.len(), 1);
+
+    let february = months[0].as_object_value().unwrap();
+    assert_scalar_value!(february, "month", String, "2025-02-01".to_string(), "february");
+    assert_scalar_value!(february, "income", f64, 500.0, "february");
+    assert_scalar_value!(february, "availableToAssign", f64, 1450.0, "february");
+
+    let envelopes = february
+        .get_field_value("envelopes")
+        .and_then(|v| v.as_list_value())
+        .expect("envelopes should be a list");
+    let kid_cash = envelopes[0].as_object_value().unwrap();
+    assert_scalar_value!(kid_cash, "carriedIn", f64, 30.0, "kid cash");
+    assert_scalar_value!(kid_cash, "assigned", f64, 0.0, "kid cash");
+    assert_scalar_value!(kid_cash, "activity", f64, -10.0, "kid cash");
+    assert_scalar_value!(kid_cash, "available", f64, 20.0, "kid cash");
+}
@@ -0,0 +1,560 @@
+use chrono::NaiveDate;
+use juniper::Variables;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use std::sync::Arc;
+use time::Month;
+use transaction_server::{
+    db_models::{
+        DbCategorizationRule, DbCategory, DbCategoryAccuracy, DbCategoryCorrection,
+        DbNewCategorizationRule, DbNewRuleApplication, DbRuleApplication, DbTaggedTransaction,
+        DbTransaction,
+    },
+    db_traits::{MockCategorizationRuleRepository, MockCategoryRepository, MockTransactionRepository},
+    gql_schema::create_schema,
+    graphql::GraphQLContext,
+};
+mod common;
+use common::test_utils::get_context;
+
+fn netflix_rule() -> DbCategorizationRule {
+    DbCategorizationRule {
+        id: 4,
+        name: "Netflix".to_string(),
+        priority: 10,
+        description_pattern: Some("netflix".to_string()),
+        match_kind: "contains".to_string(),
+        min_amount: None,
+        max_amount: Some(BigDecimal::from(30)),
+        account: None,
+        sign: Some("debit".to_string()),
+        category_id: 6,
+        category_name: Some("Subscription".to_string()),
+        tags: vec!["streaming".to_string()],
+        enabled: true,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+#[tokio::test]
+async fn test_categorization_rule_crud() {
+    let mut mock_rule_repository = MockCategorizationRuleRepository::new();
+    mock_rule_repository
+        .expect_all()
+        .returning(|| Ok(vec![netflix_rule()]));
+    mock_rule_repository
+        .expect_create()
+        .withf(|rule: &DbNewCategorizationRule| {
+            rule.name == "Netflix"
+                && rule.priority == 10
+                && rule.match_kind == "contains"
+                && rule.max_amount == Some(BigDecimal::from(30))
+                && rule.sign.as_deref() == Some("debit")
+                // blank tags are dropped
+                && rule.tags == vec!["streaming".to_string()]
+                && rule.enabled
+        })
+        .times(1)
+        .returning(|_| Ok(netflix_rule()));
+    mock_rule_repository
+        .expect_delete()
+        .withf(|id: &i32| *id == 4)
+        .times(1)
+        .returning(|_| Ok(true));
+
+    let context_mock = GraphQLContext {
+        categorization_rule_repository: Arc::new(mock_rule_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(MockTransactionRepository::new()),
+        )
+    };
+    let schema = create_schema();
+
+    let mutation = r#"
+        mutation {
+            createCategorizationRule(rule: {
+                name: " Netflix ", priority: 10, descriptionPattern: "netflix",
+                maxAmount: 30.0, sign: DEBIT, categoryId: 6, tags: ["streaming", " "]
+            }) {
+                id
+                matchKind
+                maxAmount
+                sign
+                categoryName
+                tags
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let obj = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("createCategorizationRule"))
+        .and_then(|v| v.as_object_value())
+        .expect("createCategorizationRule should be an object");
+    let context = "categorization rule";
+    assert_scalar_value!(obj, "id", i32, 4, context);
+    assert_scalar_value!(obj, "maxAmount", f64, 30.0, context);
+    assert_scalar_value!(obj, "categoryName", String, "Subscription".to_string(), context);
+    assert_eq!(
+        obj.get_field_value("sign").and_then(|v| v.as_string_value()),
+        Some("DEBIT")
+    );
+
+    let query = r#"
+        query {
+            categorizationRules {
+                id
+                name
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let rules = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("categorizationRules"))
+        .and_then(|v| v.as_list_value())
+        .expect("categorizationRules should be a list");
+    assert_eq!(rules.len(), 1);
+
+    let mutation = r#"
+        mutation {
+            deleteCategorizationRule(id: 4)
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let deleted = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("deleteCategorizationRule"))
+        .and_then(|v| v.as_scalar_value::<bool>());
+    assert_eq!(deleted, Some(&true));
+
+    // Invalid rules are rejected before touching the repository
+    for rule in [
+        r#"{ name: "Fuel", descriptionPattern: "(shell", matchKind: REGEX, categoryId: 6 }"#,
+        r#"{ name: "Everything", categoryId: 6 }"#,
+        r#"{ name: "Backwards", minAmount: 20.0, maxAmount: 10.0, categoryId: 6 }"#,
+        r#"{ name: " ", sign: CREDIT, categoryId: 6 }"#,
+    ] {
+        let mutation = format!(
+            "mutation {{ createCategorizationRule(rule: {}) {{ id }} }}",
+            rule
+        );
+        let result =
+            juniper::execute(&mutation, None, &schema, &Variables::new(), &context_mock).await;
+        let (_, errors) = result.expect("Mutation execution failed");
+        assert_eq!(errors.len(), 1, "rule {} should be rejected", rule);
+    }
+}
+
+#[tokio::test]
+async fn test_create_transaction_without_category_uses_rules() {
+    let mut mock_rule_repository = MockCategorizationRuleRepository::new();
+    mock_rule_repository
+        .expect_all()
+        .returning(|| Ok(vec![netflix_rule()]));
+
+    let mut mock_transaction_repository = Arc::new(MockTransactionRepository::new());
+    let mock =
+        Arc::get_mut(&mut mock_transaction_repository).expect("Failed to get mutable reference");
+    mock.expect_create_by_rule()
+        .withf(
+            |_amount, description: &String, _date, rule: &DbCategorizationRule, _| {
+                description == "NETFLIX.COM 8841"
+                    && rule.id == 4
+                    && rule.category_id == 6
+                    && rule.tags == vec!["streaming".to_string()]
+            },
+        )
+        .times(1)
+        .returning(|amount, description, date, rule, _| {
+            Ok(DbTransaction {
+                id: 31,
+                amount,
+                description,
+                date,
+                category_id: rule.category_id,
+                category_name: Some("Subscription".to_string()),
+                created_at: None,
+                updated_at: None,
+            })
+        });
+
+    let context_mock = GraphQLContext {
+        categorization_rule_repository: Arc::new(mock_rule_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            mock_transaction_repository.clone(),
+        )
+    };
+    let schema = create_schema();
+
+    let mutation = r#"
+        mutation {
+            createTransaction(amount: -15.99, description: "NETFLIX.COM 8841", date: "2025-03-03") {
+                id
+                categoryId
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let obj = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("createTransaction"))
+        .and_then(|v| v.as_object_value())
+        .expect("createTransaction should be an object");
+    assert_scalar_value!(obj, "categoryId", i32, 6, "transaction");
+
+    // A credit doesn't match the debit rule, and there's no category to fall back on
+    let mutation = r#"
+        mutation {
+            createTransaction(amount: 15.99, description: "NETFLIX.COM REFUND", date: "2025-03-03") {
+                id
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Mutation execution failed");
+    assert_eq!(errors.len(), 1);
+    assert!(format!("{:?}", errors[0]).contains("no categorization rule matches"));
+}
+
+fn history() -> Vec<DbTaggedTransaction> {
+    let transaction = |id: i32, description: &str, amount: i32, category_id: i32| {
+        DbTaggedTransaction {
+            id,
+            amount: BigDecimal::from(amount),
+            description: description.to_string(),
+            date: Date::from_calendar_date(2025, Month::February, 3).unwrap(),
+            account: "default".to_string(),
+            category_id,
+            category_name: Some("Other".to_string()),
+            tags: Vec::new(),
+        }
+    };
+    vec![
+        transaction(11, "NETFLIX.COM 8841", -16, 1),
+        transaction(12, "NETFLIX.COM REFUND", 16, 1),
+        transaction(13, "SPOTIFY", -10, 1),
+    ]
+}
+
+#[tokio::test]
+async fn test_preview_and_apply_rule() {
+    let mut mock_category_repository = MockCategoryRepository::new();
+    mock_category_repository.expect_all().returning(|| {
+        Ok(vec![DbCategory {
+            id: 6,
+            name: "Subscription".to_string(),
+            description: None,
+            icon: None,
+            color: None,
+            created_at: None,
+            updated_at: None,
+        }])
+    });
+
+    let mut mock_transaction_repository = Arc::new(MockTransactionRepository::new());
+    let mock =
+        Arc::get_mut(&mut mock_transaction_repository).expect("Failed to get mutable reference");
+    mock.expect_tagged_between()
+        .withf(|start: &NaiveDate, end: &NaiveDate| {
+            *start == NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+                && *end == NaiveDate::from_ymd_opt(2025, 6, 30).unwrap()
+        })
+        .returning(|_, _| Ok(history()));
+
+    let mut mock_rule_repository = MockCategorizationRuleRepository::new();
+    mock_rule_repository
+        .expect_by_id()
+        .withf(|id: &i32| *id == 4)
+        .returning(|_| Ok(netflix_rule()));
+    mock_rule_repository
+        .expect_apply()
+        .withf(|application: &DbNewRuleApplication, actor: &Option<String>| {
+            // appliedBy only labels the application, the request has no user to audit it as
+            application.rule_id == 4
+                && application.category_id == 6
+                && application.transaction_ids == vec![11]
+                && application.previous_category_ids == vec![1]
+                && application.applied_by.as_deref() == Some("sam")
+                && actor.is_none()
+        })
+        .times(1)
+        .returning(|application, _| {
+            Ok(DbRuleApplication {
+                id: 2,
+                rule_id: Some(application.rule_id),
+                rule_name: application.rule_name,
+                category_id: application.category_id,
+                category_name: Some("Subscription".to_string()),
+                tags: application.tags,
+                start_date: application.start_date,
+                end_date: application.end_date,
+                transaction_ids: application.transaction_ids,
+                previous_category_ids: application.previous_category_ids,
+                applied_by: application.applied_by,
+                applied_at: None,
+            })
+        });
+
+    let context_mock = GraphQLContext {
+        categorization_rule_repository: Arc::new(mock_rule_repository),
+        ..get_context(
+            Arc::new(mock_category_repository),
+            mock_transaction_repository.clone(),
+        )
+    };
+    let schema = create_schema();
+
+    // The preview matches only debits, as the rule being written says
+    let query = r#"
+        query {
+            previewRule(
+                rule: { name: "Netflix", descriptionPattern: "netflix", sign: DEBIT, categoryId: 6, tags: ["streaming"] }
+                startDate: "2025-01-01"
+                endDate: "2025-06-30"
+            ) {
+                transactionId
+                currentCategoryName
+                newCategoryId
+                newCategoryName
+                addedTags
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let changes = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("previewRule"))
+        .and_then(|v| v.as_list_value())
+        .expect("previewRule should be a list");
+    assert_eq!(changes.len(), 1);
+    let change = changes[0].as_object_value().unwrap();
+    let context = "rule change";
+    assert_scalar_value!(change, "transactionId", i32, 11, context);
+    assert_scalar_value!(change, "currentCategoryName", String, "Other".to_string(), context);
+    assert_scalar_value!(change, "newCategoryName", String, "Subscription".to_string(), context);
+    let added_tags = change
+        .get_field_value("addedTags")
+        .and_then(|v| v.as_list_value())
+        .expect("addedTags should be a list");
+    assert_eq!(added_tags.len(), 1);
+
+    let mutation = r#"
+        mutation {
+            applyRule(ruleId: 4, startDate: "2025-01-01", endDate: "2025-06-30", appliedBy: "sam") {
+                id
+                ruleName
+                changed
+                transactionIds
+                previousCategoryIds
+                startDate
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let obj = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("applyRule"))
+        .and_then(|v| v.as_object_value())
+        .expect("applyRule should be an object");
+    let context = "rule application";
+    assert_scalar_value!(obj, "id", i32, 2, context);
+    assert_scalar_value!(obj, "ruleName", String, "Netflix".to_string(), context);
+    assert_scalar_value!(obj, "changed", i32, 1, context);
+    assert_scalar_value!(obj, "startDate", String, "2025-01-01".to_string(), context);
+
+    // A backwards period is rejected
+    let mutation = r#"
+        mutation {
+            applyRule(ruleId: 4, startDate: "2025-06-30", endDate: "2025-01-01") {
+                id
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Mutation execution failed");
+    assert_eq!(errors.len(), 1);
+}
+
+#[tokio::test]
+async fn test_classifier_accuracy_and_rule_suggestions() {
+    let mut mock_rule_repository = MockCategorizationRuleRepository::new();
+    mock_rule_repository
+        .expect_all()
+        .returning(|| Ok(vec![netflix_rule()]));
+    let mut mock_transaction_repository = Arc::new(MockTransactionRepository::new());
+    let mock =
+        Arc::get_mut(&mut mock_transaction_repository).expect("Failed to get mutable reference");
+
+    let correction = |id: i32, description: &str, category_id: i32| DbCategoryCorrection {
+        id,
+        transaction_id: Some(id),
+        description: description.to_string(),
+        amount: BigDecimal::from(-5),
+        old_category_id: 2,
+        old_category_name: Some("Shopping".to_string()),
+        new_category_id: category_id,
+        new_category_name: Some(
+            if category_id == 1 { "Dining" } else { "Subscription" }.to_string(),
+        ),
+        source: "model".to_string(),
+        rule_id: None,
+        classifier_model: Some("nb-2025-03".to_string()),
+        confidence: Some(0.4),
+        corrected_at: None,
+    };
+    mock.expect_corrections().returning(move || {
+        Ok(vec![
+            correction(1, "STARBUCKS 1234", 1),
+            correction(2, "STARBUCKS 998", 1),
+            correction(3, "STARBUCKS #12", 1),
+            // the Netflix rule already does this
+            correction(4, "NETFLIX.COM", 6),
+            correction(5, "NETFLIX.COM", 6),
+            correction(6, "NETFLIX.COM", 6),
+        ])
+    });
+    mock.expect_classifier_accuracy()
+        .withf(|start: &NaiveDate, end: &NaiveDate| {
+            *start == NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()
+                && *end == NaiveDate::from_ymd_opt(2025, 3, 31).unwrap()
+        })
+        .times(1)
+        .returning(|_, _| {
+            Ok(vec![
+                DbCategoryAccuracy {
+                    category_id: 1,
+                    category_name: Some("Dining".to_string()),
+                    predictions: 3,
+                    corrections: 0,
+                },
+                DbCategoryAccuracy {
+                    category_id: 2,
+                    category_name: Some("Shopping".to_string()),
+                    predictions: 5,
+                    corrections: 2,
+                },
+            ])
+        });
+
+    let context_mock = GraphQLContext {
+        categorization_rule_repository: Arc::new(mock_rule_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            mock_transaction_repository.clone(),
+        )
+    };
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            classifierAccuracy(startDate: "2025-03-01", endDate: "2025-03-31") {
+                predictions
+                corrections
+                accuracy
+                categories {
+                    categoryName
+                    accuracy
+                }
+            }
+            categoryCorrections {
+                source
+                model
+            }
+            ruleSuggestions {
+                name
+                matchKind
+                descriptionPattern
+                categoryId
+                corrections
+                message
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let data = data.as_object_value().unwrap();
+
+    let accuracy = data
+        .get_field_value("classifierAccuracy")
+        .and_then(|v| v.as_object_value())
+        .expect("classifierAccuracy should be an object");
+    assert_scalar_value!(accuracy, "predictions", i32, 8, "accuracy");
+    assert_scalar_value!(accuracy, "corrections", i32, 2, "accuracy");
+    assert_scalar_value!(accuracy, "accuracy", f64, 0.75, "accuracy");
+    let categories = accuracy
+        .get_field_value("categories")
+        .and_then(|v| v.as_list_value())
+        .expect("categories should be a list");
+    let shopping = categories[1].as_object_value().unwrap();
+    assert_scalar_value!(shopping, "accuracy", f64, 0.6, "category accuracy");
+
+    let corrections = data
+        .get_field_value("categoryCorrections")
+        .and_then(|v| v.as_list_value())
+        .expect("categoryCorrections should be a list");
+    assert_eq!(corrections.len(), 6);
+    let first = corrections[0].as_object_value().unwrap();
+    assert_eq!(
+        first.get_field_value("source").and_then(|v| v.as_string_value()),
+        Some("MODEL")
+    );
+
+    let suggestions = data
+        .get_field_value("ruleSuggestions")
+        .and_then(|v| v.as_list_value())
+        .expect("ruleSuggestions should be a list");
+    assert_eq!(suggestions.len(), 1);
+    let suggestion = suggestions[0].as_object_value().unwrap();
+    assert_scalar_value!(suggestion, "name", String, "STARBUCKS".to_string(), "suggestion");
+    let pattern = "starbucks".to_string();
+    assert_scalar_value!(suggestion, "descriptionPattern", String, pattern, "suggestion");
+    assert_scalar_value!(suggestion, "categoryId", i32, 1, "suggestion");
+    assert_scalar_value!(suggestion, "corrections", i32, 3, "suggestion");
+    assert_scalar_value!(
+        suggestion,
+        "message",
+        String,
+        "You've recategorized STARBUCKS to Dining 3 times, create a rule?".to_string(),
+        "suggestion"
+    );
+
+    // The period must not run backwards
+    let query = r#"
+        query {
+            classifierAccuracy(startDate: "2025-03-31", endDate: "2025-03-01") {
+                predictions
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Query execution failed");
+    assert_eq!(errors.len(), 1);
+}
@@ -1,7 +1,7 @@
 use juniper::{InputValue, Variables};
 use std::sync::Arc;
 use transaction_server::{
-    db_models::DbCategory,
+    db_models::{DbCategory, DbCategoryAlias},
     db_traits::{MockCategoryRepository, MockTransactionRepository},
     gql_schema::create_schema,
 };
@@ -158,3 +158,92 @@ async fn test_find_by_name() {
 
     assert_object_fields!(data, "categoryByName", test_category_vec, assert_category_object);
 }
+
+#[tokio::test]
+async fn test_category_aliases() {
+    let mut mock_category_repository = MockCategoryRepository::new();
+    let alias = DbCategoryAlias {
+        id: 4,
+        name: "Restaurants".to_string(),
+        category_id: 1,
+        category_name: Some("Dining".to_string()),
+        created_at: None,
+    };
+    let expected_alias = alias.clone();
+    mock_category_repository
+        .expect_aliases()
+        .returning(move || Ok(vec![expected_alias.clone()]));
+    mock_category_repository
+        .expect_set_alias()
+        .withf(|name: &String, category_id: &i32| name == "Restaurants" && *category_id == 1)
+        .times(1)
+        .returning(move |_, _| Ok(alias.clone()));
+    mock_category_repository
+        .expect_delete_alias()
+        .withf(|id: &i32| *id == 4)
+        .times(1)
+        .returning(|_| Ok(true));
+
+    let context_mock = get_context(
+        Arc::new(mock_category_repository),
+        Arc::new(MockTransactionRepository::new()),
+    );
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            categoryAliases {
+                id
+                name
+                categoryId
+                categoryName
+            }
+        }
+    "#;
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let aliases = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("categoryAliases"))
+        .and_then(|v| v.as_list_value())
+        .expect("categoryAliases should be a list");
+    assert_eq!(aliases.len(), 1);
+    let obj = aliases[0].as_object_value().unwrap();
+    assert_scalar_value!(obj, "name", String, "Restaurants".to_string(), "category alias");
+    assert_scalar_value!(obj, "categoryId", i32, 1, "category alias");
+    assert_optional_scalar_value!(obj, "categoryName", String, Some("Dining".to_string()), "category alias");
+
+    let mutation = r#"
+        mutation {
+            setCategoryAlias(name: "Restaurants", categoryId: 1) {
+                id
+                categoryName
+            }
+            deleteCategoryAlias(id: 4)
+        }
+    "#;
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let obj = data.as_object_value().unwrap();
+    let alias = obj
+        .get_field_value("setCategoryAlias")
+        .and_then(|v| v.as_object_value())
+        .expect("setCategoryAlias should be an object");
+    assert_scalar_value!(alias, "id", i32, 4, "category alias");
+    assert_scalar_value!(obj, "deleteCategoryAlias", bool, true, "deleted alias");
+
+    // a blank name never reaches the repository
+    let mutation = r#"
+        mutation {
+            setCategoryAlias(name: "  ", categoryId: 1) {
+                id
+            }
+        }
+    "#;
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Mutation execution failed");
+    assert_eq!(errors.len(), 1);
+}
@@ -0,0 +1,116 @@
+use juniper::Variables;
+use std::sync::Arc;
+use time::{Month, PrimitiveDateTime, Time};
+use sqlx::types::time::Date;
+use transaction_server::{
+    db_models::{DbImportBatch, DbImportRollback},
+    db_traits::{MockCategoryRepository, MockImportBatchRepository, MockTransactionRepository},
+    gql_schema::create_schema,
+    graphql::GraphQLContext,
+};
+mod common;
+use common::test_utils::get_context;
+
+#[tokio::test]
+async fn test_import_batches_and_rollback() {
+    let mut mock_import_batch_repository = MockImportBatchRepository::new();
+
+    let created_at = PrimitiveDateTime::new(
+        Date::from_calendar_date(2025, Month::June, 1).unwrap(),
+        Time::from_hms(9, 30, 0).unwrap(),
+    )
+    .assume_utc();
+    mock_import_batch_repository.expect_all().returning(move || {
+        Ok(vec![DbImportBatch {
+            id: 12,
+            source_name: "june.csv".to_string(),
+            file_hash: Some("ab12".to_string()),
+            imported_by: Some("sam".to_string()),
+            row_count: 40,
+            inserted_count: 38,
+            updated_count: 0,
+            skipped_count: 1,
+            warnings: vec!["Category 'Pets' not found in database".to_string()],
+            rolled_back_at: None,
+            created_at: Some(created_at),
+        }])
+    });
+    mock_import_batch_repository
+        .expect_rollback()
+        .withf(|id: &i32, _: &Option<String>| *id == 12)
+        .times(1)
+        .returning(|_, _| Ok(DbImportRollback { removed: 36, kept: 2 }));
+
+    let context_mock = GraphQLContext {
+        import_batch_repository: Arc::new(mock_import_batch_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(MockTransactionRepository::new()),
+        )
+    };
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            importBatches {
+                id
+                sourceName
+                importedBy
+                rowCount
+                inserted
+                skipped
+                warnings
+                rolledBackAt
+                createdAt
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let batches = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("importBatches"))
+        .and_then(|v| v.as_list_value())
+        .expect("importBatches should be a list");
+    assert_eq!(batches.len(), 1);
+
+    let obj = batches[0].as_object_value().unwrap();
+    let context = "import batch";
+    assert_scalar_value!(obj, "id", i32, 12, context);
+    assert_scalar_value!(obj, "sourceName", String, "june.csv".to_string(), context);
+    assert_optional_scalar_value!(obj, "importedBy", String, Some("sam".to_string()), context);
+    assert_scalar_value!(obj, "rowCount", i32, 40, context);
+    assert_scalar_value!(obj, "inserted", i32, 38, context);
+    assert_scalar_value!(obj, "skipped", i32, 1, context);
+    assert_optional_scalar_value!(obj, "rolledBackAt", String, None::<String>, context);
+    assert_optional_scalar_value!(obj, "createdAt", String, Some("2025-06-01 09:30:00".to_string()), context);
+    let warnings = obj
+        .get_field_value("warnings")
+        .and_then(|v| v.as_list_value())
+        .expect("warnings should be a list");
+    assert_eq!(warnings.len(), 1);
+
+    let mutation = r#"
+        mutation {
+            rollbackImport(batchId: 12) {
+                removed
+                kept
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let obj = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("rollbackImport"))
+        .and_then(|v| v.as_object_value())
+        .expect("rollbackImport should be an object");
+    assert_scalar_value!(obj, "removed", i32, 36, "import rollback");
+    assert_scalar_value!(obj, "kept", i32, 2, "import rollback");
+}
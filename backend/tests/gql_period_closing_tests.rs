@@ -0,0 +1,137 @@
+use juniper::Variables;
+use sqlx::types::time::Date;
+use std::sync::Arc;
+use time::Month;
+use transaction_server::{
+    db_models::DbPeriodClosing,
+    db_traits::{MockCategoryRepository, MockPeriodClosingRepository, MockTransactionRepository},
+    gql_schema::create_schema,
+    graphql::GraphQLContext,
+};
+mod common;
+use common::test_utils::get_context;
+
+fn closing(id: i32, action: &str, closed_through: Option<Date>) -> DbPeriodClosing {
+    DbPeriodClosing {
+        id,
+        closed_through,
+        action: action.to_string(),
+        actor: Some("sam".to_string()),
+        created_at: None,
+    }
+}
+
+#[tokio::test]
+async fn test_close_and_reopen_period() {
+    let mut mock_period_closing_repository = MockPeriodClosingRepository::new();
+    let april = Date::from_calendar_date(2025, Month::April, 30).unwrap();
+    let march = Date::from_calendar_date(2025, Month::March, 31).unwrap();
+
+    mock_period_closing_repository
+        .expect_closings()
+        .returning(move || Ok(vec![closing(1, "close", Some(march))]));
+    mock_period_closing_repository
+        .expect_close()
+        .withf(
+            move |through: &Date, closed_by: &Option<String>, actor: &Option<String>| {
+                // closedBy only labels the closing, the audit log records the request's user
+                *through == april
+                    && closed_by.as_deref() == Some("sam")
+                    && actor.as_deref() == Some("alex")
+            },
+        )
+        .times(1)
+        .returning(|through, _, _| Ok(closing(2, "close", Some(through))));
+    mock_period_closing_repository
+        .expect_reopen()
+        .withf(
+            |from: &Date, reopened_by: &Option<String>, actor: &Option<String>| {
+                *from == Date::from_calendar_date(2025, Month::March, 1).unwrap()
+                    && reopened_by.as_deref() == Some("alex")
+                    && actor.as_deref() == Some("alex")
+            },
+        )
+        .times(1)
+        .returning(|from, _, _| Ok(closing(3, "reopen", from.previous_day())));
+
+    let context_mock = GraphQLContext {
+        period_closing_repository: Arc::new(mock_period_closing_repository),
+        actor: Some("alex".to_string()),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(MockTransactionRepository::new()),
+        )
+    };
+    let schema = create_schema();
+
+    let mutation = r#"
+        mutation {
+            closePeriod(throughDate: "2025-04-30", closedBy: "sam") {
+                id
+                closedThrough
+                action
+                actor
+            }
+            reopenPeriod(fromDate: "2025-03-01") {
+                closedThrough
+                action
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let data = data.as_object_value().unwrap();
+    let closed = data
+        .get_field_value("closePeriod")
+        .and_then(|v| v.as_object_value())
+        .expect("closePeriod should be an object");
+    assert_scalar_value!(
+        closed,
+        "closedThrough",
+        String,
+        "2025-04-30".to_string(),
+        "closing"
+    );
+    assert_scalar_value!(closed, "action", String, "CLOSE".to_string(), "closing");
+    assert_scalar_value!(closed, "actor", String, "sam".to_string(), "closing");
+    let reopened = data
+        .get_field_value("reopenPeriod")
+        .and_then(|v| v.as_object_value())
+        .expect("reopenPeriod should be an object");
+    assert_scalar_value!(
+        reopened,
+        "closedThrough",
+        String,
+        "2025-02-28".to_string(),
+        "closing"
+    );
+    assert_scalar_value!(reopened, "action", String, "REOPEN".to_string(), "closing");
+
+    // Closing can only move forward, and only a closed date can be reopened
+    let mutation = r#"
+        mutation {
+            closePeriod(throughDate: "2025-03-15") {
+                id
+            }
+        }
+    "#;
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Mutation execution failed");
+    assert_eq!(errors.len(), 1);
+    assert!(format!("{:?}", errors[0]).contains("already closed through 2025-03-31"));
+
+    let mutation = r#"
+        mutation {
+            reopenPeriod(fromDate: "2025-04-01") {
+                id
+            }
+        }
+    "#;
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Mutation execution failed");
+    assert_eq!(errors.len(), 1);
+    assert!(format!("{:?}", errors[0]).contains("No transactions are closed on or after"));
+}
@@ -0,0 +1,326 @@
+use chrono::{Datelike, Duration, Local, Months};
+use juniper::Variables;
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use std::sync::Arc;
+use time::Month;
+use transaction_server::{
+    db_models::{DbRecurringTransaction, DbTransaction},
+    db_traits::{MockCategoryRepository, MockRecurringTransactionRepository, MockTransactionRepository},
+    gql_schema::create_schema,
+    graphql::GraphQLContext,
+};
+mod common;
+use common::test_utils::get_context;
+
+#[tokio::test]
+async fn test_upcoming_transactions() {
+    let mut mock_recurring_repository = MockRecurringTransactionRepository::new();
+
+    // A weekly charge that started today, so the next two fall within 14 days
+    let today = Local::now().date_naive();
+    let start_date = Date::from_calendar_date(
+        today.year(),
+        Month::try_from(today.month() as u8).unwrap(),
+        today.day() as u8,
+    )
+    .unwrap();
+    mock_recurring_repository.expect_active().returning(move || {
+        Ok(vec![DbRecurringTransaction {
+            id: 3,
+            description: "Streaming".to_string(),
+            amount: BigDecimal::from(-12),
+            category_id: 6,
+            category_name: Some("Subscription".to_string()),
+            frequency: "weekly".to_string(),
+            interval_count: 1,
+            day_of_month: None,
+            start_date,
+            end_date: None,
+            last_materialized_date: None,
+            active: true,
+            created_at: None,
+            updated_at: None,
+        }])
+    });
+
+    let context_mock = GraphQLContext {
+        recurring_transaction_repository: Arc::new(mock_recurring_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(MockTransactionRepository::new()),
+        )
+    };
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            upcomingTransactions(days: 14) {
+                recurringTransactionId
+                date
+                amount
+                categoryName
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let upcoming = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("upcomingTransactions"))
+        .and_then(|v| v.as_list_value())
+        .expect("upcomingTransactions should be a list");
+    assert_eq!(upcoming.len(), 2);
+
+    for (i, week) in [1, 2].iter().enumerate() {
+        let obj = upcoming[i].as_object_value().unwrap();
+        let expected_date = (today + Duration::weeks(*week)).format("%Y-%m-%d").to_string();
+        let context = format!("upcoming transaction at index {}", i);
+        assert_scalar_value!(obj, "recurringTransactionId", i32, 3, &context);
+        assert_scalar_value!(obj, "date", String, expected_date, &context);
+        assert_scalar_value!(obj, "amount", f64, -12.0, &context);
+    }
+}
+
+#[tokio::test]
+async fn test_create_recurring_transaction_rejects_day_for_weekly() {
+    let context_mock = get_context(
+        Arc::new(MockCategoryRepository::new()),
+        Arc::new(MockTransactionRepository::new()),
+    );
+    let schema = create_schema();
+
+    let query = r#"
+        mutation {
+            createRecurringTransaction(
+                description: "Allowance",
+                amount: -10.0,
+                categoryId: 16,
+                frequency: WEEKLY,
+                dayOfMonth: 3,
+                startDate: "2025-01-01"
+            ) {
+                id
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Query execution failed");
+    assert_eq!(errors.len(), 1);
+}
+
+#[tokio::test]
+async fn test_create_recurring_transaction_rejects_interval_out_of_range() {
+    let context_mock = get_context(
+        Arc::new(MockCategoryRepository::new()),
+        Arc::new(MockTransactionRepository::new()),
+    );
+    let schema = create_schema();
+
+    for interval in [0, 2147483647] {
+        let query = format!(
+            r#"
+            mutation {{
+                createRecurringTransaction(
+                    description: "Allowance",
+                    amount: -10.0,
+                    categoryId: 16,
+                    frequency: YEARLY,
+                    interval: {},
+                    startDate: "2025-01-01"
+                ) {{
+                    id
+                }}
+            }}
+        "#,
+            interval
+        );
+
+        let result = juniper::execute(&query, None, &schema, &Variables::new(), &context_mock).await;
+        let (_, errors) = result.expect("Query execution failed");
+        assert_eq!(errors.len(), 1);
+        assert!(format!("{:?}", errors[0]).contains("Interval must be between 1 and 100"));
+    }
+}
+
+#[tokio::test]
+async fn test_detect_recurring_and_create_schedule() {
+    let mut mock_transaction_repository = MockTransactionRepository::new();
+    let mut mock_recurring_repository = MockRecurringTransactionRepository::new();
+
+    // Monthly charges on the 10th of the last three months
+    let today = Local::now().date_naive();
+    let charges: Vec<DbTransaction> = (1..=3)
+        .map(|months_ago| {
+            let date = (today - Months::new(months_ago)).with_day(10).unwrap();
+            DbTransaction {
+                id: months_ago as i32,
+                amount: BigDecimal::from(-12),
+                description: format!("SPOTIFY P{}", months_ago),
+                date: Date::from_calendar_date(
+                    date.year(),
+                    Month::try_from(date.month() as u8).unwrap(),
+                    10,
+                )
+                .unwrap(),
+                category_id: 6,
+                category_name: Some("Subscription".to_string()),
+                created_at: None,
+                updated_at: None,
+            }
+        })
+        .collect();
+    mock_transaction_repository
+        .expect_by_date_range()
+        .returning(move |_, _| Ok(charges.clone()));
+
+    let next_date = (today - Months::new(1)).with_day(10).unwrap() + Months::new(1);
+    let expected_start = Date::from_calendar_date(
+        next_date.year(),
+        Month::try_from(next_date.month() as u8).unwrap(),
+        10,
+    )
+    .unwrap();
+    mock_recurring_repository
+        .expect_create()
+        .withf(move |description, amount, category_id, frequency, interval, day_of_month, start_date, end_date| {
+            description == "SPOTIFY P1"
+                && amount == &BigDecimal::from(-12)
+                && *category_id == 6
+                && frequency == "monthly"
+                && *interval == 1
+                && *day_of_month == Some(10)
+                && *start_date == expected_start
+                && end_date.is_none()
+        })
+        .times(1)
+        .returning(|description, amount, category_id, frequency, interval_count, day_of_month, start_date, end_date| {
+            Ok(DbRecurringTransaction {
+                id: 9,
+                description,
+                amount,
+                category_id,
+                category_name: Some("Subscription".to_string()),
+                frequency,
+                interval_count,
+                day_of_month,
+                start_date,
+                end_date,
+                last_materialized_date: None,
+                active: true,
+                created_at: None,
+                updated_at: None,
+            })
+        });
+
+    let context_mock = GraphQLContext {
+        recurring_transaction_repository: Arc::new(mock_recurring_repository),
+        ..get_context(
+            Arc::new(MockCategoryRepository::new()),
+            Arc::new(mock_transaction_repository),
+        )
+    };
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            detectRecurring(minOccurrences: 3, lookbackMonths: 6) {
+                merchant
+                cadence
+                occurrences
+                averageAmount
+                nextDate
+                priceChange {
+                    newAmount
+                }
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let candidates = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("detectRecurring"))
+        .and_then(|v| v.as_list_value())
+        .expect("detectRecurring should be a list");
+    assert_eq!(candidates.len(), 1);
+
+    let obj = candidates[0].as_object_value().unwrap();
+    let context = "recurring candidate";
+    assert_scalar_value!(obj, "merchant", String, "spotify p".to_string(), context);
+    assert_scalar_value!(obj, "occurrences", i32, 3, context);
+    assert_scalar_value!(obj, "averageAmount", f64, 12.0, context);
+    assert_scalar_value!(obj, "nextDate", String, next_date.format("%Y-%m-%d").to_string(), context);
+    assert!(obj.get_field_value("priceChange").unwrap().is_null());
+
+    let mutation = r#"
+        mutation {
+            createRecurringFromCandidate(merchant: "spotify p", minOccurrences: 3, lookbackMonths: 6) {
+                id
+                frequency
+                dayOfMonth
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let obj = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("createRecurringFromCandidate"))
+        .and_then(|v| v.as_object_value())
+        .expect("createRecurringFromCandidate should be an object");
+    assert_scalar_value!(obj, "id", i32, 9, context);
+    assert_optional_scalar_value!(obj, "dayOfMonth", i32, Some(10), context);
+}
+
+#[tokio::test]
+async fn test_upcoming_transactions_rejects_days_out_of_range() {
+    let context_mock = get_context(
+        Arc::new(MockCategoryRepository::new()),
+        Arc::new(MockTransactionRepository::new()),
+    );
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            upcomingTransactions(days: 2147483647) {
+                date
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Query execution failed");
+    assert_eq!(errors.len(), 1);
+    assert!(format!("{:?}", errors[0]).contains("Days must be between 0 and 3660"));
+}
+
+#[tokio::test]
+async fn test_detect_recurring_rejects_lookback_out_of_range() {
+    let context_mock = get_context(
+        Arc::new(MockCategoryRepository::new()),
+        Arc::new(MockTransactionRepository::new()),
+    );
+    let schema = create_schema();
+
+    for lookback_months in [-1, 2147483647] {
+        let query = format!(
+            "query {{ detectRecurring(lookbackMonths: {}) {{ description }} }}",
+            lookback_months
+        );
+        let result = juniper::execute(&query, None, &schema, &Variables::new(), &context_mock).await;
+        let (_, errors) = result.expect("Query execution failed");
+        assert_eq!(errors.len(), 1);
+        assert!(format!("{:?}", errors[0]).contains("Lookback months must be between 1 and 1200"));
+    }
+}
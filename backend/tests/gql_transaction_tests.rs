@@ -2,7 +2,7 @@ use chrono::NaiveDate;
 use juniper::{InputValue, Variables};
 use std::sync::Arc;
 use transaction_server::{
-    db_models::{DbTransaction, DbCategorySummary},
+    db_models::{DbTransaction, DbCategorySummary, DbImportBatch, DbImportRow, DbNewImportBatch, DbPeriodComparison, DbReviewItem, DbTrainingExample},
     db_traits::{MockCategoryRepository, MockTransactionRepository},
     gql_schema::create_schema,
 };
@@ -350,6 +350,71 @@ async fn test_summary_by_category() {
     assert_object_fields!(data, "transactionsSummaryByCategory", summaries, assert_summary_object);
 }
 
+#[tokio::test]
+async fn test_summary_and_list_as_of() {
+    let mock_category_repository = Arc::new(MockCategoryRepository::new());
+    let mut mock_transaction_repository = Arc::new(MockTransactionRepository::new());
+    let mock =
+        Arc::get_mut(&mut mock_transaction_repository).expect("Failed to get mutable reference");
+
+    let summaries = vec![DbCategorySummary {
+        category_id: 1,
+        category_name: Some("Dining".to_string()),
+        total_amount: Some(BigDecimal::from_f64(-80.0).unwrap()),
+        transaction_count: Some(2),
+    }];
+    let april_first = time::OffsetDateTime::parse(
+        "2025-04-01T09:30:00Z",
+        &format_description::well_known::Rfc3339,
+    )
+    .unwrap();
+
+    // Only the versions at asOf are read, never the current rows
+    let expected_summaries = summaries.clone();
+    mock.expect_sum_by_category_as_of()
+        .withf(move |start: &NaiveDate, end: &NaiveDate, as_of: &time::OffsetDateTime| {
+            *start == NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()
+                && *end == NaiveDate::from_ymd_opt(2025, 3, 31).unwrap()
+                && *as_of == april_first
+        })
+        .times(1)
+        .returning(move |_, _, _| Ok(expected_summaries.clone()));
+    mock.expect_all_as_of()
+        .withf(move |as_of: &time::OffsetDateTime| *as_of == april_first)
+        .times(1)
+        .returning(|_| Ok(Vec::new()));
+    mock.expect_sum_by_category().never();
+    mock.expect_all().never();
+
+    let context_mock = get_context(
+        mock_category_repository.clone(),
+        mock_transaction_repository.clone(),
+    );
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            transactionsSummaryByCategory(
+                startDate: "2025-03-01", endDate: "2025-03-31", asOf: "2025-04-01T09:30:00Z"
+            ) {
+                categoryId
+                categoryName
+                totalAmount
+                transactionCount
+            }
+            allTransactions(asOf: "2025-04-01T09:30:00Z") {
+                id
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    assert_object_fields!(data, "transactionsSummaryByCategory", summaries, assert_summary_object);
+}
+
 #[tokio::test]
 async fn test_create() {
     let mock_category_repository = Arc::new(MockCategoryRepository::new());
@@ -371,7 +436,7 @@ async fn test_create() {
     let expected_transaction = transaction.clone();
     
     mock.expect_create()
-        .returning(move |_amount: BigDecimal, _description: String, _date: Date, _category_id: i32| {
+        .returning(move |_amount: BigDecimal, _description: String, _date: Date, _category_id: i32, _actor: Option<String>| {
             Ok(expected_transaction.clone())
         });
 
@@ -434,7 +499,7 @@ async fn test_update() {
     let expected_transaction = transaction.clone();
     
     mock.expect_update()
-        .returning(move |_id: i32, _amount: BigDecimal, _description: String, _date: Date, _category_id: i32| {
+        .returning(move |_id: i32, _amount: BigDecimal, _description: String, _date: Date, _category_id: i32, _actor: Option<String>| {
             Ok(expected_transaction.clone())
         });
 
@@ -477,3 +542,521 @@ async fn test_update() {
     let expected_transaction_vec = vec![transaction];
     assert_object_fields!(data, "updateTransaction", expected_transaction_vec, assert_transaction_object);
 }
+
+#[tokio::test]
+async fn test_compare_spending() {
+    let mock_category_repository = Arc::new(MockCategoryRepository::new());
+    let mut mock_transaction_repository = Arc::new(MockTransactionRepository::new());
+    let mock =
+        Arc::get_mut(&mut mock_transaction_repository).expect("Failed to get mutable reference");
+
+    let comparisons = vec![
+        DbPeriodComparison {
+            category_id: Some(1),
+            group_name: Some("Dining".to_string()),
+            period_a_total: Some(BigDecimal::from_f64(-200.0).unwrap()),
+            period_a_count: Some(4),
+            period_b_total: Some(BigDecimal::from_f64(-400.0).unwrap()),
+            period_b_count: Some(8),
+        },
+        DbPeriodComparison {
+            category_id: Some(2),
+            group_name: Some("Groceries".to_string()),
+            period_a_total: Some(BigDecimal::from_f64(-500.0).unwrap()),
+            period_a_count: Some(10),
+            period_b_total: Some(BigDecimal::from_f64(-510.0).unwrap()),
+            period_b_count: Some(11),
+        },
+        DbPeriodComparison {
+            category_id: Some(3),
+            group_name: Some("Subscription".to_string()),
+            period_a_total: Some(BigDecimal::from_f64(0.0).unwrap()),
+            period_a_count: Some(0),
+            period_b_total: Some(BigDecimal::from_f64(-15.0).unwrap()),
+            period_b_count: Some(1),
+        },
+    ];
+
+    let expected_comparisons = comparisons.clone();
+    mock.expect_compare_by_category()
+        .returning(move |_a_start: &NaiveDate, _a_end: &NaiveDate, _b_start: &NaiveDate, _b_end: &NaiveDate| {
+            Ok(expected_comparisons.clone())
+        });
+
+    let context_mock = get_context(
+        mock_category_repository.clone(),
+        mock_transaction_repository.clone(),
+    );
+    let schema = create_schema();
+
+    let query = r#"
+        query CompareSpending($periodA: PeriodInput!, $periodB: PeriodInput!) {
+            compareSpending(periodA: $periodA, periodB: $periodB, groupBy: CATEGORY) {
+                categoryId
+                groupName
+                periodATotal
+                periodBTotal
+                absoluteChange
+                percentChange
+                significant
+            }
+        }
+    "#;
+
+    let mut variables = Variables::new();
+    variables.insert(
+        "periodA".to_string(),
+        InputValue::object(
+            [
+                ("startDate", InputValue::scalar("2025-04-01".to_string())),
+                ("endDate", InputValue::scalar("2025-04-30".to_string())),
+            ]
+            .into_iter()
+            .collect(),
+        ),
+    );
+    variables.insert(
+        "periodB".to_string(),
+        InputValue::object(
+            [
+                ("startDate", InputValue::scalar("2025-05-01".to_string())),
+                ("endDate", InputValue::scalar("2025-05-31".to_string())),
+            ]
+            .into_iter()
+            .collect(),
+        ),
+    );
+    let result = juniper::execute(query, None, &schema, &variables, &context_mock).await;
+
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    // (absolute change, percent change, significant) for each comparison
+    let expected = [
+        (-200.0, Some(-100.0), true),
+        (-10.0, Some(-2.0), false),
+        (-15.0, None, false),
+    ];
+    let rows = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("compareSpending"))
+        .and_then(|v| v.as_list_value())
+        .expect("compareSpending should be a list");
+    assert_eq!(rows.len(), expected.len());
+
+    for (i, (row, (change, percent, significant))) in rows.iter().zip(expected.iter()).enumerate() {
+        let obj = row.as_object_value().expect("Row is not an object");
+        let context = format!("comparison at index {}", i);
+        assert_scalar_value!(obj, "categoryId", i32, comparisons[i].category_id.unwrap(), &context);
+        assert_optional_scalar_value!(obj, "groupName", String, &comparisons[i].group_name, &context);
+        assert_scalar_value!(obj, "absoluteChange", f64, *change, &context);
+        assert_optional_scalar_value!(obj, "percentChange", f64, percent, &context);
+        assert_scalar_value!(obj, "significant", bool, *significant, &context);
+    }
+}
+
+#[tokio::test]
+async fn test_find_and_resolve_duplicates() {
+    let mock_category_repository = Arc::new(MockCategoryRepository::new());
+    let mut mock_transaction_repository = Arc::new(MockTransactionRepository::new());
+    let mock =
+        Arc::get_mut(&mut mock_transaction_repository).expect("Failed to get mutable reference");
+
+    let duplicates = vec![
+        DbTransaction {
+            id: 1,
+            amount: BigDecimal::from_f64(-15.0).unwrap(),
+            description: "NETFLIX.COM 8841".to_string(),
+            date: Date::from_calendar_date(2025, Month::January, 1).unwrap(),
+            category_id: 6,
+            category_name: Some("Subscription".to_string()),
+            created_at: None,
+            updated_at: None,
+        },
+        DbTransaction {
+            id: 2,
+            amount: BigDecimal::from_f64(-15.0).unwrap(),
+            description: "Netflix.com 1193".to_string(),
+            date: Date::from_calendar_date(2025, Month::January, 1).unwrap(),
+            category_id: 6,
+            category_name: Some("Subscription".to_string()),
+            created_at: None,
+            updated_at: None,
+        },
+    ];
+
+    let expected_duplicates = duplicates.clone();
+    mock.expect_find_duplicates()
+        .withf(|_start: &NaiveDate, _end: &NaiveDate, window_days: &i64| *window_days == 0)
+        .returning(move |_, _, _| Ok(vec![expected_duplicates.clone()]));
+    mock.expect_resolve_duplicates()
+        .withf(|keep_id: &i32, remove_ids: &Vec<i32>, _: &Option<String>| {
+            *keep_id == 1 && *remove_ids == vec![2]
+        })
+        .times(1)
+        .returning(|_, _, _| Ok(1));
+
+    let context_mock = get_context(
+        mock_category_repository.clone(),
+        mock_transaction_repository.clone(),
+    );
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            findDuplicates(startDate: "2025-01-01", endDate: "2025-01-31", strategy: EXACT) {
+                normalizedDescription
+                amount
+                transactions {
+                    id
+                    amount
+                    description
+                    date
+                    categoryId
+                    categoryName
+                    createdAt
+                    updatedAt
+                }
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let groups = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("findDuplicates"))
+        .and_then(|v| v.as_list_value())
+        .expect("findDuplicates should be a list");
+    assert_eq!(groups.len(), 1);
+
+    let group = groups[0].as_object_value().unwrap();
+    assert_scalar_value!(group, "normalizedDescription", String, "netflix".to_string(), "duplicate group");
+    assert_scalar_value!(group, "amount", f64, -15.0, "duplicate group");
+    let transactions = group
+        .get_field_value("transactions")
+        .and_then(|v| v.as_list_value())
+        .expect("transactions should be a list");
+    for (i, (obj, expected)) in transactions.iter().zip(duplicates.iter()).enumerate() {
+        assert_transaction_object(obj.as_object_value().unwrap(), expected, i);
+    }
+
+    let mutation = r#"
+        mutation {
+            resolveDuplicates(keepId: 1, removeIds: [2])
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let removed = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("resolveDuplicates"))
+        .and_then(|v| v.as_scalar_value::<i32>());
+    assert_eq!(removed, Some(&1));
+
+    // Removing the transaction being kept is rejected before touching the repository
+    let mutation = r#"
+        mutation {
+            resolveDuplicates(keepId: 1, removeIds: [1, 2])
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Mutation execution failed");
+    assert_eq!(errors.len(), 1);
+}
+
+#[tokio::test]
+async fn test_import_transactions() {
+    let mock_category_repository = Arc::new(MockCategoryRepository::new());
+    let mut mock_transaction_repository = Arc::new(MockTransactionRepository::new());
+    let mock =
+        Arc::get_mut(&mut mock_transaction_repository).expect("Failed to get mutable reference");
+
+    mock.expect_import_transactions()
+        .withf(|batch: &DbNewImportBatch, rows: &Vec<DbImportRow>, actor: &Option<String>| {
+            // importedBy only labels the batch, the request has no user to audit it as
+            batch.source_name == "api"
+                && batch.imported_by == Some("sam".to_string())
+                && actor.is_none()
+                && batch.row_count == 2
+                && rows.len() == 2
+                && rows.iter().all(|row| row.account == "checking")
+                && rows[0].external_id == Some("TX-1".to_string())
+                // a blank id is treated as no id
+                && rows[1].external_id.is_none()
+                && rows[1].date == Date::from_calendar_date(2025, Month::March, 4).unwrap()
+        })
+        .times(1)
+        .returning(|batch, _, _| {
+            Ok(DbImportBatch {
+                id: 5,
+                source_name: batch.source_name,
+                file_hash: batch.file_hash,
+                imported_by: batch.imported_by,
+                row_count: batch.row_count,
+                inserted_count: 1,
+                updated_count: 0,
+                skipped_count: 1,
+                warnings: batch.warnings,
+                rolled_back_at: None,
+                created_at: None,
+            })
+        });
+
+    let context_mock = get_context(
+        mock_category_repository.clone(),
+        mock_transaction_repository.clone(),
+    );
+    let schema = create_schema();
+
+    let mutation = r#"
+        mutation {
+            importTransactions(account: "checking", importedBy: "sam", transactions: [
+                { externalId: "TX-1", amount: -40.0, description: "SAFEWAY", date: "2025-03-03", categoryId: 2 },
+                { externalId: " ", amount: -4.0, description: "COFFEE", date: "2025-03-04", categoryId: 3 }
+            ]) {
+                id
+                inserted
+                updated
+                skipped
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let obj = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("importTransactions"))
+        .and_then(|v| v.as_object_value())
+        .expect("importTransactions should be an object");
+    assert_scalar_value!(obj, "id", i32, 5, "import batch");
+    assert_scalar_value!(obj, "inserted", i32, 1, "import batch");
+    assert_scalar_value!(obj, "updated", i32, 0, "import batch");
+    assert_scalar_value!(obj, "skipped", i32, 1, "import batch");
+
+    // A bad date rejects the whole import
+    let mutation = r#"
+        mutation {
+            importTransactions(transactions: [
+                { amount: -4.0, description: "COFFEE", date: "03/04/2025", categoryId: 3 }
+            ]) {
+                inserted
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Mutation execution failed");
+    assert_eq!(errors.len(), 1);
+}
+
+#[tokio::test]
+async fn test_review_queue_and_confirm() {
+    let mock_category_repository = Arc::new(MockCategoryRepository::new());
+    let mut mock_transaction_repository = Arc::new(MockTransactionRepository::new());
+    let mock =
+        Arc::get_mut(&mut mock_transaction_repository).expect("Failed to get mutable reference");
+
+    let item = |id: i32, confidence: f64| DbReviewItem {
+        id,
+        amount: BigDecimal::from_f64(-20.0).unwrap(),
+        description: "AMZN MKTP".to_string(),
+        date: Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+        category_id: 2,
+        category_name: Some("Shopping".to_string()),
+        predicted_category: Some("Shopping".to_string()),
+        original_category: Some("Merchandise".to_string()),
+        confidence,
+        classifier_model: Some("nb-2025-03".to_string()),
+    };
+    // the repository is asked for one more item than the page holds
+    mock.expect_review_queue()
+        .withf(|threshold: &f64, after: &Option<(f64, i32)>, limit: &i64| {
+            *threshold == 0.5 && *after == Some((0.25, 7)) && *limit == 3
+        })
+        .times(1)
+        .returning(move |_, _, _| Ok(vec![item(8, 0.25), item(3, 0.4), item(9, 0.45)]));
+    mock.expect_confirm_categories()
+        .withf(|ids: &Vec<i32>, _: &Option<String>| *ids == vec![8, 3])
+        .times(1)
+        .returning(|_, _| Ok(2));
+
+    let context_mock = get_context(
+        mock_category_repository.clone(),
+        mock_transaction_repository.clone(),
+    );
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            reviewQueue(threshold: 0.5, first: 2, after: "0.25:7") {
+                items {
+                    id
+                    predictedCategory
+                    originalCategory
+                    confidence
+                    model
+                }
+                endCursor
+                hasNextPage
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let queue = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("reviewQueue"))
+        .and_then(|v| v.as_object_value())
+        .expect("reviewQueue should be an object");
+    let items = queue
+        .get_field_value("items")
+        .and_then(|v| v.as_list_value())
+        .expect("items should be a list");
+    assert_eq!(items.len(), 2);
+    let first = items[0].as_object_value().unwrap();
+    assert_scalar_value!(first, "id", i32, 8, "review item");
+    assert_scalar_value!(first, "confidence", f64, 0.25, "review item");
+    assert_scalar_value!(first, "originalCategory", String, "Merchandise".to_string(), "review item");
+    assert_scalar_value!(first, "model", String, "nb-2025-03".to_string(), "review item");
+    assert_scalar_value!(queue, "endCursor", String, "0.4:3".to_string(), "review queue");
+    assert_scalar_value!(queue, "hasNextPage", bool, true, "review queue");
+
+    let mutation = r#"
+        mutation {
+            confirmCategory(ids: [8, 3])
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let confirmed = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("confirmCategory"))
+        .and_then(|v| v.as_scalar_value::<i32>());
+    assert_eq!(confirmed, Some(&2));
+
+    // A malformed cursor is rejected before touching the repository
+    let query = r#"
+        query {
+            reviewQueue(after: "abc") {
+                hasNextPage
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Query execution failed");
+    assert_eq!(errors.len(), 1);
+}
+
+#[tokio::test]
+async fn test_suggest_category_and_retrain() {
+    let mock_category_repository = Arc::new(MockCategoryRepository::new());
+    let mut mock_transaction_repository = Arc::new(MockTransactionRepository::new());
+    let mock =
+        Arc::get_mut(&mut mock_transaction_repository).expect("Failed to get mutable reference");
+
+    let example = |description: &str, amount: f64, category_id: i32, name: &str| {
+        DbTrainingExample {
+            description: description.to_string(),
+            amount: BigDecimal::from_f64(amount).unwrap(),
+            category_id,
+            category_name: name.to_string(),
+            corrected: false,
+        }
+    };
+    // trained once on first use, then again when asked to
+    mock.expect_training_examples()
+        .withf(|min_confidence: &f64| *min_confidence == 0.6)
+        .times(2)
+        .returning(move |_| {
+            Ok(vec![
+                example("STARBUCKS STORE 1234", -4.5, 1, "Dining"),
+                example("CHIPOTLE ONLINE", -12.8, 1, "Dining"),
+                example("WHOLE FOODS MARKET", -84.2, 2, "Groceries"),
+                example("ACME CORP PAYROLL", 2500.0, 3, "Income"),
+            ])
+        });
+
+    let context_mock = get_context(
+        mock_category_repository.clone(),
+        mock_transaction_repository.clone(),
+    );
+    let schema = create_schema();
+
+    let query = r#"
+        query {
+            suggestCategory(description: "Starbucks Seattle", amount: -5.25, k: 2) {
+                categoryId
+                categoryName
+                probability
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let suggestions = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("suggestCategory"))
+        .and_then(|v| v.as_list_value())
+        .expect("suggestCategory should be a list");
+    assert_eq!(suggestions.len(), 2);
+    let first = suggestions[0].as_object_value().unwrap();
+    assert_scalar_value!(first, "categoryId", i32, 1, "suggestion");
+    assert_scalar_value!(first, "categoryName", String, "Dining".to_string(), "suggestion");
+
+    // A second suggestion reuses the trained classifier
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Query execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+
+    let mutation = r#"
+        mutation {
+            retrainClassifier {
+                examples
+                categories
+                vocabularySize
+                trainedAt
+            }
+        }
+    "#;
+
+    let result = juniper::execute(mutation, None, &schema, &Variables::new(), &context_mock).await;
+    let (data, errors) = result.expect("Mutation execution failed");
+    assert!(errors.is_empty(), "Unexpected GraphQL errors: {:?}", errors);
+    let info = data
+        .as_object_value()
+        .and_then(|o| o.get_field_value("retrainClassifier"))
+        .and_then(|v| v.as_object_value())
+        .expect("retrainClassifier should be an object");
+    assert_scalar_value!(info, "examples", i32, 4, "classifier info");
+    assert_scalar_value!(info, "categories", i32, 3, "classifier info");
+
+    // k must ask for at least one category
+    let query = r#"
+        query {
+            suggestCategory(description: "Starbucks", amount: -5.25, k: 0) {
+                categoryId
+            }
+        }
+    "#;
+
+    let result = juniper::execute(query, None, &schema, &Variables::new(), &context_mock).await;
+    let (_, errors) = result.expect("Query execution failed");
+    assert_eq!(errors.len(), 1);
+}
@@ -0,0 +1,76 @@
+use sqlx::types::BigDecimal;
+use std::path::Path;
+use std::str::FromStr;
+use time::Month;
+use transaction_server::import::{
+    camt::{is_camt, read_camt},
+    FileFormat, LedgerBalance,
+};
+mod common;
+use common::fixtures::{date, fixture};
+
+#[test]
+fn test_read_camt_053() {
+    let statement = read_camt(&fixture("statement.camt053.xml")).unwrap();
+
+    assert_eq!(
+        statement.account_id.as_deref(),
+        Some("DE89370400440532013000")
+    );
+    // the closing balance, not the opening one
+    assert_eq!(
+        statement.ledger_balance,
+        Some(LedgerBalance {
+            amount: BigDecimal::from_str("2910.60").unwrap(),
+            as_of: date(2025, Month::March, 31),
+        })
+    );
+    // the pending entry is left out
+    assert_eq!(statement.records.len(), 4);
+
+    let utilities = statement.records[0].as_ref().unwrap();
+    assert_eq!(utilities.line, 26);
+    assert_eq!(utilities.external_id.as_deref(), Some("20250303-0001"));
+    assert_eq!(utilities.date, date(2025, Month::March, 3));
+    assert_eq!(utilities.amount, BigDecimal::from_str("-84.50").unwrap());
+    assert_eq!(
+        utilities.description,
+        "Stadtwerke München Abschlag Strom März"
+    );
+    assert_eq!(utilities.category, None);
+
+    // money coming in is described by the debtor, the end to end id stands in for a reference
+    let salary = statement.records[1].as_ref().unwrap();
+    assert_eq!(salary.external_id.as_deref(), Some("PAYROLL-2025-03"));
+    assert_eq!(salary.date, date(2025, Month::March, 28));
+    assert_eq!(salary.amount, BigDecimal::from(2000));
+    assert_eq!(salary.description, "ACME GmbH");
+
+    let fee = statement.records[2].as_ref().unwrap();
+    assert_eq!(fee.description, "Kontoführungsgebühr");
+
+    let error = statement.records[3].as_ref().unwrap_err();
+    assert_eq!(error.line, Some(73));
+    assert_eq!(error.column.as_deref(), Some("Amt"));
+}
+
+#[test]
+fn test_read_camt_rejects_other_files() {
+    let error = read_camt(b"<Document><Stmt>").unwrap_err();
+    assert!(error.message.contains("Invalid XML"));
+
+    assert!(read_camt(b"<Document></Document>").is_err());
+}
+
+#[test]
+fn test_detect_camt() {
+    let camt = fixture("statement.camt053.xml");
+
+    assert!(is_camt(&camt));
+    assert!(!is_camt(&fixture("statement_v2.qfx")));
+    assert_eq!(
+        FileFormat::detect(Path::new("statement.xml"), &camt),
+        FileFormat::Camt
+    );
+    assert_eq!("camt.053".parse::<FileFormat>().unwrap(), FileFormat::Camt);
+}
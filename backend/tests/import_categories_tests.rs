@@ -0,0 +1,92 @@
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use time::Month;
+use transaction_server::{
+    db_models::{DbCategory, DbCategoryAlias},
+    import::{
+        categories::CategoryMap,
+        rejected::{write_rejected, RejectedRow},
+        ImportError, ImportRecord,
+    },
+};
+
+fn category(id: i32, name: &str) -> DbCategory {
+    DbCategory {
+        id,
+        name: name.to_string(),
+        description: None,
+        icon: None,
+        color: None,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+fn alias(name: &str, category_id: i32) -> DbCategoryAlias {
+    DbCategoryAlias {
+        id: 1,
+        name: name.to_string(),
+        category_id,
+        category_name: None,
+        created_at: None,
+    }
+}
+
+#[test]
+fn test_category_map_uses_names_and_aliases() {
+    let categories = vec![category(1, "Dining"), category(2, "Groceries")];
+    let aliases = vec![
+        alias("Restaurants", 1),
+        // an alias can't take over a category's own name
+        alias("groceries", 1),
+        // nor point at a category that's gone
+        alias("Pets", 9),
+    ];
+    let mut map = CategoryMap::new(&categories, &aliases);
+
+    assert_eq!(map.get(" dining "), Some((1, "Dining".to_string())));
+    assert_eq!(map.get("RESTAURANTS"), Some((1, "Dining".to_string())));
+    assert_eq!(map.get("Groceries"), Some((2, "Groceries".to_string())));
+    assert_eq!(map.get("Pets"), None);
+
+    map.insert("Pets", 3, "Pets");
+    assert_eq!(map.get("pets"), Some((3, "Pets".to_string())));
+}
+
+#[test]
+fn test_write_rejected_rows() {
+    let record = ImportRecord {
+        line: 4,
+        external_id: Some("TX-4".to_string()),
+        date: Date::from_calendar_date(2025, Month::March, 2).unwrap(),
+        amount: BigDecimal::from(-30),
+        description: "VET, DOWNTOWN".to_string(),
+        category: Some("Pets".to_string()),
+        original_category: None,
+        confidence: Some(0.42),
+        model: None,
+    };
+    let error = ImportError::at_line(7, "Invalid amount 'x'").in_column("Amount");
+    let rows = vec![
+        RejectedRow::unknown_category(&record, "Pets"),
+        RejectedRow::unreadable(&error),
+    ];
+
+    let mut output = Vec::new();
+    write_rejected(&mut output, &rows).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "line,reason,date,amount,description,category,external_id\n\
+         4,Category 'Pets' not found in database,2025-03-02,-30,\"VET, DOWNTOWN\",Pets,TX-4\n\
+         7,line 7: column 'Amount': Invalid amount 'x',,,,,\n"
+    );
+
+    // the header is written even when nothing was rejected
+    let mut output = Vec::new();
+    write_rejected(&mut output, &[]).unwrap();
+    assert_eq!(
+        output,
+        b"line,reason,date,amount,description,category,external_id\n"
+    );
+}
@@ -0,0 +1,99 @@
+use sqlx::types::BigDecimal;
+use std::path::Path;
+use std::str::FromStr;
+use time::Month;
+use transaction_server::import::{
+    ofx::{is_ofx, read_ofx},
+    FileFormat, LedgerBalance,
+};
+mod common;
+use common::fixtures::{date, fixture};
+
+#[test]
+fn test_read_ofx_1_sgml() {
+    let statement = read_ofx(&fixture("statement_v1.ofx")).unwrap();
+
+    assert_eq!(statement.account_id.as_deref(), Some("000111222"));
+    assert_eq!(
+        statement.ledger_balance,
+        Some(LedgerBalance {
+            amount: BigDecimal::from_str("1237.50").unwrap(),
+            as_of: date(2025, Month::March, 5),
+        })
+    );
+
+    let records: Vec<_> = statement.records.into_iter().map(Result::unwrap).collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].external_id.as_deref(), Some("202503010001"));
+    assert_eq!(records[0].date, date(2025, Month::March, 1));
+    assert_eq!(records[0].amount, BigDecimal::from_str("-12.50").unwrap());
+    // the header's CHARSET:1252 decodes the accent, NAME and MEMO make the description
+    assert_eq!(records[0].description, "CAFÉ ROMA POS PURCHASE");
+    assert_eq!(records[0].category, None);
+    // a check without a payee is described by its number
+    assert_eq!(records[1].description, "Check 1042");
+    assert_eq!(records[2].description, "ACME PAYROLL & CO");
+    assert_eq!(records[2].amount, BigDecimal::from(1500));
+}
+
+#[test]
+fn test_read_ofx_2_xml() {
+    let statement = read_ofx(&fixture("statement_v2.qfx")).unwrap();
+
+    assert_eq!(statement.account_id.as_deref(), Some("4111XXXXXXXX1111"));
+    let ledger_balance = statement.ledger_balance.unwrap();
+    assert_eq!(
+        ledger_balance.amount,
+        BigDecimal::from_str("-25.99").unwrap()
+    );
+    assert_eq!(ledger_balance.as_of, date(2025, Month::March, 31));
+
+    assert_eq!(statement.records.len(), 3);
+    let first = statement.records[0].as_ref().unwrap();
+    assert_eq!(first.external_id.as_deref(), Some("CC-0001"));
+    assert_eq!(first.description, "BOOKSTORE");
+    assert_eq!(first.date, date(2025, Month::March, 2));
+    let second = statement.records[1].as_ref().unwrap();
+    assert_eq!(second.amount, BigDecimal::from(20));
+
+    // a bad value names the transaction's line and the element
+    let error = statement.records[2].as_ref().unwrap_err();
+    assert_eq!(error.line, Some(29));
+    assert_eq!(error.column.as_deref(), Some("DTPOSTED"));
+}
+
+#[test]
+fn test_read_ofx_requires_values() {
+    let contents = b"<OFX><STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20250301<FITID>1</STMTTRN></OFX>";
+
+    let statement = read_ofx(contents).unwrap();
+
+    let error = statement.records[0].as_ref().unwrap_err();
+    assert_eq!(error.column.as_deref(), Some("TRNAMT"));
+    assert!(statement.ledger_balance.is_none());
+    assert!(read_ofx(b"Date,Amount\n").is_err());
+}
+
+#[test]
+fn test_detect_file_format() {
+    let ofx = fixture("statement_v1.ofx");
+    let csv = fixture("default.csv");
+
+    assert!(is_ofx(&ofx));
+    assert!(is_ofx(&fixture("statement_v2.qfx")));
+    assert!(!is_ofx(&csv));
+    assert_eq!(
+        FileFormat::detect(Path::new("march.QFX"), &csv),
+        FileFormat::Ofx
+    );
+    assert_eq!(
+        FileFormat::detect(Path::new("download"), &ofx),
+        FileFormat::Ofx
+    );
+    assert_eq!(
+        FileFormat::detect(Path::new("download"), &csv),
+        FileFormat::Csv
+    );
+    assert_eq!("ofx".parse::<FileFormat>().unwrap(), FileFormat::Ofx);
+    assert!("pdf".parse::<FileFormat>().is_err());
+}
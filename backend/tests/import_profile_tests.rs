@@ -0,0 +1,210 @@
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+use time::Month;
+use transaction_server::import::{
+    csv_file::{read_csv, read_headers},
+    profile::{detect_profile, find_profile, Profile, SignConvention, BUILT_IN_PROFILE},
+    ImportRecord,
+};
+mod common;
+use common::fixtures::{date, fixture, profiles_dir};
+
+fn read_fixture(name: &str, profile: &Profile) -> Vec<ImportRecord> {
+    let contents = fixture(name);
+    read_csv(&contents, profile)
+        .unwrap()
+        .into_iter()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn test_built_in_profile_reads_the_original_export() {
+    let records = read_fixture("default.csv", &Profile::built_in());
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].line, 2);
+    assert_eq!(records[0].external_id.as_deref(), Some("T1"));
+    assert_eq!(records[0].date, date(2025, Month::March, 1));
+    assert_eq!(records[0].amount, BigDecimal::from_str("-12.50").unwrap());
+    assert_eq!(records[0].description, "COFFEE SHOP");
+    assert_eq!(records[0].category.as_deref(), Some("Dining"));
+    assert_eq!(records[0].confidence, Some(0.9));
+    assert_eq!(records[0].original_category, None);
+    // the reference number stands in for a missing transaction id
+    assert_eq!(records[1].external_id.as_deref(), Some("R2"));
+    assert_eq!(records[1].amount, BigDecimal::from(1500));
+}
+
+#[test]
+fn test_debit_credit_profile_with_header_below_account_details() {
+    let profile = Profile::load(&profiles_dir().join("credit_union.toml")).unwrap();
+    assert_eq!(profile.sign, SignConvention::DebitCredit);
+    assert_eq!(profile.header_row, 3);
+
+    let records = read_fixture("credit_union.csv", &profile);
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].line, 4);
+    assert_eq!(records[0].date, date(2025, Month::March, 1));
+    assert_eq!(records[0].amount, BigDecimal::from_str("-42.10").unwrap());
+    assert_eq!(records[0].external_id.as_deref(), Some("A1"));
+    // the blank line is skipped but still counted
+    assert_eq!(records[1].line, 6);
+    assert_eq!(records[1].amount, BigDecimal::from(2000));
+}
+
+#[test]
+fn test_inverted_profile_with_decimal_comma_and_windows_1252() {
+    let profile = Profile::load(&profiles_dir().join("card_eu.yaml")).unwrap();
+
+    let records = read_fixture("card_eu.csv", &profile);
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].description, "Café Müller");
+    assert_eq!(records[0].date, date(2025, Month::March, 3));
+    assert_eq!(records[0].amount, BigDecimal::from_str("-1234.56").unwrap());
+    assert_eq!(records[1].amount, BigDecimal::from(10));
+    assert_eq!(records[1].category.as_deref(), Some("Refunds"));
+}
+
+#[test]
+fn test_profile_name_defaults_to_file_name() {
+    let profile = Profile::from_toml(
+        r#"
+        [columns]
+        date = "Date"
+        description = "Memo"
+        amount = "Amount"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(profile.name, "");
+    assert_eq!(profile.date_format, "%Y-%m-%d");
+    assert_eq!(profile.sign, SignConvention::Signed);
+
+    let profile = Profile::load(&profiles_dir().join("credit_union.toml")).unwrap();
+    assert_eq!(profile.name, "credit_union");
+}
+
+#[test]
+fn test_invalid_profiles_are_rejected() {
+    let missing_amount =
+        Profile::from_yaml("columns:\n  date: Date\n  description: Memo\nsign: inverted\n");
+    assert!(missing_amount
+        .unwrap_err()
+        .message
+        .contains("amount column"));
+
+    let missing_credit = Profile::from_toml(
+        "sign = \"debit_credit\"\n[columns]\ndate = \"Date\"\ndescription = \"Memo\"\ndebit = \"Out\"\n",
+    );
+    assert!(missing_credit
+        .unwrap_err()
+        .message
+        .contains("Debit and credit"));
+
+    let bad_encoding = Profile::from_toml(
+        "encoding = \"klingon\"\n[columns]\ndate = \"Date\"\ndescription = \"Memo\"\namount = \"Amount\"\n",
+    );
+    assert!(bad_encoding.unwrap_err().message.contains("klingon"));
+
+    let unknown_field = Profile::from_toml(
+        "[columns]\ndate = \"Date\"\ndescription = \"Memo\"\namount = \"Amount\"\nmemo = \"Memo\"\n",
+    );
+    assert!(unknown_field.is_err());
+}
+
+#[test]
+fn test_detect_profile_from_header() {
+    let profiles = Profile::load_all(&profiles_dir()).unwrap();
+    assert_eq!(profiles[0].name, BUILT_IN_PROFILE);
+    assert_eq!(profiles.len(), 3);
+
+    for (file, expected) in [
+        ("default.csv", BUILT_IN_PROFILE),
+        ("credit_union.csv", "credit_union"),
+        ("card_eu.csv", "card_eu"),
+    ] {
+        let contents = fixture(file);
+        let profile =
+            detect_profile(&profiles, |profile| read_headers(&contents, profile)).unwrap();
+        assert_eq!(profile.name, expected, "{}", file);
+    }
+
+    let unknown = b"When,What,How much\n2025-03-01,Coffee,-4\n";
+    let error = detect_profile(&profiles, |profile| read_headers(unknown, profile)).unwrap_err();
+    assert!(error.message.contains("credit_union"));
+}
+
+#[test]
+fn test_find_profile_by_name_or_path() {
+    let profiles = Profile::load_all(&profiles_dir()).unwrap();
+
+    assert_eq!(find_profile(&profiles, "card_eu").unwrap().name, "card_eu");
+    let path = profiles_dir().join("credit_union.toml");
+    assert_eq!(
+        find_profile(&[Profile::built_in()], path.to_str().unwrap())
+            .unwrap()
+            .name,
+        "credit_union"
+    );
+    assert!(find_profile(&profiles, "missing").is_err());
+}
+
+#[test]
+fn test_errors_name_line_and_column() {
+    let profile = Profile::from_toml(
+        "date_format = \"%m/%d/%Y\"\n[columns]\ndate = \"Effective Date\"\ndescription = \"Description\"\namount = \"Amount\"\n",
+    )
+    .unwrap();
+    let contents = b"Effective Date,Description,Amount\n03/01/2025,COFFEE,-4\n03/02/2025,TEA,four\n2025-03-03,CAKE,-6\n";
+
+    let records = read_csv(contents, &profile).unwrap();
+
+    assert!(records[0].is_ok());
+    let error = records[1].as_ref().unwrap_err();
+    assert_eq!(error.line, Some(3));
+    assert_eq!(error.column.as_deref(), Some("Amount"));
+    assert_eq!(
+        error.to_string(),
+        "line 3: column 'Amount': Invalid amount 'four'"
+    );
+    let error = records[2].as_ref().unwrap_err();
+    assert_eq!(error.line, Some(4));
+    assert_eq!(error.column.as_deref(), Some("Effective Date"));
+
+    let missing_column = read_csv(b"Date,Description,Amount\n", &profile);
+    assert!(missing_column
+        .unwrap_err()
+        .message
+        .contains("'Effective Date'"));
+}
+
+#[test]
+fn test_parse_amount_formats() {
+    let profile = Profile::built_in();
+    let amount = |value: &str| profile.parse_amount(value).unwrap();
+
+    assert_eq!(
+        amount("$1,234.56"),
+        BigDecimal::from_str("1234.56").unwrap()
+    );
+    assert_eq!(amount("(12.00)"), BigDecimal::from(-12));
+    assert_eq!(amount(" -3 "), BigDecimal::from(-3));
+    assert!(profile.parse_amount("").is_err());
+}
+
+#[test]
+fn test_parse_confidence() {
+    let profile = Profile::built_in();
+
+    assert_eq!(profile.parse_confidence(" 0.42 "), Ok(0.42));
+    assert_eq!(profile.parse_confidence("1"), Ok(1.0));
+    assert!(profile.parse_confidence("1.5").is_err());
+    assert!(profile.parse_confidence("-0.1").is_err());
+    assert!(profile
+        .parse_confidence("high")
+        .unwrap_err()
+        .contains("Invalid confidence 'high'"));
+}
@@ -0,0 +1,82 @@
+use sqlx::types::BigDecimal;
+use std::path::Path;
+use std::str::FromStr;
+use time::Month;
+use transaction_server::import::{
+    qif::{is_qif, parse_date, read_qif},
+    FileFormat,
+};
+mod common;
+use common::fixtures::{date, fixture};
+
+#[test]
+fn test_read_qif() {
+    let statement = read_qif(&fixture("history.qif")).unwrap();
+
+    assert_eq!(statement.account_id.as_deref(), Some("Everyday Checking"));
+    assert!(statement.ledger_balance.is_none());
+    // the category list at the end is not a transaction
+    assert_eq!(statement.records.len(), 5);
+
+    let rent = statement.records[0].as_ref().unwrap();
+    assert_eq!(rent.line, 8);
+    assert_eq!(rent.date, date(2025, Month::March, 1));
+    assert_eq!(rent.amount, BigDecimal::from_str("-1234.56").unwrap());
+    assert_eq!(rent.description, "LANDLORD PROPERTY MGMT March rent");
+    assert_eq!(rent.external_id, None);
+    // the subcategory is the category, the class is dropped
+    assert_eq!(rent.category.as_deref(), Some("Rent"));
+
+    let groceries = statement.records[1].as_ref().unwrap();
+    assert_eq!(groceries.amount, BigDecimal::from_str("-45.10").unwrap());
+    assert_eq!(groceries.category.as_deref(), Some("Groceries"));
+
+    // a transfer has no category, a check without a payee is described by its number
+    let transfer = statement.records[2].as_ref().unwrap();
+    assert_eq!(transfer.category, None);
+    assert_eq!(transfer.description, "Check 1043");
+
+    let refund = statement.records[3].as_ref().unwrap();
+    assert_eq!(refund.date, date(1998, Month::December, 31));
+    assert_eq!(refund.description, "COFFEE REFUND");
+
+    let error = statement.records[4].as_ref().unwrap_err();
+    assert_eq!(error.line, Some(32));
+    assert_eq!(error.column.as_deref(), Some("D"));
+}
+
+#[test]
+fn test_read_qif_rejects_a_cut_off_file() {
+    let contents = b"!Type:Bank\nD03/01/2025\nT-4.00\n^\nD03/02/2025\nT-5.00\n";
+
+    let error = read_qif(contents).unwrap_err();
+
+    assert!(error.message.contains("middle of a transaction"));
+}
+
+#[test]
+fn test_parse_qif_dates() {
+    assert_eq!(parse_date("3/ 1'25").unwrap(), date(2025, Month::March, 1));
+    assert_eq!(
+        parse_date(" 1/ 5/98").unwrap(),
+        date(1998, Month::January, 5)
+    );
+    assert_eq!(
+        parse_date("2025-03-01").unwrap(),
+        date(2025, Month::March, 1)
+    );
+    assert!(parse_date("31/12/2025").is_err());
+    assert!(parse_date("March 1").is_err());
+}
+
+#[test]
+fn test_detect_qif() {
+    let qif = fixture("history.qif");
+
+    assert!(is_qif(&qif));
+    assert!(!is_qif(&fixture("default.csv")));
+    assert_eq!(
+        FileFormat::detect(Path::new("export.txt"), &qif),
+        FileFormat::Qif
+    );
+}
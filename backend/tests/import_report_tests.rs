@@ -0,0 +1,84 @@
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+use transaction_server::{
+    db_models::DbImportAction,
+    import::{report::ImportReport, ImportError},
+};
+
+fn amount(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+fn dry_run() -> ImportReport {
+    let mut report = ImportReport::new("march.csv", "checking", true);
+    report.add_row("Groceries", &amount("-40.10"), DbImportAction::Insert);
+    report.add_row("Dining", &amount("-12.50"), DbImportAction::Update);
+    report.add_row("Groceries", &amount("-9.90"), DbImportAction::Insert);
+    report.add_row("Groceries", &amount("-100"), DbImportAction::Skip);
+    report.add_unknown_category("Pets", 6);
+    report.add_rejected();
+    report.add_unknown_category("pets", 9);
+    report.add_rejected();
+    report.add_parse_error(&ImportError::at_line(7, "Invalid amount 'abc'").in_column("Amount"));
+    report.finish()
+}
+
+#[test]
+fn test_report_counts_rows_by_action() {
+    let report = dry_run();
+
+    assert_eq!(report.rows, 7);
+    assert_eq!(
+        (
+            report.inserted,
+            report.updated,
+            report.duplicates,
+            report.rejected
+        ),
+        (2, 1, 1, 2)
+    );
+    // unknown categories are grouped regardless of case, with every line they're on
+    assert_eq!(report.unknown_categories.len(), 1);
+    assert_eq!(report.unknown_categories[0].lines, vec![6, 9]);
+    assert_eq!(report.parse_errors[0].line, Some(7));
+
+    // duplicates are left out of the totals, they don't change anything
+    let totals: Vec<_> = report
+        .category_totals
+        .iter()
+        .map(|total| (total.category.as_str(), total.rows, total.total.clone()))
+        .collect();
+    assert_eq!(
+        totals,
+        vec![
+            ("Dining", 1, amount("-12.50")),
+            ("Groceries", 2, amount("-50.00")),
+        ]
+    );
+}
+
+#[test]
+fn test_report_as_text() {
+    let text = dry_run().to_string();
+
+    assert!(text.starts_with("Dry run of march.csv into 'checking'"));
+    assert!(text.contains("Rows to insert:         2"));
+    assert!(text.contains("Rows rejected:          2"));
+    assert!(text.contains("Pets (lines 6, 9)"));
+    assert!(text.contains("line 7: column 'Amount': Invalid amount 'abc'"));
+    assert!(text.contains("Groceries"));
+    assert!(text.contains("-50.00"));
+}
+
+#[test]
+fn test_report_as_json() {
+    let json: serde_json::Value = serde_json::from_str(&dry_run().to_json()).unwrap();
+
+    assert_eq!(json["dry_run"], true);
+    assert_eq!(json["inserted"], 2);
+    assert_eq!(json["unknown_categories"][0]["name"], "Pets");
+    assert_eq!(json["parse_errors"][0]["line"], 7);
+    assert_eq!(json["parse_errors"][0]["column"], "Amount");
+    // amounts are strings so they keep every digit
+    assert_eq!(json["category_totals"][1]["total"], "-50.00");
+}
@@ -0,0 +1,138 @@
+use sqlx::types::BigDecimal;
+use std::path::Path;
+use std::str::FromStr;
+use time::Month;
+use transaction_server::import::{
+    profile::{detect_profile, Profile},
+    spreadsheet::{column_letter, read_headers, read_spreadsheet, sheet_names},
+    FileFormat,
+};
+mod common;
+use common::fixtures::{date, fixture};
+
+fn card_profile() -> Profile {
+    Profile::from_toml(
+        r#"
+        name = "card"
+        sheet = "Transactions"
+        header_row = 3
+        sign = "inverted"
+        date_format = "%m/%d/%Y"
+
+        [columns]
+        date = "Date"
+        description = "Merchant"
+        amount = "Amount"
+        reference_number = "Reference"
+        "#,
+    )
+    .unwrap()
+}
+
+fn giro_profile() -> Profile {
+    Profile::from_yaml(
+        r#"
+        name: giro
+        date_format: "%d.%m.%Y"
+        decimal_separator: ","
+        columns:
+          date: Datum
+          description: Beschreibung
+          amount: Betrag
+        "#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_read_xlsx_sheet_and_header_row() {
+    let contents = fixture("card.xlsx");
+    assert_eq!(sheet_names(&contents).unwrap(), ["Summary", "Transactions"]);
+
+    let records = read_spreadsheet(&contents, &card_profile()).unwrap();
+
+    assert_eq!(records.len(), 3);
+    let purchase = records[0].as_ref().unwrap();
+    assert_eq!(purchase.line, 4);
+    // the date cell is read as a date, whatever the profile's format
+    assert_eq!(purchase.date, date(2025, Month::March, 1));
+    assert_eq!(purchase.amount, BigDecimal::from_str("-45.99").unwrap());
+    assert_eq!(purchase.description, "BOOKSTORE");
+    assert_eq!(purchase.external_id.as_deref(), Some("R-1"));
+    let refund = records[1].as_ref().unwrap();
+    assert_eq!(refund.date, date(2025, Month::March, 12));
+    assert_eq!(refund.amount, BigDecimal::from(20));
+
+    // the blank row 6 is skipped, the error names the sheet, row and column
+    let error = records[2].as_ref().unwrap_err();
+    assert_eq!(error.sheet.as_deref(), Some("Transactions"));
+    assert_eq!(error.line, Some(7));
+    assert_eq!(error.column.as_deref(), Some("Amount (D)"));
+    assert_eq!(
+        error.to_string(),
+        "sheet 'Transactions' row 7: column 'Amount (D)': Invalid amount 'n/a'"
+    );
+}
+
+#[test]
+fn test_read_ods_with_decimal_comma() {
+    let records = read_spreadsheet(&fixture("giro.ods"), &giro_profile()).unwrap();
+
+    assert_eq!(records.len(), 2);
+    let bakery = records[0].as_ref().unwrap();
+    assert_eq!(bakery.line, 2);
+    assert_eq!(bakery.date, date(2025, Month::March, 5));
+    assert_eq!(bakery.amount, BigDecimal::from_str("-3.80").unwrap());
+    assert_eq!(bakery.description, "Bäckerei");
+    let salary = records[1].as_ref().unwrap();
+    assert_eq!(salary.date, date(2025, Month::March, 6));
+    assert_eq!(salary.amount, BigDecimal::from(2500));
+}
+
+#[test]
+fn test_spreadsheet_errors_name_the_sheet() {
+    let contents = fixture("card.xlsx");
+
+    let mut profile = card_profile();
+    profile.sheet = Some("Payments".to_string());
+    let error = read_spreadsheet(&contents, &profile).unwrap_err();
+    assert!(error.message.contains("Summary, Transactions"));
+
+    // the first sheet is read without a sheet in the profile
+    profile.sheet = None;
+    let error = read_spreadsheet(&contents, &profile).unwrap_err();
+    assert_eq!(error.sheet.as_deref(), Some("Summary"));
+
+    assert!(read_spreadsheet(b"Date,Amount\n", &profile).is_err());
+}
+
+#[test]
+fn test_detect_spreadsheet_profile() {
+    let profiles = vec![Profile::built_in(), giro_profile(), card_profile()];
+
+    let contents = fixture("card.xlsx");
+    let profile = detect_profile(&profiles, |profile| read_headers(&contents, profile)).unwrap();
+    assert_eq!(profile.name, "card");
+
+    let contents = fixture("giro.ods");
+    let profile = detect_profile(&profiles, |profile| read_headers(&contents, profile)).unwrap();
+    assert_eq!(profile.name, "giro");
+
+    assert_eq!(
+        FileFormat::detect(Path::new("download"), &contents),
+        FileFormat::Spreadsheet
+    );
+    assert_eq!(
+        FileFormat::detect(Path::new("card.XLSX"), b""),
+        FileFormat::Spreadsheet
+    );
+}
+
+#[test]
+fn test_column_letters() {
+    assert_eq!(column_letter(0), "A");
+    assert_eq!(column_letter(25), "Z");
+    assert_eq!(column_letter(26), "AA");
+    assert_eq!(column_letter(701), "ZZ");
+    assert_eq!(column_letter(702), "AAA");
+}
@@ -0,0 +1,52 @@
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use time::Month;
+use transaction_server::{
+    db_models::DbImportRow,
+    import::{fingerprint, fingerprints},
+};
+
+fn row(account: &str, amount: i32, description: &str) -> DbImportRow {
+    DbImportRow {
+        account: account.to_string(),
+        external_id: None,
+        amount: BigDecimal::from(amount),
+        description: description.to_string(),
+        date: Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+        category_id: 1,
+        classification: None,
+        rule_id: None,
+        tags: Vec::new(),
+        new_category: None,
+    }
+}
+
+#[test]
+fn test_fingerprint_ignores_case_and_spacing() {
+    assert_eq!(
+        fingerprint(&row("checking", -4, "COFFEE  SHOP"), 0),
+        fingerprint(&row("checking", -4, "coffee shop "), 0)
+    );
+    assert_ne!(
+        fingerprint(&row("checking", -4, "COFFEE SHOP"), 0),
+        fingerprint(&row("savings", -4, "COFFEE SHOP"), 0)
+    );
+}
+
+#[test]
+fn test_identical_rows_get_distinct_fingerprints() {
+    let rows = vec![
+        row("checking", -4, "COFFEE"),
+        row("checking", -9, "LUNCH"),
+        row("checking", -4, "COFFEE"),
+    ];
+
+    let first = fingerprints(&rows);
+    assert_eq!(first.len(), 3);
+    assert_ne!(first[0], first[2]);
+    assert_eq!(first[0], fingerprint(&rows[0], 0));
+    assert_eq!(first[2], fingerprint(&rows[2], 1));
+
+    // The same file always produces the same fingerprints
+    assert_eq!(fingerprints(&rows), first);
+}
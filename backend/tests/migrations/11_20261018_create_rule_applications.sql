@@ -0,0 +1,19 @@
+-- Create the rule_applications table, the audit trail of rules applied to existing
+-- transactions. Every changed transaction is listed with the category it had before,
+-- transaction_ids and previous_category_ids line up, so an application can be reviewed
+-- or undone. The rule's name, category and tags are copied as they were when applied.
+CREATE TABLE IF NOT EXISTS rule_applications (
+    id SERIAL PRIMARY KEY,
+    rule_id INTEGER REFERENCES categorization_rules(id) ON DELETE SET NULL,
+    rule_name TEXT NOT NULL,
+    category_id INTEGER NOT NULL REFERENCES categories(id),
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    transaction_ids INTEGER[] NOT NULL DEFAULT '{}',
+    previous_category_ids INTEGER[] NOT NULL DEFAULT '{}',
+    applied_by TEXT,
+    applied_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_rule_applications_rule_id ON rule_applications(rule_id);
@@ -0,0 +1,24 @@
+-- Remember the categorization rule that chose each transaction's category, so a
+-- correction can tell a rule's choice from the classifier's or a person's
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS category_rule_id INTEGER
+    REFERENCES categorization_rules(id) ON DELETE SET NULL;
+
+-- Create the category_corrections table, written whenever updateTransaction changes a
+-- category. source says who chose the category that was corrected: a categorization
+-- rule, the classifier (with its model and confidence) or a person.
+CREATE TABLE IF NOT EXISTS category_corrections (
+    id SERIAL PRIMARY KEY,
+    transaction_id INTEGER REFERENCES transactions(id) ON DELETE SET NULL,
+    description TEXT NOT NULL,
+    amount DECIMAL(10, 2) NOT NULL,
+    old_category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    new_category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    source TEXT NOT NULL CHECK (source IN ('manual', 'model', 'rule')),
+    rule_id INTEGER REFERENCES categorization_rules(id) ON DELETE SET NULL,
+    classifier_model TEXT,
+    confidence DOUBLE PRECISION,
+    corrected_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_category_corrections_transaction_id
+    ON category_corrections(transaction_id);
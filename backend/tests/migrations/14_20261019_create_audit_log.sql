@@ -0,0 +1,78 @@
+-- Create the audit_log table, one row per change to a transaction or category with the
+-- row before and after it. A trigger writes it in the same database transaction as the
+-- change. Repositories say where a change came from with the audit.source setting and
+-- who made it with audit.actor, both local to their transaction. Without them a change
+-- comes from the api and is made by the database user.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id SERIAL PRIMARY KEY,
+    entity_type TEXT NOT NULL CHECK (entity_type IN ('transaction', 'category')),
+    entity_id INTEGER NOT NULL,
+    action TEXT NOT NULL CHECK (action IN ('create', 'update', 'delete', 'merge')),
+    actor TEXT NOT NULL,
+    source TEXT NOT NULL CHECK (source IN ('api', 'import', 'rule', 'recurring')),
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_entity ON audit_log(entity_type, entity_id);
+
+-- A transaction deleted while audit.merged_into is set was merged into that transaction,
+-- which is recorded as the row after it
+CREATE OR REPLACE FUNCTION write_audit_log()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    before_row JSONB;
+    after_row JSONB;
+    audit_action TEXT;
+    merged_into INTEGER;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        audit_action := 'create';
+        after_row := to_jsonb(NEW);
+    ELSIF TG_OP = 'UPDATE' THEN
+        audit_action := 'update';
+        before_row := to_jsonb(OLD);
+        after_row := to_jsonb(NEW);
+        -- filling in the normalized description isn't a change
+        IF after_row - 'normalized_description' - 'updated_at'
+            = before_row - 'normalized_description' - 'updated_at' THEN
+            RETURN NULL;
+        END IF;
+    ELSE
+        audit_action := 'delete';
+        before_row := to_jsonb(OLD);
+        merged_into := NULLIF(current_setting('audit.merged_into', true), '')::INTEGER;
+        IF TG_TABLE_NAME = 'transactions' AND merged_into IS NOT NULL THEN
+            audit_action := 'merge';
+            SELECT to_jsonb(t) INTO after_row FROM transactions t WHERE t.id = merged_into;
+        END IF;
+    END IF;
+
+    INSERT INTO audit_log (entity_type, entity_id, action, actor, source, before, after)
+    VALUES (
+        CASE TG_TABLE_NAME WHEN 'transactions' THEN 'transaction' ELSE 'category' END,
+        (COALESCE(before_row, after_row) ->> 'id')::INTEGER,
+        audit_action,
+        COALESCE(NULLIF(current_setting('audit.actor', true), ''), session_user),
+        COALESCE(NULLIF(current_setting('audit.source', true), ''), 'api'),
+        before_row,
+        after_row
+    );
+    RETURN NULL;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS audit_transactions ON transactions;
+CREATE TRIGGER audit_transactions
+    AFTER INSERT OR UPDATE OR DELETE ON transactions
+    FOR EACH ROW
+    EXECUTE FUNCTION write_audit_log();
+
+DROP TRIGGER IF EXISTS audit_categories ON categories;
+CREATE TRIGGER audit_categories
+    AFTER INSERT OR UPDATE OR DELETE ON categories
+    FOR EACH ROW
+    EXECUTE FUNCTION write_audit_log();
@@ -0,0 +1,54 @@
+-- Create the period_closings table, one row each time closePeriod or reopenPeriod moves
+-- the date through which transactions are closed. The newest row is in effect, a NULL
+-- closed_through means no period is closed.
+CREATE TABLE IF NOT EXISTS period_closings (
+    id SERIAL PRIMARY KEY,
+    closed_through DATE,
+    action TEXT NOT NULL CHECK (action IN ('close', 'reopen')),
+    actor TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Closing and reopening are written to the audit log too
+ALTER TABLE audit_log DROP CONSTRAINT IF EXISTS audit_log_entity_type_check;
+ALTER TABLE audit_log ADD CONSTRAINT audit_log_entity_type_check
+    CHECK (entity_type IN ('transaction', 'category', 'period'));
+ALTER TABLE audit_log DROP CONSTRAINT IF EXISTS audit_log_action_check;
+ALTER TABLE audit_log ADD CONSTRAINT audit_log_action_check
+    CHECK (action IN ('create', 'update', 'delete', 'merge', 'close', 'reopen'));
+
+-- Reject every change to a transaction dated in the closed period, or moved into it,
+-- whoever makes it. Filling in the normalized description isn't a change.
+CREATE OR REPLACE FUNCTION reject_closed_period_changes()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    closed_through DATE;
+BEGIN
+    SELECT p.closed_through INTO closed_through
+    FROM period_closings p
+    ORDER BY p.id DESC
+    LIMIT 1;
+
+    IF closed_through IS NULL THEN
+        RETURN COALESCE(NEW, OLD);
+    END IF;
+    IF TG_OP = 'UPDATE' AND to_jsonb(NEW) - 'normalized_description' - 'updated_at'
+        = to_jsonb(OLD) - 'normalized_description' - 'updated_at' THEN
+        RETURN NEW;
+    END IF;
+    IF (TG_OP <> 'INSERT' AND OLD.date <= closed_through)
+        OR (TG_OP <> 'DELETE' AND NEW.date <= closed_through) THEN
+        RAISE EXCEPTION 'Transactions dated through % are in a closed period', closed_through
+            USING HINT = 'Reopen the period with reopenPeriod first';
+    END IF;
+    RETURN COALESCE(NEW, OLD);
+END;
+$$;
+
+DROP TRIGGER IF EXISTS reject_closed_period_transactions ON transactions;
+CREATE TRIGGER reject_closed_period_transactions
+    BEFORE INSERT OR UPDATE OR DELETE ON transactions
+    FOR EACH ROW
+    EXECUTE FUNCTION reject_closed_period_changes();
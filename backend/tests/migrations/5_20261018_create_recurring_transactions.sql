@@ -0,0 +1,38 @@
+-- Create the recurring_transactions table
+-- frequency/interval_count describe the schedule, e.g. every 2 weeks or monthly on day_of_month.
+-- last_materialized_date is the last day occurrences have been written to transactions.
+CREATE TABLE IF NOT EXISTS recurring_transactions (
+    id SERIAL PRIMARY KEY,
+    description TEXT NOT NULL,
+    amount DECIMAL(10, 2) NOT NULL,
+    category_id INTEGER NOT NULL REFERENCES categories(id),
+    frequency TEXT NOT NULL CHECK (frequency IN ('weekly', 'monthly', 'yearly')),
+    interval_count INTEGER NOT NULL DEFAULT 1 CHECK (interval_count > 0),
+    day_of_month INTEGER CHECK (day_of_month BETWEEN 1 AND 31),
+    start_date DATE NOT NULL,
+    end_date DATE,
+    last_materialized_date DATE,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create the trigger for recurring_transactions table
+DO $$ 
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'update_recurring_transactions_updated_at') THEN
+        CREATE TRIGGER update_recurring_transactions_updated_at
+            BEFORE UPDATE ON recurring_transactions
+            FOR EACH ROW
+            EXECUTE FUNCTION update_updated_at_column();
+    END IF;
+END
+$$;
+
+-- Link materialized occurrences back to their schedule
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS recurring_transaction_id INTEGER REFERENCES recurring_transactions(id) ON DELETE SET NULL;
+
+-- A schedule can only produce one transaction per date, so re-running materialization is safe
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_recurring_occurrence
+    ON transactions(recurring_transaction_id, date)
+    WHERE recurring_transaction_id IS NOT NULL;
@@ -0,0 +1,15 @@
+-- Identify imported transactions so running an import again is safe.
+-- external_id is the bank's own transaction id when the file has one,
+-- import_fingerprint is a hash of the row's content for files that don't.
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS account TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS external_id TEXT;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS import_fingerprint TEXT;
+
+-- Ids and fingerprints only have to be unique within the account they were imported into
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_account_external_id
+    ON transactions(account, external_id)
+    WHERE external_id IS NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_account_import_fingerprint
+    ON transactions(account, import_fingerprint)
+    WHERE import_fingerprint IS NOT NULL;
@@ -0,0 +1,23 @@
+-- Create the import_batches table, one row per run of an import.
+-- file_hash is a SHA-256 of the imported file, so a file imported twice can be recognized.
+-- rolled_back_at is set when the batch's transactions are removed again.
+CREATE TABLE IF NOT EXISTS import_batches (
+    id SERIAL PRIMARY KEY,
+    source_name TEXT NOT NULL,
+    file_hash TEXT,
+    imported_by TEXT,
+    row_count INTEGER NOT NULL DEFAULT 0,
+    inserted_count INTEGER NOT NULL DEFAULT 0,
+    updated_count INTEGER NOT NULL DEFAULT 0,
+    skipped_count INTEGER NOT NULL DEFAULT 0,
+    warnings TEXT[] NOT NULL DEFAULT '{}',
+    rolled_back_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_batches_file_hash ON import_batches(file_hash);
+
+-- Stamp every transaction an import inserted with its batch
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS import_batch_id INTEGER REFERENCES import_batches(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_import_batch_id ON transactions(import_batch_id);
@@ -0,0 +1,10 @@
+-- Create the category_aliases table, mapping the category names banks and the
+-- classifier use to our own categories. Names are matched case-insensitively.
+CREATE TABLE IF NOT EXISTS category_aliases (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_category_aliases_name ON category_aliases(LOWER(name));
@@ -0,0 +1,103 @@
+use transaction_server::normalize::{
+    clean_text, normalize_description, strip_noise, transform_text,
+};
+use transaction_server::recurrence::merchant_key;
+
+#[test]
+fn test_clean_text_matches_the_python_cleaning() {
+    // lower case, punctuation removed rather than replaced, single spaces
+    assert_eq!(clean_text("  Trader Joe's   Market "), "trader joes market");
+    assert_eq!(clean_text("AMAZON.COM"), "amazoncom");
+    assert_eq!(clean_text("Café\tDu Monde"), "caf du monde");
+    assert_eq!(clean_text("A\x1fB"), "a b");
+    assert_eq!(clean_text(""), "");
+    // the noise is left for the model
+    assert_eq!(clean_text("SQ *BLUE BOTTLE #12"), "sq blue bottle 12");
+}
+
+#[test]
+fn test_transform_text_matches_the_python_transforms() {
+    assert_eq!(transform_text("NETFLIX.COM 8841"), "netflix");
+    assert_eq!(transform_text("AMAZON.COM*2K4"), "amazon goods");
+    // patterns are matched in their case
+    assert_eq!(transform_text("Netflix.com"), "netflixcom");
+    assert_eq!(transform_text("SQ *BLUE BOTTLE"), "sq blue bottle");
+    // and normalizing transforms what is left once the noise is stripped
+    assert_eq!(normalize_description("SQ *NETFLIX.COM 8841"), "netflix");
+}
+
+#[test]
+fn test_processor_prefixes() {
+    assert_eq!(
+        normalize_description("SQ *BLUE BOTTLE COFFEE"),
+        "blue bottle coffee"
+    );
+    assert_eq!(normalize_description("TST* SHAKE SHACK"), "shake shack");
+    assert_eq!(normalize_description("POS DEBIT SAFEWAY"), "safeway");
+    assert_eq!(
+        normalize_description("POS DEBIT SQ *BLUE BOTTLE"),
+        "blue bottle"
+    );
+    assert_eq!(normalize_description("PAYPAL *SPOTIFY"), "spotify");
+    // only whole words are prefixes
+    assert_eq!(normalize_description("POSH CAFE"), "posh cafe");
+}
+
+#[test]
+fn test_numbers_and_dates() {
+    assert_eq!(
+        normalize_description("STARBUCKS STORE #1234"),
+        "starbucks store"
+    );
+    assert_eq!(
+        normalize_description("PURCHASE AUTHORIZED ON 03/14 WHOLEFDS MKT 10233 CARD 4421"),
+        "wholefds mkt card"
+    );
+    assert_eq!(
+        normalize_description("SPOTIFY.COM XXXX1234 2025-03-14"),
+        "spotifycom"
+    );
+    assert_eq!(
+        normalize_description("SHELL OIL 57444 ****9921"),
+        "shell oil"
+    );
+    // a short number can be part of the name
+    assert_eq!(normalize_description("7-ELEVEN"), "7eleven");
+}
+
+#[test]
+fn test_city_and_state_suffixes() {
+    assert_eq!(
+        normalize_description("STARBUCKS STORE 1234 SEATTLE WA"),
+        "starbucks store"
+    );
+    assert_eq!(
+        normalize_description("BLUE BOTTLE SAN FRANCISCO CA"),
+        "blue bottle"
+    );
+    assert_eq!(
+        normalize_description("TARGET T-0123 AUSTIN, TX 78701"),
+        "target"
+    );
+    // a merchant's name is never taken for a city
+    assert_eq!(normalize_description("SAFEWAY WA"), "safeway wa");
+    // nor is a word in lower case taken for a state
+    assert_eq!(normalize_description("Pizza Place In"), "pizza place in");
+}
+
+#[test]
+fn test_strip_noise_keeps_case_and_punctuation() {
+    assert_eq!(
+        strip_noise("SQ *Joe's Pizza #12 BROOKLYN NY"),
+        "Joe's Pizza"
+    );
+}
+
+#[test]
+fn test_merchant_key_ignores_the_noise() {
+    assert_eq!(
+        merchant_key("POS DEBIT STARBUCKS #998 SEATTLE WA"),
+        merchant_key("Starbucks 5521")
+    );
+    assert_eq!(merchant_key("NETFLIX.COM 8841"), "netflix com");
+}
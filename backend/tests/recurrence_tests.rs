@@ -0,0 +1,254 @@
+use chrono::{Datelike, NaiveDate};
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use time::Month;
+use transaction_server::{
+    db_models::{DbRecurringTransaction, DbTransaction},
+    db_traits::MockRecurringTransactionRepository,
+    recurrence::{detect_recurring, materialize_due, Cadence, Frequency, PriceChange, Schedule},
+};
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn test_monthly_on_day_clamps_to_month_end() {
+    let schedule = Schedule {
+        frequency: Frequency::Monthly,
+        interval: 1,
+        day_of_month: Some(31),
+        start_date: date(2025, 1, 1),
+        end_date: None,
+    };
+
+    let occurrences = schedule.occurrences_between(date(2025, 1, 1), date(2025, 4, 30));
+    assert_eq!(
+        occurrences,
+        vec![date(2025, 1, 31), date(2025, 2, 28), date(2025, 3, 31), date(2025, 4, 30)]
+    );
+}
+
+#[test]
+fn test_huge_interval_stops_at_the_first_occurrence() {
+    for frequency in [Frequency::Weekly, Frequency::Monthly, Frequency::Yearly] {
+        let schedule = Schedule {
+            frequency,
+            interval: 400_000_000,
+            day_of_month: None,
+            start_date: date(2025, 1, 20),
+            end_date: None,
+        };
+
+        let occurrences = schedule.occurrences_between(date(2025, 1, 1), date(2030, 1, 1));
+        assert_eq!(occurrences, vec![date(2025, 1, 20)]);
+    }
+}
+
+#[test]
+fn test_monthly_day_before_start_skips_first_month() {
+    let schedule = Schedule {
+        frequency: Frequency::Monthly,
+        interval: 1,
+        day_of_month: Some(5),
+        start_date: date(2025, 1, 20),
+        end_date: None,
+    };
+
+    let occurrences = schedule.occurrences_between(date(2025, 1, 1), date(2025, 3, 1));
+    assert_eq!(occurrences, vec![date(2025, 2, 5)]);
+}
+
+#[test]
+fn test_every_two_weeks_until_end_date() {
+    let schedule = Schedule {
+        frequency: Frequency::Weekly,
+        interval: 2,
+        day_of_month: None,
+        start_date: date(2025, 1, 3),
+        end_date: Some(date(2025, 2, 14)),
+    };
+
+    let occurrences = schedule.occurrences_between(date(2025, 1, 10), date(2025, 12, 31));
+    assert_eq!(
+        occurrences,
+        vec![date(2025, 1, 17), date(2025, 1, 31), date(2025, 2, 14)]
+    );
+}
+
+#[test]
+fn test_yearly_on_leap_day() {
+    let schedule = Schedule {
+        frequency: Frequency::Yearly,
+        interval: 1,
+        day_of_month: None,
+        start_date: date(2024, 2, 29),
+        end_date: None,
+    };
+
+    let occurrences = schedule.occurrences_between(date(2024, 1, 1), date(2026, 12, 31));
+    assert_eq!(
+        occurrences,
+        vec![date(2024, 2, 29), date(2025, 2, 28), date(2026, 2, 28)]
+    );
+}
+
+#[tokio::test]
+async fn test_materialize_due_starts_after_last_materialized_date() {
+    let mut mock = MockRecurringTransactionRepository::new();
+
+    mock.expect_active().returning(|| {
+        Ok(vec![DbRecurringTransaction {
+            id: 7,
+            description: "Phone".to_string(),
+            amount: BigDecimal::from(-80),
+            category_id: 10,
+            category_name: Some("Phone".to_string()),
+            frequency: "monthly".to_string(),
+            interval_count: 1,
+            day_of_month: Some(15),
+            start_date: Date::from_calendar_date(2025, Month::January, 1).unwrap(),
+            end_date: None,
+            last_materialized_date: Some(Date::from_calendar_date(2025, Month::February, 20).unwrap()),
+            active: true,
+            created_at: None,
+            updated_at: None,
+        }])
+    });
+    mock.expect_materialize()
+        .withf(|id: &i32, dates: &Vec<NaiveDate>, through: &NaiveDate| {
+            *id == 7
+                && *dates == vec![date(2025, 3, 15), date(2025, 4, 15)]
+                && *through == date(2025, 4, 20)
+        })
+        .times(1)
+        .returning(|_, dates, _| Ok(dates.len() as u64));
+
+    let created = materialize_due(&mock, date(2025, 4, 20))
+        .await
+        .expect("Failed to materialize");
+    assert_eq!(created, 2);
+}
+
+#[tokio::test]
+async fn test_materialize_due_continues_past_a_failing_schedule() {
+    let mut mock = MockRecurringTransactionRepository::new();
+
+    mock.expect_active().returning(|| {
+        Ok([7, 8]
+            .into_iter()
+            .map(|id| DbRecurringTransaction {
+                id,
+                description: "Phone".to_string(),
+                amount: BigDecimal::from(-80),
+                category_id: 10,
+                category_name: Some("Phone".to_string()),
+                frequency: "monthly".to_string(),
+                interval_count: 1,
+                day_of_month: Some(15),
+                start_date: Date::from_calendar_date(2025, Month::April, 1).unwrap(),
+                end_date: None,
+                last_materialized_date: None,
+                active: true,
+                created_at: None,
+                updated_at: None,
+            })
+            .collect())
+    });
+    mock.expect_materialize()
+        .withf(|id: &i32, _: &Vec<NaiveDate>, _: &NaiveDate| *id == 7)
+        .times(1)
+        .returning(|_, _, _| Err(sqlx::Error::RowNotFound));
+    mock.expect_materialize()
+        .withf(|id: &i32, _: &Vec<NaiveDate>, _: &NaiveDate| *id == 8)
+        .times(1)
+        .returning(|_, dates, _| Ok(dates.len() as u64));
+
+    let created = materialize_due(&mock, date(2025, 4, 20))
+        .await
+        .expect("Failed to materialize");
+    assert_eq!(created, 1);
+}
+
+fn charge(id: i32, description: &str, amount: i32, date: NaiveDate) -> DbTransaction {
+    DbTransaction {
+        id,
+        amount: BigDecimal::from(amount),
+        description: description.to_string(),
+        date: Date::from_calendar_date(
+            date.year(),
+            Month::try_from(date.month() as u8).unwrap(),
+            date.day() as u8,
+        )
+        .unwrap(),
+        category_id: 6,
+        category_name: Some("Subscription".to_string()),
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+#[test]
+fn test_detect_monthly_subscription_with_price_change() {
+    let transactions = vec![
+        charge(1, "NETFLIX.COM 8841", -15, date(2025, 1, 3)),
+        charge(2, "Netflix.com 1193", -15, date(2025, 2, 3)),
+        charge(3, "NETFLIX.COM 0412", -18, date(2025, 3, 4)),
+        charge(4, "NETFLIX.COM 7730", -18, date(2025, 4, 3)),
+        // Same merchant, but a refund is never a recurring charge
+        charge(5, "NETFLIX.COM 7730", 18, date(2025, 4, 5)),
+        // Too few charges
+        charge(6, "GYM", -40, date(2025, 3, 1)),
+        charge(7, "GYM", -40, date(2025, 4, 1)),
+    ];
+
+    let candidates = detect_recurring(&transactions, 3);
+    assert_eq!(candidates.len(), 1);
+
+    let candidate = &candidates[0];
+    assert_eq!(candidate.merchant, "netflix com");
+    assert_eq!(candidate.description, "NETFLIX.COM 7730");
+    assert_eq!(candidate.cadence, Cadence::Monthly);
+    assert_eq!(candidate.occurrences, 4);
+    assert_eq!(candidate.average_amount, 16.5);
+    assert_eq!(candidate.last_date, date(2025, 4, 3));
+    assert_eq!(candidate.next_date, date(2025, 5, 3));
+    assert_eq!(
+        candidate.price_change,
+        Some(PriceChange {
+            previous_amount: 15.0,
+            new_amount: 18.0,
+            changed_on: date(2025, 3, 4),
+        })
+    );
+
+    let schedule = candidate.schedule();
+    assert_eq!(schedule.frequency, Frequency::Monthly);
+    assert_eq!(schedule.day_of_month, Some(3));
+    assert_eq!(schedule.start_date, date(2025, 5, 3));
+}
+
+#[test]
+fn test_detect_ignores_irregular_charges() {
+    let transactions = vec![
+        // Every two weeks
+        charge(1, "DAYCARE", -300, date(2025, 1, 3)),
+        charge(2, "DAYCARE", -300, date(2025, 1, 17)),
+        charge(3, "DAYCARE", -300, date(2025, 1, 31)),
+        // Irregular gaps
+        charge(4, "COFFEE SHOP", -5, date(2025, 1, 2)),
+        charge(5, "COFFEE SHOP", -5, date(2025, 1, 9)),
+        charge(6, "COFFEE SHOP", -5, date(2025, 2, 20)),
+        // Regular but wildly different amounts
+        charge(7, "HARDWARE", -20, date(2025, 1, 5)),
+        charge(8, "HARDWARE", -250, date(2025, 2, 5)),
+        charge(9, "HARDWARE", -35, date(2025, 3, 5)),
+    ];
+
+    let candidates = detect_recurring(&transactions, 3);
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].merchant, "daycare");
+    assert_eq!(candidates[0].cadence, Cadence::Biweekly);
+    assert_eq!(candidates[0].next_date, date(2025, 2, 14));
+    assert_eq!(candidates[0].price_change, None);
+}
@@ -0,0 +1,214 @@
+use sqlx::types::time::Date;
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+use time::Month;
+use transaction_server::{
+    db_models::{DbCategorizationRule, DbCategoryCorrection, DbTaggedTransaction},
+    rules::{compile_pattern, rule_changes, suggest_rules, RuleMatchKind, RuleSet},
+};
+
+fn rule(id: i32, priority: i32, category_id: i32) -> DbCategorizationRule {
+    DbCategorizationRule {
+        id,
+        name: format!("rule {}", id),
+        priority,
+        description_pattern: None,
+        match_kind: "contains".to_string(),
+        min_amount: None,
+        max_amount: None,
+        account: None,
+        sign: None,
+        category_id,
+        category_name: None,
+        tags: Vec::new(),
+        enabled: true,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+fn amount(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+fn matched(rules: &RuleSet, description: &str, value: &str, account: &str) -> Option<i32> {
+    rules
+        .first_match(description, &amount(value), account)
+        .map(|rule| rule.id)
+}
+
+#[test]
+fn test_description_contains_and_regex_ignore_case() {
+    let mut contains = rule(1, 0, 10);
+    contains.description_pattern = Some("netflix.com".to_string());
+    let mut regex = rule(2, 0, 11);
+    regex.description_pattern = Some(r"^SHELL\s+\d+".to_string());
+    regex.match_kind = "regex".to_string();
+    let rules = RuleSet::new(vec![contains, regex]).unwrap();
+
+    assert_eq!(
+        matched(&rules, "NETFLIX.COM 8841", "-15", "default"),
+        Some(1)
+    );
+    // contains is literal, the dot isn't a wildcard
+    assert_eq!(matched(&rules, "NETFLIXXCOM", "-15", "default"), None);
+    assert_eq!(matched(&rules, "shell 0042 oil", "-40", "default"), Some(2));
+    assert_eq!(
+        matched(&rules, "ROYAL DUTCH SHELL 0042", "-40", "default"),
+        None
+    );
+}
+
+#[test]
+fn test_amount_range_sign_and_account() {
+    let mut small_debits = rule(1, 0, 10);
+    small_debits.sign = Some("debit".to_string());
+    small_debits.min_amount = Some(amount("2"));
+    small_debits.max_amount = Some(amount("6.50"));
+    let mut savings_credits = rule(2, 0, 11);
+    savings_credits.sign = Some("credit".to_string());
+    savings_credits.account = Some("Savings".to_string());
+    let rules = RuleSet::new(vec![small_debits, savings_credits]).unwrap();
+
+    // the range is on the amount's size
+    assert_eq!(matched(&rules, "COFFEE", "-4.25", "checking"), Some(1));
+    assert_eq!(matched(&rules, "COFFEE", "-6.50", "checking"), Some(1));
+    assert_eq!(matched(&rules, "LUNCH", "-12", "checking"), None);
+    assert_eq!(matched(&rules, "REFUND", "4.25", "checking"), None);
+    assert_eq!(matched(&rules, "INTEREST", "4.25", "savings"), Some(2));
+}
+
+#[test]
+fn test_highest_priority_enabled_rule_wins() {
+    let mut general = rule(1, 0, 10);
+    general.description_pattern = Some("amazon".to_string());
+    let mut specific = rule(2, 5, 11);
+    specific.description_pattern = Some("amazon prime".to_string());
+    let mut disabled = rule(3, 9, 12);
+    disabled.description_pattern = Some("amazon".to_string());
+    disabled.enabled = false;
+    let mut tie = rule(4, 0, 13);
+    tie.description_pattern = Some("amazon".to_string());
+    let rules = RuleSet::new(vec![tie, disabled, general, specific]).unwrap();
+
+    assert_eq!(
+        matched(&rules, "AMAZON PRIME*2K4", "-14.99", "default"),
+        Some(2)
+    );
+    // the older of two rules with the same priority is tried first
+    assert_eq!(matched(&rules, "AMAZON MKTP", "-30", "default"), Some(1));
+}
+
+#[test]
+fn test_invalid_regex_is_an_error() {
+    let mut invalid = rule(1, 0, 10);
+    invalid.description_pattern = Some("(unclosed".to_string());
+    invalid.match_kind = "regex".to_string();
+
+    assert!(RuleSet::new(vec![invalid]).is_err());
+}
+
+#[test]
+fn test_rule_changes_skip_transactions_already_changed() {
+    let mut streaming = rule(1, 0, 10);
+    streaming.description_pattern = Some("netflix".to_string());
+    streaming.tags = vec!["streaming".to_string(), "shared".to_string()];
+    // a rule applied by hand counts even while it's disabled
+    streaming.enabled = false;
+
+    let transaction =
+        |id: i32, description: &str, category_id: i32, tags: Vec<&str>| DbTaggedTransaction {
+            id,
+            amount: amount("-15.99"),
+            description: description.to_string(),
+            date: Date::from_calendar_date(2025, Month::March, 3).unwrap(),
+            account: "default".to_string(),
+            category_id,
+            category_name: None,
+            tags: tags.into_iter().map(str::to_string).collect(),
+        };
+    let changes = rule_changes(
+        &streaming,
+        vec![
+            transaction(1, "NETFLIX.COM", 3, vec![]),
+            transaction(2, "NETFLIX.COM", 10, vec!["shared", "streaming"]),
+            transaction(3, "NETFLIX.COM", 10, vec!["streaming"]),
+            transaction(4, "SPOTIFY", 3, vec![]),
+        ],
+    )
+    .unwrap();
+
+    let changed: Vec<(i32, Vec<String>)> = changes
+        .into_iter()
+        .map(|change| (change.transaction.id, change.added_tags))
+        .collect();
+    assert_eq!(
+        changed,
+        vec![
+            (1, vec!["streaming".to_string(), "shared".to_string()]),
+            (3, vec!["shared".to_string()]),
+        ]
+    );
+}
+
+fn correction(
+    id: i32,
+    transaction_id: i32,
+    description: &str,
+    category_id: i32,
+) -> DbCategoryCorrection {
+    DbCategoryCorrection {
+        id,
+        transaction_id: Some(transaction_id),
+        description: description.to_string(),
+        amount: amount("-5.25"),
+        old_category_id: 1,
+        old_category_name: Some("Shopping".to_string()),
+        new_category_id: category_id,
+        new_category_name: Some(format!("category {}", category_id)),
+        source: "model".to_string(),
+        rule_id: None,
+        classifier_model: None,
+        confidence: None,
+        corrected_at: None,
+    }
+}
+
+#[test]
+fn test_suggest_rules_from_repeated_corrections() {
+    let corrections = vec![
+        correction(1, 1, "STARBUCKS STORE 1234", 10),
+        correction(2, 2, "Starbucks Store 998", 10),
+        correction(3, 3, "STARBUCKS.STORE #55", 10),
+        // only the latest correction of a transaction counts
+        correction(4, 4, "NETFLIX.COM", 10),
+        correction(5, 4, "NETFLIX.COM", 11),
+        correction(6, 5, "NETFLIX.COM", 11),
+        correction(7, 6, "NETFLIX.COM", 11),
+        correction(8, 7, "#1234", 10),
+    ];
+
+    let suggestions = suggest_rules(&corrections, &RuleSet::new(vec![]).unwrap(), 3);
+    let suggested: Vec<(&str, i32, usize)> = suggestions
+        .iter()
+        .map(|s| (s.merchant.as_str(), s.category_id, s.corrections))
+        .collect();
+    assert_eq!(
+        suggested,
+        vec![("netflix com", 11, 3), ("starbucks store", 10, 3)]
+    );
+
+    // The pattern matches every description it was suggested from
+    let pattern =
+        compile_pattern(RuleMatchKind::Regex, &suggestions[1].description_pattern).unwrap();
+    assert!(pattern.is_match("STARBUCKS STORE 1234"));
+    assert!(pattern.is_match("STARBUCKS.STORE #55"));
+    assert!(!pattern.is_match("STARBUCKSSTORE"));
+
+    // A merchant the rules already categorize that way needs no rule
+    let mut coffee = rule(1, 0, 10);
+    coffee.description_pattern = Some("starbucks".to_string());
+    let suggestions = suggest_rules(&corrections, &RuleSet::new(vec![coffee]).unwrap(), 3);
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!(suggestions[0].merchant, "netflix com");
+}
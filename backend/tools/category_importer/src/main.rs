@@ -1,76 +1,137 @@
 use clap::Parser;
-use csv::Reader;
-use serde::{Deserialize};
 use sqlx::postgres::PgPoolOptions;
-use sqlx::types::time::Date;
-use sqlx::types::BigDecimal;
-use std::str::FromStr;
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::error::Error;
 use std::env;
 use std::path::Path;
 use dotenv::dotenv;
-use time::Month;
+use tokio::sync::mpsc::unbounded_channel;
+use transaction_server::db_models::{
+    to_naive_date, DbClassification, DbImportRow, DbNewImportBatch, PgCategorizationRuleRepository,
+    PgCategoryRepository, PgImportBatchRepository, PgTransactionRepository,
+};
+use transaction_server::db_traits::{
+    CategorizationRuleRepository, CategoryRepository, ImportBatchRepository, TransactionRepository,
+};
+use transaction_server::import::camt::read_camt;
+use transaction_server::import::categories::CategoryMap;
+use transaction_server::import::csv_file::{read_csv, read_headers};
+use transaction_server::import::ofx::read_ofx;
+use transaction_server::import::profile::{detect_profile, find_profile, Profile};
+use transaction_server::import::qif::read_qif;
+use transaction_server::import::rejected::{write_rejected, RejectedRow};
+use transaction_server::import::spreadsheet::{self, read_spreadsheet};
+use transaction_server::import::report::ImportReport;
+use transaction_server::import::{file_hash, FileFormat, Statement, DEFAULT_ACCOUNT};
+use transaction_server::rules::RuleSet;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to the input CSV file
+    /// Path to the input CSV, XLSX, ODS, OFX, QFX, QIF or camt.053 file
     #[arg(short, long)]
     input: String,
+
+    /// Format of the input file: csv, xlsx, xls, ods, ofx, qfx, qif or camt. Detected from the file when not given
+    #[arg(short, long)]
+    format: Option<String>,
+
+    /// Account the transactions belong to, transaction ids only have to be unique within an account.
+    /// Defaults to the account in an OFX, QIF or camt.053 file, otherwise "default"
+    #[arg(short, long)]
+    account: Option<String>,
+
+    /// Category for transactions the file doesn't categorize, such as every OFX and camt.053 transaction
+    #[arg(long, default_value = "Other")]
+    uncategorized: String,
+
+    /// Who ran the import, recorded on the import batch. Defaults to the current user,
+    /// who the audit log always records as the actor
+    #[arg(long)]
+    imported_by: Option<String>,
+
+    /// Bank profile to read the file with, by name or path. Detected from the file's header when not given
+    #[arg(short, long)]
+    profile: Option<String>,
+
+    /// Directory of TOML or YAML bank profiles
+    #[arg(long, default_value = "profiles")]
+    profiles_dir: String,
+
+    /// Sheet to read from a spreadsheet, instead of the profile's sheet or the first one
+    #[arg(long)]
+    sheet: Option<String>,
+
+    /// Read the file and report what the import would do, without writing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// How to print the report: text, or json for scripts
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    report: ReportFormat,
+
+    /// What to do with a row that can't be read: abort the import, or skip it and import the rest
+    #[arg(long, value_enum, default_value_t = OnError::Abort)]
+    on_error: OnError,
+
+    /// Create the categories that aren't in the database, instead of rejecting their rows
+    #[arg(long, conflicts_with = "fallback_category")]
+    create_categories: bool,
+
+    /// Import rows with an unknown category into this category, instead of rejecting them
+    #[arg(long)]
+    fallback_category: Option<String>,
+
+    /// Write the rows that weren't imported to this CSV file, to fix and import again
+    #[arg(long)]
+    rejected_file: Option<String>,
+
+    /// Classifier model that categorized the file, kept with each row's predicted category.
+    /// A model column in the profile takes precedence
+    #[arg(long)]
+    model: Option<String>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OnError {
+    Skip,
+    Abort,
 }
 
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-struct TransactionRow {
-    #[serde(rename = "Transaction ID")]
-    transaction_id: String,
-    #[serde(rename = "Posting Date")]
-    posting_date: String,
-    #[serde(rename = "Effective Date")]
-    effective_date: String,
-    #[serde(rename = "Transaction Type")]
-    transaction_type: String,
-    #[serde(rename = "Amount")]
-    amount: f64,
-    #[serde(rename = "Check Number")]
-    check_number: String,
-    #[serde(rename = "Reference Number")]
-    reference_number: String,
-    #[serde(rename = "Description")]
-    description: String,
-    #[serde(rename = "Transaction Category")]
-    transaction_category: String,
-    #[serde(rename = "Type")]
-    type_field: String,
-    #[serde(rename = "Balance")]
-    balance: f64,
-    #[serde(rename = "Memo")]
-    memo: String,
-    #[serde(rename = "Extended Description")]
-    extended_description: String,
-    #[serde(rename = "clean_text")]
-    clean_text: String,
-    #[serde(rename = "Category")]
-    category_name: String,
-    #[serde(rename = "Predicted_Category")]
-    predicted_category: String,
-    #[serde(rename = "Confidence_Score")]
-    confidence_score: f64,
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ReportFormat {
+    Text,
+    Json,
 }
 
-#[derive(Debug)]
-struct Transaction {
-    amount: BigDecimal,
-    description: String,
-    date: Date,
-    category_id: i32,
+// The profile named on the command line, or the one matching the file's header
+fn choose_profile(
+    args: &Args,
+    headers_for: impl Fn(&Profile) -> Option<Vec<String>>,
+) -> Result<Profile, Box<dyn Error>> {
+    let mut profiles = Profile::load_all(Path::new(&args.profiles_dir))?;
+    // a sheet given on the command line is read whatever the profiles say
+    if args.sheet.is_some() {
+        for profile in &mut profiles {
+            profile.sheet = args.sheet.clone();
+        }
+    }
+    let mut profile = match &args.profile {
+        Some(name) => find_profile(&profiles, name)?,
+        None => detect_profile(&profiles, headers_for)?.clone(),
+    };
+    if args.sheet.is_some() {
+        profile.sheet = args.sheet.clone();
+    }
+    Ok(profile)
 }
 
-#[derive(Debug, sqlx::FromRow)]
-struct Category {
-    id: i32,
-    name: String
+// JSON goes to stdout on its own, so a script can read it
+fn print_report(report: &ImportReport, format: ReportFormat) {
+    match format {
+        ReportFormat::Text => print!("{}", report),
+        ReportFormat::Json => println!("{}", report.to_json()),
+    }
 }
 
 #[tokio::main]
@@ -108,62 +169,278 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .connect(&database_url)
         .await?;
 
-    // Fetch all categories from database
-    let categories: Vec<Category> = sqlx::query_as!(
-        Category,
-        r#"
-        SELECT id, name FROM categories
-        "#
-    )
-    .fetch_all(&pool)
-    .await?;
-
-    // Create a map of category name to id
-    let category_map: HashMap<String, i32> = categories
-        .into_iter()
-        .map(|c| (c.name.to_lowercase(), c.id))
-        .collect();
-
-    // Open input CSV file
+    // Map category names and their aliases to categories
+    let category_repository = PgCategoryRepository { pool: pool.clone() };
+    let mut category_map = CategoryMap::new(
+        &category_repository.all().await?,
+        &category_repository.aliases().await?,
+    );
+    let rules = RuleSet::new(
+        PgCategorizationRuleRepository { pool: pool.clone() }.all().await?,
+    )?;
+    let fallback = match &args.fallback_category {
+        Some(name) => Some(
+            category_map
+                .get(name)
+                .ok_or_else(|| format!("Fallback category '{}' not found in database", name))?,
+        ),
+        None => None,
+    };
+
+    // Read input CSV file, the hash recognizes a file that was imported before
     let input_path = Path::new(&args.input);
-    let file = std::fs::File::open(input_path)?;
-    let mut rdr = Reader::from_reader(file);
-
-    // Process each row and insert into database
-    for result in rdr.deserialize() {
-        let row: TransactionRow = result?;
-        let category_name = row.predicted_category.to_lowercase();
-
-        if let Some(&category_id) = category_map.get(&category_name) {
-            let parts: Vec<&str> = row.effective_date.split('/').collect();
-            let month = parts[0].parse::<u8>()?;
-            let day = parts[1].parse::<u8>()?;
-            let year = parts[2].parse::<i32>()?;
-            let transaction = Transaction {
-                amount: BigDecimal::from_str(&row.amount.to_string())?,
-                description: row.description,
-                date: Date::from_calendar_date(year, Month::try_from(month)?, day)?,
-                category_id,
-            };
-
-            // Insert into transactions table
-            sqlx::query!(
-                r#"
-                INSERT INTO transactions (amount, description, date, category_id)
-                VALUES ($1, $2, $3, $4)
-                "#,
-                transaction.amount,
-                transaction.description,
-                transaction.date,
-                transaction.category_id
-            )
-            .execute(&pool)
-            .await?;
+    let contents = std::fs::read(input_path)?;
+    let file_hash = file_hash(&contents);
+
+    let import_batch_repository = PgImportBatchRepository { pool: pool.clone() };
+    for previous in import_batch_repository.by_file_hash(&file_hash).await? {
+        if previous.rolled_back_at.is_none() {
+            eprintln!(
+                "Warning: This file was already imported as batch {}, rows from it will be updated or skipped",
+                previous.id
+            );
+        }
+    }
+
+    let format = match &args.format {
+        Some(format) => format.parse()?,
+        None => FileFormat::detect(input_path, &contents),
+    };
+    let statement = match format {
+        FileFormat::Csv => {
+            let profile = choose_profile(&args, |profile| read_headers(&contents, profile))?;
+            eprintln!("Reading {} with profile '{}'", args.input, profile.name);
+            Statement::from_records(read_csv(&contents, &profile)?)
+        }
+        FileFormat::Spreadsheet => {
+            let profile = choose_profile(&args, |profile| spreadsheet::read_headers(&contents, profile))?;
+            eprintln!(
+                "Reading {} with profile '{}' from sheet '{}'",
+                args.input,
+                profile.name,
+                profile.sheet.as_deref().unwrap_or("the first sheet")
+            );
+            Statement::from_records(read_spreadsheet(&contents, &profile)?)
+        }
+        FileFormat::Ofx => {
+            eprintln!("Reading {} as OFX", args.input);
+            read_ofx(&contents)?
+        }
+        FileFormat::Qif => {
+            eprintln!("Reading {} as QIF", args.input);
+            read_qif(&contents)?
+        }
+        FileFormat::Camt => {
+            eprintln!("Reading {} as camt.053", args.input);
+            read_camt(&contents)?
+        }
+    };
+    let account = args
+        .account
+        .or(statement.account_id)
+        .unwrap_or_else(|| DEFAULT_ACCOUNT.to_string());
+
+    let source_name = input_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| args.input.clone());
+    let mut report = ImportReport::new(&source_name, &account, args.dry_run);
+
+    // Process each row, the rows are imported together at the end
+    let mut rows = Vec::new();
+    let mut row_categories = Vec::new();
+    let mut rejected = Vec::new();
+    let mut warnings = Vec::new();
+    let mut new_categories = HashSet::new();
+    let mut row_count = 0;
+    for result in statement.records {
+        row_count += 1;
+        let record = match result {
+            Ok(record) => record,
+            // a dry run lists every row it can't read instead of stopping at the first
+            Err(error) if args.dry_run => {
+                report.add_parse_error(&error);
+                rejected.push(RejectedRow::unreadable(&error));
+                continue;
+            }
+            Err(error) if args.on_error == OnError::Skip => {
+                report.add_parse_error(&error);
+                rejected.push(RejectedRow::unreadable(&error));
+                let warning = format!("Skipped {}", error);
+                eprintln!("Warning: {}", warning);
+                warnings.push(warning);
+                continue;
+            }
+            // nothing has been written yet, the rows are imported together at the end
+            Err(error) => {
+                return Err(format!(
+                    "{}. Nothing was imported, use --on-error skip to import the other rows",
+                    error
+                )
+                .into())
+            }
+        };
+        let category = record.category.clone().unwrap_or_else(|| args.uncategorized.clone());
+        let model = record.model.clone().or_else(|| args.model.clone());
+        // the category in the file is the classifier's prediction when it says how sure it was
+        let classification = (record.confidence.is_some() || model.is_some() || record.original_category.is_some())
+            .then(|| DbClassification {
+                predicted_category: record.category.clone(),
+                original_category: record.original_category.clone(),
+                confidence: record.confidence,
+                model,
+            });
+
+        // a row the file doesn't categorize goes to the first categorization rule that matches
+        let rule = match record.category {
+            Some(_) => None,
+            None => rules.first_match(&record.description, &record.amount, &account),
+        };
+        let tags = rule.map(|rule| rule.tags.clone()).unwrap_or_default();
+
+        let known = if let Some(rule) = rule {
+            Some((rule.category_id, rule.category_name.clone().unwrap_or_default()))
+        } else if let Some(known) = category_map.get(&category) {
+            Some(known)
         } else {
-            eprintln!("Warning: Category '{}' not found in database", row.predicted_category);
+            report.add_unknown_category(&category, record.line);
+            if args.create_categories {
+                // the import creates it with its rows, previews don't look at ids
+                let name = category.trim().to_string();
+                report.add_new_category(&name);
+                category_map.insert(&name, 0, &name);
+                new_categories.insert(name.clone());
+                category_map.get(&name)
+            } else {
+                fallback.clone()
+            }
+        };
+
+        match known {
+            Some((category_id, category_name)) => {
+                rows.push(DbImportRow {
+                    account: account.clone(),
+                    external_id: record.external_id,
+                    amount: record.amount,
+                    description: record.description,
+                    date: record.date,
+                    category_id,
+                    classification,
+                    rule_id: rule.map(|rule| rule.id),
+                    tags,
+                    new_category: new_categories.contains(&category_name).then(|| category_name.clone()),
+                });
+                row_categories.push(category_name);
+            }
+            None => {
+                report.add_rejected();
+                rejected.push(RejectedRow::unknown_category(&record, &category));
+            }
         }
     }
 
-    println!("All transactions imported successfully");
+    // One warning per unknown category, saying what became of its rows
+    for unknown in &report.unknown_categories {
+        let lines: Vec<String> = unknown.lines.iter().map(usize::to_string).collect();
+        let outcome = if args.create_categories {
+            "the category was created".to_string()
+        } else if let Some((_, fallback)) = &fallback {
+            format!("imported as '{}'", fallback)
+        } else {
+            "not imported".to_string()
+        };
+        let warning = format!(
+            "Category '{}' not found in database, {} {}: {}",
+            unknown.name,
+            if lines.len() == 1 { "line" } else { "lines" },
+            lines.join(", "),
+            outcome
+        );
+        if !args.dry_run {
+            eprintln!("Warning: {}", warning);
+        }
+        warnings.push(warning);
+    }
+
+    // Rejected rows are written even on a dry run, the file is for fixing them
+    if let Some(path) = &args.rejected_file {
+        write_rejected(std::fs::File::create(path)?, &rejected)?;
+        eprintln!("Wrote {} rejected rows to {}", rejected.len(), path);
+    } else if !rejected.is_empty() && !args.dry_run {
+        eprintln!(
+            "Warning: {} rows were not imported, use --rejected-file to keep them for review",
+            rejected.len()
+        );
+    }
+
+    let transaction_repository = PgTransactionRepository { pool };
+    if args.dry_run || args.report == ReportFormat::Json {
+        let actions = transaction_repository.preview_import(rows.clone()).await?;
+        for ((row, category), action) in rows.iter().zip(&row_categories).zip(actions) {
+            report.add_row(category, &row.amount, action);
+        }
+    }
+    if args.dry_run {
+        print_report(&report.finish(), args.report);
+        return Ok(());
+    }
+
+    let user = env::var("USER").ok().or_else(|| env::var("USERNAME").ok());
+    let batch = DbNewImportBatch {
+        source_name,
+        file_hash: Some(file_hash),
+        imported_by: args.imported_by.or_else(|| user.clone()),
+        row_count,
+        warnings,
+    };
+
+    // Every row is written in one database transaction, a failure leaves nothing behind
+    let total = rows.len();
+    let (progress, mut written) = unbounded_channel();
+    let progress_printer = tokio::spawn(async move {
+        while let Some(count) = written.recv().await {
+            eprint!("\rImported {} of {} rows", count, total);
+        }
+        if total > 0 {
+            eprintln!();
+        }
+    });
+    let batch = transaction_repository
+        .import_transactions_with_progress(batch, rows, progress, user)
+        .await?;
+    progress_printer.await?;
+
+    if args.report == ReportFormat::Json {
+        // the batch's counts are what the import did, the preview only what it expected to
+        report.inserted = batch.inserted_count as usize;
+        report.updated = batch.updated_count as usize;
+        report.duplicates = batch.skipped_count as usize;
+        print_report(&report.finish(), args.report);
+    } else {
+        println!(
+            "Import batch {} complete: {} inserted, {} updated, {} skipped as already imported",
+            batch.id,
+            batch.inserted_count,
+            batch.updated_count,
+            batch.skipped_count
+        );
+    }
+
+    // The bank's balance only matches when every earlier transaction in the account was imported too
+    if let Some(ledger_balance) = statement.ledger_balance {
+        let as_of = to_naive_date(&ledger_balance.as_of);
+        let balance = transaction_repository.account_balance(&account, &as_of).await?;
+        if balance == ledger_balance.amount {
+            eprintln!("Balance on {} matches the bank's: {}", as_of, balance);
+        } else {
+            eprintln!(
+                "Warning: The bank's balance on {} is {} but the transactions in '{}' add up to {}, some may be missing",
+                as_of,
+                ledger_balance.amount,
+                account,
+                balance
+            );
+        }
+    }
     Ok(())
 }
\ No newline at end of file
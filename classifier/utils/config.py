@@ -29,6 +29,7 @@ LABEL_LIST = [
 # This is used to simplify the desciption field to make it easier for the model to interpret.
 # Add transactions lables here in the form of "pattern": "label" where the pattern is the label
 # for a transaction and the lable is a simplified form. 
+# backend/src/normalize.rs has a copy of these, so change both together.
 TEXT_TRANSFORMS = {
 "NETFLIX": "netflix",
 "AMAZON.COM": "amazon goods"